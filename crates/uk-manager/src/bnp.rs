@@ -6,7 +6,6 @@ use std::{
 use anyhow::anyhow;
 use anyhow_ext::{Context, Result};
 use dashmap::{DashMap, DashSet};
-use fs_err as fs;
 use rayon::prelude::*;
 use roead::{
     aamp::{ParameterIO, ParameterList, ParameterListing},
@@ -15,11 +14,10 @@ use roead::{
 };
 use rustc_hash::FxHashMap;
 use uk_content::{constants::Language, resource::ResourceData};
-use uk_mod::pack::ModPacker;
+use uk_mod::{pack::ModPacker, source::ModSource};
 use uk_reader::ResourceReader;
-use uk_util::PathExt;
 
-use crate::{settings::Platform, util::extract_7z};
+use crate::{settings::Platform, util};
 mod actorinfo;
 mod areadata;
 mod aslist;
@@ -100,6 +98,7 @@ struct BnpConverter {
     dump: Arc<ResourceReader>,
     game_lang: Language,
     platform: Platform,
+    source: ModSource,
     path: PathBuf,
     current_root: PathBuf,
     content: &'static str,
@@ -124,11 +123,13 @@ impl BnpConverter {
             Ok(self.dump.get_data(path)?)
         } else {
             let root_path = self.path.join(self.content).join(path.as_ref());
-            if root_path.exists() {
+            if self.source.exists(&root_path) {
                 let data = self
                     .opt_master_cache
                     .entry(path.as_ref().to_path_buf())
-                    .or_try_insert_with(|| -> Result<Vec<u8>> { Ok(fs::read(root_path)?) })?;
+                    .or_try_insert_with(|| -> Result<Vec<u8>> {
+                        Ok(self.source.read(&root_path)?)
+                    })?;
                 Ok(Arc::new(ResourceData::from_binary(
                     path.as_ref(),
                     data.as_slice(),
@@ -144,11 +145,13 @@ impl BnpConverter {
             Ok(self.dump.get_bytes_uncached(path)?)
         } else {
             let root_path = self.path.join(self.content).join(path.as_ref());
-            if root_path.exists() {
+            if self.source.exists(&root_path) {
                 let data = self
                     .opt_master_cache
                     .entry(path.as_ref().to_path_buf())
-                    .or_try_insert_with(|| -> Result<Vec<u8>> { Ok(fs::read(root_path)?) })?;
+                    .or_try_insert_with(|| -> Result<Vec<u8>> {
+                        Ok(self.source.read(&root_path)?)
+                    })?;
                 Ok(data.to_vec())
             } else {
                 Ok(self.dump.get_bytes_uncached(path)?)
@@ -162,11 +165,10 @@ impl BnpConverter {
             Ok(self.dump.get_aoc_bytes_uncached(path)?)
         } else {
             let root_path = self.path.join(self.aoc).join(&path);
-            if root_path.exists() {
-                let data = self
-                    .opt_master_cache
-                    .entry(path)
-                    .or_try_insert_with(|| -> Result<Vec<u8>> { Ok(fs::read(root_path)?) })?;
+            if self.source.exists(&root_path) {
+                let data = self.opt_master_cache.entry(path).or_try_insert_with(
+                    || -> Result<Vec<u8>> { Ok(self.source.read(&root_path)?) },
+                )?;
                 Ok(data.to_vec())
             } else {
                 Ok(self.dump.get_aoc_bytes_uncached(path)?)
@@ -182,11 +184,13 @@ impl BnpConverter {
         } else {
             let parts = path.split("//").collect::<Vec<_>>();
             let root_path = self.path.join(self.content).join(parts[0]);
-            if root_path.exists() {
+            if self.source.exists(&root_path) {
                 let root_sarc = self
                     .opt_master_cache
                     .entry(parts[0].into())
-                    .or_try_insert_with(|| -> Result<Vec<u8>> { Ok(fs::read(root_path)?) })?;
+                    .or_try_insert_with(|| -> Result<Vec<u8>> {
+                        Ok(self.source.read(&root_path)?)
+                    })?;
                 let root_sarc = Sarc::new(root_sarc.as_slice())?;
                 let nested_parent = if parts.len() == 3 {
                     let nested_data = self
@@ -277,17 +281,16 @@ impl BnpConverter {
         }
 
         let base_sarc = self.dump.get_bytes_uncached(root_path);
-        if !dest_path.exists() {
+        if !self.source.exists(&dest_path) {
             let base_sarc = base_sarc
                 .with_context(|| format!("Failed to get base game SARC at {root_path}"))?;
-            dest_path.parent().map(fs::create_dir_all).transpose()?;
-            fs::write(dest_path, &base_sarc)?;
+            self.source.write(&dest_path, &base_sarc)?;
             Ok(SarcWriter::from_sarc(&Sarc::new(&base_sarc).with_context(
                 || format!("Failed to parse SARC {root_path} from dump"),
             )?))
         } else {
             self.packs.remove(dest_path);
-            match Sarc::new(fs::read(dest_path)?)
+            match Sarc::new(self.source.read(&dest_path)?)
                 .with_context(|| format!("Failed to parse SARC in mod at {root_path}"))
             {
                 Ok(stripped) => {
@@ -299,7 +302,7 @@ impl BnpConverter {
                             self.parent_packs
                                 .get(&self.path.join(self.aoc).join(root_path))
                         })
-                        .and_then(|path| fs::read(&*path).ok())
+                        .and_then(|path| self.source.read(&*path).ok())
                         .and_then(|bytes| Sarc::new(decompress_if(&bytes).to_vec()).ok())
                     {
                         inflate_sarc(&mut sarc, &stripped, parent_sarc);
@@ -327,8 +330,7 @@ impl BnpConverter {
                         .unwrap_or(false)
                     {
                         let base_sarc = base_sarc?;
-                        dest_path.parent().map(fs::create_dir_all).transpose()?;
-                        fs::write(dest_path, &base_sarc)?;
+                        self.source.write(&dest_path, &base_sarc)?;
                         Ok(SarcWriter::from_sarc(&Sarc::new(&base_sarc).with_context(
                             || format!("Failed to parse SARC {root_path} from dump"),
                         )?))
@@ -365,14 +367,14 @@ impl BnpConverter {
             let nested_path = parts[1];
             sarc.add_file(nested_path, compress_if(&nested.to_binary(), nested_path));
         }
-        base_path.parent().map(fs::create_dir_all).transpose()?;
-        fs::write(&base_path, compress_if(&sarc.to_binary(), &base_path))?;
+        self.source
+            .write(&base_path, compress_if(&sarc.to_binary(), &base_path))?;
         Ok(())
     }
 
     fn convert_root(&self) -> Result<()> {
         let packs_path = self.current_root.join("logs/packs.json");
-        if packs_path.exists() {
+        if self.source.exists(&packs_path) {
             let is_root = self
                 .current_root
                 .parent()
@@ -381,7 +383,10 @@ impl BnpConverter {
                 .map(|n| n != "options")
                 .unwrap_or(false);
             let log: FxHashMap<String, String> = serde_json::from_str(
-                &fs::read_to_string(packs_path).context("Failed to read packs.json")?,
+                &self
+                    .source
+                    .read_to_string(&packs_path)
+                    .context("Failed to read packs.json")?,
             )
             .context("Failed to parse packs.json")?;
             for pack in log.into_values().filter_map(|p| {
@@ -435,7 +440,11 @@ impl BnpConverter {
                     .file_name()
                     .and_then(|n| n.to_str().map(|n| n == "AocMainField.pack"))
                     .unwrap_or(false)
-                    && file.metadata().map(|m| m.len()).unwrap_or_default() == 0)
+                    && self
+                        .source
+                        .read(file)
+                        .map(|data| data.is_empty())
+                        .unwrap_or(true))
             })
             .try_for_each(|file| -> Result<()> {
                 let mut sarc = self.open_or_create_sarc(
@@ -449,7 +458,7 @@ impl BnpConverter {
                 )?;
                 let data = sarc.to_binary();
                 let data = compress_if(&data, &file);
-                fs::write(file, data)?;
+                self.source.write(&file, data)?;
                 Ok(())
             })?;
         Ok(())
@@ -460,13 +469,8 @@ impl BnpConverter {
         self.convert_root()?;
 
         let opt_dir = root.join("options");
-        if opt_dir.exists() {
-            for option in fs::read_dir(opt_dir)?.filter_map(|r| {
-                r.ok().and_then(|r| {
-                    let path = r.path();
-                    path.is_dir().then_some(path)
-                })
-            }) {
+        if self.source.exists(&opt_dir) {
+            for option in self.source.subfolders(&opt_dir) {
                 log::info!(
                     "Processing BNP logs for option {}",
                     option
@@ -482,17 +486,15 @@ impl BnpConverter {
     }
 }
 
-pub fn unpack_bnp(core: &crate::core::Manager, path: &Path) -> Result<PathBuf> {
-    let tempdir = crate::util::get_temp_folder();
-    if path.is_dir() {
-        crate::util::copy_dir(path, tempdir.as_path())
-            .context("Failed to copy files to temp folder")?;
-    } else {
-        log::info!("Extracting BNP…");
-        extract_7z(path, &tempdir).context("Failed to extract BNP")?;
-    }
-    if tempdir.join("rules.txt").exists() && !tempdir.join("info.json").exists() {
-        old::Bnp2xConverter::new(&tempdir)
+/// Convert the logs of a BNP into the files they change. The BNP is read in
+/// place, and the files converted from its logs are written to a temp folder
+/// overlaid on it.
+pub fn unpack_bnp(core: &crate::core::Manager, path: &Path) -> Result<ModSource> {
+    log::info!("Opening BNP…");
+    let source = util::open_mod_source(path).context("Failed to open BNP")?;
+    let root = source.root().to_path_buf();
+    if source.exists(&root.join("rules.txt")) && !source.exists(&root.join("info.json")) {
+        old::Bnp2xConverter::new(&source)
             .convert()
             .context("Failed to upgrade 2.x BNP")?;
     }
@@ -513,17 +515,18 @@ pub fn unpack_bnp(core: &crate::core::Manager, path: &Path) -> Result<PathBuf> {
         aoc,
         packs: Default::default(),
         parent_packs: Default::default(),
-        current_root: tempdir.clone(),
-        path: tempdir.clone(),
+        current_root: root.clone(),
+        path: root,
+        source: source.clone(),
         opt_master_cache: Default::default(),
     };
-    let path = converter.convert()?;
+    converter.convert()?;
     log::info!("BNP unpacked");
-    Ok(path)
+    Ok(source)
 }
 
 pub fn convert_bnp(core: &crate::core::Manager, path: &Path) -> Result<PathBuf> {
-    let tempdir = unpack_bnp(core, path).with_context(|| {
+    let source = unpack_bnp(core, path).with_context(|| {
         format!(
             "Failed to unpack {}",
             path.file_name()
@@ -532,13 +535,13 @@ pub fn convert_bnp(core: &crate::core::Manager, path: &Path) -> Result<PathBuf>
         )
     })?;
     let tempfile = std::env::temp_dir();
-    let meta = if let Some(info_path) = tempdir.join("info.json").exists_then() {
-        ModPacker::parse_info(info_path)?
+    let meta = if source.is_file(&source.root().join("info.json")) {
+        ModPacker::parse_source_info(&source)?
     } else {
-        ModPacker::parse_rules(tempdir.join("rules.txt")).context("Failed to parse BNP metadata")?
+        ModPacker::parse_source_rules(&source).context("Failed to parse BNP metadata")?
     };
     let name = meta.name.clone();
-    let new_mod = ModPacker::new(tempdir, tempfile.as_path(), Some(meta), vec![
+    let new_mod = ModPacker::from_source(source, tempfile.as_path(), Some(meta), vec![
         core.settings()
             .dump()
             .context("No dump for current platform")?,
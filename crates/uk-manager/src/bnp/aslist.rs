@@ -1,5 +1,4 @@
 use anyhow_ext::{Context, Result};
-use fs_err as fs;
 use rayon::prelude::*;
 use roead::{
     aamp::ParameterIO,
@@ -48,14 +47,13 @@ fn handle_diff_entry(
 impl BnpConverter {
     pub fn handle_aslist(&self) -> Result<()> {
         let aslist_path = self.current_root.join("logs/aslist.aamp");
-        if aslist_path.exists() {
+        if self.source.exists(&aslist_path) {
             log::debug!("Processing AS list log");
-            let pio = ParameterIO::from_binary(fs::read(aslist_path)?)?;
+            let pio = ParameterIO::from_binary(self.source.read(&aslist_path)?)?;
             let diff = parse_aamp_diff("FileTable", &pio)?;
             diff.into_par_iter()
                 .try_for_each(|(root, contents)| -> Result<()> {
                     let base_path = self.current_root.join(&root);
-                    base_path.parent().iter().try_for_each(fs::create_dir_all)?;
                     match contents {
                         AampDiffEntry::Sarc(map) => {
                             let mut sarc = self
@@ -71,7 +69,8 @@ impl BnpConverter {
                                     || format!("Failed to process {} in {}", nest_root, root),
                                 )
                             })?;
-                            fs::write(&base_path, compress_if(&sarc.to_binary(), &root))?;
+                            self.source
+                                .write(&base_path, compress_if(&sarc.to_binary(), &root))?;
                         }
                         AampDiffEntry::Aamp(plist) => {
                             let pio = ASList::try_from(&ParameterIO::from_binary(
@@ -82,7 +81,7 @@ impl BnpConverter {
                                 .merge(&diff)
                                 .into_binary(uk_content::prelude::Endian::Little);
                             let data = compress_if(&data, &root);
-                            fs::write(base_path, data)?;
+                            self.source.write(&base_path, data)?;
                         }
                     }
                     Ok(())
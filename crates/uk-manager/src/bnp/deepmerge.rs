@@ -1,5 +1,4 @@
 use anyhow_ext::{Context, Result};
-use fs_err as fs;
 use rayon::prelude::*;
 use roead::{
     aamp::ParameterIO,
@@ -44,18 +43,17 @@ fn handle_diff_entry(
 impl BnpConverter {
     pub fn handle_deepmerge(&self) -> Result<()> {
         let deepmerge_path = self.current_root.join("logs/deepmerge.aamp");
-        if deepmerge_path.exists() {
+        if self.source.exists(&deepmerge_path) {
             log::debug!("Processing deepmerge log");
-            let data = fs::read(&deepmerge_path)?;
-            let pio  = match &data[0..4] {
+            let data = self.source.read(&deepmerge_path)?;
+            let pio = match &data[0..4] {
                 b"AAMP" => ParameterIO::from_binary(data),
-                _ => ParameterIO::from_text(fs::read_to_string(&deepmerge_path)?),
+                _ => ParameterIO::from_text(self.source.read_to_string(&deepmerge_path)?),
             }?;
             let diff = parse_aamp_diff("FileTable", &pio)?;
             diff.into_par_iter()
                 .try_for_each(|(root, contents)| -> Result<()> {
                     let base_path = self.current_root.join(&root);
-                    base_path.parent().iter().try_for_each(fs::create_dir_all)?;
                     match contents {
                         AampDiffEntry::Sarc(map) => {
                             let mut sarc = self
@@ -71,14 +69,15 @@ impl BnpConverter {
                                     || format!("Failed to process {} in {}", nest_root, root),
                                 )
                             })?;
-                            fs::write(&base_path, compress_if(&sarc.to_binary(), &root))?;
+                            self.source
+                                .write(&base_path, compress_if(&sarc.to_binary(), &root))?;
                         }
                         AampDiffEntry::Aamp(plist) => {
                             let mut pio = ParameterIO::from_binary(
                                 self.get_master_bytes(self.trim_prefixes(&root))?,
                             )?;
                             pio.param_root = merge_plist(&pio.param_root, &plist);
-                            fs::write(base_path, pio.to_binary())?;
+                            self.source.write(&base_path, pio.to_binary())?;
                         }
                     }
                     Ok(())
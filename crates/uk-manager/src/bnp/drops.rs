@@ -1,5 +1,4 @@
 use anyhow_ext::{Context, Result};
-use fs_err as fs;
 use rayon::prelude::*;
 use roead::aamp::{Parameter, ParameterIO, ParameterObject};
 use rustc_hash::FxHashMap;
@@ -32,9 +31,9 @@ const UNDERRIDE: &str = "UNDERRIDE_CONST";
 impl BnpConverter {
     pub fn handle_drops(&self) -> Result<()> {
         let drops_path = self.current_root.join("logs/drops.json");
-        if drops_path.exists() {
+        if self.source.exists(&drops_path) {
             log::debug!("Processing drops log");
-            let text = fs::read_to_string(drops_path)?;
+            let text = self.source.read_to_string(&drops_path)?;
             let do_refs = text.contains(UNDERRIDE);
             let drops: DropDiff = serde_json::from_str(&text)?;
             drops
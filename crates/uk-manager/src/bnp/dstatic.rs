@@ -1,5 +1,4 @@
 use anyhow_ext::{Context, Result};
-use fs_err as fs;
 use roead::{
     byml::{map, Byml},
     yaz0::{compress, decompress},
@@ -12,9 +11,9 @@ use super::BnpConverter;
 impl BnpConverter {
     pub fn handle_dungeon_static(&self) -> Result<()> {
         let dstatic_path = self.current_root.join("logs/dstatic.yml");
-        if dstatic_path.exists() {
+        if self.source.exists(&dstatic_path) {
             log::debug!("Processing dungeon static log");
-            let dstatic_diff = Byml::from_text(fs::read_to_string(&dstatic_path)?)?;
+            let dstatic_diff = Byml::from_text(self.source.read_to_string(&dstatic_path)?)?;
             let base = Byml::from_binary(decompress(
                 self.get_master_aoc_bytes("Map/CDungeon/Static.smubin")?,
             )?)?;
@@ -52,8 +51,10 @@ impl BnpConverter {
                 .current_root
                 .join(self.aoc)
                 .join("Map/CDungeon/Static.smubin");
-            dest_path.parent().iter().try_for_each(fs::create_dir_all)?;
-            fs::write(dest_path, compress(dstatic.to_binary(self.platform.into())))?;
+            self.source.write(
+                &dest_path,
+                compress(dstatic.to_binary(self.platform.into())),
+            )?;
         }
         Ok(())
     }
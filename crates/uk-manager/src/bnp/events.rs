@@ -1,5 +1,4 @@
 use anyhow_ext::Result;
-use fs_err as fs;
 use roead::byml::Byml;
 use uk_content::{
     prelude::{Mergeable, Resource},
@@ -12,9 +11,10 @@ use super::BnpConverter;
 impl BnpConverter {
     pub fn handle_events(&self) -> Result<()> {
         let events_path = self.current_root.join("logs/eventinfo.yml");
-        if events_path.exists() {
+        if self.source.exists(&events_path) {
             log::debug!("Processing eventinfo log");
-            let diff = EventInfo::from_byml(&Byml::from_text(fs::read_to_string(events_path)?)?)?;
+            let diff =
+                EventInfo::from_byml(&Byml::from_text(self.source.read_to_string(&events_path)?)?)?;
             let base =
                 self.get_from_master_sarc("Pack/Bootup.pack//Event/EventInfo.product.sbyml")?;
             if let Ok(base) = EventInfo::from_binary(base) {
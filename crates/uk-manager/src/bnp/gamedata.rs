@@ -1,5 +1,4 @@
 use anyhow_ext::{Context, Result};
-use fs_err as fs;
 use roead::byml::{Byml, Map};
 use uk_content::{
     data::gamedata::{FlagData, GameData},
@@ -12,9 +11,9 @@ use super::BnpConverter;
 impl BnpConverter {
     pub fn handle_gamedata(&self) -> Result<()> {
         let gamedata_path = self.current_root.join("logs/gamedata.yml");
-        if gamedata_path.exists() {
+        if self.source.exists(&gamedata_path) {
             log::debug!("Processing gamedata log");
-            let diff = Byml::from_text(fs::read_to_string(gamedata_path)?)?.into_map()?;
+            let diff = Byml::from_text(self.source.read_to_string(&gamedata_path)?)?.into_map()?;
             let base = self
                 .get_from_master_sarc("Pack/Bootup.pack//GameData/gamedata.ssarc")
                 .context("Failed to parse gamedata pack from game dump")?;
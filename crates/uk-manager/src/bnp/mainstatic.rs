@@ -1,5 +1,4 @@
 use anyhow_ext::{Context, Result};
-use fs_err as fs;
 use roead::{
     byml::{Byml, Map},
     yaz0::{compress, decompress},
@@ -51,13 +50,16 @@ fn get_id(item: &Map) -> Result<String> {
 impl BnpConverter {
     pub fn handle_mainfield_static(&self) -> Result<()> {
         let mstatic_path = self.current_root.join("logs/mainstatic.yml");
-        if mstatic_path.exists() {
+        if self.source.exists(&mstatic_path) {
             log::debug!("Processing mainfield static log");
-            let diff: FxHashMap<String, Map> = Byml::from_text(fs::read_to_string(mstatic_path)?)?
-                .into_map()?
-                .into_iter()
-                .map(|(cat, entries)| -> Result<(String, Map)> { Ok((cat, entries.into_map()?)) })
-                .collect::<Result<_>>()?;
+            let diff: FxHashMap<String, Map> =
+                Byml::from_text(self.source.read_to_string(&mstatic_path)?)?
+                    .into_map()?
+                    .into_iter()
+                    .map(|(cat, entries)| -> Result<(String, Map)> {
+                        Ok((cat, entries.into_map()?))
+                    })
+                    .collect::<Result<_>>()?;
             let mut base: FxHashMap<String, Map> = Byml::from_binary(decompress(
                 self.get_master_aoc_bytes("Map/MainField/Static.smubin")?,
             )?)?
@@ -87,8 +89,8 @@ impl BnpConverter {
                 .current_root
                 .join(self.aoc)
                 .join("Map/MainField/Static.smubin");
-            dest_path.parent().iter().try_for_each(fs::create_dir_all)?;
-            fs::write(dest_path, compress(output.to_binary(self.platform.into())))?;
+            self.source
+                .write(&dest_path, compress(output.to_binary(self.platform.into())))?;
         }
         Ok(())
     }
@@ -1,7 +1,6 @@
 use std::collections::BTreeMap;
 
 use anyhow_ext::{Context, Result};
-use fs_err as fs;
 use join_str::jstr;
 use rayon::prelude::*;
 use roead::{
@@ -77,9 +76,9 @@ fn merge_map(base: &mut Byml, diff: Byml) -> Result<()> {
 impl BnpConverter {
     pub fn handle_maps(&self) -> Result<()> {
         let maps_path = self.current_root.join("logs/map.yml");
-        if maps_path.exists() {
+        if self.source.exists(&maps_path) {
             log::debug!("Processing maps log");
-            let diff = Byml::from_text(fs::read_to_string(maps_path)?)
+            let diff = Byml::from_text(self.source.read_to_string(&maps_path)?)
                 .context("Could not parse maps log")?
                 .into_map()?;
             let base_pack = Sarc::new(self.get_master_aoc_bytes("Pack/AocMainField.pack")?)
@@ -117,16 +116,14 @@ impl BnpConverter {
                 .into_par_iter()
                 .try_for_each(|(path, data)| -> Result<()> {
                     let dest_path = self.current_root.join(self.aoc).join(path.as_str());
-                    dest_path.parent().iter().try_for_each(fs::create_dir_all)?;
-                    fs::write(dest_path, data)?;
+                    self.source.write(&dest_path, data)?;
                     Ok(())
                 })?;
             let dest_path = self
                 .current_root
                 .join(self.aoc)
                 .join("Pack/AocMainField.pack");
-            dest_path.parent().iter().try_for_each(fs::create_dir_all)?;
-            fs::write(dest_path, merged_pack.to_binary())?;
+            self.source.write(&dest_path, merged_pack.to_binary())?;
         }
         Ok(())
     }
@@ -1,7 +1,6 @@
 use std::{path::Path, str::FromStr};
 
 use anyhow_ext::{bail, Context, Result};
-use fs_err as fs;
 use roead::{
     aamp::*,
     byml::{map, Byml},
@@ -15,6 +14,7 @@ use uk_content::{
     message::{Entry, Msyt},
     util::{HashMap, IteratorExt},
 };
+use uk_mod::source::ModSource;
 
 use super::texts::TextsLog;
 
@@ -234,12 +234,16 @@ fn plist_from_value(value: Value) -> Result<ParameterList> {
 }
 
 pub struct Bnp2xConverter<'a> {
-    path: &'a Path,
+    source: &'a ModSource,
+    path:   &'a Path,
 }
 
 impl<'a> Bnp2xConverter<'a> {
-    pub fn new(path: &'a Path) -> Self {
-        Self { path }
+    pub fn new(source: &'a ModSource) -> Self {
+        Self {
+            source,
+            path: source.root(),
+        }
     }
 
     pub fn convert(&self) -> Result<()> {
@@ -282,9 +286,9 @@ impl<'a> Bnp2xConverter<'a> {
 
     fn convert_pack_log(&self) -> Result<()> {
         let packs_path = self.path.join("logs/packs.log");
-        if packs_path.exists() {
+        if self.source.exists(&packs_path) {
             log::debug!("Converting old pack log");
-            let text = fs::read_to_string(packs_path)?;
+            let text = self.source.read_to_string(&packs_path)?;
             let json: HashMap<String, String> = text
                 .lines()
                 .skip(1)
@@ -295,8 +299,8 @@ impl<'a> Bnp2xConverter<'a> {
                     canon.and_then(|c| path.map(|p| (c, p)))
                 })
                 .collect();
-            fs::write(
-                self.path.join("logs/packs.json"),
+            self.source.write(
+                &self.path.join("logs/packs.json"),
                 serde_json::to_string_pretty(&json)?,
             )?;
         }
@@ -305,9 +309,9 @@ impl<'a> Bnp2xConverter<'a> {
 
     fn convert_aamp_log(&self) -> Result<()> {
         let aamp_path = self.path.join("logs/deepmerge.yml");
-        if aamp_path.exists() {
+        if self.source.exists(&aamp_path) {
             log::debug!("Converting old deepmerge log");
-            let merge_log: Value = serde_yaml::from_str(&fs::read_to_string(aamp_path)?)?;
+            let merge_log: Value = serde_yaml::from_str(&self.source.read_to_string(&aamp_path)?)?;
             let Value::Mapping(merge_log) = merge_log else {
                 bail!("Invalid deepmerge log")
             };
@@ -318,33 +322,27 @@ impl<'a> Bnp2xConverter<'a> {
                 file_table.insert(index, Parameter::StringRef(key.into()));
                 new_log.param_root.lists.insert(key, plist_from_value(v)?);
             }
-            fs::write(self.path.join("logs/deepmerge.aamp"), new_log.to_binary())?;
+            self.source
+                .write(&self.path.join("logs/deepmerge.aamp"), new_log.to_binary())?;
         }
         Ok(())
     }
 
     fn convert_text_logs(&self) -> Result<()> {
         use smartstring::alias::String;
-        let yaml_logs = jwalk::WalkDir::new(self.path.join("logs"))
-            .into_iter()
-            .filter_map(|e| {
-                e.ok().and_then(|e| {
-                    e.file_name()
-                        .to_str()
-                        .and_then(|n| n.starts_with("texts_").then(|| e.path()))
-                })
-            })
-            .collect::<Vec<_>>();
-        let sarc_logs = jwalk::WalkDir::new(self.path.join("logs"))
-            .into_iter()
-            .filter_map(|e| {
-                e.ok().and_then(|e| {
-                    e.file_name()
-                        .to_str()
-                        .and_then(|n| n.starts_with("newtexts_").then(|| e.path()))
+        let logs = self.source.files(&self.path.join("logs"));
+        let logs_named = |prefix: &str| {
+            logs.iter()
+                .filter(|log| {
+                    log.file_name()
+                        .and_then(|n| n.to_str())
+                        .is_some_and(|n| n.starts_with(prefix))
                 })
-            })
-            .collect::<Vec<_>>();
+                .cloned()
+                .collect::<Vec<_>>()
+        };
+        let yaml_logs = logs_named("texts_");
+        let sarc_logs = logs_named("newtexts_");
 
         #[derive(Debug, Deserialize)]
         struct MsbtEntries {
@@ -364,7 +362,7 @@ impl<'a> Bnp2xConverter<'a> {
                         .context("Bad file language")?,
                 )?;
                 let log: HashMap<String, MsbtEntries> =
-                    serde_yaml::from_str(&fs::read_to_string(yaml_log)?)?;
+                    serde_yaml::from_str(&self.source.read_to_string(&yaml_log)?)?;
                 diff.insert(lang, log.into_iter().map(|(k, v)| (k, v.entries)).collect());
             }
             for sarc_log in sarc_logs {
@@ -376,7 +374,7 @@ impl<'a> Bnp2xConverter<'a> {
                         .context("Bad file language")?,
                 )?;
                 let lang_diff = diff.entry(lang).or_default();
-                let sarc = Sarc::new(fs::read(sarc_log)?)?;
+                let sarc = Sarc::new(self.source.read(&sarc_log)?)?;
                 for file in sarc.files().filter(|f| f.name.is_some()) {
                     let msyt = Msyt::from_msbt_bytes(file.data)?;
                     lang_diff.insert(
@@ -388,8 +386,8 @@ impl<'a> Bnp2xConverter<'a> {
                     );
                 }
             }
-            fs::write(
-                self.path.join("logs/texts.json"),
+            self.source.write(
+                &self.path.join("logs/texts.json"),
                 serde_json::to_string(&diff)?,
             )?;
         }
@@ -398,27 +396,27 @@ impl<'a> Bnp2xConverter<'a> {
 
     fn convert_gamedata_log(&self) -> Result<()> {
         let gdata_log = self.path.join("logs/gamedata.yml");
-        if gdata_log.exists() {
+        if self.source.exists(&gdata_log) {
             log::debug!("Converting old gamedata log");
-            let log = Byml::from_text(fs::read_to_string(&gdata_log)?)?.into_map()?;
+            let log = Byml::from_text(self.source.read_to_string(&gdata_log)?)?.into_map()?;
             let new_log = log
                 .into_iter()
                 .map(|(data_type, diff)| {
                     (data_type, map!("add" => diff, "del" => Byml::Array(vec![])))
                 })
                 .collect::<Byml>();
-            fs::write(gdata_log, new_log.to_text())?;
+            self.source.write(&gdata_log, new_log.to_text())?;
         }
         Ok(())
     }
 
     fn convert_savedata_log(&self) -> Result<()> {
         let sdata_log = self.path.join("logs/savedata.yml");
-        if sdata_log.exists() {
+        if self.source.exists(&sdata_log) {
             log::debug!("Converting old savedata log");
-            let log = Byml::from_text(fs::read_to_string(&sdata_log)?)?;
-            fs::write(
-                sdata_log,
+            let log = Byml::from_text(self.source.read_to_string(&sdata_log)?)?;
+            self.source.write(
+                &sdata_log,
                 map!("add" => log, "del" => Byml::Array(vec![])).to_text(),
             )?;
         }
@@ -427,9 +425,10 @@ impl<'a> Bnp2xConverter<'a> {
 
     fn convert_map_log(&self) -> Result<()> {
         let map_log = self.path.join("logs/map.yml");
-        if map_log.exists() {
+        if self.source.exists(&map_log) {
             log::debug!("Converting old map log");
-            let Value::Mapping(log) = serde_yaml::from_str(&fs::read_to_string(&map_log)?)? else {
+            let Value::Mapping(log) = serde_yaml::from_str(&self.source.read_to_string(&map_log)?)?
+            else {
                 bail!("Invalid map log")
             };
             let new_log = log
@@ -472,7 +471,7 @@ impl<'a> Bnp2xConverter<'a> {
                     Ok((unit, new_diff))
                 })
                 .collect::<Result<Byml>>()?;
-            fs::write(map_log, new_log.to_text())?;
+            self.source.write(&map_log, new_log.to_text())?;
         }
         Ok(())
     }
@@ -1,5 +1,4 @@
 use anyhow_ext::Result;
-use fs_err as fs;
 use roead::{byml::Byml, yaz0::compress};
 use rustc_hash::FxHashMap;
 use smartstring::alias::String;
@@ -9,13 +8,14 @@ use super::BnpConverter;
 impl BnpConverter {
     pub fn handle_quests(&self) -> Result<()> {
         let quests_path = self.current_root.join("logs/quests.yml");
-        if quests_path.exists() {
+        if self.source.exists(&quests_path) {
             log::debug!("Processing quests log");
-            let mut diff = Byml::from_text(fs::read_to_string(quests_path)?)?.into_map()?;
-            let mut quests = Byml::from_binary(
-                self.dump
-                    .get_bytes_from_sarc("Pack/TitleBG.pack//Quest/QuestProduct.sbquestpack", false)?,
-            )?
+            let mut diff =
+                Byml::from_text(self.source.read_to_string(&quests_path)?)?.into_map()?;
+            let mut quests = Byml::from_binary(self.dump.get_bytes_from_sarc(
+                "Pack/TitleBG.pack//Quest/QuestProduct.sbquestpack",
+                false,
+            )?)?
             .into_array()?;
             let quest_hashes: FxHashMap<String, usize> = quests
                 .iter()
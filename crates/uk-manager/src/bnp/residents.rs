@@ -1,5 +1,4 @@
 use anyhow_ext::Result;
-use fs_err as fs;
 use roead::byml::Byml;
 use uk_content::{
     actor::residents::ResidentActorData, prelude::Resource, resource::ResidentActors,
@@ -10,9 +9,9 @@ use super::BnpConverter;
 impl BnpConverter {
     pub fn handle_residents(&self) -> Result<()> {
         let residents_path = self.current_root.join("logs/residents.yml");
-        if residents_path.exists() {
+        if self.source.exists(&residents_path) {
             log::debug!("Processing resident actors log");
-            let diff = Byml::from_text(fs::read_to_string(residents_path)?)?.into_map()?;
+            let diff = Byml::from_text(self.source.read_to_string(&residents_path)?)?.into_map()?;
             let data = self.get_from_master_sarc("Pack/Bootup.pack//Actor/ResidentActors.byml")?;
             if let Ok(mut residents) = ResidentActors::from_binary(data) {
                 residents
@@ -1,5 +1,4 @@
 use anyhow_ext::Result;
-use fs_err as fs;
 use roead::byml::Byml;
 use uk_content::{prelude::Resource, resource::SaveDataPack};
 
@@ -8,9 +7,9 @@ use super::BnpConverter;
 impl BnpConverter {
     pub fn handle_savedata(&self) -> Result<()> {
         let save_path = self.current_root.join("logs/savedata.yml");
-        if save_path.exists() {
+        if self.source.exists(&save_path) {
             log::debug!("Processing savedata log");
-            let mut diff = Byml::from_text(fs::read_to_string(save_path)?)?.into_map()?;
+            let mut diff = Byml::from_text(self.source.read_to_string(&save_path)?)?.into_map()?;
             let base =
                 self.get_from_master_sarc("Pack/Bootup.pack//GameData/savedataformat.ssarc")?;
             if let Ok(mut base) = SaveDataPack::from_binary(base) {
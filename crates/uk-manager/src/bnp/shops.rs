@@ -1,5 +1,4 @@
 use anyhow_ext::{Context, Result};
-use fs_err as fs;
 use rayon::prelude::*;
 use roead::{
     aamp::{get_default_name_table, ParameterIO, ParameterList, ParameterListing},
@@ -145,14 +144,13 @@ fn handle_diff_entry(
 impl BnpConverter {
     pub fn handle_shops(&self) -> Result<()> {
         let shops_path = self.current_root.join("logs/shop.aamp");
-        if shops_path.exists() {
+        if self.source.exists(&shops_path) {
             log::debug!("Processing shops log");
-            let pio = ParameterIO::from_binary(fs::read(shops_path)?)?;
+            let pio = ParameterIO::from_binary(self.source.read(&shops_path)?)?;
             let diff = parse_aamp_diff("Filenames", &pio)?;
             diff.into_par_iter()
                 .try_for_each(|(root, contents)| -> Result<()> {
                     let base_path = self.current_root.join(&root);
-                    base_path.parent().iter().try_for_each(fs::create_dir_all)?;
                     match contents {
                         AampDiffEntry::Sarc(map) => {
                             let mut sarc = self
@@ -168,14 +166,15 @@ impl BnpConverter {
                                     || format!("Failed to process {} in {}", nest_root, root),
                                 )
                             })?;
-                            fs::write(&base_path, compress_if(&sarc.to_binary(), &root))?;
+                            self.source
+                                .write(&base_path, compress_if(&sarc.to_binary(), &root))?;
                         }
                         AampDiffEntry::Aamp(plist) => {
                             let mut pio = ParameterIO::from_binary(
                                 self.get_master_bytes(self.trim_prefixes(&root))?,
                             )?;
                             pio.param_root = merge_plist(&pio.param_root, &plist);
-                            fs::write(base_path, pio.to_binary())?;
+                            self.source.write(&base_path, pio.to_binary())?;
                         }
                     }
                     Ok(())
@@ -1,5 +1,4 @@
 use anyhow_ext::{Context, Result};
-use fs_err as fs;
 use roead::sarc::SarcWriter;
 use rustc_hash::FxHashMap;
 use smartstring::alias::String;
@@ -12,9 +11,10 @@ pub(crate) type TextsLog = FxHashMap<Language, FxHashMap<String, FxHashMap<Strin
 impl BnpConverter {
     pub fn handle_texts(&self) -> Result<()> {
         let texts_path = self.current_root.join("logs/texts.json");
-        if texts_path.exists() {
+        if self.source.exists(&texts_path) {
             log::debug!("Processing texts log");
-            let mut diff: TextsLog = serde_json::from_str(&fs::read_to_string(texts_path)?)?;
+            let mut diff: TextsLog =
+                serde_json::from_str(&self.source.read_to_string(&texts_path)?)?;
             if diff.is_empty() {
                 log::debug!("Empty text diff, moving on");
                 return Ok(());
@@ -59,12 +59,11 @@ impl BnpConverter {
                     .current_root
                     .join(self.content)
                     .join(self.game_lang.bootup_path().as_str());
-                out.parent().iter().try_for_each(fs::create_dir_all)?;
                 let mut sarc = SarcWriter::new(self.platform.into()).with_file(
                     format!("Message/Msg_{}.product.ssarc", self.game_lang),
                     roead::yaz0::compress(texts.into_binary(self.platform.into())),
                 );
-                fs::write(out, sarc.to_binary())?;
+                self.source.write(&out, sarc.to_binary())?;
             }
         }
         Ok(())
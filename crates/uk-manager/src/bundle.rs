@@ -0,0 +1,287 @@
+//! Portable profile bundles, which hold a profile's load order and mod states
+//! in a single ZIP, optionally along with the mods themselves, so that an
+//! exact setup can be shared and restored on another machine.
+use std::{
+    io::{BufReader, Read, Write},
+    path::{Path, PathBuf},
+};
+
+use anyhow_ext::{Context, Result};
+use fs_err as fs;
+use serde::{Deserialize, Serialize};
+use serde_with::{serde_as, DisplayFromStr};
+use smartstring::alias::String;
+use uk_mod::{Meta, ModOptionGroup, ModPlatform};
+use zip::{write::SimpleFileOptions, CompressionMethod, ZipArchive, ZipWriter};
+
+use crate::{
+    core,
+    mods::{Mod, Profile},
+    util,
+};
+
+/// Name of the bundle description inside the ZIP.
+const BUNDLE_FILE: &str = "bundle.yml";
+
+/// A mod as stored in a profile bundle.
+#[serde_as]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BundledMod {
+    pub meta: Meta,
+    /// The mod's ID, which is a hash of its meta.
+    #[serde_as(as = "DisplayFromStr")]
+    pub hash: usize,
+    pub enabled: bool,
+    /// Paths of the selected options.
+    pub enabled_options: Vec<PathBuf>,
+    /// Path of the mod file inside the bundle, if it was included.
+    pub file: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProfileBundle {
+    /// The UKMM version which exported the bundle.
+    pub version: String,
+    pub profile: String,
+    /// Every mod in the profile, in load order.
+    pub mods:    Vec<BundledMod>,
+}
+
+/// The outcome of importing a profile bundle.
+#[derive(Debug, Default, Clone, Serialize)]
+pub struct ImportReport {
+    pub profile: String,
+    pub installed: Vec<String>,
+    /// Mods neither included in the bundle nor already installed.
+    pub missing: Vec<String>,
+    /// Mods made for the other platform.
+    pub incompatible: Vec<String>,
+    /// Mods which could not be installed, with the reason.
+    pub failed: Vec<(String, std::string::String)>,
+}
+
+impl ImportReport {
+    #[inline]
+    pub fn is_complete(&self) -> bool {
+        self.missing.is_empty() && self.incompatible.is_empty() && self.failed.is_empty()
+    }
+
+    /// Render the report as Markdown for display in the GUI.
+    pub fn to_markdown(&self) -> std::string::String {
+        use std::fmt::Write;
+        let mut out = std::string::String::new();
+        let _ = writeln!(
+            out,
+            "Imported **{}** mods into profile **{}**\n",
+            self.installed.len(),
+            self.profile
+        );
+        for (heading, mods) in [
+            ("Missing mods", &self.missing),
+            ("Mods for another platform", &self.incompatible),
+        ] {
+            if mods.is_empty() {
+                continue;
+            }
+            let _ = writeln!(out, "## {heading}\n");
+            for name in mods {
+                let _ = writeln!(out, "- {name}");
+            }
+            out.push('\n');
+        }
+        if !self.failed.is_empty() {
+            let _ = writeln!(out, "## Failed to install\n");
+            for (name, reason) in self.failed.iter() {
+                let _ = writeln!(out, "- **{name}**: {reason}");
+            }
+        }
+        out
+    }
+}
+
+/// Export a profile to a bundle at the given path. If `include_mods` is not
+/// set, only the meta and ID of each mod are stored, so importing the bundle
+/// needs the mods to be installed already.
+pub fn export_profile(
+    core: &core::Manager,
+    profile: &str,
+    path: &Path,
+    include_mods: bool,
+) -> Result<ProfileBundle> {
+    let mod_manager = core.mod_manager();
+    let profile: String = profile.into();
+    if !mod_manager.profile_names().any(|p| p == profile) {
+        anyhow_ext::bail!("Profile {profile} does not exist");
+    }
+    let mut zip = ZipWriter::new(fs::File::create(path)?);
+    let opts = SimpleFileOptions::default();
+    let mut mods = vec![];
+    for mod_ in Profile::iter(mod_manager.get_profile(Some(&profile))) {
+        let file = if include_mods && mod_.path.is_file() {
+            let name = mod_
+                .path
+                .file_name()
+                .context("Mod path has no file name")?
+                .to_string_lossy();
+            let zip_path: String = format!("mods/{name}").into();
+            // Mods are already compressed
+            zip.start_file(
+                zip_path.as_str(),
+                opts.compression_method(CompressionMethod::Stored),
+            )?;
+            std::io::copy(&mut fs::File::open(&mod_.path)?, &mut zip)
+                .with_context(|| format!("Failed to add {} to bundle", mod_.meta.name))?;
+            Some(zip_path)
+        } else {
+            if include_mods {
+                log::warn!(
+                    "{} is not stored as a single file, exporting only its meta",
+                    mod_.meta.name
+                );
+            }
+            None
+        };
+        mods.push(BundledMod {
+            hash: mod_.hash(),
+            enabled: mod_.enabled,
+            enabled_options: mod_
+                .enabled_options
+                .iter()
+                .map(|o| o.path.clone())
+                .collect(),
+            file,
+            meta: mod_.meta,
+        });
+    }
+    let bundle = ProfileBundle {
+        version: env!("CARGO_PKG_VERSION").into(),
+        profile,
+        mods,
+    };
+    zip.start_file(BUNDLE_FILE, opts)?;
+    zip.write_all(serde_yaml::to_string(&bundle)?.as_bytes())?;
+    zip.finish()?;
+    log::info!(
+        "Exported profile {} with {} mods to {}",
+        bundle.profile,
+        bundle.mods.len(),
+        path.display()
+    );
+    Ok(bundle)
+}
+
+/// Import a profile bundle as a new profile, named after the exported profile
+/// unless another name is given. Mods made for the other platform are
+/// skipped, and mods not included in the bundle are taken from other profiles
+/// if they are installed there.
+pub fn import_profile(
+    core: &core::Manager,
+    path: &Path,
+    name: Option<&str>,
+) -> Result<ImportReport> {
+    let mut zip = ZipArchive::new(BufReader::new(fs::File::open(path)?))
+        .context("Failed to open profile bundle")?;
+    let bundle: ProfileBundle = {
+        let mut text = std::string::String::new();
+        zip.by_name(BUNDLE_FILE)
+            .context("Not a profile bundle")?
+            .read_to_string(&mut text)?;
+        serde_yaml::from_str(&text).context("Failed to parse profile bundle")?
+    };
+    let profile: String = name.map(String::from).unwrap_or_else(|| bundle.profile.clone());
+    let platform = ModPlatform::Specific(core.settings().current_mode.into());
+    let mod_manager = core.mod_manager();
+    let profiles = mod_manager.profile_names().collect::<Vec<_>>();
+    if profiles.contains(&profile) {
+        anyhow_ext::bail!("Profile {profile} already exists");
+    }
+    let installed = profiles
+        .iter()
+        .flat_map(|p| Profile::iter(mod_manager.get_profile(Some(p))).collect::<Vec<_>>())
+        .collect::<Vec<_>>();
+    let tmp = util::get_temp_folder().clone();
+    mod_manager.create_profile_if(&profile)?;
+    let mut report = ImportReport {
+        profile: profile.clone(),
+        ..Default::default()
+    };
+    for bundled in bundle.mods.iter() {
+        let mod_name = bundled.meta.name.clone();
+        if bundled.meta.platform != ModPlatform::Universal && bundled.meta.platform != platform {
+            log::warn!("{mod_name} is for {}, skipping it", bundled.meta.platform);
+            report.incompatible.push(mod_name);
+            continue;
+        }
+        // Work out the options before installing anything, so a mod whose
+        // options cannot be resolved is reported without being added.
+        let options = bundled
+            .enabled_options
+            .iter()
+            .filter_map(|path| {
+                bundled
+                    .meta
+                    .options
+                    .iter()
+                    .flat_map(|g| g.options().iter())
+                    .find(|o| o.path == *path)
+                    .cloned()
+            })
+            .collect::<Vec<_>>();
+        let resolution = match crate::options::resolve_options(&bundled.meta, &options) {
+            Ok(resolution) => resolution,
+            Err(e) => {
+                log::warn!("Failed to resolve the options of {mod_name}: {e:?}");
+                report.failed.push((mod_name, e.to_string()));
+                continue;
+            }
+        };
+        for issue in resolution.fixed.iter() {
+            log::warn!("Fixed options for {mod_name}: {issue}");
+        }
+        let mod_ = if let Some(file) = bundled.file.as_ref() {
+            let mut install = || -> Result<Mod> {
+                let dest = tmp.join(
+                    Path::new(file.as_str())
+                        .file_name()
+                        .context("Bad mod path in bundle")?,
+                );
+                std::io::copy(&mut zip.by_name(file)?, &mut fs::File::create(&dest)?)
+                    .with_context(|| format!("Failed to extract {mod_name} from bundle"))?;
+                mod_manager.add(&dest, Some(&profile))
+            };
+            match install() {
+                Ok(mod_) => mod_,
+                Err(e) => {
+                    log::warn!("Failed to install {mod_name}: {e:?}");
+                    report.failed.push((mod_name, e.to_string()));
+                    continue;
+                }
+            }
+        } else if let Some(mod_) = installed.iter().find(|m| m.hash() == bundled.hash) {
+            let profile_data = mod_manager.get_profile(Some(&profile));
+            profile_data.mods_mut().insert(mod_.hash(), mod_.clone());
+            profile_data.load_order_mut().push(mod_.hash());
+            mod_.clone()
+        } else {
+            log::warn!("{mod_name} is not in the bundle or installed, skipping it");
+            report.missing.push(mod_name);
+            continue;
+        };
+        if let Some(imported) = mod_manager
+            .get_profile(Some(&profile))
+            .mods_mut()
+            .get_mut(&mod_.hash())
+        {
+            imported.enabled = bundled.enabled;
+            imported.enabled_options = resolution.options;
+        }
+        report.installed.push(mod_name);
+    }
+    mod_manager.save_profile(&profile)?;
+    log::info!(
+        "Imported profile {profile} with {} of {} mods",
+        report.installed.len(),
+        bundle.mods.len()
+    );
+    Ok(report)
+}
@@ -0,0 +1,438 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    path::Path,
+};
+
+use anyhow_ext::{Context, Result};
+use serde::{Deserialize, Serialize};
+use serde_yaml::Value;
+use smartstring::alias::String;
+use uk_content::{
+    constants::Language,
+    resource::{MergeableResource, ResourceData},
+};
+use uk_mod::unpack::ModReader;
+use uk_reader::ResourceReader;
+
+use crate::mods::Mod;
+
+/// How the changes of several mods to the same resource are combined.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConflictKind {
+    /// The mods edit different parts of the resource, so all of their changes
+    /// survive the merge.
+    Merge,
+    /// The mods edit some of the same keys. Every change is merged, but for
+    /// the shared keys the last mod in load order wins.
+    Overlap,
+    /// The resource cannot be merged, so only the copy from the last mod in
+    /// load order is used.
+    Override,
+    /// The resource is merged, but the changes could not be compared to find
+    /// the shared keys, so they may or may not overlap.
+    Unknown,
+}
+
+impl std::fmt::Display for ConflictKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            ConflictKind::Merge => "clean merge",
+            ConflictKind::Overlap => "partial overlap",
+            ConflictKind::Override => "binary override",
+            ConflictKind::Unknown => "not comparable",
+        })
+    }
+}
+
+/// A single resource touched by more than one enabled mod.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Conflict {
+    /// The resource path, including the path inside its SARC if nested, e.g.
+    /// `Actor/Pack/Enemy_Moriblin_Junior.sbactorpack//Actor/ActorLink/Enemy_Moriblin_Junior.bxml`
+    pub resource: String,
+    pub kind:     ConflictKind,
+    /// The names of the mods touching the resource, in load order.
+    pub mods:     Vec<String>,
+    /// The mod whose version takes priority.
+    pub winner:   String,
+    /// For partial overlaps, the keys edited by more than one mod.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub keys:     Vec<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ConflictReport {
+    pub conflicts: Vec<Conflict>,
+}
+
+impl ConflictReport {
+    #[inline(always)]
+    pub fn is_empty(&self) -> bool {
+        self.conflicts.is_empty()
+    }
+
+    pub fn count(&self, kind: ConflictKind) -> usize {
+        self.conflicts.iter().filter(|c| c.kind == kind).count()
+    }
+
+    /// Render the report as Markdown for display in the GUI.
+    pub fn to_markdown(&self) -> std::string::String {
+        use std::fmt::Write;
+        let mut out = std::string::String::new();
+        if self.is_empty() {
+            out.push_str("No enabled mods edit the same resources.\n");
+            return out;
+        }
+        let _ = writeln!(
+            out,
+            "**{}** shared resources: {} clean merges, {} partial overlaps, {} binary overrides, \
+             {} not comparable\n",
+            self.conflicts.len(),
+            self.count(ConflictKind::Merge),
+            self.count(ConflictKind::Overlap),
+            self.count(ConflictKind::Override),
+            self.count(ConflictKind::Unknown),
+        );
+        for (kind, heading) in [
+            (ConflictKind::Override, "Binary overrides"),
+            (ConflictKind::Overlap, "Partial overlaps"),
+            (ConflictKind::Merge, "Clean merges"),
+            (ConflictKind::Unknown, "Not comparable"),
+        ] {
+            if self.count(kind) == 0 {
+                continue;
+            }
+            let _ = writeln!(out, "## {heading}\n");
+            for conflict in self.conflicts.iter().filter(|c| c.kind == kind) {
+                let _ = writeln!(
+                    out,
+                    "- `{}`: {} (**{}** wins)",
+                    conflict.resource,
+                    conflict.mods.join(", "),
+                    conflict.winner
+                );
+                for key in &conflict.keys {
+                    let _ = writeln!(out, "  - `{key}`");
+                }
+            }
+            out.push('\n');
+        }
+        out
+    }
+}
+
+impl std::fmt::Display for ConflictReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.is_empty() {
+            return writeln!(f, "No enabled mods edit the same resources");
+        }
+        for conflict in &self.conflicts {
+            writeln!(
+                f,
+                "{} [{}] {} -> {}",
+                conflict.resource,
+                conflict.kind,
+                conflict.mods.join(", "),
+                conflict.winner
+            )?;
+            for key in &conflict.keys {
+                writeln!(f, "    {key}")?;
+            }
+        }
+        writeln!(
+            f,
+            "{} shared resources: {} clean merges, {} partial overlaps, {} binary overrides, {} \
+             not comparable",
+            self.conflicts.len(),
+            self.count(ConflictKind::Merge),
+            self.count(ConflictKind::Overlap),
+            self.count(ConflictKind::Override),
+            self.count(ConflictKind::Unknown),
+        )
+    }
+}
+
+/// Find every resource touched by more than one of the provided mods, which
+/// are expected to be the enabled mods of a profile in load order. The game
+/// dump, if set up, supplies the stock resources older mods need to be read.
+pub fn find_conflicts(
+    mods: impl IntoIterator<Item = Mod>,
+    dump: Option<&ResourceReader>,
+) -> Result<ConflictReport> {
+    let readers = mods
+        .into_iter()
+        .map(|mod_| {
+            ModReader::open_peek(&mod_.path, mod_.enabled_options.clone())
+                .with_context(|| format!("Failed to open mod {}", mod_.meta.name))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let mut owners: BTreeMap<String, Vec<usize>> = BTreeMap::new();
+    for (i, reader) in readers.iter().enumerate() {
+        for file in reader.manifest.content_files.iter() {
+            let file = match Language::from_path(Path::new(file.as_str())) {
+                Some(lang) if lang.bootup_path() == *file => lang.message_path(),
+                _ => file.clone(),
+            };
+            owners.entry(file).or_default().push(i);
+        }
+        for file in reader.manifest.aoc_files.iter() {
+            let file = if file.starts_with("Aoc/0010/") {
+                file.clone()
+            } else {
+                ["Aoc/0010/", file.as_str()].join("").into()
+            };
+            owners.entry(file).or_default().push(i);
+        }
+    }
+    let names = readers
+        .iter()
+        .map(|reader| reader.meta.name.clone())
+        .collect::<Vec<_>>();
+    let get = |i: usize, path: &Path| -> Result<Vec<ResourceData>> {
+        let stock = dump.and_then(|dump| dump.get_data(path).ok());
+        readers[i].get_resources(path, stock.as_deref())
+    };
+    let mut report = ConflictReport::default();
+    for (file, owners) in owners.into_iter().filter(|(_, o)| o.len() > 1) {
+        let versions = owners
+            .iter()
+            .map(|&i| get(i, Path::new(file.as_str())).map(|res| (i, res)))
+            .collect::<Result<Vec<_>>>();
+        match versions {
+            Ok(versions) => {
+                analyze_resource(
+                    &names,
+                    &|i, path| get(i, path).ok(),
+                    file.as_str(),
+                    versions,
+                    &mut report.conflicts,
+                );
+            }
+            Err(e) => {
+                // A resource which cannot be read should not fail the whole
+                // report
+                log::warn!("Could not compare changes to {file}: {e:?}");
+                let mods = owners.iter().map(|&i| names[i].clone()).collect::<Vec<_>>();
+                report.conflicts.push(Conflict {
+                    resource: file,
+                    kind: ConflictKind::Unknown,
+                    winner: mods.last().cloned().unwrap_or_default(),
+                    mods,
+                    keys: vec![],
+                });
+            }
+        }
+    }
+    log::debug!("{:#?}", &report);
+    Ok(report)
+}
+
+/// Classify a resource from the versions of each mod touching it. `names`
+/// holds the name of each mod by index, and `get` looks up the resources of
+/// a mod by path, for the files nested in SARCs.
+fn analyze_resource(
+    names: &[String],
+    get: &dyn Fn(usize, &Path) -> Option<Vec<ResourceData>>,
+    resource: &str,
+    versions: Vec<(usize, Vec<ResourceData>)>,
+    conflicts: &mut Vec<Conflict>,
+) {
+    let mods = versions
+        .iter()
+        .map(|(i, _)| names[*i].clone())
+        .collect::<Vec<_>>();
+    let all = || versions.iter().flat_map(|(_, res)| res.iter());
+    if all().all(|res| matches!(res, ResourceData::Sarc(_))) {
+        // SARCs merge by file, so only the nested files can conflict.
+        let mut nested: BTreeMap<&str, Vec<usize>> = BTreeMap::new();
+        for (i, res) in versions.iter() {
+            for file in res
+                .iter()
+                .filter_map(|r| r.as_sarc())
+                .flat_map(|s| s.files.iter())
+            {
+                let owners = nested.entry(file.as_str()).or_default();
+                if !owners.contains(i) {
+                    owners.push(*i);
+                }
+            }
+        }
+        let aoc = resource.starts_with("Aoc/0010/");
+        for (file, owners) in nested.into_iter().filter(|(_, o)| o.len() > 1) {
+            let path = match (aoc, file.starts_with("Aoc/0010/")) {
+                (true, false) => ["Aoc/0010/", file].join(""),
+                _ => file.to_owned(),
+            };
+            let versions = owners
+                .into_iter()
+                .filter_map(|i| get(i, Path::new(&path)).map(|res| (i, res)))
+                .collect::<Vec<_>>();
+            if versions.len() > 1 {
+                analyze_resource(
+                    names,
+                    get,
+                    &[resource, file].join("//"),
+                    versions,
+                    conflicts,
+                );
+            }
+        }
+        return;
+    }
+    let is_binary = |res: &ResourceData| {
+        matches!(
+            res,
+            ResourceData::Binary(_)
+                | ResourceData::Sarc(_)
+                | ResourceData::Mergeable(MergeableResource::BinaryOverride(_))
+        )
+    };
+    if all().any(is_binary) {
+        let winner = versions
+            .iter()
+            .rev()
+            .find(|(_, res)| res.iter().any(is_binary))
+            .map(|(i, _)| names[*i].clone())
+            .unwrap_or_default();
+        conflicts.push(Conflict {
+            resource: resource.into(),
+            kind: ConflictKind::Override,
+            mods,
+            winner,
+            keys: vec![],
+        });
+        return;
+    }
+    let mut seen: BTreeMap<String, usize> = BTreeMap::new();
+    let mut comparable = true;
+    'versions: for (_, res) in versions.iter() {
+        let mut keys = BTreeSet::new();
+        for res in res.iter().filter_map(|r| r.as_mergeable()) {
+            match serde_yaml::to_value(res) {
+                Ok(value) => collect_keys(&value, String::new(), &mut keys),
+                Err(e) => {
+                    // Some resources, like those holding nested enums, cannot be
+                    // represented in YAML, which should not fail the whole report.
+                    log::warn!("Could not compare changes to {resource}: {e}");
+                    comparable = false;
+                    break 'versions;
+                }
+            }
+        }
+        for key in keys {
+            *seen.entry(key).or_default() += 1;
+        }
+    }
+    let keys = seen
+        .into_iter()
+        .filter_map(|(key, count)| (count > 1).then_some(key))
+        .collect::<Vec<_>>();
+    conflicts.push(Conflict {
+        resource: resource.into(),
+        kind: match (comparable, keys.is_empty()) {
+            (false, _) => ConflictKind::Unknown,
+            (true, true) => ConflictKind::Merge,
+            (true, false) => ConflictKind::Overlap,
+        },
+        winner: mods.last().cloned().unwrap_or_default(),
+        mods,
+        keys: if comparable { keys } else { vec![] },
+    });
+}
+
+/// Collect the paths of all leaf values in a serialized diff. Sequences are
+/// treated as leaves, since they are generally replaced as a whole.
+fn collect_keys(value: &Value, path: String, keys: &mut BTreeSet<String>) {
+    match value {
+        Value::Mapping(map) if !map.is_empty() => {
+            for (key, value) in map {
+                let key: String = match key {
+                    Value::String(s) => s.as_str().into(),
+                    other => {
+                        serde_yaml::to_string(other)
+                            .unwrap_or_default()
+                            .trim()
+                            .into()
+                    }
+                };
+                let path = if path.is_empty() {
+                    key
+                } else {
+                    [path.as_str(), key.as_str()].join(".").into()
+                };
+                collect_keys(value, path, keys);
+            }
+        }
+        Value::Tagged(tagged) => {
+            let tag = tagged.tag.to_string();
+            let path = if path.is_empty() {
+                tag.into()
+            } else {
+                [path.as_str(), tag.as_str()].join(".").into()
+            };
+            collect_keys(&tagged.value, path, keys);
+        }
+        _ => {
+            keys.insert(path);
+        }
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use uk_content::{
+        actor::residents::{ResidentActorData, ResidentActors},
+        util::SortedDeleteMap,
+    };
+
+    use super::*;
+
+    fn residents(actors: &[&str]) -> Vec<ResourceData> {
+        let actors: SortedDeleteMap<String, ResidentActorData> = actors
+            .iter()
+            .map(|name| ((*name).into(), ResidentActorData::default()))
+            .collect();
+        vec![ResourceData::Mergeable(MergeableResource::ResidentActors(
+            Box::new(ResidentActors(actors)),
+        ))]
+    }
+
+    fn analyze(versions: Vec<Vec<ResourceData>>) -> Conflict {
+        let names = ["First".into(), "Second".into()];
+        let mut conflicts = vec![];
+        analyze_resource(
+            &names,
+            &|_, _| None,
+            "Actor/ResidentActors.byml",
+            versions.into_iter().enumerate().collect(),
+            &mut conflicts,
+        );
+        assert_eq!(conflicts.len(), 1);
+        conflicts.remove(0)
+    }
+
+    #[test]
+    fn two_mod_overlap() {
+        let conflict = analyze(vec![
+            residents(&["Enemy_Bokoblin_Junior", "Enemy_Lizalfos_Junior"]),
+            residents(&["Enemy_Bokoblin_Junior", "Enemy_Moriblin_Junior"]),
+        ]);
+        assert_eq!(conflict.kind, ConflictKind::Overlap);
+        assert_eq!(conflict.mods, ["First", "Second"]);
+        assert_eq!(conflict.winner, "Second");
+        assert_eq!(conflict.keys.len(), 1);
+        assert!(conflict.keys[0].ends_with("Enemy_Bokoblin_Junior"));
+    }
+
+    #[test]
+    fn two_mod_clean_merge() {
+        let conflict = analyze(vec![
+            residents(&["Enemy_Lizalfos_Junior"]),
+            residents(&["Enemy_Moriblin_Junior"]),
+        ]);
+        assert_eq!(conflict.kind, ConflictKind::Merge);
+        assert!(conflict.keys.is_empty());
+    }
+}
@@ -0,0 +1,217 @@
+use anyhow_ext::{Context, Result};
+use lenient_semver::Version;
+use serde::Serialize;
+use smartstring::alias::String;
+use uk_mod::Meta;
+
+use crate::{mods::Mod, util::HashMap};
+
+/// Check whether a version satisfies a master version requirement from
+/// [`Meta::masters`]. A bare version, as written by the packager, requires at
+/// least that version with the same major version (like a Cargo `^`
+/// requirement). Comma-separated comparisons using `>=`, `>`, `<=`, `<`, `=`,
+/// `^` and `~` are also supported, as is `*` for any version.
+pub fn version_matches(req: &str, version: &str) -> Result<bool> {
+    let version = Version::parse(version)
+        .map_err(|e| anyhow_ext::anyhow!("{e}"))
+        .with_context(|| format!("Invalid mod version {version}"))?;
+    for part in req.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+        if part == "*" {
+            continue;
+        }
+        let (op, ver) = [">=", "<=", ">", "<", "=", "^", "~"]
+            .into_iter()
+            .find_map(|op| part.strip_prefix(op).map(|v| (op, v.trim())))
+            .unwrap_or(("^", part));
+        let req = Version::parse(ver)
+            .map_err(|e| anyhow_ext::anyhow!("{e}"))
+            .with_context(|| format!("Invalid version requirement {part}"))?;
+        let matches = match op {
+            ">=" => version >= req,
+            "<=" => version <= req,
+            ">" => version > req,
+            "<" => version < req,
+            "=" => version == req,
+            "~" => version >= req && version.major == req.major && version.minor == req.minor,
+            _ => {
+                version >= req
+                    && version.major == req.major
+                    && (req.major > 0 || version.minor == req.minor)
+            }
+        };
+        if !matches {
+            return Ok(false);
+        }
+    }
+    Ok(true)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IssueKind {
+    /// No installed mod matches the master.
+    Missing,
+    /// The master is installed, but its version (included) does not satisfy
+    /// the requirement.
+    WrongVersion(String),
+    Disabled,
+    /// The master is loaded after the mod which depends on it.
+    LoadsBefore,
+}
+
+/// A problem with one of the masters required by a mod.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DependencyIssue {
+    #[serde(rename = "mod")]
+    pub mod_name: String,
+    pub master:   String,
+    pub required: String,
+    pub kind:     IssueKind,
+}
+
+impl std::fmt::Display for DependencyIssue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let Self {
+            mod_name,
+            master,
+            required,
+            kind,
+        } = self;
+        match kind {
+            IssueKind::Missing => {
+                write!(
+                    f,
+                    "{mod_name} requires {master} (version {required}), which is not installed"
+                )
+            }
+            IssueKind::WrongVersion(found) => {
+                write!(
+                    f,
+                    "{mod_name} requires {master} version {required}, but version {found} is \
+                     installed"
+                )
+            }
+            IssueKind::Disabled => write!(f, "{mod_name} requires {master}, which is disabled"),
+            IssueKind::LoadsBefore => {
+                write!(f, "{mod_name} must load after its master {master}")
+            }
+        }
+    }
+}
+
+/// Find the installed mod matching a master, first by hash and then by name,
+/// so that updated masters are still found.
+pub fn find_master<'a>(
+    hash: usize,
+    name: &str,
+    mods: impl Iterator<Item = &'a Mod> + Clone,
+) -> Option<&'a Mod> {
+    mods.clone()
+        .find(|m| m.hash() == hash)
+        .or_else(|| mods.into_iter().find(|m| m.meta.name == name))
+}
+
+/// Check the masters of a mod against a list of installed mods in load order.
+/// If the mod is itself in the list, its masters must also come before it.
+pub fn check_masters(meta: &Meta, mods: &[Mod]) -> Vec<DependencyIssue> {
+    let position = mods.iter().position(|m| m.meta == *meta);
+    meta.masters
+        .iter()
+        .filter_map(|(hash, (name, required))| {
+            let issue = |kind| {
+                Some(DependencyIssue {
+                    mod_name: meta.name.clone(),
+                    master: name.clone(),
+                    required: required.clone(),
+                    kind,
+                })
+            };
+            let Some(master) = find_master(*hash, name, mods.iter()) else {
+                return issue(IssueKind::Missing);
+            };
+            if master.hash() != *hash
+                && !version_matches(required, &master.meta.version).unwrap_or(false)
+            {
+                return issue(IssueKind::WrongVersion(master.meta.version.clone()));
+            }
+            if !master.enabled {
+                return issue(IssueKind::Disabled);
+            }
+            match (position, mods.iter().position(|m| m == master)) {
+                (Some(pos), Some(master_pos)) if master_pos > pos => issue(IssueKind::LoadsBefore),
+                _ => None,
+            }
+        })
+        .collect()
+}
+
+/// Check the masters of every enabled mod in a profile, given in load order.
+pub fn check_profile(mods: &[Mod]) -> Vec<DependencyIssue> {
+    mods.iter()
+        .filter(|m| m.enabled)
+        .flat_map(|m| check_masters(&m.meta, mods))
+        .collect()
+}
+
+/// Find the mods which require the given mod as a master.
+pub fn dependents<'a>(
+    master: &'a Mod,
+    mods: impl IntoIterator<Item = &'a Mod>,
+) -> impl Iterator<Item = &'a Mod> {
+    mods.into_iter().filter(move |m| {
+        *m != master
+            && m.meta
+                .masters
+                .iter()
+                .any(|(hash, (name, _))| *hash == master.hash() || *name == master.meta.name)
+    })
+}
+
+/// Reorder a load order so that every mod comes after its masters, keeping
+/// the existing order as much as possible. Dependency cycles are broken in
+/// favour of the existing order.
+pub fn sort_order(order: &[usize], mods: &HashMap<usize, Mod>) -> Vec<usize> {
+    let masters_of = |hash: &usize| -> Vec<usize> {
+        mods.get(hash)
+            .map(|mod_| {
+                mod_.meta
+                    .masters
+                    .iter()
+                    .filter_map(|(hash, (name, _))| {
+                        find_master(*hash, name, order.iter().filter_map(|h| mods.get(h)))
+                            .map(|m| m.hash())
+                    })
+                    .filter(|h| h != &mod_.hash())
+                    .collect()
+            })
+            .unwrap_or_default()
+    };
+    let masters: HashMap<usize, Vec<usize>> = order.iter().map(|h| (*h, masters_of(h))).collect();
+    let mut remaining = order.to_vec();
+    let mut sorted = Vec::with_capacity(order.len());
+    while !remaining.is_empty() {
+        let next = remaining
+            .iter()
+            .position(|h| masters[h].iter().all(|m| !remaining.contains(m)))
+            .unwrap_or(0);
+        sorted.push(remaining.remove(next));
+    }
+    sorted
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    #[test]
+    fn version_requirements() {
+        use super::version_matches;
+        assert!(version_matches("1.2.0", "1.4.1").unwrap());
+        assert!(!version_matches("1.2.0", "1.1.9").unwrap());
+        assert!(!version_matches("1.2.0", "2.0.0").unwrap());
+        assert!(version_matches(">=1.2, <3", "2.5.0").unwrap());
+        assert!(!version_matches(">=1.2, <3", "3.0.0").unwrap());
+        assert!(version_matches("~0.3.1", "0.3.7").unwrap());
+        assert!(!version_matches("0.3.1", "0.4.0").unwrap());
+        assert!(version_matches("*", "0.0.1").unwrap());
+    }
+}
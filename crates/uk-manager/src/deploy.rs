@@ -1,10 +1,16 @@
 #![allow(clippy::unwrap_used, unstable_name_collisions)]
 
+mod archive;
+mod backup;
 mod folder;
 mod file;
+mod hashes;
 mod pending_log;
+mod report;
+mod staging;
 
 use std::{
+    collections::{BTreeMap, BTreeSet},
     path::{Path, PathBuf},
     sync::{Arc, Weak},
 };
@@ -14,23 +20,33 @@ use dashmap::DashMap;
 use fs_err as fs;
 use join_str::jstr;
 use parking_lot::RwLock;
+use path_slash::PathExt;
 use rayon::prelude::*;
 use roead::yaz0::{compress, decompress};
 use rstb::ResourceSizeTable;
 use serde::{Deserialize, Serialize};
 use smartstring::alias::String;
-use uk_content::{constants::Language, platform_prefixes};
+use uk_content::platform_prefixes;
 use uk_mod::{
-    unpack::{ModReader, ModUnpacker},
+    unpack::{FileChange, ModReader, ModUnpacker},
     Manifest,
 };
 
 use crate::{
     mods,
-    settings::{DeployMethod, Platform, Settings},
+    settings::{DeployConfig, DeployMethod, Platform, Settings, DEFAULT_TARGET},
     util,
 };
+use backup::OutputBackup;
+use folder::Folder;
+use hashes::{DeployedFile, DeployedHashes};
+pub use hashes::{DeployDrift, FolderDrift};
 use pending_log::PendingLog;
+pub use report::MergeReport;
+use staging::Staging;
+
+static RSTB_PATH: &str = "System/Resource/ResourceSizeTable.product.srsizetable";
+static RULES: &str = include_str!("../../../assets/rules.txt");
 
 #[derive(Debug, Default, Serialize, Deserialize)]
 struct OldPendingLog {
@@ -38,31 +54,88 @@ struct OldPendingLog {
     delete: Manifest,
 }
 
+#[inline(always)]
+fn hashes_path(target_dir: &Path) -> PathBuf {
+    target_dir.join("deployed.json")
+}
+
+#[inline(always)]
+fn log_path(target_dir: &Path) -> PathBuf {
+    target_dir.join("pending.yml")
+}
+
+/// Open a mod for merging, passing its content hash on to the merge cache.
+fn open_mod(mod_: &mods::Mod) -> Result<ModReader> {
+    let reader = ModReader::open(&mod_.path, mod_.enabled_options.clone())
+        .with_context(|| jstr!("Failed to open mod: {&mod_.meta.name}"))?;
+    Ok(match mod_.content_hash.as_ref() {
+        Some(hash) => reader.with_content_hash(hash.clone()),
+        None => reader,
+    })
+}
+
+/// The files in a deployed folder which UKMM put there, by path relative to
+/// the folder: those in the deployed file hashes or still waiting to be
+/// deleted.
+fn owned_files(recorded: &BTreeMap<String, DeployedFile>, deletes: &Folder) -> BTreeSet<String> {
+    recorded.keys().cloned().chain(deletes.paths()).collect()
+}
+
+/// The files UKMM has put in the deployed folders of a backup, by path
+/// relative to the content or DLC folder: every merged file, along with those
+/// in the deployed file hashes or still waiting to be deleted.
+fn deployed_files(
+    target_dir: &Path,
+    backup: &OutputBackup,
+    log: &PendingLog,
+    (merged_content, merged_aoc): (&Path, &Path),
+) -> (BTreeSet<String>, BTreeSet<String>) {
+    let recorded = DeployedHashes::load(&hashes_path(target_dir))
+        .for_output(&backup.content_dir, &backup.aoc_dir);
+    let mut content = hashes::list_files(merged_content);
+    content.extend(owned_files(&recorded.content, &log.content_deletes));
+    let mut aoc = hashes::list_files(merged_aoc);
+    aoc.extend(owned_files(&recorded.aoc, &log.aoc_deletes));
+    (content, aoc)
+}
+
+/// Remove everything deployed for a deploy target and put back the files
+/// which were in its output folder before UKMM first deployed there. Returns
+/// whether there was a deployment to undo.
+pub(crate) fn undeploy(target_dir: &Path, merged: (&Path, &Path)) -> Result<bool> {
+    let Some(backup) = OutputBackup::load(target_dir) else {
+        return Ok(false);
+    };
+    log::info!("Restoring original deploy output folder");
+    let log_path = log_path(target_dir);
+    let log = match log_path.exists() {
+        true => Manager::load_log(&log_path),
+        false => PendingLog::default(),
+    };
+    let (content, aoc) = deployed_files(target_dir, &backup, &log, merged);
+    backup.restore(target_dir, RULES, (&content, &aoc))?;
+    let hashes_path = hashes_path(target_dir);
+    if hashes_path.exists() {
+        fs::remove_file(hashes_path).context("Failed to remove deployed file hashes")?;
+    }
+    Ok(true)
+}
+
 #[derive(Debug)]
 pub struct Manager {
     settings: Weak<RwLock<Settings>>,
     mod_manager: Weak<RwLock<mods::Manager>>,
-    pending_log: RwLock<PendingLog>,
+    /// Changes waiting to be deployed, by deploy target
+    pending_logs: RwLock<BTreeMap<String, PendingLog>>,
     //pending_files: RwLock<Manifest>,
     //pending_delete: RwLock<Manifest>,
 }
 
 impl Manager {
-    #[inline(always)]
-    fn log_path(settings: &Settings) -> PathBuf {
-        settings.platform_dir().join("pending.yml")
-    }
-
-    pub fn init(
-        settings: &Arc<RwLock<Settings>>,
-        mod_manager: &Arc<RwLock<mods::Manager>>,
-    ) -> Result<Self> {
-        log::info!("Initializing deployment manager");
-        let pending = match fs::read_to_string(Self::log_path(&settings.read()))
-            .map_err(anyhow_ext::Error::from) {
+    fn load_log(path: &Path) -> PendingLog {
+        match fs::read_to_string(path).map_err(anyhow_ext::Error::from) {
             Ok(text) => {
-                match serde_yaml::from_str::<PendingLog>(&text)
-                {
+                match serde_yaml::from_str::<PendingLog>(&text) {
                     Ok(log) => {
                         if log.has_some() {
                             log::info!("Pending deployment data found");
@@ -103,70 +176,161 @@ impl Manager {
                 log::info!("No files pending deployment");
                 Default::default()
             }
+        }
+    }
+
+    /// Work out everything which needs deploying to a target by comparing
+    /// the merged folder with its output.
+    fn compile_log(settings: &Settings, config: &DeployConfig) -> Result<PendingLog> {
+        let source = settings.merged_dir();
+        let (content, aoc) = platform_prefixes(settings.current_mode.into());
+        let (dest_content, dest_aoc) = config.final_output_paths(settings.current_mode.into());
+        PendingLog::try_from((
+            source.join(content),
+            source.join(aoc),
+            dest_content,
+            dest_aoc,
+        ))
+    }
+
+    pub fn init(
+        settings: &Arc<RwLock<Settings>>,
+        mod_manager: &Arc<RwLock<mods::Manager>>,
+    ) -> Result<Self> {
+        log::info!("Initializing deployment manager");
+        let pending_logs = {
+            let settings = settings.read();
+            settings
+                .platform_config()
+                .into_iter()
+                .flat_map(|c| c.targets())
+                .map(|(name, config)| {
+                    log::info!("Loading pending deployment data for {name}");
+                    let path = log_path(&settings.target_dir(name));
+                    let log = if path.exists() || name == DEFAULT_TARGET {
+                        Self::load_log(&path)
+                    } else {
+                        // A new target needs everything merged so far
+                        Self::compile_log(&settings, config).unwrap_or_else(|e| {
+                            log::warn!("Could not compile pending deployment data:\n{}", &e);
+                            Default::default()
+                        })
+                    };
+                    (name.into(), log)
+                })
+                .collect()
         };
         Ok(Self {
-            settings: Arc::downgrade(settings),
-            mod_manager: Arc::downgrade(mod_manager),
-            pending_log: RwLock::new(pending),
+            settings:     Arc::downgrade(settings),
+            mod_manager:  Arc::downgrade(mod_manager),
+            pending_logs: RwLock::new(pending_logs),
         })
     }
 
+    /// Whether any deploy target has changes waiting to be deployed.
     #[inline]
     pub fn pending(&self) -> bool {
-        self.pending_log.read().has_some()
+        self.pending_logs.read().values().any(PendingLog::has_some)
+    }
+
+    /// Whether a deploy target has changes waiting to be deployed.
+    #[inline]
+    pub fn target_pending(&self, target: &str) -> bool {
+        self.pending_logs
+            .read()
+            .get(target)
+            .is_some_and(PendingLog::has_some)
     }
 
     #[inline]
     pub fn pending_len(&self) -> usize {
-        self.pending_log.read().len()
+        self.pending_logs.read().values().map(PendingLog::len).sum()
+    }
+
+    /// The names of the deploy targets for the current platform.
+    pub fn targets(&self) -> Vec<String> {
+        self.settings
+            .upgrade()
+            .and_then(|s| {
+                s.read()
+                    .platform_config()
+                    .map(|c| c.targets().map(|(name, _)| name.into()).collect())
+            })
+            .unwrap_or_default()
     }
 
     pub fn reset_pending(&self) -> Result<()> {
-        self.pending_log.write().clear();
         let settings = self
             .settings
             .upgrade()
             .expect("YIKES the settings manager is gone");
         let settings = settings.read();
-        let source = settings.merged_dir();
-        let (content, aoc) = platform_prefixes(settings.current_mode.into());
-        let config = settings
+        let platform_config = settings
             .platform_config()
-            .and_then(|c| c.deploy_config.as_ref())
             .context("No deployment config for current platform")?;
-        let (dest_content, dest_aoc) = config.final_output_paths(settings.current_mode.into());
-
-        *self.pending_log.write() = PendingLog::try_from((
-            source.join(content), source.join(aoc), dest_content, dest_aoc
-        ))?;
+        let mut pending_logs = BTreeMap::new();
+        for (name, config) in platform_config.targets() {
+            pending_logs.insert(name.into(), Self::compile_log(&settings, config)?);
+        }
+        *self.pending_logs.write() = pending_logs;
 
         Ok(())
     }
 
     pub fn save(&self) -> Result<()> {
-        fs::write(
-            Self::log_path(&self.settings.upgrade().unwrap().read()),
-            serde_yaml::to_string(&self.pending_log.read().clone())?,
-        )?;
+        let settings = self.settings.upgrade().unwrap();
+        let settings = settings.read();
+        for (name, log) in self.pending_logs.read().iter() {
+            let target_dir = settings.target_dir(name);
+            fs::create_dir_all(&target_dir)?;
+            fs::write(log_path(&target_dir), serde_yaml::to_string(log)?)?;
+        }
         Ok(())
     }
 
+    /// Deploy to every deploy target for the current platform.
     pub fn deploy(&self) -> Result<()> {
+        for target in self.targets() {
+            self.deploy_target(&target)?;
+        }
+        Ok(())
+    }
+
+    /// Deploy to the targets set to deploy automatically, if they have any
+    /// pending changes.
+    pub fn deploy_auto(&self) -> Result<()> {
+        let auto_targets = self
+            .settings
+            .upgrade()
+            .and_then(|s| {
+                s.read().platform_config().map(|c| {
+                    c.targets()
+                        .filter(|(_, config)| config.auto)
+                        .map(|(name, _)| String::from(name))
+                        .collect::<Vec<_>>()
+                })
+            })
+            .unwrap_or_default();
+        for target in auto_targets {
+            if self.target_pending(&target) {
+                self.deploy_target(&target)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Deploy pending changes to one deploy target.
+    pub fn deploy_target(&self, target: &str) -> Result<()> {
         let settings = self
             .settings
             .upgrade()
             .expect("YIKES, the settings manager is gone");
         let settings = settings.read();
-        let mut lang = Language::USen;
-        let mut profile = String::from("");
         let config = settings
             .platform_config()
-            .and_then(|c| {
-                lang = c.language;
-                profile = c.profile.clone();
-                c.deploy_config.as_ref()
-            })
-            .context("No deployment config for current platform")?;
+            .and_then(|c| c.target(target))
+            .with_context(|| format!("No deploy target named {target} for current platform"))?;
+        log::info!("Deploying to {target}");
         log::debug!("Deployment config:\n{:#?}", &config);
 
         // Determine src and dest folders
@@ -174,12 +338,96 @@ impl Manager {
         let src_content  = settings.merged_dir().join(content);
         let src_aoc = settings.merged_dir().join(aoc);
         let (dest_content, dest_aoc) = config.final_output_paths(settings.current_mode.into());
+        let target_dir = settings.target_dir(target);
+        fs::create_dir_all(&target_dir)?;
+        let hashes_path = hashes_path(&target_dir);
+        // Hashes recorded by another deploy method would go stale
+        if (!config.compare_hashes || config.method == DeployMethod::Symlink)
+            && hashes_path.exists()
+        {
+            fs::remove_file(&hashes_path).context("Failed to remove deployed file hashes")?;
+        }
+        if config.method == DeployMethod::Archive {
+            // The hashes record what was last exported, so only a delta export
+            // starts from them
+            let exported_path = target_dir.join("exported.json");
+            let recorded =
+                DeployedHashes::load(&exported_path).for_output(&dest_content, &dest_aoc);
+            let recorded = if config.archive_delta {
+                recorded
+            } else {
+                DeployedHashes {
+                    content_dir: recorded.content_dir,
+                    aoc_dir: recorded.aoc_dir,
+                    ..Default::default()
+                }
+            };
+            let (layout_content, layout_aoc) = DeployConfig {
+                output: PathBuf::new(),
+                ..config.clone()
+            }
+            .final_output_paths(settings.current_mode.into());
+            let export = archive::export(
+                &config.output,
+                config.archive_format,
+                (&src_content, &src_aoc),
+                (&layout_content, &layout_aoc),
+                recorded,
+                (settings.current_mode == Platform::WiiU && config.cemu_rules).then_some(RULES),
+            )
+            .context("Failed to export deployment archive")?;
+            if let Some(path) = export.path.as_ref() {
+                log::info!("Deployment archive written to {}", path.display());
+            }
+            export.hashes.save(&exported_path)?;
+            self.pending_logs
+                .write()
+                .entry(target.into())
+                .or_default()
+                .clear();
+            drop(settings);
+            self.save()?;
+            return Ok(());
+        }
         // Remove old behavior
         if util::is_symlink(&config.output) {
             log::info!("Removing old symlink deployment behavior");
             util::remove_symlink(&config.output)
                 .context("Failed to remove old deployment behavior symlink")?;
         }
+        // Set aside anything in the output which was there before UKMM
+        match OutputBackup::load(&target_dir) {
+            Some(backup) if backup.is_for(&dest_content, &dest_aoc) => (),
+            old_backup => {
+                let log = self
+                    .pending_logs
+                    .read()
+                    .get(target)
+                    .cloned()
+                    .unwrap_or_default();
+                if let Some(old_backup) = old_backup {
+                    log::info!("Deploy output has moved, restoring the old output folder");
+                    let (content, aoc) =
+                        deployed_files(&target_dir, &old_backup, &log, (&src_content, &src_aoc));
+                    old_backup.restore(&target_dir, RULES, (&content, &aoc))?;
+                }
+                let backup = OutputBackup::take(
+                    &target_dir,
+                    &config.output,
+                    &log,
+                    (&src_content, &dest_content),
+                    (&src_aoc, &dest_aoc),
+                )
+                .context("Failed to back up files in the deploy output")?;
+                if !backup.is_empty() {
+                    log::info!("Backed up {} files from the deploy output", backup.len());
+                    // The merged copies of any moved files need deploying again
+                    self.pending_logs
+                        .write()
+                        .insert(target.into(), Self::compile_log(&settings, config)?);
+                }
+            }
+        }
 
         if config.method == DeployMethod::Symlink {
             log::info!("Deploy method is symlink, checking for symlink");
@@ -243,54 +491,187 @@ impl Manager {
                 std::fs::create_dir_all(&dest_aoc)?;
             }
 
-            let log = self.pending_log.read();
+            let log = self
+                .pending_logs
+                .read()
+                .get(target)
+                .cloned()
+                .unwrap_or_default();
             log::debug!("Pending log:\n{:#?}", &log);
             log::info!("Deploying by {}", match config.method {
                 DeployMethod::Copy => "copy",
                 DeployMethod::HardLink => "hard links",
                 DeployMethod::Symlink => unsafe { std::hint::unreachable_unchecked() },
+                DeployMethod::Archive => unreachable!("archives are exported before this"),
             });
             log::info!("Deploy layout: {}", config.layout.name());
 
             log.content_deletes.delete(&dest_content)?;
             log.aoc_deletes.delete(&dest_aoc)?;
 
-            match config.method {
-                DeployMethod::Copy => {
-                    log.content_copies.copy(&src_content, &dest_content)?;
-                    log.aoc_copies.copy(&src_aoc, &dest_aoc)?;
-                },
-                DeployMethod::HardLink => {
-                    log.content_copies.hard_link(&src_content, &dest_content)?;
-                    log.aoc_copies.hard_link(&src_aoc, &dest_aoc)?;
-                },
-                DeployMethod::Symlink => unsafe { std::hint::unreachable_unchecked() },
+            if config.compare_hashes {
+                log::info!("Comparing content hashes with the deployed files");
+                let recorded =
+                    DeployedHashes::load(&hashes_path).for_output(&dest_content, &dest_aoc);
+                let (content_hashes, content_drift) =
+                    hashes::compare(&src_content, &dest_content, &recorded.content, false)?;
+                let (aoc_hashes, aoc_drift) =
+                    hashes::compare(&src_aoc, &dest_aoc, &recorded.aoc, false)?;
+                log::debug!(
+                    "Deployment changes:\n{:#?}\n{:#?}",
+                    &content_drift,
+                    &aoc_drift
+                );
+                hashes::repair(
+                    &src_content,
+                    &dest_content,
+                    &content_drift,
+                    &owned_files(&recorded.content, &log.content_deletes),
+                    config.method,
+                )?;
+                hashes::repair(
+                    &src_aoc,
+                    &dest_aoc,
+                    &aoc_drift,
+                    &owned_files(&recorded.aoc, &log.aoc_deletes),
+                    config.method,
+                )?;
+                DeployedHashes {
+                    content: content_hashes,
+                    aoc: aoc_hashes,
+                    ..recorded
+                }
+                .save(&hashes_path)?;
+            } else {
+                match config.method {
+                    DeployMethod::Copy => {
+                        log.content_copies.copy(&src_content, &dest_content)?;
+                        log.aoc_copies.copy(&src_aoc, &dest_aoc)?;
+                    },
+                    DeployMethod::HardLink => {
+                        log.content_copies.hard_link(&src_content, &dest_content)?;
+                        log.aoc_copies.hard_link(&src_aoc, &dest_aoc)?;
+                    },
+                    DeployMethod::Symlink => unsafe { std::hint::unreachable_unchecked() },
+                    DeployMethod::Archive => unreachable!("archives are exported before this"),
+                }
             }
 
             log::info!("Deployment complete");
         }
         let rules_path = dest_content.parent().unwrap().join("rules.txt");
-        if settings.current_mode == Platform::WiiU
-            && settings
-                .platform_config()
-                .and_then(|c| c.deploy_config.as_ref().map(|c| c.cemu_rules))
-                .unwrap_or(false)
-            && !rules_path.exists()
-        {
-            fs::write(rules_path, include_str!("../../../assets/rules.txt"))?;
+        if settings.current_mode == Platform::WiiU && config.cemu_rules && !rules_path.exists() {
+            fs::write(rules_path, RULES)?;
         }
-        self.pending_log.write().clear();
+        self.pending_logs
+            .write()
+            .entry(target.into())
+            .or_default()
+            .clear();
+        drop(settings);
         self.save()?;
         Ok(())
     }
 
-    fn handle_orphans(
-        &self,
-        total_manifest: Manifest,
+    /// Undo every deployment to the output folder of a deploy target, putting
+    /// back the files which were there before UKMM first deployed to it.
+    /// Returns whether there was a deployment to undo.
+    pub fn restore_output(&self, target: &str) -> Result<bool> {
+        let settings = self
+            .settings
+            .upgrade()
+            .context("YIKES, the settings manager is gone")?;
+        let restored = {
+            let settings = settings.read();
+            let (content, aoc) = platform_prefixes(settings.current_mode.into());
+            let merged = settings.merged_dir();
+            undeploy(
+                &settings.target_dir(target),
+                (&merged.join(content), &merged.join(aoc)),
+            )?
+        };
+        if restored {
+            // Everything merged now needs deploying again
+            self.reset_pending()?;
+            self.save()?;
+        }
+        Ok(restored)
+    }
+
+    /// Hash every merged file and its deployed copy to find where the
+    /// deployed output has drifted from the merged folder, such as files
+    /// edited, deleted or left behind outside of UKMM, optionally repairing
+    /// the output to match.
+    pub fn verify_deployment(&self, target: &str, repair: bool) -> Result<DeployDrift> {
+        let settings = self
+            .settings
+            .upgrade()
+            .context("YIKES, the settings manager is gone")?;
+        let settings = settings.read();
+        let config = settings
+            .platform_config()
+            .and_then(|c| c.target(target))
+            .with_context(|| format!("No deploy target named {target} for current platform"))?;
+        if matches!(config.method, DeployMethod::Symlink | DeployMethod::Archive) {
+            log::info!("Deploy method does not copy files, nothing to verify");
+            return Ok(DeployDrift::default());
+        }
+        let (content, aoc) = platform_prefixes(settings.current_mode.into());
+        let src_content = settings.merged_dir().join(content);
+        let src_aoc = settings.merged_dir().join(aoc);
+        let (dest_content, dest_aoc) = config.final_output_paths(settings.current_mode.into());
+        let hashes_path = hashes_path(&settings.target_dir(target));
+        let recorded = DeployedHashes::load(&hashes_path).for_output(&dest_content, &dest_aoc);
+        let (content_hashes, content_drift) =
+            hashes::compare(&src_content, &dest_content, &recorded.content, true)?;
+        let (aoc_hashes, aoc_drift) = hashes::compare(&src_aoc, &dest_aoc, &recorded.aoc, true)?;
+        let mut drift = DeployDrift {
+            content: content_drift,
+            aoc: aoc_drift,
+            repaired: false,
+        };
+        if repair && !drift.is_empty() {
+            log::info!("Repairing deployed files");
+            // Extra files UKMM did not deploy stay in the report
+            let log = self
+                .pending_logs
+                .read()
+                .get(target)
+                .cloned()
+                .unwrap_or_default();
+            hashes::repair(
+                &src_content,
+                &dest_content,
+                &drift.content,
+                &owned_files(&recorded.content, &log.content_deletes),
+                config.method,
+            )?;
+            hashes::repair(
+                &src_aoc,
+                &dest_aoc,
+                &drift.aoc,
+                &owned_files(&recorded.aoc, &log.aoc_deletes),
+                config.method,
+            )?;
+            drift.repaired = true;
+        }
+        if config.compare_hashes && (drift.repaired || drift.is_empty()) {
+            DeployedHashes {
+                content: content_hashes,
+                aoc: aoc_hashes,
+                ..recorded
+            }
+            .save(&hashes_path)?;
+        }
+        Ok(drift)
+    }
+
+    /// Split the files in the change manifest which no longer belong to any
+    /// mod out of it.
+    fn take_orphans(
+        total_manifest: &Manifest,
         manifest: &mut Manifest,
-        out_dir: &Path,
-        platform: Platform,
-    ) -> Result<()> {
+    ) -> (Vec<String>, Vec<String>) {
         let (orphans_content, orphans_aoc): (Vec<_>, Vec<_>) = (
             manifest
                 .content_files
@@ -303,6 +684,21 @@ impl Manager {
                 .cloned()
                 .collect(),
         );
+        manifest
+            .content_files
+            .retain(|f| !orphans_content.contains(f));
+        manifest.aoc_files.retain(|f| !orphans_aoc.contains(f));
+        (orphans_content, orphans_aoc)
+    }
+
+    fn handle_orphans(
+        deletes: &mut Manifest,
+        total_manifest: Manifest,
+        manifest: &mut Manifest,
+        out_dir: &Path,
+        platform: Platform,
+    ) -> Result<()> {
+        let (orphans_content, orphans_aoc) = Self::take_orphans(&total_manifest, manifest);
         if orphans_content.is_empty() && orphans_aoc.is_empty() {
             log::debug!("No orphans");
             return Ok(());
@@ -312,15 +708,10 @@ impl Manager {
             &orphans_content,
             &orphans_aoc
         );
-        manifest
-            .content_files
-            .retain(|f| !orphans_content.contains(f));
-        manifest.aoc_files.retain(|f| !orphans_aoc.contains(f));
-        let mut log = self.pending_log.write();
-        log.extend_deletes(&Manifest {
+        deletes.extend(&Manifest {
             content_files: orphans_content.iter().map(|s| s.clone()).collect(),
             aoc_files: orphans_aoc.iter().map(|s| s.clone()).collect(),
-        })?;
+        });
         let (content, dlc) = platform_prefixes(platform.into());
         for (dir, orphans) in [(content, orphans_content), (dlc, orphans_aoc)] {
             let out_dir = out_dir.join(dir);
@@ -344,17 +735,8 @@ impl Manager {
         Ok(())
     }
 
-    fn apply_rstb(
-        &self,
-        merged: &Path,
-        platform: Platform,
-        updates: DashMap<String, Option<u32>>,
-    ) -> Result<()> {
-        static RSTB_PATH: &str = "System/Resource/ResourceSizeTable.product.srsizetable";
-        log::debug!("RSTB updates:\n{:#?}", &updates);
-        let content = uk_content::platform_content(platform.into());
-        let table_path = merged.join(content).join(RSTB_PATH);
-        let mut table = if table_path.exists() {
+    fn load_rstb(table_path: &Path, platform: Platform) -> Result<ResourceSizeTable> {
+        Ok(if table_path.exists() {
             log::debug!("Updating existing merged RSTB");
             ResourceSizeTable::from_binary(
                 decompress(fs::read(&table_path).context("Failed to open merged RSTB")?)
@@ -364,7 +746,18 @@ impl Manager {
         } else {
             log::debug!("Creating new RSTB");
             ResourceSizeTable::new_from_stock(platform.into())
-        };
+        })
+    }
+
+    fn apply_rstb(
+        merged: &Path,
+        platform: Platform,
+        updates: DashMap<String, Option<u32>>,
+    ) -> Result<()> {
+        log::debug!("RSTB updates:\n{:#?}", &updates);
+        let content = uk_content::platform_content(platform.into());
+        let table_path = merged.join(content).join(RSTB_PATH);
+        let mut table = Self::load_rstb(&table_path, platform)?;
         for (canon, size) in updates {
             match size {
                 Some(size) => {
@@ -379,28 +772,70 @@ impl Manager {
         }
         log::info!("Updated RSTB");
         fs::create_dir_all(table_path.parent().unwrap())?;
+        // The merged folder may be staged with hard links to the current one
+        if table_path.exists() {
+            fs::remove_file(&table_path).context("Failed to replace merged RSTB")?;
+        }
         fs::write(table_path, compress(table.to_binary(platform.into())))
             .context("Failed to write merged RSTB")?;
-        self.pending_log.write().add_rstb()?;
         Ok(())
     }
 
+    /// Apply changes to the merged folder, or remerge every mod if no
+    /// manifest of changes is provided. The changes are built in a staging
+    /// copy of the merged folder which only replaces it once they have all
+    /// been applied, so if anything fails the previous merge and pending
+    /// deployment logs are left as they were.
     pub fn apply(&self, manifest: Option<Manifest>) -> Result<()> {
-        let mod_manager = self
-            .mod_manager
-            .upgrade()
-            .context("YIKES, the mod manager system is gone")?;
         let settings = self
             .settings
             .upgrade()
             .context("YIKES, the settings manager is gone")?;
         let settings = settings.try_read()
             .context("Could not read settings")?;
+        let merged_dir = settings.merged_dir();
+        let cache_path = settings.profile_dir().join("merge_cache.json");
+        let staging = Staging::new(&settings.profile_dir());
+        staging
+            .begin(&merged_dir, &cache_path)
+            .context("Failed to stage merged files")?;
+        let mut copies = Manifest::default();
+        let mut deletes = Manifest::default();
+        if let Err(e) = self.apply_staged(&settings, manifest, &staging, &mut copies, &mut deletes)
+        {
+            log::warn!("Failed to apply changes, keeping the previous merge");
+            staging.discard();
+            return Err(e);
+        }
+        staging.commit(&merged_dir, &cache_path)?;
+        for log in self.pending_logs.write().values_mut() {
+            log.extend_deletes(&deletes)?;
+            log.extend_copies(&copies)?;
+            log.add_rstb()?;
+        }
+        drop(settings);
+        self.save()?;
+        log::info!("All changed applied successfully");
+        Ok(())
+    }
+
+    fn apply_staged(
+        &self,
+        settings: &Settings,
+        manifest: Option<Manifest>,
+        staging: &Staging,
+        copies: &mut Manifest,
+        deletes: &mut Manifest,
+    ) -> Result<()> {
+        let mod_manager = self
+            .mod_manager
+            .upgrade()
+            .context("YIKES, the mod manager system is gone")?;
         let dump = settings
             .dump()
             .context("No dump available for current platform")?;
         let endian = settings.current_mode.into();
-        let out_dir = settings.merged_dir();
+        let out_dir = staging.merged_dir();
         let unpacker = if let Some(mut manifest) = manifest {
             log::info!("Manifest provided, applying limited changes");
             let mut total_manifest = Manifest::default();
@@ -408,19 +843,19 @@ impl Manager {
                 .read()
                 .mods_by_manifest(&manifest)
                 .map(|m| {
-                    ModReader::open(&m.path, m.enabled_options.clone())
+                    open_mod(&m)
                         .inspect(|m| total_manifest.extend(&m.manifest))
-                        .with_context(|| jstr!("Failed to open mod: {&m.meta.name}"))
                 })
                 .collect::<Result<Vec<_>>>()?;
-            self.handle_orphans(
+            Self::handle_orphans(
+                deletes,
                 total_manifest,
                 &mut manifest,
                 &out_dir,
                 settings.current_mode,
             )?;
             log::debug!("Change manifest: {:#?}", &manifest);
-            self.pending_log.write().extend_copies(&manifest)?;
+            copies.extend(&manifest);
             ModUnpacker::new(
                 dump,
                 endian,
@@ -436,13 +871,19 @@ impl Manager {
                 .read()
                 .mods()
                 .map(|m| {
-                    ModReader::open(&m.path, m.enabled_options.clone())
+                    open_mod(&m)
                         .inspect(|m| total_manifest.extend(&m.manifest))
-                        .with_context(|| jstr!("Failed to open mod: {&m.meta.name}"))
                 })
                 .collect::<Result<Vec<_>>>()?;
-            util::remove_dir_all(&out_dir).context("Failed to clear merged folder")?;
-            self.pending_log.write().extend_copies(&total_manifest)?;
+            // Unchanged files are reused from the merge cache and the rest are
+            // removed by the unpacker, but the RSTB must start from stock.
+            let rstb = out_dir
+                .join(uk_content::platform_content(endian))
+                .join(RSTB_PATH);
+            if rstb.exists() {
+                fs::remove_file(rstb).context("Failed to remove merged RSTB")?;
+            }
+            copies.extend(&total_manifest);
             ModUnpacker::new(
                 dump,
                 endian,
@@ -452,10 +893,118 @@ impl Manager {
             )
         };
         log::info!("Applying changes");
-        let rstb_updates = unpacker.unpack()?;
-        self.apply_rstb(&out_dir, settings.current_mode, rstb_updates)?;
-        self.save()?;
-        log::info!("All changed applied successfully");
+        let rstb_updates = unpacker.with_cache(staging.cache_path()).unpack()?;
+        Self::apply_rstb(&out_dir, settings.current_mode, rstb_updates)?;
         Ok(())
     }
+
+    /// Work out what [`apply`](Self::apply) would do with the same manifest,
+    /// without touching the merged folder or the pending deployment log.
+    pub fn apply_dry_run(&self, manifest: Option<Manifest>) -> Result<MergeReport> {
+        let mod_manager = self
+            .mod_manager
+            .upgrade()
+            .context("YIKES, the mod manager system is gone")?;
+        let settings = self
+            .settings
+            .upgrade()
+            .context("YIKES, the settings manager is gone")?;
+        let settings = settings.try_read().context("Could not read settings")?;
+        let dump = settings
+            .dump()
+            .context("No dump available for current platform")?;
+        let endian = settings.current_mode.into();
+        let out_dir = settings.merged_dir();
+        let (content, dlc) = platform_prefixes(endian);
+        let mods = match manifest.as_ref() {
+            Some(manifest) => {
+                mod_manager
+                    .read()
+                    .mods_by_manifest(manifest)
+                    .collect::<Vec<_>>()
+            }
+            None => mod_manager.read().mods().collect::<Vec<_>>(),
+        };
+        let mut total_manifest = Manifest::default();
+        let mods = mods
+            .into_iter()
+            .map(|m| {
+                open_mod(&m)
+                    .inspect(|m| total_manifest.extend(&m.manifest))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let mut report = MergeReport::default();
+        let full = manifest.is_none();
+        let unpacker = ModUnpacker::new(
+            dump,
+            endian,
+            settings
+                .platform_config()
+                .context("No config for platform")?
+                .language,
+            mods,
+            out_dir.clone(),
+        )
+        .with_cache(settings.profile_dir().join("merge_cache.json"));
+        let plan = if let Some(mut manifest) = manifest {
+            let (orphans_content, orphans_aoc) = Self::take_orphans(&total_manifest, &mut manifest);
+            report.orphaned.extend(
+                orphans_content
+                    .into_iter()
+                    .map(|f| jstr!("{content}/{&f}").into())
+                    .chain(orphans_aoc.into_iter().map(|f| jstr!("{dlc}/{&f}").into())),
+            );
+            unpacker.with_manifest(manifest).plan()?
+        } else {
+            let plan = unpacker.plan()?;
+            // A full remerge removes anything in the merged folder which it
+            // does not rebuild.
+            let mut rebuilt = plan
+                .files
+                .iter()
+                .map(|f| f.path.clone())
+                .collect::<BTreeSet<String>>();
+            rebuilt.insert(jstr!("{content}/{RSTB_PATH}").into());
+            report.orphaned.extend(
+                jwalk::WalkDir::new(&out_dir)
+                    .into_iter()
+                    .filter_map(Result::ok)
+                    .filter(|e| e.file_type.is_file())
+                    .filter_map(|e| {
+                        let path = e.path();
+                        let rel: String = path.strip_prefix(&out_dir).ok()?.to_slash_lossy().into();
+                        (!rebuilt.contains(&rel)).then_some(rel)
+                    }),
+            );
+            plan
+        };
+        for file in plan.files {
+            match file.change {
+                FileChange::Create => report.created.push(file),
+                FileChange::Change => report.changed.push(file),
+                FileChange::Unchanged => report.unchanged += 1,
+            }
+        }
+        let table = if full {
+            ResourceSizeTable::new_from_stock(settings.current_mode.into())
+        } else {
+            Self::load_rstb(
+                &out_dir.join(content).join(RSTB_PATH),
+                settings.current_mode,
+            )?
+        };
+        for (canon, size) in plan.rstb {
+            match (table.get(canon.as_str()), size) {
+                (None, Some(size)) => {
+                    report.rstb_added.insert(canon, size);
+                }
+                (Some(old), Some(size)) if old != size => {
+                    report.rstb_changed.insert(canon, (old, size));
+                }
+                (Some(_), None) => report.rstb_removed.push(canon),
+                _ => (),
+            }
+        }
+        Ok(report)
+    }
 }
@@ -0,0 +1,265 @@
+use std::{
+    collections::BTreeMap,
+    io::Write,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use anyhow_ext::{Context, Result};
+use fs_err as fs;
+use path_slash::PathExt;
+use smartstring::alias::String;
+use zip::{write::SimpleFileOptions, ZipWriter};
+
+use crate::{
+    deploy::hashes::{hash_merged, DeployedFile, DeployedHashes},
+    settings::ArchiveFormat,
+};
+
+/// Name of the list of files to delete in a delta archive, one path per line.
+pub(crate) const DELETIONS_FILE: &str = "ukmm_deletions.txt";
+
+enum ArchiveWriter {
+    Zip(ZipWriter<fs::File>),
+    Tar(tar::Builder<fs::File>),
+}
+
+impl ArchiveWriter {
+    fn create(path: &Path, format: ArchiveFormat) -> Result<Self> {
+        let file = fs::File::create(path)?;
+        Ok(match format {
+            ArchiveFormat::Zip => Self::Zip(ZipWriter::new(file)),
+            ArchiveFormat::Tar => Self::Tar(tar::Builder::new(file)),
+        })
+    }
+
+    fn add_file(&mut self, name: &str, source: &Path) -> Result<()> {
+        match self {
+            Self::Zip(zip) => {
+                zip.start_file(name, SimpleFileOptions::default())?;
+                std::io::copy(&mut fs::File::open(source)?, zip)?;
+            }
+            Self::Tar(tar) => tar.append_path_with_name(source, name)?,
+        }
+        Ok(())
+    }
+
+    fn add_data(&mut self, name: &str, data: &[u8]) -> Result<()> {
+        match self {
+            Self::Zip(zip) => {
+                zip.start_file(name, SimpleFileOptions::default())?;
+                zip.write_all(data)?;
+            }
+            Self::Tar(tar) => {
+                let mut header = tar::Header::new_gnu();
+                header.set_size(data.len() as u64);
+                header.set_mode(0o644);
+                header.set_cksum();
+                tar.append_data(&mut header, name, data)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn finish(self) -> Result<()> {
+        match self {
+            Self::Zip(zip) => {
+                zip.finish()?;
+            }
+            Self::Tar(tar) => {
+                tar.into_inner()?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The outcome of exporting an archive.
+#[derive(Debug)]
+pub(crate) struct ArchiveExport {
+    /// The archive written, if there was anything to put in it
+    pub path:   Option<PathBuf>,
+    /// The hashes of the merged files as exported
+    pub hashes: DeployedHashes,
+}
+
+/// Path of a file inside the archive, from its folder in the deployed layout.
+fn archive_path(dir: &Path, rel: &str) -> String {
+    dir.join(rel).to_slash_lossy().into()
+}
+
+/// Write the merged files into an archive in the output folder, laid out as
+/// they would be deployed. Files whose hashes match the `recorded` ones from
+/// the last export are left out, and recorded files which are no longer
+/// merged are listed in [`DELETIONS_FILE`], so an empty record makes a full
+/// archive. `layout` gives the content and DLC folders inside the archive.
+pub(crate) fn export(
+    output: &Path,
+    format: ArchiveFormat,
+    (merged_content, merged_aoc): (&Path, &Path),
+    (content_dir, aoc_dir): (&Path, &Path),
+    recorded: DeployedHashes,
+    rules: Option<&str>,
+) -> Result<ArchiveExport> {
+    let delta = !recorded.content.is_empty() || !recorded.aoc.is_empty();
+    let content = hash_merged(merged_content, &recorded.content)
+        .context("Failed to hash merged content files")?;
+    let aoc = hash_merged(merged_aoc, &recorded.aoc).context("Failed to hash merged DLC files")?;
+    let changed = |hashes: &BTreeMap<String, DeployedFile>,
+                   recorded: &BTreeMap<String, DeployedFile>| {
+        hashes
+            .iter()
+            .filter(|(rel, file)| recorded.get(*rel).map(|r| &r.hash) != Some(&file.hash))
+            .map(|(rel, _)| rel.clone())
+            .collect::<Vec<_>>()
+    };
+    let files = [
+        (
+            merged_content,
+            content_dir,
+            changed(&content, &recorded.content),
+        ),
+        (merged_aoc, aoc_dir, changed(&aoc, &recorded.aoc)),
+    ];
+    let deleted = [
+        (content_dir, &recorded.content, &content),
+        (aoc_dir, &recorded.aoc, &aoc),
+    ]
+    .into_iter()
+    .flat_map(|(dir, recorded, hashes)| {
+        recorded
+            .keys()
+            .filter(|rel| !hashes.contains_key(*rel))
+            .map(move |rel| archive_path(dir, rel))
+    })
+    .collect::<Vec<_>>();
+    let hashes = DeployedHashes {
+        content,
+        aoc,
+        ..recorded
+    };
+    if files.iter().all(|(_, _, files)| files.is_empty()) && deleted.is_empty() {
+        log::info!("No changes since the last export");
+        return Ok(ArchiveExport { path: None, hashes });
+    }
+
+    fs::create_dir_all(output)?;
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default();
+    let path = output.join(format!(
+        "BreathOfTheWild_UKMM-{timestamp}{}.{}",
+        if delta { "-delta" } else { "" },
+        format.extension()
+    ));
+    let partial = path.with_extension("part");
+    let mut writer = ArchiveWriter::create(&partial, format)?;
+    let mut count = 0;
+    for (merged, dir, files) in files.iter() {
+        for rel in files {
+            writer
+                .add_file(&archive_path(dir, rel), &merged.join(rel.as_str()))
+                .with_context(|| format!("Failed to add {rel} to archive"))?;
+            count += 1;
+        }
+    }
+    // Cemu rules are only ever added, so a delta never needs them again
+    if let Some(rules) = rules.filter(|_| !delta) {
+        let dir = content_dir.parent().unwrap_or(Path::new(""));
+        writer.add_data(&archive_path(dir, "rules.txt"), rules.as_bytes())?;
+    }
+    if !deleted.is_empty() {
+        writer.add_data(DELETIONS_FILE, deleted.join("\n").as_bytes())?;
+    }
+    writer.finish().context("Failed to finish archive")?;
+    fs::rename(&partial, &path)?;
+    log::info!("Exported {count} files and {} deletions", deleted.len());
+    Ok(ArchiveExport {
+        path: Some(path),
+        hashes,
+    })
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use std::io::Read;
+
+    use super::*;
+
+    fn read_zip(path: &Path) -> BTreeMap<std::string::String, Vec<u8>> {
+        let mut zip = zip::ZipArchive::new(fs::File::open(path).unwrap()).unwrap();
+        (0..zip.len())
+            .map(|i| {
+                let mut file = zip.by_index(i).unwrap();
+                let mut data = vec![];
+                file.read_to_end(&mut data).unwrap();
+                (file.name().to_owned(), data)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn full_and_delta() {
+        let dir = tempfile::tempdir().unwrap();
+        let merged = dir.path().join("merged");
+        let output = dir.path().join("output");
+        let (content, aoc) = (merged.join("content"), merged.join("aoc/0010"));
+        let layout = (
+            Path::new("BreathOfTheWild_UKMM/content"),
+            Path::new("BreathOfTheWild_UKMM/aoc/0010"),
+        );
+        fs::create_dir_all(content.join("Actor")).unwrap();
+        fs::write(content.join("Actor/A.txt"), b"a").unwrap();
+        fs::write(content.join("B.txt"), b"b").unwrap();
+
+        let full = export(
+            &output,
+            ArchiveFormat::Zip,
+            (&content, &aoc),
+            layout,
+            Default::default(),
+            Some("[Definition]"),
+        )
+        .unwrap();
+        let files = read_zip(full.path.as_ref().unwrap());
+        assert_eq!(files.keys().collect::<Vec<_>>(), [
+            "BreathOfTheWild_UKMM/content/Actor/A.txt",
+            "BreathOfTheWild_UKMM/content/B.txt",
+            "BreathOfTheWild_UKMM/rules.txt",
+        ]);
+
+        let unchanged = export(
+            &output,
+            ArchiveFormat::Zip,
+            (&content, &aoc),
+            layout,
+            full.hashes.clone(),
+            None,
+        )
+        .unwrap();
+        assert!(unchanged.path.is_none());
+
+        fs::remove_file(content.join("B.txt")).unwrap();
+        fs::write(content.join("Actor/A.txt"), b"changed").unwrap();
+        let delta = export(
+            &output,
+            ArchiveFormat::Zip,
+            (&content, &aoc),
+            layout,
+            full.hashes,
+            Some("[Definition]"),
+        )
+        .unwrap();
+        let delta_path = delta.path.unwrap();
+        assert!(delta_path.to_string_lossy().ends_with("-delta.zip"));
+        let files = read_zip(&delta_path);
+        assert_eq!(
+            files["BreathOfTheWild_UKMM/content/Actor/A.txt"],
+            b"changed"
+        );
+        assert_eq!(files[DELETIONS_FILE], b"BreathOfTheWild_UKMM/content/B.txt");
+        assert_eq!(files.len(), 2);
+    }
+}
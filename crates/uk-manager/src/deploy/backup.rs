@@ -0,0 +1,284 @@
+use std::{
+    collections::BTreeSet,
+    path::{Path, PathBuf},
+};
+
+use anyhow_ext::{Context, Result};
+use fs_err as fs;
+use serde::{Deserialize, Serialize};
+use smartstring::alias::String;
+
+use crate::{
+    deploy::{hashes::list_files, pending_log::PendingLog},
+    util,
+};
+
+/// Files which were in the deploy output before UKMM first deployed there,
+/// moved aside so that the output folder can be restored to how it was.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub(crate) struct OutputBackup {
+    /// The output folder the deployed folders are in
+    pub output: PathBuf,
+    pub content_dir: PathBuf,
+    pub aoc_dir: PathBuf,
+    /// Backed up files, by path relative to the content or DLC folder
+    pub content: Vec<String>,
+    pub aoc: Vec<String>,
+}
+
+#[inline(always)]
+fn backup_dir(target_dir: &Path) -> PathBuf {
+    target_dir.join("output_backup")
+}
+
+/// Move a file, copying it if it is on another drive.
+fn move_file(from: &Path, to: &Path) -> Result<()> {
+    if let Some(parent) = to.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    if fs::rename(from, to).is_err() {
+        fs::copy(from, to)?;
+        fs::remove_file(from)?;
+    }
+    Ok(())
+}
+
+/// The first link found going up from a deployed folder to the output
+/// folder, if it was deployed by symlink.
+fn deployed_link(dir: &Path, output: &Path) -> Option<PathBuf> {
+    dir.ancestors()
+        .take_while(|d| *d != output && d.starts_with(output))
+        .find(|d| util::is_symlink(d))
+        .map(Path::to_path_buf)
+}
+
+impl OutputBackup {
+    pub fn load(target_dir: &Path) -> Option<Self> {
+        fs::read(backup_dir(target_dir).join("backup.json"))
+            .ok()
+            .and_then(|data| serde_json::from_slice(&data).ok())
+    }
+
+    fn save(&self, target_dir: &Path) -> Result<()> {
+        let dir = backup_dir(target_dir);
+        fs::create_dir_all(&dir)?;
+        fs::write(dir.join("backup.json"), serde_json::to_vec_pretty(self)?)
+            .context("Failed to write deploy output backup")?;
+        Ok(())
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.content.len() + self.aoc.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.content.is_empty() && self.aoc.is_empty()
+    }
+
+    /// Whether the backup was taken from the given deployed folders.
+    pub fn is_for(&self, content_dir: &Path, aoc_dir: &Path) -> bool {
+        self.content_dir == content_dir && self.aoc_dir == aoc_dir
+    }
+
+    /// Move every file in the deployed folders which UKMM did not put there
+    /// into a backup. Files matching the merged copy are left in place, as
+    /// they were deployed by an earlier version or would be overwritten with
+    /// the same contents anyway, and so are those the pending log still has
+    /// to copy or delete.
+    pub fn take(
+        target_dir: &Path,
+        output: &Path,
+        log: &PendingLog,
+        (merged_content, content_dir): (&Path, &Path),
+        (merged_aoc, aoc_dir): (&Path, &Path),
+    ) -> Result<Self> {
+        let mut backup = Self {
+            output: output.to_path_buf(),
+            content_dir: content_dir.to_path_buf(),
+            aoc_dir: aoc_dir.to_path_buf(),
+            ..Default::default()
+        };
+        let dir = backup_dir(target_dir);
+        for (merged, deployed, name, files, (copies, deletes)) in [
+            (
+                merged_content,
+                content_dir,
+                "content",
+                &mut backup.content,
+                (&log.content_copies, &log.content_deletes),
+            ),
+            (
+                merged_aoc,
+                aoc_dir,
+                "aoc",
+                &mut backup.aoc,
+                (&log.aoc_copies, &log.aoc_deletes),
+            ),
+        ] {
+            if deployed_link(deployed, output).is_some() {
+                continue;
+            }
+            let pending: BTreeSet<String> =
+                copies.paths().into_iter().chain(deletes.paths()).collect();
+            for rel in list_files(deployed) {
+                if pending.contains(&rel) {
+                    continue;
+                }
+                let file = deployed.join(rel.as_str());
+                let merged_file = merged.join(rel.as_str());
+                if merged_file.exists()
+                    && util::content_hash(&merged_file)? == util::content_hash(&file)?
+                {
+                    continue;
+                }
+                log::debug!("Backing up {}", file.display());
+                move_file(&file, &dir.join(name).join(rel.as_str()))
+                    .with_context(|| format!("Failed to back up {}", file.display()))?;
+                files.push(rel);
+            }
+        }
+        backup.save(target_dir)?;
+        Ok(backup)
+    }
+
+    /// Remove the files UKMM deployed to the output folder, given by path
+    /// relative to the content and DLC folders, and put the backed up files
+    /// back where they were, removing the backup. Anything else in the output
+    /// is left alone.
+    pub fn restore(
+        self,
+        target_dir: &Path,
+        rules: &str,
+        (content_files, aoc_files): (&BTreeSet<String>, &BTreeSet<String>),
+    ) -> Result<()> {
+        let dir = backup_dir(target_dir);
+        for (deployed, name, files, deployed_files) in [
+            (&self.content_dir, "content", &self.content, content_files),
+            (&self.aoc_dir, "aoc", &self.aoc, aoc_files),
+        ] {
+            if let Some(link) = deployed_link(deployed, &self.output) {
+                util::remove_symlink(&link)
+                    .with_context(|| format!("Failed to remove link at {}", link.display()))?;
+            } else {
+                for rel in deployed_files {
+                    let file = deployed.join(rel.as_str());
+                    if !file.is_file() {
+                        continue;
+                    }
+                    fs::remove_file(&file)
+                        .with_context(|| format!("Failed to remove {}", file.display()))?;
+                    let mut parent = file;
+                    while parent.pop() && parent.starts_with(deployed) && parent != *deployed {
+                        let empty =
+                            std::fs::read_dir(&parent).is_ok_and(|mut d| d.next().is_none());
+                        if !empty {
+                            break;
+                        }
+                        fs::remove_dir(&parent)?;
+                    }
+                }
+            }
+            for rel in files {
+                let file = deployed.join(rel.as_str());
+                move_file(&dir.join(name).join(rel.as_str()), &file)
+                    .with_context(|| format!("Failed to restore {}", file.display()))?;
+            }
+        }
+        // Cemu rules are only ever added, never overwritten
+        if let Some(rules_path) = self.content_dir.parent().map(|p| p.join("rules.txt")) {
+            if fs::read_to_string(&rules_path).is_ok_and(|text| text == rules) {
+                fs::remove_file(rules_path)?;
+            }
+        }
+        // Clear out the folders which only held deployed files
+        for deployed in [&self.content_dir, &self.aoc_dir] {
+            for parent in deployed
+                .ancestors()
+                .take_while(|d| *d != self.output && d.starts_with(&self.output))
+            {
+                if !parent.exists() {
+                    continue;
+                }
+                let empty = std::fs::read_dir(parent).is_ok_and(|mut d| d.next().is_none());
+                if !empty {
+                    break;
+                }
+                fs::remove_dir(parent)?;
+            }
+        }
+        util::remove_dir_all(dir).context("Failed to remove deploy output backup")?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn take_and_restore() {
+        let dir = tempfile::tempdir().unwrap();
+        let target_dir = dir.path().join("wiiu");
+        let merged = dir.path().join("merged/content");
+        let output = dir.path().join("output");
+        let content = output.join("BreathOfTheWild_UKMM/content");
+        let aoc = output.join("BreathOfTheWild_UKMM/aoc/0010");
+        fs::create_dir_all(merged.join("Actor")).unwrap();
+        fs::create_dir_all(content.join("Actor")).unwrap();
+        fs::write(merged.join("Actor/A.txt"), b"merged").unwrap();
+        fs::write(content.join("Actor/A.txt"), b"merged").unwrap();
+        fs::write(merged.join("B.txt"), b"merged").unwrap();
+        fs::write(content.join("B.txt"), b"mine").unwrap();
+        fs::write(content.join("C.txt"), b"mine").unwrap();
+        fs::write(output.join("notes.txt"), b"mine").unwrap();
+        // Deployed before, and still waiting to be deleted
+        fs::write(content.join("F.txt"), b"old").unwrap();
+        let mut log = PendingLog::default();
+        log.content_deletes.extend(["F.txt".into()].into()).unwrap();
+
+        let backup = OutputBackup::take(
+            &target_dir,
+            &output,
+            &log,
+            (&merged, &content),
+            (&dir.path().join("merged/aoc/0010"), &aoc),
+        )
+        .unwrap();
+        assert_eq!(backup.content, vec![String::from("B.txt"), "C.txt".into()]);
+        assert!(!content.join("B.txt").exists());
+        assert!(content.join("Actor/A.txt").exists());
+        assert!(content.join("F.txt").exists());
+
+        // Deploy over the top of it
+        fs::write(content.join("B.txt"), b"merged").unwrap();
+        fs::create_dir_all(&aoc).unwrap();
+        fs::write(aoc.join("D.txt"), b"merged").unwrap();
+        let rules = "[Definition]";
+        fs::write(output.join("BreathOfTheWild_UKMM/rules.txt"), rules).unwrap();
+
+        // Added after the backup was taken, so not ours to remove
+        fs::create_dir_all(content.join("Map")).unwrap();
+        fs::write(content.join("Map/E.txt"), b"mine").unwrap();
+
+        let backup = OutputBackup::load(&target_dir).unwrap();
+        assert!(backup.is_for(&content, &aoc));
+        let content_files: BTreeSet<String> =
+            ["Actor/A.txt".into(), "B.txt".into(), "F.txt".into()].into();
+        let aoc_files: BTreeSet<String> = ["D.txt".into()].into();
+        backup
+            .restore(&target_dir, rules, (&content_files, &aoc_files))
+            .unwrap();
+        assert_eq!(fs::read(content.join("B.txt")).unwrap(), b"mine");
+        assert_eq!(fs::read(content.join("C.txt")).unwrap(), b"mine");
+        assert_eq!(fs::read(content.join("Map/E.txt")).unwrap(), b"mine");
+        assert!(!content.join("Actor").exists());
+        assert!(!content.join("F.txt").exists());
+        assert!(!output.join("BreathOfTheWild_UKMM/aoc").exists());
+        assert!(!output.join("BreathOfTheWild_UKMM/rules.txt").exists());
+        assert!(output.join("notes.txt").exists());
+        assert!(OutputBackup::load(&target_dir).is_none());
+    }
+}
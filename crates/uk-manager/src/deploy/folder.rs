@@ -225,6 +225,18 @@ impl Folder {
         Ok(())
     }
 
+    /// The path of every file in the folder and its subfolders.
+    pub fn paths(&self) -> Vec<String> {
+        self.files.iter()
+            .map(|file| file.name().into())
+            .chain(self.folders.iter().flat_map(|(folder_name, folder)| {
+                folder.paths()
+                    .into_iter()
+                    .map(move |path| format!("{folder_name}/{path}").into())
+            }))
+            .collect()
+    }
+
     pub fn len(&self) -> usize {
         self.files.len() + self.folders.par_iter().map(|(_, v)| v.len()).sum::<usize>()
     }
@@ -0,0 +1,322 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use anyhow_ext::{Context, Result};
+use fs_err as fs;
+use path_slash::PathExt;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use smartstring::alias::String;
+
+use crate::{deploy::file::File, settings::DeployMethod, util};
+
+/// The hash of one deployed file, along with the size and modified time of
+/// the merged copy it was hashed from, so that a merged file which has not
+/// been touched since does not need to be hashed again.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct DeployedFile {
+    pub hash: std::string::String,
+    pub len: u64,
+    pub modified: Option<SystemTime>,
+}
+
+/// A manifest of the content hashes of every file in the deployed output, by
+/// path relative to the content or DLC folder.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub(crate) struct DeployedHashes {
+    /// The deployed folders the hashes were recorded for
+    pub content_dir: PathBuf,
+    pub aoc_dir: PathBuf,
+    pub content: BTreeMap<String, DeployedFile>,
+    pub aoc: BTreeMap<String, DeployedFile>,
+}
+
+impl DeployedHashes {
+    /// Load the manifest, falling back to an empty one if it is missing or
+    /// unreadable.
+    pub fn load(path: &Path) -> Self {
+        fs::read(path)
+            .map_err(anyhow_ext::Error::from)
+            .and_then(|data| {
+                serde_json::from_slice(&data).context("Failed to parse deployed file hashes")
+            })
+            .unwrap_or_else(|e| {
+                log::debug!("No usable deployed file hashes: {e}");
+                Self::default()
+            })
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        fs::write(path, serde_json::to_vec(self)?)
+            .context("Failed to write deployed file hashes")?;
+        Ok(())
+    }
+
+    /// The recorded hashes, if they are for the given deployed folders.
+    pub fn for_output(self, content_dir: &Path, aoc_dir: &Path) -> Self {
+        if self.content_dir == content_dir && self.aoc_dir == aoc_dir {
+            self
+        } else {
+            Self {
+                content_dir: content_dir.to_path_buf(),
+                aoc_dir: aoc_dir.to_path_buf(),
+                ..Default::default()
+            }
+        }
+    }
+}
+
+/// Differences between the merged files and their deployed copies, by path
+/// relative to the content or DLC folder.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct FolderDrift {
+    /// Merged files which have not been deployed.
+    pub missing: Vec<String>,
+    /// Deployed files whose contents do not match the merged copy.
+    pub changed: Vec<String>,
+    /// Deployed files with no merged copy.
+    pub extra:   Vec<String>,
+}
+
+impl FolderDrift {
+    pub fn is_empty(&self) -> bool {
+        self.missing.is_empty() && self.changed.is_empty() && self.extra.is_empty()
+    }
+}
+
+/// Differences between the merged folder and the deployed output, found by
+/// [`Manager::verify_deployment`](super::Manager::verify_deployment).
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct DeployDrift {
+    pub content: FolderDrift,
+    pub aoc: FolderDrift,
+    /// Whether the deployed output was repaired to match the merged folder.
+    pub repaired: bool,
+}
+
+impl DeployDrift {
+    pub fn is_empty(&self) -> bool {
+        self.content.is_empty() && self.aoc.is_empty()
+    }
+}
+
+impl std::fmt::Display for DeployDrift {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (folder, drift) in [("content", &self.content), ("aoc", &self.aoc)] {
+            for file in &drift.missing {
+                writeln!(f, "missing  {folder}/{file}")?;
+            }
+            for file in &drift.changed {
+                writeln!(f, "changed  {folder}/{file}")?;
+            }
+            for file in &drift.extra {
+                writeln!(f, "extra    {folder}/{file}")?;
+            }
+        }
+        writeln!(
+            f,
+            "{} missing, {} changed, {} extra{}",
+            self.content.missing.len() + self.aoc.missing.len(),
+            self.content.changed.len() + self.aoc.changed.len(),
+            self.content.extra.len() + self.aoc.extra.len(),
+            if self.repaired { "; repaired" } else { "" }
+        )
+    }
+}
+
+/// Every relative file path under a folder, if it exists.
+pub(crate) fn list_files(dir: &Path) -> BTreeSet<String> {
+    if !dir.exists() {
+        return Default::default();
+    }
+    jwalk::WalkDir::new(dir)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file())
+        .filter_map(|e| {
+            e.path()
+                .strip_prefix(dir)
+                .ok()
+                .map(|rel| rel.to_slash_lossy().into())
+        })
+        .collect()
+}
+
+/// Hash every file in a merged folder, reusing the recorded hash of any file
+/// whose size and modified time have not changed.
+pub(crate) fn hash_merged(
+    dir: &Path,
+    recorded: &BTreeMap<String, DeployedFile>,
+) -> Result<BTreeMap<String, DeployedFile>> {
+    list_files(dir)
+        .into_par_iter()
+        .map(|rel| -> Result<(String, DeployedFile)> {
+            let path = dir.join(rel.as_str());
+            let meta = fs::metadata(&path)?;
+            let len = meta.len();
+            let modified = meta.modified().ok();
+            let unchanged = recorded
+                .get(&rel)
+                .filter(|file| file.len == len && file.modified == modified && modified.is_some());
+            let hash = match unchanged {
+                Some(file) => file.hash.clone(),
+                None => util::content_hash(&path)?,
+            };
+            Ok((rel, DeployedFile {
+                hash,
+                len,
+                modified,
+            }))
+        })
+        .collect()
+}
+
+/// Compare a merged folder with its deployed copy, returning the hashes of
+/// the merged files and the drift between them. Deployed files are trusted
+/// to match the recorded hashes of the last deployment unless `check_all` is
+/// set, in which case every one of them is hashed.
+pub(crate) fn compare(
+    merged: &Path,
+    deployed: &Path,
+    recorded: &BTreeMap<String, DeployedFile>,
+    check_all: bool,
+) -> Result<(BTreeMap<String, DeployedFile>, FolderDrift)> {
+    let hashes = hash_merged(merged, recorded)
+        .with_context(|| format!("Failed to hash merged files in {}", merged.display()))?;
+    let deployed_files = list_files(deployed);
+    let mut drift = FolderDrift {
+        extra: deployed_files
+            .iter()
+            .filter(|f| !hashes.contains_key(*f))
+            .cloned()
+            .collect(),
+        ..Default::default()
+    };
+    let checked = hashes
+        .par_iter()
+        .map(|(rel, file)| -> Result<Option<(bool, String)>> {
+            if !deployed_files.contains(rel) {
+                return Ok(Some((true, rel.clone())));
+            }
+            let matches = match recorded.get(rel) {
+                Some(recorded) if !check_all => recorded.hash == file.hash,
+                _ => util::content_hash(&deployed.join(rel.as_str()))? == file.hash,
+            };
+            Ok((!matches).then(|| (false, rel.clone())))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    for (missing, rel) in checked.into_iter().flatten() {
+        if missing {
+            drift.missing.push(rel);
+        } else {
+            drift.changed.push(rel);
+        }
+    }
+    drift.missing.sort();
+    drift.changed.sort();
+    Ok((hashes, drift))
+}
+
+/// Bring a deployed folder in line with the merged folder by deleting extra
+/// files and copying or linking the missing and changed ones. Only extra
+/// files listed in `owned` are deleted, so that files put in the output by
+/// something else are left alone.
+pub(crate) fn repair(
+    merged: &Path,
+    deployed: &Path,
+    drift: &FolderDrift,
+    owned: &BTreeSet<String>,
+    method: DeployMethod,
+) -> Result<()> {
+    let extra = drift
+        .extra
+        .iter()
+        .filter(|rel| owned.contains(*rel))
+        .collect::<Vec<_>>();
+    extra.par_iter().try_for_each(|rel| -> Result<()> {
+        let path = deployed.join(rel.as_str());
+        fs::remove_file(&path)
+            .with_context(|| format!("Failed to delete file {}", path.display()))?;
+        Ok(())
+    })?;
+    for rel in extra {
+        let mut parent = deployed.join(rel.as_str());
+        while parent.pop() && parent.starts_with(deployed) && parent != deployed {
+            let empty = std::fs::read_dir(&parent).is_ok_and(|mut d| d.next().is_none());
+            if !empty {
+                break;
+            }
+            fs::remove_dir(&parent)?;
+        }
+    }
+    drift
+        .missing
+        .par_iter()
+        .chain(drift.changed.par_iter())
+        .try_for_each(|rel| -> Result<()> {
+            if let Some(parent) = deployed.join(rel.as_str()).parent() {
+                fs::create_dir_all(parent)?;
+            }
+            let file = File::from(rel.clone());
+            let (merged, deployed) = (merged.to_path_buf(), deployed.to_path_buf());
+            match method {
+                DeployMethod::HardLink => file.hard_link(&merged, &deployed),
+                _ => file.copy(&merged, &deployed),
+            }
+        })?;
+    Ok(())
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn compare_and_repair() {
+        let dir = tempfile::tempdir().unwrap();
+        let merged = dir.path().join("merged");
+        let deployed = dir.path().join("deployed");
+        fs::create_dir_all(merged.join("Actor/Pack")).unwrap();
+        fs::create_dir_all(deployed.join("Map")).unwrap();
+        fs::write(merged.join("Actor/Pack/A.sbactorpack"), b"new").unwrap();
+        fs::write(merged.join("B.txt"), b"same").unwrap();
+        fs::write(deployed.join("B.txt"), b"same").unwrap();
+        fs::write(merged.join("C.txt"), b"merged").unwrap();
+        fs::write(deployed.join("C.txt"), b"edited").unwrap();
+        fs::write(deployed.join("Map/D.smubin"), b"old").unwrap();
+        let (hashes, drift) = compare(&merged, &deployed, &Default::default(), false).unwrap();
+        assert_eq!(hashes.len(), 3);
+        assert_eq!(drift, FolderDrift {
+            missing: vec!["Actor/Pack/A.sbactorpack".into()],
+            changed: vec!["C.txt".into()],
+            extra:   vec!["Map/D.smubin".into()],
+        });
+
+        // Only extra files from the last deployment are removed by a deploy
+        let recorded: BTreeMap<String, DeployedFile> =
+            [("Map/D.smubin".into(), hashes["B.txt"].clone())].into();
+        fs::write(deployed.join("E.txt"), b"other").unwrap();
+        let (_, drift) = compare(&merged, &deployed, &recorded, false).unwrap();
+        let owned: BTreeSet<String> = recorded.keys().cloned().collect();
+        repair(&merged, &deployed, &drift, &owned, DeployMethod::Copy).unwrap();
+        assert!(!deployed.join("Map").exists());
+        assert!(deployed.join("E.txt").exists());
+        let (_, drift) = compare(&merged, &deployed, &hashes, true).unwrap();
+        assert_eq!(drift.extra, vec![String::from("E.txt")]);
+        let owned: BTreeSet<String> = [String::from("E.txt")].into();
+        repair(&merged, &deployed, &drift, &owned, DeployMethod::Copy).unwrap();
+        let (_, drift) = compare(&merged, &deployed, &hashes, true).unwrap();
+        assert!(drift.is_empty());
+
+        // A deployed file edited behind our back is only caught when checked
+        fs::write(deployed.join("B.txt"), b"drift").unwrap();
+        let (_, drift) = compare(&merged, &deployed, &hashes, false).unwrap();
+        assert!(drift.is_empty());
+        let (_, drift) = compare(&merged, &deployed, &hashes, true).unwrap();
+        assert_eq!(drift.changed, vec![String::from("B.txt")]);
+    }
+}
@@ -0,0 +1,123 @@
+use std::collections::BTreeMap;
+
+use serde::Serialize;
+use smartstring::alias::String;
+use uk_mod::unpack::PlannedFile;
+
+/// A summary of what applying changes to the merged folder would do, built by
+/// [`Manager::apply_dry_run`](super::Manager::apply_dry_run) without writing
+/// anything.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct MergeReport {
+    /// Files that do not exist in the merged folder yet.
+    pub created: Vec<PlannedFile>,
+    /// Files that exist in the merged folder but would be rebuilt with
+    /// different contents.
+    pub changed: Vec<PlannedFile>,
+    /// Files that would be deleted because no enabled mod provides them anymore.
+    pub orphaned: Vec<String>,
+    /// The number of files that would be rebuilt with identical contents.
+    pub unchanged: usize,
+    pub rstb_added: BTreeMap<String, u32>,
+    /// RSTB entries which would be given a different size, as (old, new)
+    /// sizes.
+    pub rstb_changed: BTreeMap<String, (u32, u32)>,
+    pub rstb_removed: Vec<String>,
+}
+
+impl MergeReport {
+    pub fn is_empty(&self) -> bool {
+        self.created.is_empty()
+            && self.changed.is_empty()
+            && self.orphaned.is_empty()
+            && self.rstb_added.is_empty()
+            && self.rstb_changed.is_empty()
+            && self.rstb_removed.is_empty()
+    }
+
+    /// Render the report as Markdown for display in the GUI.
+    pub fn to_markdown(&self) -> std::string::String {
+        use std::fmt::Write;
+        let mut out = std::string::String::new();
+        if self.is_empty() {
+            out.push_str("No changes would be made to the merged files.\n");
+            return out;
+        }
+        let _ = writeln!(
+            out,
+            "**{}** created, **{}** changed, **{}** orphaned, {} unchanged\n",
+            self.created.len(),
+            self.changed.len(),
+            self.orphaned.len(),
+            self.unchanged
+        );
+        for (heading, files) in [("Created", &self.created), ("Changed", &self.changed)] {
+            if files.is_empty() {
+                continue;
+            }
+            let _ = writeln!(out, "## {heading}\n");
+            for file in files {
+                let _ = writeln!(out, "- `{}`: {}", file.path, file.mods.join(", "));
+            }
+            out.push('\n');
+        }
+        if !self.orphaned.is_empty() {
+            out.push_str("## Orphaned\n\n");
+            for file in &self.orphaned {
+                let _ = writeln!(out, "- `{file}`");
+            }
+            out.push('\n');
+        }
+        if !(self.rstb_added.is_empty()
+            && self.rstb_changed.is_empty()
+            && self.rstb_removed.is_empty())
+        {
+            out.push_str("## RSTB\n\n");
+            for (name, size) in &self.rstb_added {
+                let _ = writeln!(out, "- Add `{name}`: {size}");
+            }
+            for (name, (old, new)) in &self.rstb_changed {
+                let _ = writeln!(out, "- Change `{name}`: {old} → {new}");
+            }
+            for name in &self.rstb_removed {
+                let _ = writeln!(out, "- Remove `{name}`");
+            }
+        }
+        out
+    }
+}
+
+impl std::fmt::Display for MergeReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for file in &self.created {
+            writeln!(f, "create  {} ({})", file.path, file.mods.join(", "))?;
+        }
+        for file in &self.changed {
+            writeln!(f, "change  {} ({})", file.path, file.mods.join(", "))?;
+        }
+        for file in &self.orphaned {
+            writeln!(f, "orphan  {file}")?;
+        }
+        for (name, size) in &self.rstb_added {
+            writeln!(f, "rstb +  {name} = {size}")?;
+        }
+        for (name, (old, new)) in &self.rstb_changed {
+            writeln!(f, "rstb ~  {name} {old} -> {new}")?;
+        }
+        for name in &self.rstb_removed {
+            writeln!(f, "rstb -  {name}")?;
+        }
+        writeln!(
+            f,
+            "{} created, {} changed, {} orphaned, {} unchanged; RSTB: {} added, {} changed, {} \
+             removed",
+            self.created.len(),
+            self.changed.len(),
+            self.orphaned.len(),
+            self.unchanged,
+            self.rstb_added.len(),
+            self.rstb_changed.len(),
+            self.rstb_removed.len()
+        )
+    }
+}
@@ -0,0 +1,140 @@
+use std::path::{Path, PathBuf};
+
+use anyhow_ext::{Context, Result};
+use fs_err as fs;
+
+use crate::util;
+
+/// A copy of the merged folder and merge cache for changes to be applied to,
+/// which is only swapped in once they have all been applied, so that a failed
+/// apply leaves the current merge untouched.
+///
+/// Staged files are hard links to the merged ones where possible, so they
+/// must be replaced rather than written over.
+#[derive(Debug)]
+pub(crate) struct Staging {
+    root: PathBuf,
+}
+
+impl Staging {
+    pub fn new(profile_dir: &Path) -> Self {
+        Self {
+            root: profile_dir.join("staging"),
+        }
+    }
+
+    #[inline(always)]
+    pub fn merged_dir(&self) -> PathBuf {
+        self.root.join("merged")
+    }
+
+    #[inline(always)]
+    pub fn cache_path(&self) -> PathBuf {
+        self.root.join("merge_cache.json")
+    }
+
+    #[inline(always)]
+    fn previous_dir(&self) -> PathBuf {
+        self.root.join("previous")
+    }
+
+    /// Stage a copy of the merged folder and merge cache, first restoring the
+    /// merged folder if an earlier swap was interrupted and clearing out
+    /// anything left over from it.
+    pub fn begin(&self, merged: &Path, cache: &Path) -> Result<()> {
+        if !merged.exists() && self.previous_dir().exists() {
+            log::warn!("Restoring merged files from an interrupted apply");
+            fs::rename(self.previous_dir(), merged)
+                .context("Failed to restore merged files from an interrupted apply")?;
+        }
+        if self.root.exists() {
+            util::remove_dir_all(&self.root).context("Failed to clear old staging folder")?;
+        }
+        fs::create_dir_all(&self.root)?;
+        if merged.exists() {
+            log::debug!("Staging merged files");
+            if let Err(e) = util::hardlink_dir(merged, self.merged_dir()) {
+                log::debug!("Could not hard link merged files, copying instead: {e:?}");
+                if self.merged_dir().exists() {
+                    util::remove_dir_all(self.merged_dir())?;
+                }
+                util::copy_dir(merged, self.merged_dir())
+                    .context("Failed to stage merged files")?;
+            }
+        }
+        if cache.exists() {
+            fs::copy(cache, self.cache_path()).context("Failed to stage merge cache")?;
+        }
+        Ok(())
+    }
+
+    /// Swap the staged merge in for the current one.
+    pub fn commit(self, merged: &Path, cache: &Path) -> Result<()> {
+        let previous = self.previous_dir();
+        if merged.exists() {
+            fs::rename(merged, &previous).context("Failed to move aside old merged files")?;
+        }
+        if let Err(e) = fs::rename(self.merged_dir(), merged) {
+            if previous.exists() {
+                fs::rename(&previous, merged)?;
+            }
+            return Err(e).context("Failed to swap in new merged files");
+        }
+        // The old cache is only safe to keep if it does not describe the new
+        // merged files, so drop it if the new one can't take its place
+        if self.cache_path().exists() {
+            if let Err(e) = fs::rename(self.cache_path(), cache) {
+                log::warn!("Failed to update merge cache: {e}");
+                fs::remove_file(cache).unwrap_or(());
+            }
+        }
+        self.discard();
+        Ok(())
+    }
+
+    /// Remove the staging folder, keeping the current merge.
+    pub fn discard(&self) {
+        if self.root.exists() {
+            if let Err(e) = util::remove_dir_all(&self.root) {
+                log::warn!("Failed to remove staging folder: {e:?}");
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stage_and_swap() {
+        let dir = tempfile::tempdir().unwrap();
+        let merged = dir.path().join("merged");
+        let cache = dir.path().join("merge_cache.json");
+        fs::create_dir_all(merged.join("content")).unwrap();
+        fs::write(merged.join("content/A.txt"), b"old").unwrap();
+        fs::write(&cache, b"old cache").unwrap();
+
+        let staging = Staging::new(dir.path());
+        staging.begin(&merged, &cache).unwrap();
+        let staged = staging.merged_dir().join("content/A.txt");
+        fs::remove_file(&staged).unwrap();
+        fs::write(&staged, b"new").unwrap();
+        fs::write(staging.cache_path(), b"new cache").unwrap();
+        staging.discard();
+        assert_eq!(fs::read(merged.join("content/A.txt")).unwrap(), b"old");
+        assert_eq!(fs::read(&cache).unwrap(), b"old cache");
+
+        let staging = Staging::new(dir.path());
+        staging.begin(&merged, &cache).unwrap();
+        let staged = staging.merged_dir().join("content/A.txt");
+        fs::remove_file(&staged).unwrap();
+        fs::write(&staged, b"new").unwrap();
+        fs::write(staging.cache_path(), b"new cache").unwrap();
+        staging.commit(&merged, &cache).unwrap();
+        assert_eq!(fs::read(merged.join("content/A.txt")).unwrap(), b"new");
+        assert_eq!(fs::read(&cache).unwrap(), b"new cache");
+        assert!(!dir.path().join("staging").exists());
+    }
+}
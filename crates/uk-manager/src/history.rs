@@ -0,0 +1,225 @@
+//! A bounded, timestamped history of profile states, so that changes to the
+//! load order, mod states and options can be rolled back.
+use std::{
+    collections::VecDeque,
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicBool, Ordering},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use anyhow_ext::Result;
+use fs_err as fs;
+use serde::{Deserialize, Serialize};
+use smartstring::alias::String;
+use uk_mod::Manifest;
+
+use crate::{
+    mods::Profile,
+    util::{HashMap, HashSet},
+};
+
+/// The most states kept for each profile.
+pub const MAX_HISTORY: usize = 50;
+/// Name of the history file in a profile folder.
+pub(crate) const HISTORY_FILE: &str = "history.yml";
+/// Name of the folder in the mods folder which keeps the files of removed or
+/// updated mods for as long as a profile's history refers to them.
+pub(crate) const STASH_DIR: &str = "history";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    /// When the change was made, in seconds since the Unix epoch.
+    pub timestamp: u64,
+    /// A description of the change.
+    pub action:    String,
+    /// The state of the profile before the change.
+    pub state:     Profile,
+}
+
+impl HistoryEntry {
+    pub(crate) fn new(action: String, state: Profile) -> Self {
+        Self {
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or_default(),
+            action,
+            state,
+        }
+    }
+
+    /// How long ago the change was made, for display.
+    pub fn age(&self) -> std::string::String {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default();
+        match now.saturating_sub(self.timestamp) {
+            0..60 => "just now".into(),
+            secs @ 60..3600 => format!("{} min ago", secs / 60),
+            secs @ 3600..86400 => format!("{} h ago", secs / 3600),
+            secs => format!("{} d ago", secs / 86400),
+        }
+    }
+}
+
+/// The result of rolling a profile back to an earlier state.
+#[derive(Debug, Default)]
+pub struct Rollback {
+    /// The files which need to be remerged.
+    pub manifest: Manifest,
+    /// Mods in the restored state whose files could not be restored, and
+    /// which were left out.
+    pub missing:  Vec<String>,
+}
+
+/// Where the file of the mod with the given ID is kept in the stash folder.
+pub(crate) fn stash_path(dir: &Path, hash: usize) -> PathBuf {
+    dir.join(format!("{hash}.zip"))
+}
+
+/// Marks a batch of changes in progress until it is dropped, so the flag is
+/// cleared even if the batch returns early or panics.
+pub(crate) struct BatchGuard<'a>(&'a AtomicBool);
+
+impl<'a> BatchGuard<'a> {
+    pub(crate) fn new(batching: &'a AtomicBool) -> Self {
+        batching.store(true, Ordering::Relaxed);
+        Self(batching)
+    }
+}
+
+impl Drop for BatchGuard<'_> {
+    fn drop(&mut self) {
+        self.0.store(false, Ordering::Relaxed);
+    }
+}
+
+pub(crate) fn load(path: &Path) -> VecDeque<HistoryEntry> {
+    fs::read_to_string(path)
+        .ok()
+        .and_then(|text| {
+            serde_yaml::from_str(&text)
+                .inspect_err(|e| log::warn!("Failed to parse profile history: {e}"))
+                .ok()
+        })
+        .unwrap_or_default()
+}
+
+pub(crate) fn save(path: &Path, history: &VecDeque<HistoryEntry>) -> Result<()> {
+    fs::write(path, serde_yaml::to_string(history)?)?;
+    Ok(())
+}
+
+/// Find the mods whose files may merge differently between two states of a
+/// profile: mods which were added or removed, had their enabled state or
+/// options changed, or moved relative to another mod in the load order.
+pub fn affected_mods(current: &Profile, target: &Profile) -> HashSet<usize> {
+    let (current_mods, target_mods) = (current.mods(), target.mods());
+    let mut affected = HashSet::default();
+    for (hash, mod_) in current_mods.iter() {
+        match target_mods.get(hash) {
+            Some(other) if mod_.state_eq(other) => (),
+            _ => {
+                affected.insert(*hash);
+            }
+        }
+    }
+    affected.extend(
+        target_mods
+            .keys()
+            .filter(|hash| !current_mods.contains_key(hash)),
+    );
+    let positions = target
+        .load_order()
+        .iter()
+        .enumerate()
+        .map(|(i, hash)| (*hash, i))
+        .collect::<HashMap<_, _>>();
+    let common = current
+        .load_order()
+        .iter()
+        .filter_map(|hash| positions.get(hash).map(|pos| (*hash, *pos)))
+        .collect::<Vec<_>>();
+    for (i, (a, pos_a)) in common.iter().enumerate() {
+        for (b, pos_b) in common.iter().skip(i + 1) {
+            if pos_a > pos_b {
+                affected.insert(*a);
+                affected.insert(*b);
+            }
+        }
+    }
+    affected
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use uk_mod::{Meta, ModCategory, ModPlatform};
+
+    use super::*;
+    use crate::mods::Mod;
+
+    fn profile(mods: &[(usize, bool)]) -> Profile {
+        let profile = Profile::default();
+        for (hash, enabled) in mods {
+            profile.mods_mut().insert(*hash, Mod {
+                meta: Meta {
+                    api: env!("CARGO_PKG_VERSION").into(),
+                    name: format!("Mod {hash}").into(),
+                    version: "1.0.0".into(),
+                    author: "Tester".into(),
+                    category: ModCategory::Other,
+                    description: Default::default(),
+                    platform: ModPlatform::Universal,
+                    url: None,
+                    masters: Default::default(),
+                    options: vec![],
+                },
+                enabled_options: vec![],
+                enabled: *enabled,
+                path: format!("{hash}.zip").into(),
+                hash: *hash,
+                content_hash: None,
+                source: None,
+            });
+            profile.load_order_mut().push(*hash);
+        }
+        profile
+    }
+
+    #[test]
+    fn affected() {
+        let current = profile(&[(1, true), (2, true), (3, true)]);
+        assert!(affected_mods(&current, &current.clone()).is_empty());
+        let swapped = profile(&[(1, true), (3, true), (2, true)]);
+        assert_eq!(
+            affected_mods(&current, &swapped),
+            [2, 3].into_iter().collect()
+        );
+        let disabled = profile(&[(1, true), (2, false), (3, true)]);
+        assert_eq!(
+            affected_mods(&current, &disabled),
+            [2].into_iter().collect()
+        );
+        let removed = profile(&[(1, true), (3, true)]);
+        assert_eq!(affected_mods(&current, &removed), [2].into_iter().collect());
+        assert_eq!(affected_mods(&removed, &current), [2].into_iter().collect());
+    }
+
+    #[test]
+    fn batch_guard() {
+        let batching = AtomicBool::new(false);
+        {
+            let _guard = BatchGuard::new(&batching);
+            assert!(batching.load(Ordering::Relaxed));
+        }
+        assert!(!batching.load(Ordering::Relaxed));
+        let result = std::panic::catch_unwind(|| {
+            let _guard = BatchGuard::new(&batching);
+            panic!("Batch failed");
+        });
+        assert!(result.is_err());
+        assert!(!batching.load(Ordering::Relaxed));
+    }
+}
@@ -1,8 +1,15 @@
 #![deny(clippy::unwrap_used)]
 
 pub mod bnp;
+pub mod bundle;
+pub mod conflicts;
 pub mod core;
+pub mod dependencies;
 pub mod deploy;
+pub mod history;
+pub mod lock;
 pub mod mods;
+pub mod options;
 pub mod settings;
 pub mod util;
+pub mod watch;
@@ -0,0 +1,243 @@
+//! Lock snapshots of profiles, which record the exact content of every mod
+//! along with its options and place in the load order, so that a profile can
+//! later be checked for stored mods which were changed or replaced.
+use std::path::PathBuf;
+
+use anyhow_ext::{Context, Result};
+use fs_err as fs;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use serde_with::{serde_as, DisplayFromStr};
+use smartstring::alias::String;
+
+use crate::{
+    mods::{self, Mod, Profile},
+    util,
+};
+
+/// Name of the lock snapshot in a profile folder.
+const LOCK_FILE: &str = "lock.yml";
+
+/// A mod as recorded in a lock snapshot.
+#[serde_as]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LockedMod {
+    pub name: String,
+    pub version: String,
+    /// The mod's ID, which is a hash of its meta.
+    #[serde_as(as = "DisplayFromStr")]
+    pub hash: usize,
+    /// SHA-256 of the stored mod.
+    pub content_hash: String,
+    pub enabled: bool,
+    /// Paths of the selected options.
+    pub options: Vec<PathBuf>,
+}
+
+impl LockedMod {
+    #[inline]
+    fn matches(&self, mod_: &Mod) -> bool {
+        self.hash == mod_.hash() || self.name == mod_.meta.name
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProfileLock {
+    /// The UKMM version which took the snapshot.
+    pub version: String,
+    pub profile: String,
+    /// Every mod in the profile, in load order.
+    pub mods:    Vec<LockedMod>,
+}
+
+/// A difference between a profile and its lock snapshot.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum LockIssue {
+    /// The stored mod no longer has the locked content.
+    Changed {
+        name:     String,
+        path:     PathBuf,
+        expected: String,
+        found:    String,
+    },
+    /// A locked mod was removed, or its stored file is gone.
+    Missing { name: String },
+    /// A mod was added to the profile after the snapshot.
+    Added { name: String },
+    /// A mod was enabled, disabled, or had its options changed.
+    StateChanged { name: String },
+    /// The mods are in a different load order.
+    OrderChanged,
+}
+
+impl std::fmt::Display for LockIssue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LockIssue::Changed { name, path, .. } => {
+                write!(
+                    f,
+                    "{name} at {} has changed or been replaced since the snapshot",
+                    path.display()
+                )
+            }
+            LockIssue::Missing { name } => write!(f, "{name} is no longer installed"),
+            LockIssue::Added { name } => write!(f, "{name} was installed after the snapshot"),
+            LockIssue::StateChanged { name } => {
+                write!(f, "{name} was enabled, disabled or had its options changed")
+            }
+            LockIssue::OrderChanged => write!(f, "The load order has changed"),
+        }
+    }
+}
+
+fn check_profile(mod_manager: &mods::Manager, profile: Option<&String>) -> Result<()> {
+    if let Some(profile) = profile {
+        if !mod_manager.profile_names().any(|p| p == *profile) {
+            anyhow_ext::bail!("Profile {profile} does not exist");
+        }
+    }
+    Ok(())
+}
+
+/// The path of the lock snapshot of a profile, by default the current one.
+#[inline]
+pub fn lock_path(mod_manager: &mods::Manager, profile: Option<&String>) -> PathBuf {
+    mod_manager.profile_path(profile).join(LOCK_FILE)
+}
+
+/// Take a lock snapshot of a profile, replacing any previous one.
+pub fn lock_profile(mod_manager: &mods::Manager, profile: Option<&String>) -> Result<ProfileLock> {
+    check_profile(mod_manager, profile)?;
+    let mods = Profile::iter(mod_manager.get_profile(profile)).collect::<Vec<_>>();
+    let locked = mods
+        .par_iter()
+        .map(|mod_| -> Result<LockedMod> {
+            let content_hash: String = util::content_hash(&mod_.path)
+                .with_context(|| format!("Failed to hash {}", mod_.meta.name))?
+                .into();
+            if mod_
+                .content_hash
+                .as_ref()
+                .is_some_and(|hash| *hash != content_hash)
+            {
+                log::warn!("{} has changed since it was installed", mod_.meta.name);
+            }
+            Ok(LockedMod {
+                name: mod_.meta.name.clone(),
+                version: mod_.meta.version.clone(),
+                hash: mod_.hash(),
+                content_hash,
+                enabled: mod_.enabled,
+                options: mod_
+                    .enabled_options
+                    .iter()
+                    .map(|o| o.path.clone())
+                    .collect(),
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let lock = ProfileLock {
+        version: env!("CARGO_PKG_VERSION").into(),
+        profile: profile
+            .map(|p| p.as_str())
+            .unwrap_or_else(|| mod_manager.current_profile())
+            .into(),
+        mods:    locked,
+    };
+    fs::write(
+        lock_path(mod_manager, profile),
+        serde_yaml::to_string(&lock)?,
+    )?;
+    log::info!(
+        "Saved lock snapshot of profile {} with {} mods",
+        lock.profile,
+        lock.mods.len()
+    );
+    Ok(lock)
+}
+
+/// Load the lock snapshot of a profile.
+pub fn load_lock(mod_manager: &mods::Manager, profile: Option<&String>) -> Result<ProfileLock> {
+    check_profile(mod_manager, profile)?;
+    let path = lock_path(mod_manager, profile);
+    let text = fs::read_to_string(&path).context("The profile has no lock snapshot")?;
+    serde_yaml::from_str(&text)
+        .with_context(|| format!("Failed to parse lock snapshot at {}", path.display()))
+}
+
+/// Check a profile against its lock snapshot, hashing every stored mod to
+/// find any which were changed or replaced. Mods are matched by ID, or by
+/// name if they were updated.
+pub fn verify_lock(
+    mod_manager: &mods::Manager,
+    profile: Option<&String>,
+) -> Result<Vec<LockIssue>> {
+    let lock = load_lock(mod_manager, profile)?;
+    let mods = Profile::iter(mod_manager.get_profile(profile)).collect::<Vec<_>>();
+    let mut issues = lock
+        .mods
+        .par_iter()
+        .map(|locked| -> Result<Vec<LockIssue>> {
+            let mut issues = vec![];
+            let Some(mod_) = mods.iter().find(|m| locked.matches(m)) else {
+                return Ok(vec![LockIssue::Missing {
+                    name: locked.name.clone(),
+                }]);
+            };
+            if !mod_.path.exists() {
+                return Ok(vec![LockIssue::Missing {
+                    name: locked.name.clone(),
+                }]);
+            }
+            let found: String = util::content_hash(&mod_.path)
+                .with_context(|| format!("Failed to hash {}", mod_.meta.name))?
+                .into();
+            if found != locked.content_hash {
+                issues.push(LockIssue::Changed {
+                    name: locked.name.clone(),
+                    path: mod_.path.clone(),
+                    expected: locked.content_hash.clone(),
+                    found,
+                });
+            }
+            if mod_.enabled != locked.enabled
+                || !mod_
+                    .enabled_options
+                    .iter()
+                    .map(|o| &o.path)
+                    .eq(locked.options.iter())
+            {
+                issues.push(LockIssue::StateChanged {
+                    name: locked.name.clone(),
+                });
+            }
+            Ok(issues)
+        })
+        .collect::<Result<Vec<_>>>()?
+        .into_iter()
+        .flatten()
+        .collect::<Vec<_>>();
+    issues.extend(
+        mods.iter()
+            .filter(|m| !lock.mods.iter().any(|l| l.matches(m)))
+            .map(|m| {
+                LockIssue::Added {
+                    name: m.meta.name.clone(),
+                }
+            }),
+    );
+    let locked_order = lock
+        .mods
+        .iter()
+        .filter(|l| mods.iter().any(|m| l.matches(m)))
+        .map(|l| &l.name);
+    let current_order = mods
+        .iter()
+        .filter(|m| lock.mods.iter().any(|l| l.matches(m)))
+        .map(|m| &m.meta.name);
+    if !locked_order.eq(current_order) {
+        issues.push(LockIssue::OrderChanged);
+    }
+    Ok(issues)
+}
@@ -1,9 +1,11 @@
 use std::{
+    collections::VecDeque,
     hash::{Hash, Hasher},
-    io::BufReader,
-    ops::Deref,
     path::{Path, PathBuf},
-    sync::{Arc, LazyLock, Weak},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, LazyLock, Weak,
+    },
 };
 
 use anyhow_ext::{Context, Result};
@@ -16,15 +18,19 @@ use serde::{Deserialize, Serialize};
 use serde_with::{serde_as, DisplayFromStr};
 use smartstring::alias::String;
 use uk_content::platform_prefixes;
-use uk_mod::{pack::ModPacker, unpack::ModReader, Manifest, Meta, ModOption};
+use uk_mod::{pack::ModPacker, source::ModSource, unpack::ModReader, Manifest, Meta, ModOption};
 
 use crate::{
+    dependencies::{self, DependencyIssue, IssueKind},
+    history::{self, BatchGuard, HistoryEntry, Rollback},
     settings::Settings,
-    util::{self, extract_7z, HashMap},
+    util::{self, HashMap, HashSet},
 };
 
 type ManifestCache = LazyLock<RwLock<HashMap<(usize, Vec<PathBuf>), Result<Arc<Manifest>>>>>;
 
+static MANIFEST_CACHE: ManifestCache = LazyLock::new(|| RwLock::new(HashMap::default()));
+
 #[serde_as]
 #[derive(Clone, Serialize, Deserialize)]
 pub struct Mod {
@@ -34,6 +40,13 @@ pub struct Mod {
     pub path: PathBuf,
     #[serde_as(as = "DisplayFromStr")]
     pub(crate) hash: usize,
+    /// SHA-256 of the stored mod when it was installed. Unlike the hash, this
+    /// tells apart different builds of a mod with the same meta.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content_hash: Option<String>,
+    /// The folder the mod was last repackaged from during development.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<PathBuf>,
 }
 
 impl std::fmt::Debug for Mod {
@@ -44,6 +57,8 @@ impl std::fmt::Debug for Mod {
             .field("enabled", &self.enabled)
             .field("path", &self.path)
             .field("hash", &self.hash)
+            .field("content_hash", &self.content_hash)
+            .field("source", &self.source)
             .finish()
     }
 }
@@ -70,6 +85,8 @@ impl Mod {
             enabled_options: vec![],
             path: reader.path,
             enabled: false,
+            content_hash: None,
+            source: None,
         }
     }
 
@@ -78,7 +95,6 @@ impl Mod {
     }
 
     pub fn manifest_with_options(&self, options: impl AsRef<[ModOption]>) -> Result<Arc<Manifest>> {
-        static MANIFEST_CACHE: ManifestCache = LazyLock::new(|| RwLock::new(HashMap::default()));
         match MANIFEST_CACHE
             .write()
             .entry((
@@ -93,6 +109,13 @@ impl Mod {
         }
     }
 
+    /// Drop the cached manifests of the mod, after its stored file changed.
+    fn forget_manifests(&self) {
+        MANIFEST_CACHE
+            .write()
+            .retain(|(hash, _), _| *hash != self.hash);
+    }
+
     pub fn state_eq(&self, other: &Self) -> bool {
         self.enabled == other.enabled && self.enabled_options == other.enabled_options
     }
@@ -103,24 +126,13 @@ impl Mod {
     }
 
     pub fn enable_default_options(&mut self) {
-        if !self.meta.options.is_empty() {
-            for group in self.meta.options.iter_mut() {
-                match group {
-                    uk_mod::OptionGroup::Exclusive(group) => {
-                        self.enabled_options
-                            .extend(group.default.iter().filter_map(|path| {
-                                group.options.iter().find(|o| &o.path == path).cloned()
-                            }));
-                    }
-                    uk_mod::OptionGroup::Multiple(group) => {
-                        self.enabled_options
-                            .extend(group.defaults.iter().filter_map(|path| {
-                                group.options.iter().find(|o| &o.path == path).cloned()
-                            }));
-                    }
-                };
-            }
-        }
+        self.enabled_options.extend(
+            self.meta
+                .options
+                .iter()
+                .flat_map(|group| group.defaults())
+                .cloned(),
+        );
     }
 }
 
@@ -283,6 +295,8 @@ pub struct Manager {
     profiles: DashMap<String, Profile>,
     current_profile: String,
     settings: Weak<RwLock<Settings>>,
+    history: DashMap<String, VecDeque<HistoryEntry>>,
+    batching: AtomicBool,
 }
 
 impl Manager {
@@ -291,6 +305,13 @@ impl Manager {
         self.dir.join(self.current_profile.as_str())
     }
 
+    /// The folder holding the data of a profile, by default the current one.
+    #[inline(always)]
+    pub fn profile_path(&self, profile: Option<&String>) -> PathBuf {
+        self.dir
+            .join(profile.unwrap_or(&self.current_profile).as_str())
+    }
+
     #[inline(always)]
     pub fn profile(&self) -> MappedRef<'_, String, Profile, Profile> {
         self.profiles
@@ -331,13 +352,75 @@ impl Manager {
         let path = self.dir.join(profile);
         if !path.exists() {
             log::info!("Profile {profile} does not exist, creating it now");
-            fs::create_dir_all(path)?;
+            fs::create_dir_all(&path)?;
+            fs::write(
+                path.join("profile.yml"),
+                serde_yaml::to_string(&Profile::default())?,
+            )?;
             self.profiles.insert(profile.into(), Default::default());
             self.save()?;
         }
         Ok(())
     }
 
+    /// Iterate the names of all profiles.
+    pub fn profile_names(&self) -> impl Iterator<Item = String> + '_ {
+        self.profiles.iter().map(|p| p.key().clone())
+    }
+
+    #[inline(always)]
+    pub fn current_profile(&self) -> &str {
+        self.current_profile.as_str()
+    }
+
+    /// Copy a profile, including its mod states and load order, under a new
+    /// name.
+    pub fn copy_profile(&self, profile: &str, new_name: &str) -> Result<()> {
+        if self.profiles.contains_key(new_name) {
+            anyhow_ext::bail!("Profile {new_name} already exists");
+        }
+        let copy = self
+            .profiles
+            .get(profile)
+            .with_context(|| format!("Profile {profile} does not exist"))?
+            .clone();
+        util::copy_dir(self.dir.join(profile), self.dir.join(new_name))?;
+        self.profiles.insert(new_name.into(), copy);
+        log::info!("Copied profile {profile} to {new_name}");
+        Ok(())
+    }
+
+    /// Delete a profile other than the current one, along with any mods which
+    /// no other profile uses.
+    pub fn delete_profile(&self, profile: &str) -> Result<()> {
+        if profile == self.current_profile.as_str() {
+            anyhow_ext::bail!("Cannot delete the current profile");
+        }
+        let (_, removed) = self
+            .profiles
+            .remove(profile)
+            .with_context(|| format!("Profile {profile} does not exist"))?;
+        self.history.remove(profile);
+        util::remove_dir_all(self.dir.join(profile))?;
+        for (hash, mod_) in removed.mods().iter() {
+            if !self
+                .profiles
+                .iter()
+                .any(|p| p.value().mods().contains_key(hash))
+            {
+                self.stash(mod_)?;
+                if mod_.path.is_dir() {
+                    util::remove_dir_all(&mod_.path)?;
+                } else if mod_.path.exists() {
+                    fs::remove_file(&mod_.path)?;
+                }
+            }
+        }
+        self.prune_stash()?;
+        log::info!("Deleted profile {profile}");
+        Ok(())
+    }
+
     pub fn set_profile(&mut self, profile: &str) -> Result<()> {
         self.current_profile = profile.into();
         self.create_profile_if(profile)?;
@@ -389,18 +472,35 @@ impl Manager {
             profiles,
             current_profile: current_profile.clone(),
             settings: Arc::downgrade(settings),
+            history: DashMap::new(),
+            batching: AtomicBool::new(false),
         };
         self_.create_profile_if(&current_profile)?;
         Ok(self_)
     }
 
     pub fn save(&self) -> Result<()> {
+        self.save_profile(self.current_profile.as_str())
+    }
+
+    /// Save the data of a profile, which need not be the current one.
+    pub fn save_profile(&self, profile: &str) -> Result<()> {
+        let data = self
+            .profiles
+            .get(profile)
+            .with_context(|| format!("Profile {profile} does not exist"))?;
         fs::write(
-            self.path().join("profile.yml"),
-            serde_yaml::to_string(self.profile().deref())?,
+            self.dir.join(profile).join("profile.yml"),
+            serde_yaml::to_string(data.value())?,
         )?;
+        if let Some(history) = self.history.get(profile) {
+            history::save(
+                &self.dir.join(profile).join(history::HISTORY_FILE),
+                history.value(),
+            )?;
+        }
         log::info!("Saved profile data");
-        log::debug!("{:#?}", &self.profile());
+        log::debug!("{:#?}", data.value());
         Ok(())
     }
 
@@ -453,6 +553,25 @@ impl Manager {
                     anyhow_ext::bail!("Mod \"{}\" already installed", peeker.meta.name);
                 }
             }
+            let installed = Profile::iter(self.get_profile(profile)).collect::<Vec<_>>();
+            let (missing, disabled): (Vec<_>, Vec<_>) =
+                dependencies::check_masters(&peeker.meta, &installed)
+                    .into_iter()
+                    .partition(|issue| issue.kind != IssueKind::Disabled);
+            for issue in disabled {
+                log::warn!("{issue}");
+            }
+            if !missing.is_empty() {
+                anyhow_ext::bail!(
+                    "Cannot install {}: {}",
+                    name,
+                    missing
+                        .iter()
+                        .map(|i| i.to_string())
+                        .collect::<Vec<_>>()
+                        .join("; ")
+                );
+            }
             peeker.meta.name
         };
         let san_opts: sfn::Options<Option<char>> = sfn::Options {
@@ -472,6 +591,9 @@ impl Manager {
             log::debug!("Mod already stored, no need to store it");
         } else {
             stored_path.parent().map(fs::create_dir_all).transpose()?;
+            if let Some(old_mod) = old_version.as_ref().filter(|m| m.path == stored_path) {
+                self.stash(old_mod)?;
+            }
             if mod_path.is_file() {
                 fs::copy(mod_path, &stored_path).context("Failed to copy mod to storage folder")?;
             } else {
@@ -482,6 +604,15 @@ impl Manager {
         let reader = ModReader::open_peek(&stored_path, vec![])?;
         let mut mod_ = Mod::from_reader(reader);
         mod_.enabled = true;
+        mod_.content_hash = Some(util::content_hash(&stored_path)?.into());
+        self.record(
+            profile,
+            if old_version.is_some() {
+                format!("Update {}", mod_.meta.name)
+            } else {
+                format!("Install {}", mod_.meta.name)
+            },
+        );
         let profile_data = self.get_profile(profile);
         profile_data.load_order_mut().push(mod_.hash);
         profile_data.mods_mut().insert(mod_.hash, mod_.clone());
@@ -507,6 +638,32 @@ impl Manager {
 
     pub fn del(&self, mod_: impl LookupMod, profile: Option<&String>) -> Result<Arc<Manifest>> {
         let hash = mod_.as_map_id();
+        let installed = Profile::iter(self.get_profile(profile)).collect::<Vec<_>>();
+        if let Some(target) = installed.iter().find(|m| m.hash == hash) {
+            let (enabled, disabled): (Vec<_>, Vec<_>) =
+                dependencies::dependents(target, &installed).partition(|m| m.enabled);
+            if !enabled.is_empty() {
+                anyhow_ext::bail!(
+                    "Cannot remove {} because it is required by {}",
+                    target.meta.name,
+                    enabled
+                        .iter()
+                        .map(|m| m.meta.name.as_str())
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                );
+            }
+            for dependent in disabled {
+                log::warn!(
+                    "Removing {}, which is required by disabled mod {}",
+                    target.meta.name,
+                    dependent.meta.name
+                );
+            }
+        }
+        if let Some(target) = installed.iter().find(|m| m.hash == hash) {
+            self.record(profile, format!("Remove {}", target.meta.name));
+        }
         let profile_data = self.get_profile(profile);
         let mod_ = profile_data.mods_mut().remove(&hash);
         if let Some(mod_) = mod_ {
@@ -517,6 +674,7 @@ impl Manager {
                 .iter()
                 .any(|p| p.value().mods().contains_key(&hash))
             {
+                self.stash(&mod_)?;
                 if mod_.path.is_dir() {
                     util::remove_dir_all(&mod_.path)?;
                 } else {
@@ -537,6 +695,14 @@ impl Manager {
     }
 
     pub fn replace(&self, mut mod_: Mod, old_hash: usize) -> Result<Mod> {
+        let old_name = self
+            .profile()
+            .mods()
+            .get(&old_hash)
+            .map(|m| m.meta.name.clone());
+        if let Some(name) = old_name {
+            self.record(None, format!("Update {name}"));
+        }
         let profile_data = self.profile();
         let old_mod = profile_data
             .mods_mut()
@@ -544,6 +710,10 @@ impl Manager {
             .expect("The old mod has to be in the profile");
         mod_.enabled = old_mod.enabled;
         mod_.path = old_mod.path;
+        if mod_.source.is_none() {
+            mod_.source = old_mod.source;
+        }
+        old_mod.forget_manifests();
         profile_data.mods_mut().insert(mod_.hash, mod_.clone());
         let mut load_order = profile_data.load_order_mut();
         if let Some(idx) = load_order.iter().position(|m| *m == old_hash) {
@@ -552,6 +722,93 @@ impl Manager {
         Ok(mod_)
     }
 
+    /// Re-save a stored mod in place with its resources migrated to the
+    /// current mod format, updating and saving every profile which uses it.
+    /// Returns how many resources were rewritten.
+    pub fn migrate(&self, mod_: impl LookupMod) -> Result<usize> {
+        let hash = mod_.as_map_id();
+        let target = self
+            .get_mod(hash)
+            .with_context(|| format!("Mod with ID {hash} does not exist"))?;
+        let dump = self
+            .settings
+            .upgrade()
+            .expect("Settings is GONE!")
+            .read()
+            .dump()
+            .context("No dump available for current platform")?;
+        let count = uk_mod::migrate::migrate_mod(&target.path, &dump)?;
+        let meta = ModReader::open_peek(&target.path, vec![])?.meta;
+        let content_hash: String = util::content_hash(&target.path)?.into();
+        let mut updated = vec![];
+        for profile in self.profiles.iter() {
+            if let Some(mod_) = profile.value().mods_mut().get_mut(&hash) {
+                mod_.meta = meta.clone();
+                mod_.content_hash = Some(content_hash.clone());
+                updated.push(profile.key().clone());
+            }
+        }
+        for profile in updated {
+            self.save_profile(&profile)?;
+        }
+        Ok(count)
+    }
+
+    /// Remember the folder a mod is developed in, so that it can be updated
+    /// from it again without asking.
+    pub fn set_source(&self, mod_: impl LookupMod, source: Option<PathBuf>) -> Result<()> {
+        let hash = mod_.as_map_id();
+        self.profile()
+            .mods_mut()
+            .get_mut(&hash)
+            .with_context(|| format!("Mod with ID {hash} does not exist"))?
+            .source = source;
+        self.save()
+    }
+
+    /// Repackage a mod in place from a source folder during development,
+    /// reusing whatever did not change since its last build, and remember
+    /// the folder for the next update. The mod keeps its installed meta.
+    /// Returns the game files which may have changed, to be merged again.
+    pub fn dev_update(&self, mod_: impl LookupMod, source: &Path) -> Result<Manifest> {
+        let hash = mod_.as_map_id();
+        let target = self
+            .get_mod(hash)
+            .with_context(|| format!("Mod with ID {hash} does not exist"))?;
+        let dump = self
+            .settings
+            .upgrade()
+            .expect("Settings is GONE!")
+            .read()
+            .dump()
+            .context("No dump available for current platform")?;
+        let old_manifest = target.manifest()?;
+        self.stash(&target)?;
+        let mut meta = target.meta.clone();
+        meta.api = env!("CARGO_PKG_VERSION").into();
+        let (_, changed) = ModPacker::new(source, &target.path, Some(meta), vec![dump])?
+            .incremental(true)
+            .pack_changes()
+            .with_context(|| format!("Failed to repackage {}", target.meta.name))?;
+        let reader = ModReader::open_peek(&target.path, target.enabled_options.clone())?;
+        let dirty = changed.unwrap_or_else(|| {
+            // Without a previous build to compare, anything in the old or new
+            // build may have changed
+            let mut dirty = Manifest::default();
+            dirty.extend(&old_manifest);
+            dirty.extend(&reader.manifest);
+            dirty
+        });
+        let mut updated = Mod::from_reader(reader);
+        updated.enabled_options = target.enabled_options;
+        updated.content_hash = Some(util::content_hash(&target.path)?.into());
+        updated.source = Some(source.to_path_buf());
+        self.replace(updated, hash)?;
+        self.save()?;
+        log::info!("Updated {} from {}", target.meta.name, source.display());
+        Ok(dirty)
+    }
+
     pub fn set_enabled(
         &self,
         mod_: impl LookupMod,
@@ -559,6 +816,44 @@ impl Manager {
         profile: Option<&String>,
     ) -> Result<Arc<Manifest>> {
         let hash = mod_.as_map_id();
+        let installed = Profile::iter(self.get_profile(profile)).collect::<Vec<_>>();
+        if let Some(target) = installed.iter().find(|m| m.hash == hash) {
+            if enabled {
+                let issues = dependencies::check_masters(&target.meta, &installed)
+                    .into_iter()
+                    .filter(|i| i.kind != IssueKind::LoadsBefore)
+                    .map(|i| i.to_string())
+                    .collect::<Vec<_>>();
+                if !issues.is_empty() {
+                    anyhow_ext::bail!("Cannot enable {}: {}", target.meta.name, issues.join("; "));
+                }
+            } else {
+                let dependents = dependencies::dependents(target, &installed)
+                    .filter(|m| m.enabled)
+                    .map(|m| m.meta.name.as_str())
+                    .collect::<Vec<_>>();
+                if !dependents.is_empty() {
+                    anyhow_ext::bail!(
+                        "Cannot disable {} because it is required by {}",
+                        target.meta.name,
+                        dependents.join(", ")
+                    );
+                }
+            }
+        }
+        if let Some(target) = installed
+            .iter()
+            .find(|m| m.hash == hash && m.enabled != enabled)
+        {
+            self.record(
+                profile,
+                format!(
+                    "{} {}",
+                    if enabled { "Enable" } else { "Disable" },
+                    target.meta.name
+                ),
+            );
+        }
         let manifest;
         let profile_data = self.get_profile(profile);
         if let Some(mod_) = profile_data.mods_mut().get_mut(&hash) {
@@ -583,19 +878,270 @@ impl Manager {
         options: Vec<ModOption>,
     ) -> Result<Arc<Manifest>> {
         let hash = mod_.as_map_id();
-        let manifest;
-        if let Some(mod_) = self.profile().mods_mut().get_mut(&hash) {
-            manifest = mod_.manifest_with_options(&options)?;
-            mod_.enabled_options = options;
-        } else {
+        let Some(current) = self.profile().mods().get(&hash).cloned() else {
             log::warn!("Mod with ID {} does not exist, doing nothing", hash);
             return Ok(Default::default());
+        };
+        crate::options::validate_options(&current.meta, &options)?;
+        let manifest = current.manifest_with_options(&options)?;
+        if current.enabled_options != options {
+            self.record(None, format!("Change options of {}", current.meta.name));
+        }
+        if let Some(mod_) = self.profile().mods_mut().get_mut(&hash) {
+            mod_.enabled_options = options;
         }
         Ok(manifest)
     }
 
+    /// Set the load order of the current profile. Mods which would load before
+    /// their masters are moved after them.
     pub fn set_order(&self, order: Vec<usize>) {
-        *self.profile().load_order_mut() = order;
+        let sorted = dependencies::sort_order(&order, &self.profile().mods());
+        if sorted != order {
+            log::warn!("Moved mods after their masters in the load order");
+        }
+        if *self.profile().load_order() != sorted {
+            self.record(None, "Reorder mods");
+        }
+        *self.profile().load_order_mut() = sorted;
+    }
+
+    /// Save the state of a profile to its history before a change, dropping
+    /// the oldest states beyond [`history::MAX_HISTORY`].
+    fn record(&self, profile: Option<&String>, action: impl Into<String>) {
+        if self.batching.load(Ordering::Relaxed) {
+            return;
+        }
+        let name = profile.unwrap_or(&self.current_profile);
+        let state = Profile::clone(&self.get_profile(Some(name)));
+        let mut history = self.history.entry(name.clone()).or_insert_with(|| {
+            history::load(&self.dir.join(name.as_str()).join(history::HISTORY_FILE))
+        });
+        history.push_back(HistoryEntry::new(action.into(), state));
+        let mut dropped = false;
+        while history.len() > history::MAX_HISTORY {
+            history.pop_front();
+            dropped = true;
+        }
+        drop(history);
+        if dropped {
+            if let Err(e) = self.prune_stash() {
+                log::warn!("Failed to clean up mods kept for the history: {e:?}");
+            }
+        }
+    }
+
+    /// Make changes to the current profile which are recorded in its history
+    /// as a single entry.
+    pub fn batch<T>(&self, action: &str, f: impl FnOnce() -> Result<T>) -> Result<T> {
+        self.record(None, action);
+        let result = {
+            let _guard = BatchGuard::new(&self.batching);
+            f()
+        };
+        let mut unchanged = false;
+        if let Some(mut history) = self.history.get_mut(self.current_profile.as_str()) {
+            if history.back().is_some_and(|entry| {
+                history::affected_mods(&entry.state, &self.profile()).is_empty()
+            }) {
+                history.pop_back();
+                unchanged = true;
+            }
+        }
+        if unchanged {
+            if let Err(e) = self.prune_stash() {
+                log::warn!("Failed to clean up mods kept for the history: {e:?}");
+            }
+        }
+        result
+    }
+
+    /// The history of a profile, by default the current one, newest first.
+    pub fn history(&self, profile: Option<&String>) -> Vec<HistoryEntry> {
+        let name = profile.unwrap_or(&self.current_profile);
+        self.history
+            .entry(name.clone())
+            .or_insert_with(|| {
+                history::load(&self.dir.join(name.as_str()).join(history::HISTORY_FILE))
+            })
+            .iter()
+            .rev()
+            .cloned()
+            .collect()
+    }
+
+    /// Roll a profile back to the state before a change in its history,
+    /// counting from 0 for the newest. Mods which were removed or updated
+    /// since are restored from the files kept for the history, and mods which
+    /// cannot be restored are left out. The rollback is itself recorded, so
+    /// it can be undone.
+    pub fn rollback(&self, profile: Option<&String>, index: usize) -> Result<Rollback> {
+        let name = profile.unwrap_or(&self.current_profile);
+        let target = self
+            .history(Some(name))
+            .into_iter()
+            .nth(index)
+            .with_context(|| {
+                format!("Profile {name} has no change {} in its history", index + 1)
+            })?;
+        let stash_dir = self.stash_dir();
+        let current = Profile::clone(&self.get_profile(Some(name)));
+        let mut rollback = Rollback::default();
+        let mut restore = vec![];
+        target.state.mods_mut().retain(|hash, mod_| {
+            let replaced_by = |profile: &Profile| {
+                profile
+                    .mods()
+                    .values()
+                    .find(|m| m.path == mod_.path && m.hash != *hash)
+                    .cloned()
+            };
+            let occupant = replaced_by(&current);
+            if mod_.path.exists() && occupant.is_none() {
+                return true;
+            }
+            // A mod stored over this one can only make way if nothing else
+            // uses it
+            let shared = self
+                .profiles
+                .iter()
+                .any(|p| p.key() != name && replaced_by(p.value()).is_some());
+            let stashed = history::stash_path(&stash_dir, *hash);
+            if stashed.exists() && !shared {
+                restore.push((stashed, mod_.path.clone(), occupant));
+                true
+            } else {
+                log::warn!("{} is no longer stored, leaving it out", mod_.meta.name);
+                rollback.missing.push(mod_.meta.name.clone());
+                false
+            }
+        });
+        let kept = target.state.mods().keys().copied().collect::<Vec<_>>();
+        target
+            .state
+            .load_order_mut()
+            .retain(|hash| kept.contains(hash));
+        let affected = history::affected_mods(&current, &target.state);
+        let mut add_manifests = |state: &Profile| {
+            for hash in affected.iter() {
+                if let Some(mod_) = state.mods().get(hash) {
+                    match mod_.manifest() {
+                        Ok(manifest) => rollback.manifest.extend(manifest.as_ref()),
+                        Err(e) => log::warn!("Could not read {}: {e}", mod_.meta.name),
+                    }
+                }
+            }
+        };
+        add_manifests(&current);
+        self.record(Some(name), format!("Roll back \"{}\"", target.action));
+        for (stashed, path, occupant) in restore {
+            if let Some(occupant) = occupant {
+                self.stash(&occupant)?;
+            }
+            path.parent().map(fs::create_dir_all).transpose()?;
+            if path.is_dir() {
+                util::remove_dir_all(&path)?;
+            }
+            if stashed.is_dir() {
+                util::copy_dir(&stashed, &path)?;
+            } else {
+                fs::copy(&stashed, &path)?;
+            }
+            log::debug!("Restored {} from the history", path.display());
+        }
+        add_manifests(&target.state);
+        let profile_data = self.get_profile(Some(name));
+        *profile_data.mods_mut() = target.state.mods().clone();
+        *profile_data.load_order_mut() = target.state.load_order().clone();
+        log::info!("Rolled back profile {name} to before \"{}\"", target.action);
+        Ok(rollback)
+    }
+
+    #[inline]
+    fn stash_dir(&self) -> PathBuf {
+        self.settings
+            .upgrade()
+            .expect("Settings is GONE!")
+            .read()
+            .mods_dir()
+            .join(history::STASH_DIR)
+    }
+
+    /// Keep a copy of a mod's stored file before it is removed or
+    /// overwritten, so that rolling back the change can restore it. Copies
+    /// which no history refers to are removed again as entries expire.
+    pub fn stash(&self, mod_: &Mod) -> Result<()> {
+        let dest = history::stash_path(&self.stash_dir(), mod_.hash);
+        if !mod_.path.exists() || dest.exists() {
+            return Ok(());
+        }
+        dest.parent().map(fs::create_dir_all).transpose()?;
+        if mod_.path.is_dir() {
+            util::copy_dir(&mod_.path, &dest)
+        } else {
+            fs::copy(&mod_.path, &dest).map(|_| ()).map_err(Into::into)
+        }
+        .with_context(|| {
+            format!(
+                "Failed to keep a copy of {} for the history",
+                mod_.meta.name
+            )
+        })?;
+        log::debug!("Kept a copy of {} for the history", mod_.meta.name);
+        Ok(())
+    }
+
+    /// Every mod which a state in some profile's history refers to.
+    fn history_mods(&self) -> HashSet<usize> {
+        let mut hashes = HashSet::default();
+        for name in self.profile_names().collect::<Vec<_>>() {
+            let mut collect = |history: &VecDeque<HistoryEntry>| {
+                for entry in history {
+                    hashes.extend(entry.state.mods().keys().copied());
+                }
+            };
+            match self.history.get(name.as_str()) {
+                Some(history) => collect(history.value()),
+                None => {
+                    collect(&history::load(
+                        &self.dir.join(name.as_str()).join(history::HISTORY_FILE),
+                    ))
+                }
+            }
+        }
+        hashes
+    }
+
+    /// Remove the kept copies of mods which no history refers to any more.
+    fn prune_stash(&self) -> Result<()> {
+        let dir = self.stash_dir();
+        if !dir.exists() {
+            return Ok(());
+        }
+        let referenced = self.history_mods();
+        for entry in fs::read_dir(&dir)? {
+            let path = entry?.path();
+            let used = path
+                .file_stem()
+                .and_then(|stem| stem.to_str())
+                .and_then(|stem| stem.parse::<usize>().ok())
+                .is_some_and(|hash| referenced.contains(&hash));
+            if used {
+                continue;
+            }
+            if path.is_dir() {
+                util::remove_dir_all(&path)?;
+            } else {
+                fs::remove_file(&path)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Check the masters of every enabled mod in a profile.
+    pub fn dependency_issues(&self, profile: Option<&String>) -> Vec<DependencyIssue> {
+        let mods = Profile::iter(self.get_profile(profile)).collect::<Vec<_>>();
+        dependencies::check_profile(&mods)
     }
 
     pub fn get_mod(&self, hash: usize) -> Option<Mod> {
@@ -609,99 +1155,59 @@ pub fn convert_gfx(
     meta: Option<Meta>,
 ) -> Result<PathBuf> {
     log::info!("Attempting to convert mod at {}", path.display());
-    let path = if path.is_file() {
-        let ext = path
-            .extension()
-            .and_then(|e| e.to_str())
-            .unwrap_or("")
-            .to_uppercase();
-
-        let find_rules = |path: &Path| -> Option<PathBuf> {
-            jwalk::WalkDir::new(path)
-                .into_iter()
-                .filter_map(Result::ok)
-                .find_map(|f| {
-                    [Some("rules.txt"), Some("info.json")].contains(&f.file_name().to_str())
-                        .then(|| f.parent_path().into())
+    let source = if path.is_file() {
+        if path.file_name().context("No file name")?.to_str() == Some("rules.txt") {
+            ModSource::open(path.parent().expect("Parent path gotta' exist, right?"))?
+        } else {
+            log::info!("Reading mod archive...");
+            let source = ModSource::open(path).inspect_err(|_| {
+                log::error!("{} is not a supported mod archive", path.display())
+            })?;
+            let root = source.root().to_path_buf();
+            let files = source.files(&root);
+            // The shallowest folder in the archive holding the mod
+            let find = |holds: &dyn Fn(&Path) -> Option<PathBuf>| {
+                files
+                    .iter()
+                    .flat_map(|file| {
+                        file.ancestors()
+                            .take_while(|dir| *dir != root)
+                            .filter_map(holds)
+                    })
+                    .min_by_key(|dir| dir.components().count())
+            };
+            let mod_root = if meta.is_none() {
+                find(&|file| {
+                    ["rules.txt", "info.json"]
+                        .iter()
+                        .any(|name| file.ends_with(name))
+                        .then(|| file.parent().map(Path::to_path_buf))
+                        .flatten()
                 })
-        };
-
-        let find_root = |path: &Path| -> Option<PathBuf> {
-            let (content, dlc) = platform_prefixes(core.settings().current_mode.into());
-            jwalk::WalkDir::new(path)
-                .into_iter()
-                .filter_map(Result::ok)
-                .find_map(|f| {
-                    (f.path().join(content).exists() || f.path().join(dlc).exists())
-                        .then(|| f.path())
-                        .or_else(|| {
-                            [Some(content), Some(dlc)].contains(&f.file_name().to_str())
-                                .then(|| f.parent_path().into())
+                .context("Could not find rules.txt in mod archive")?
+            } else {
+                let (content, dlc) = platform_prefixes(core.settings().current_mode.into());
+                find(&|dir| {
+                    [content, dlc]
+                        .into_iter()
+                        .find(|prefix| dir.ends_with(prefix))
+                        .and_then(|prefix| {
+                            dir.ancestors().nth(Path::new(prefix).components().count())
                         })
+                        .map(Path::to_path_buf)
                 })
-        };
-
-        if ext == "ZIP" {
-            log::info!("Extracting ZIP file...");
-            let tmpdir = util::get_temp_folder();
-            zip::ZipArchive::new(BufReader::new(fs::File::open(path)?))
-                .context("Failed to open ZIP")?
-                .extract(&*tmpdir)
-                .context("Failed to extract ZIP")?;
-            if meta.is_none() {
-                find_rules(&tmpdir).context("Could not find rules.txt in extracted mod")?
-            } else {
-                find_root(&tmpdir)
-                    .context("Could not find base or DLC content folder in extracted mod")?
-            }
-        } else if ext == "7Z" {
-            log::info!("Extracting 7Z file...");
-            let tmpdir = util::get_temp_folder();
-            extract_7z(path, &tmpdir).context("Failed to extract 7Z file")?;
-            if meta.is_none() {
-                find_rules(&tmpdir).context("Could not find rules.txt in extracted mod")?
-            } else {
-                find_root(&tmpdir)
-                    .context("Could not find base or DLC content folder in extracted mod")?
-            }
-        } else if ext == "RAR" {
-            log::info!("Extracting RAR file...");
-            let tmpdir = util::get_temp_folder();
-            let mut archive = unrar::Archive::new(path)
-                .open_for_processing()
-                .context("Failed to open RAR for extraction")?;
-            while let Some(header) = archive.read_header()
-                .context("Failed to read header")? {
-                archive = if header.entry().is_file() {
-                    log::info!("Extracting {}...", header.entry().filename.to_string_lossy());
-                    header.extract_with_base::<&Path>(tmpdir.as_ref())
-                        .context("Failed to extract file")?
-                } else {
-                    header.skip().context("Failed to skip folder")?
-                };
-            }
-            if meta.is_none() {
-                find_rules(&tmpdir).context("Could not find rules.txt in extracted mod")?
-            } else {
-                find_root(&tmpdir)
-                    .context("Could not find base or DLC content folder in extracted mod")?
-            }
-        } else if path.file_name().context("No file name")?.to_str() == Some("rules.txt") {
-            path.parent()
-                .expect("Parent path gotta' exist, right?")
-                .to_owned()
-        } else {
-            log::error!("{} is not a supported mod archive", path.display());
-            anyhow_ext::bail!("{} files are not supported", ext)
+                .context("Could not find base or DLC content folder in mod archive")?
+            };
+            source.rooted_at(&mod_root)?
         }
     } else {
         log::info!("Unpacked mod, that's easy");
-        path.to_path_buf()
+        ModSource::open(path)?
     };
     let temp = util::get_temp_folder();
     log::debug!("Temp folder: {}", temp.display());
     log::info!("Attempting to convert mod...");
-    let packer = ModPacker::new(path, &*temp, meta, vec![
+    let packer = ModPacker::from_source(source, &*temp, meta, vec![
         core.settings()
             .dump()
             .context("No dump available for current platform")?,
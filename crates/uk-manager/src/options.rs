@@ -0,0 +1,319 @@
+use std::path::PathBuf;
+
+use anyhow_ext::Result;
+use serde::Serialize;
+use smartstring::alias::String;
+use uk_mod::{Meta, ModOption, ModOptionGroup, OptionGroup};
+
+/// A rule of a mod's option groups broken by a selection of options.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum OptionIssue {
+    /// A required group has no option selected.
+    MissingRequired { group: String },
+    /// More than one option is selected in an exclusive group.
+    TooMany {
+        group:   String,
+        options: Vec<String>,
+    },
+    /// A selected option requires another which is not selected.
+    Unmet { option: String, requires: PathBuf },
+    /// A selected option is not one of the mod's options.
+    Unknown { option: String },
+}
+
+impl std::fmt::Display for OptionIssue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            OptionIssue::MissingRequired { group } => {
+                write!(f, "An option must be selected from \"{group}\"")
+            }
+            OptionIssue::TooMany { group, options } => {
+                write!(
+                    f,
+                    "Only one option can be selected from \"{group}\", but {} are selected",
+                    options.join(", ")
+                )
+            }
+            OptionIssue::Unmet { option, requires } => {
+                write!(
+                    f,
+                    "\"{option}\" requires the option {}, which is not selected",
+                    requires.display()
+                )
+            }
+            OptionIssue::Unknown { option } => {
+                write!(f, "\"{option}\" is not an option of this mod")
+            }
+        }
+    }
+}
+
+/// An option selection which was fixed by [`resolve_options`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Resolution {
+    pub options: Vec<ModOption>,
+    /// The issues which had to be fixed, in the order they were found.
+    pub fixed:   Vec<OptionIssue>,
+}
+
+fn find_option<'a>(
+    meta: &'a Meta,
+    path: &std::path::Path,
+) -> Option<(&'a OptionGroup, &'a ModOption)> {
+    meta.options
+        .iter()
+        .find_map(|g| g.options().iter().find(|o| o.path == path).map(|o| (g, o)))
+}
+
+/// Options are matched by path, since the other fields of a saved selection
+/// may be out of date.
+#[inline]
+fn in_group(group: &OptionGroup, option: &ModOption) -> bool {
+    group.options().iter().any(|o| o.path == option.path)
+}
+
+/// Check a selection of options against the rules of the mod's option
+/// groups. An empty result means the selection is valid.
+pub fn check_options(meta: &Meta, selected: &[ModOption]) -> Vec<OptionIssue> {
+    let mut issues = vec![];
+    for option in selected {
+        if find_option(meta, &option.path).is_none() {
+            issues.push(OptionIssue::Unknown {
+                option: option.name.clone(),
+            });
+        }
+    }
+    for group in meta.options.iter() {
+        let chosen = selected
+            .iter()
+            .filter(|o| in_group(group, o))
+            .collect::<Vec<_>>();
+        if matches!(group, OptionGroup::Exclusive(_)) && chosen.len() > 1 {
+            issues.push(OptionIssue::TooMany {
+                group:   group.name().into(),
+                options: chosen.iter().map(|o| o.name.clone()).collect(),
+            });
+        }
+        if group.required() && chosen.is_empty() {
+            issues.push(OptionIssue::MissingRequired {
+                group: group.name().into(),
+            });
+        }
+    }
+    for option in selected {
+        for requires in option.requires.iter() {
+            if !selected.iter().any(|o| o.path == *requires) {
+                issues.push(OptionIssue::Unmet {
+                    option:   option.name.clone(),
+                    requires: requires.clone(),
+                });
+            }
+        }
+    }
+    issues
+}
+
+/// Check a selection of options, failing with an explanation of every
+/// issue if it is invalid.
+pub fn validate_options(meta: &Meta, selected: &[ModOption]) -> Result<()> {
+    let issues = check_options(meta, selected);
+    if !issues.is_empty() {
+        anyhow_ext::bail!(
+            "Invalid options for {}: {}",
+            meta.name,
+            issues
+                .iter()
+                .map(|i| i.to_string())
+                .collect::<Vec<_>>()
+                .join("; ")
+        );
+    }
+    Ok(())
+}
+
+/// Fix an invalid selection of options where possible. Unknown options are
+/// dropped, missing requirements are selected, exclusive groups keep their
+/// most recently selected option, and required groups without a selection
+/// get their default or else their first option. Fails if the fixes conflict
+/// with each other or a requirement is not one of the mod's options.
+pub fn resolve_options(meta: &Meta, selected: &[ModOption]) -> Result<Resolution> {
+    let mut options = selected.to_vec();
+    let mut fixed = vec![];
+    // Each round fixes at least one issue, and there cannot be more distinct
+    // fixes than options, so anything beyond that is a conflict.
+    let max_rounds = meta
+        .options
+        .iter()
+        .map(|g| g.options().len())
+        .sum::<usize>()
+        + 2;
+    for _ in 0..max_rounds {
+        let issues = check_options(meta, &options);
+        if issues.is_empty() {
+            return Ok(Resolution { options, fixed });
+        }
+        for issue in issues.iter() {
+            match issue {
+                OptionIssue::Unknown { .. } => {
+                    options.retain(|o| find_option(meta, &o.path).is_some())
+                }
+                OptionIssue::Unmet { requires, .. } => {
+                    let Some((group, required)) = find_option(meta, requires) else {
+                        anyhow_ext::bail!("{issue}, and it is not an option of {}", meta.name);
+                    };
+                    if !options.iter().any(|o| o.path == required.path) {
+                        if matches!(group, OptionGroup::Exclusive(_)) {
+                            options.retain(|o| !in_group(group, o));
+                        }
+                        options.push(required.clone());
+                    }
+                }
+                OptionIssue::TooMany { group, .. } => {
+                    if let Some(group) = meta.options.iter().find(|g| g.name() == group.as_str()) {
+                        if let Some(keep) = options
+                            .iter()
+                            .rev()
+                            .find(|o| in_group(group, o))
+                            .map(|o| o.path.clone())
+                        {
+                            options.retain(|o| !in_group(group, o) || o.path == keep);
+                        }
+                    }
+                }
+                OptionIssue::MissingRequired { group } => {
+                    if let Some(group) = meta.options.iter().find(|g| g.name() == group.as_str()) {
+                        let choice = group.defaults().next().or_else(|| group.options().first());
+                        if let Some(choice) = choice {
+                            options.push(choice.clone());
+                        }
+                    }
+                }
+            }
+        }
+        fixed.extend(issues);
+    }
+    anyhow_ext::bail!(
+        "Could not fix the options for {}: {}",
+        meta.name,
+        check_options(meta, &options)
+            .iter()
+            .map(|i| i.to_string())
+            .collect::<Vec<_>>()
+            .join("; ")
+    )
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use uk_mod::{ExclusiveOptionGroup, ModCategory, ModPlatform, MultipleOptionGroup};
+
+    use super::*;
+
+    fn option(path: &str, requires: &[&str]) -> ModOption {
+        ModOption {
+            name: path.into(),
+            description: Default::default(),
+            path: path.into(),
+            requires: requires.iter().map(PathBuf::from).collect(),
+        }
+    }
+
+    /// A mod with a required exclusive group of difficulty levels and an
+    /// optional group of extras, one of which needs the hard difficulty.
+    fn meta() -> Meta {
+        Meta {
+            api: env!("CARGO_PKG_VERSION").into(),
+            name: "Options Test".into(),
+            version: "1.0.0".into(),
+            author: "Tester".into(),
+            category: ModCategory::Difficulty,
+            description: Default::default(),
+            platform: ModPlatform::Universal,
+            url: None,
+            masters: Default::default(),
+            options: vec![
+                OptionGroup::Exclusive(ExclusiveOptionGroup {
+                    name: "Difficulty".into(),
+                    description: Default::default(),
+                    required: true,
+                    default: Some("normal".into()),
+                    options: vec![
+                        option("easy", &[]),
+                        option("normal", &[]),
+                        option("hard", &[]),
+                    ],
+                }),
+                OptionGroup::Multiple(MultipleOptionGroup {
+                    name: "Extras".into(),
+                    description: Default::default(),
+                    required: false,
+                    defaults: Default::default(),
+                    options: vec![option("more_enemies", &["hard"]), option("new_armor", &[])],
+                }),
+            ],
+        }
+    }
+
+    fn find(meta: &Meta, path: &str) -> ModOption {
+        find_option(meta, path.as_ref()).unwrap().1.clone()
+    }
+
+    #[test]
+    fn valid_selection() {
+        let meta = meta();
+        let selected = [find(&meta, "hard"), find(&meta, "more_enemies")];
+        assert!(check_options(&meta, &selected).is_empty());
+        assert!(validate_options(&meta, &selected).is_ok());
+    }
+
+    #[test]
+    fn invalid_selections() {
+        let meta = meta();
+        assert_eq!(check_options(&meta, &[]), vec![
+            OptionIssue::MissingRequired {
+                group: "Difficulty".into(),
+            }
+        ]);
+        let issues = check_options(&meta, &[find(&meta, "easy"), find(&meta, "hard")]);
+        assert!(
+            matches!(&issues[..], [OptionIssue::TooMany { options, .. }] if options.len() == 2)
+        );
+        let issues = check_options(&meta, &[find(&meta, "easy"), find(&meta, "more_enemies")]);
+        assert_eq!(issues, vec![OptionIssue::Unmet {
+            option:   "more_enemies".into(),
+            requires: "hard".into(),
+        }]);
+        let issues = check_options(&meta, &[find(&meta, "normal"), option("missing", &[])]);
+        assert_eq!(issues, vec![OptionIssue::Unknown {
+            option: "missing".into(),
+        }]);
+        assert!(validate_options(&meta, &[]).is_err());
+    }
+
+    #[test]
+    fn resolve() {
+        let meta = meta();
+        let resolved = resolve_options(&meta, &[]).unwrap();
+        assert_eq!(resolved.options, vec![find(&meta, "normal")]);
+        let resolved =
+            resolve_options(&meta, &[find(&meta, "easy"), find(&meta, "more_enemies")]).unwrap();
+        assert!(check_options(&meta, &resolved.options).is_empty());
+        assert!(resolved.options.contains(&find(&meta, "hard")));
+        assert!(!resolved.options.contains(&find(&meta, "easy")));
+        let resolved = resolve_options(&meta, &[find(&meta, "easy"), find(&meta, "hard")]).unwrap();
+        assert_eq!(resolved.options, vec![find(&meta, "hard")]);
+        assert_eq!(resolved.fixed.len(), 1);
+    }
+
+    #[test]
+    fn unresolvable() {
+        let mut meta = meta();
+        if let OptionGroup::Multiple(group) = &mut meta.options[1] {
+            group.options.push(option("broken", &["nowhere"]));
+        }
+        let broken = find(&meta, "broken");
+        assert!(resolve_options(&meta, &[find(&meta, "normal"), broken]).is_err());
+    }
+}
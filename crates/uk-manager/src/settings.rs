@@ -1,6 +1,7 @@
 #![allow(clippy::unwrap_used)]
 
 use std::{
+    collections::BTreeMap,
     path::{Path, PathBuf},
     sync::{Arc, LazyLock},
 };
@@ -11,7 +12,7 @@ use parking_lot::RwLock;
 use serde::{Deserialize, Serialize};
 use serde_with::{serde_as, DefaultOnError};
 use smartstring::alias::String;
-use uk_content::{constants::Language, prelude::Endian};
+use uk_content::{constants::Language, platform_prefixes, prelude::Endian};
 use uk_localization::LocLang;
 use uk_reader::ResourceReader;
 
@@ -110,6 +111,17 @@ pub struct DeployConfig {
     pub executable: Option<std::string::String>,
     #[serde(default)]
     pub layout: DeployLayout,
+    /// Decide which files to deploy by comparing content hashes instead of
+    /// modified times
+    #[serde(default)]
+    pub compare_hashes: bool,
+    /// The kind of archive to export when deploying by archive
+    #[serde(default)]
+    pub archive_format: ArchiveFormat,
+    /// Only export the files changed since the last export, along with a list
+    /// of files to delete
+    #[serde(default)]
+    pub archive_delta: bool,
 }
 
 impl DeployConfig {
@@ -161,6 +173,9 @@ impl Default for DeployConfig {
             cemu_rules: false,
             executable: None,
             layout: DeployLayout::WithoutName,
+            compare_hashes: false,
+            archive_format: ArchiveFormat::Zip,
+            archive_delta: false,
         }
     }
 }
@@ -170,6 +185,8 @@ pub enum DeployMethod {
     Copy,
     HardLink,
     Symlink,
+    /// Export an archive of the deployed layout to the output folder
+    Archive,
 }
 
 impl DeployMethod {
@@ -179,6 +196,24 @@ impl DeployMethod {
             DeployMethod::Copy => "Settings_Platform_Deploy_Method_Copy",
             DeployMethod::HardLink => "Settings_Platform_Deploy_Method_HardLink",
             DeployMethod::Symlink => "Settings_Platform_Deploy_Method_Symlink",
+            DeployMethod::Archive => "Settings_Platform_Deploy_Method_Archive",
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize, Default)]
+pub enum ArchiveFormat {
+    #[default]
+    Zip,
+    Tar,
+}
+
+impl ArchiveFormat {
+    #[inline(always)]
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ArchiveFormat::Zip => "zip",
+            ArchiveFormat::Tar => "tar",
         }
     }
 }
@@ -206,6 +241,51 @@ pub struct PlatformSettings {
     pub profile: String,
     pub dump: Arc<ResourceReader>,
     pub deploy_config: Option<DeployConfig>,
+    /// Deploy targets besides the default one, by name
+    #[serde(default)]
+    pub deploy_targets: BTreeMap<String, DeployConfig>,
+}
+
+/// The name of the deploy target set by `deploy_config`
+pub const DEFAULT_TARGET: &str = "Default";
+
+impl PlatformSettings {
+    /// Every configured deploy target by name, starting with the default one.
+    pub fn targets(&self) -> impl Iterator<Item = (&str, &DeployConfig)> {
+        self.deploy_config
+            .iter()
+            .map(|config| (DEFAULT_TARGET, config))
+            .chain(
+                self.deploy_targets
+                    .iter()
+                    .map(|(name, config)| (name.as_str(), config)),
+            )
+    }
+
+    pub fn target(&self, name: &str) -> Option<&DeployConfig> {
+        if name == DEFAULT_TARGET {
+            self.deploy_config.as_ref()
+        } else {
+            self.deploy_targets.get(name)
+        }
+    }
+
+    /// Check that every deploy target can be told apart, both by name and by
+    /// the folder its records are kept in.
+    pub fn validate_targets(&self) -> Result<()> {
+        let mut folders = BTreeMap::new();
+        for name in self.deploy_targets.keys() {
+            if name.trim().is_empty() || name == DEFAULT_TARGET {
+                anyhow_ext::bail!("Deploy target name \"{name}\" is not allowed");
+            }
+            if let Some(other) = folders.insert(sanitise_file_name::sanitise(name), name) {
+                anyhow_ext::bail!(
+                    "Deploy target names \"{other}\" and \"{name}\" are too similar to tell apart"
+                );
+            }
+        }
+        Ok(())
+    }
 }
 
 #[inline]
@@ -323,6 +403,9 @@ impl Settings {
         if !Self::path().parent().unwrap().exists() {
             fs::create_dir_all(Self::path().parent().unwrap())?;
         }
+        for config in self.wiiu_config.iter().chain(self.switch_config.iter()) {
+            config.validate_targets()?;
+        }
         log::debug!("Saving settings:\n{:#?}", self);
         let _ = crate::util::USE_SZ.compare_exchange_weak(
             !self.system_7z,
@@ -335,23 +418,27 @@ impl Settings {
         Ok(())
     }
 
-    pub fn wipe_output(&self, endian: Endian) -> Result<()> {
-        let (content, aoc) = match endian {
-            Endian::Big => self.wiiu_config
-                .as_ref()
-                .unwrap()
-                .deploy_config
-                .as_ref()
-                .unwrap()
-                .final_output_paths(endian),
-            Endian::Little => self.switch_config
-                .as_ref()
-                .unwrap()
-                .deploy_config
-                .as_ref()
-                .unwrap()
-                .final_output_paths(endian),
-        };
+    /// Undeploy from the output folder of a deploy target, putting back any
+    /// files which were there before UKMM first deployed to it.
+    pub fn wipe_output(&self, endian: Endian, target: &str) -> Result<()> {
+        let platform = Platform::from(endian);
+        let config = match platform {
+            Platform::WiiU => self.wiiu_config.as_ref(),
+            Platform::Switch => self.switch_config.as_ref(),
+        }
+        .and_then(|c| c.target(target))
+        .with_context(|| format!("No deploy target named {target}"))?;
+        let merged = self.merged_dir();
+        let (merged_content, merged_aoc) = platform_prefixes(endian);
+        if crate::deploy::undeploy(
+            &self.get_target_dir(platform, target),
+            (&merged.join(merged_content), &merged.join(merged_aoc)),
+        )? {
+            return Ok(());
+        }
+        // Without a backup the output folders are taken to hold nothing but
+        // deployed files
+        let (content, aoc) = config.final_output_paths(endian);
         if util::is_symlink(content.as_ref()) {
             util::remove_symlink(content)?;
         } else if content.exists() {
@@ -378,6 +465,25 @@ impl Settings {
         }
     }
 
+    /// The folder holding the pending changes and deployment records of a
+    /// deploy target. The default target keeps them in the platform folder.
+    #[inline]
+    pub fn target_dir(&self, target: &str) -> PathBuf {
+        self.get_target_dir(self.current_mode, target)
+    }
+
+    #[inline]
+    pub fn get_target_dir(&self, platform: Platform, target: &str) -> PathBuf {
+        let platform_dir = self.get_platform_dir(platform);
+        if target == DEFAULT_TARGET {
+            platform_dir
+        } else {
+            platform_dir
+                .join("targets")
+                .join(sanitise_file_name::sanitise(target))
+        }
+    }
+
     #[inline]
     pub fn profiles_dir(&self) -> PathBuf {
         self.platform_dir().join("profiles")
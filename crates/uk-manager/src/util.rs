@@ -6,6 +6,7 @@ use std::{
 use anyhow_ext::Context;
 use parking_lot::{MappedRwLockReadGuard, RwLock, RwLockReadGuard};
 pub use rustc_hash::{FxHashMap as HashMap, FxHashSet as HashSet};
+use uk_mod::source::ModSource;
 
 pub fn copy_dir<T: AsRef<Path>, U: AsRef<Path>>(src: T, dst: U) -> anyhow_ext::Result<()> {
     for p in jwalk::WalkDir::new(&src) {
@@ -128,6 +129,14 @@ pub fn clear_temp() {
     });
 }
 
+/// Open the source files of a mod to convert or package it, reading archives
+/// in place. Files changed while converting the mod are written to a new temp
+/// folder.
+pub fn open_mod_source(path: &Path) -> anyhow_ext::Result<ModSource> {
+    let overlay = get_temp_folder().clone();
+    Ok(ModSource::open(path)?.with_overlay(overlay))
+}
+
 pub static USE_SZ: AtomicBool = AtomicBool::new(true);
 
 pub fn extract_7z(file: &Path, folder: &Path) -> anyhow_ext::Result<()> {
@@ -162,3 +171,66 @@ pub fn extract_7z(file: &Path, folder: &Path) -> anyhow_ext::Result<()> {
         Ok(sevenz_rust::decompress_file(file, folder)?)
     }
 }
+
+/// Compute the SHA-256 hash of a stored mod as a hex string. Mods stored as
+/// folders are hashed by the relative path and contents of each file, in
+/// sorted order, each prefixed with its length so that no two different
+/// folders can hash the same.
+pub fn content_hash(path: &Path) -> anyhow_ext::Result<std::string::String> {
+    use sha2::{Digest, Sha256};
+    let mut hasher = Sha256::new();
+    if path.is_dir() {
+        for entry in jwalk::WalkDir::new(path).sort(true) {
+            let entry = entry?;
+            if !entry.file_type().is_file() {
+                continue;
+            }
+            let file = entry.path();
+            let rel = file
+                .strip_prefix(path)
+                .map(|rel| rel.to_string_lossy().replace('\\', "/"))
+                .unwrap_or_default();
+            hasher.update((rel.len() as u64).to_le_bytes());
+            hasher.update(rel.as_bytes());
+            let mut file = fs_err::File::open(&file)?;
+            hasher.update(file.metadata()?.len().to_le_bytes());
+            std::io::copy(&mut file, &mut hasher)?;
+        }
+    } else {
+        std::io::copy(&mut fs_err::File::open(path)?, &mut hasher)
+            .with_context(|| format!("Failed to hash {}", path.display()))?;
+    }
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    #[test]
+    fn content_hash() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("mod.zip");
+        fs_err::write(&file, b"first build").unwrap();
+        let first = super::content_hash(&file).unwrap();
+        assert_eq!(first, super::content_hash(&file).unwrap());
+        fs_err::write(&file, b"second build").unwrap();
+        assert_ne!(first, super::content_hash(&file).unwrap());
+        let folder = dir.path().join("mod");
+        fs_err::create_dir_all(folder.join("content")).unwrap();
+        fs_err::write(folder.join("content/a.txt"), b"a").unwrap();
+        fs_err::write(folder.join("b.txt"), b"b").unwrap();
+        let unpacked = super::content_hash(&folder).unwrap();
+        fs_err::rename(folder.join("b.txt"), folder.join("content/b.txt")).unwrap();
+        assert_ne!(unpacked, super::content_hash(&folder).unwrap());
+        // Paths and contents running together must not collide
+        let (first, second) = (dir.path().join("first"), dir.path().join("second"));
+        fs_err::create_dir_all(&first).unwrap();
+        fs_err::create_dir_all(&second).unwrap();
+        fs_err::write(first.join("ab"), b"c").unwrap();
+        fs_err::write(second.join("a"), b"bc").unwrap();
+        assert_ne!(
+            super::content_hash(&first).unwrap(),
+            super::content_hash(&second).unwrap()
+        );
+    }
+}
@@ -0,0 +1,147 @@
+//! Watch mode for mod development, which repackages a mod from its source
+//! folder and merges the files it changed each time the folder changes.
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+    time::{Duration, Instant, SystemTime},
+};
+
+use anyhow_ext::{Context, Result};
+use jwalk::WalkDir;
+use uk_mod::Manifest;
+
+use crate::{core::Manager, mods::LookupMod};
+
+/// How often to look for changes.
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// How long a folder must be left alone after a change before it is handled,
+/// unless another interval is asked for.
+pub const DEFAULT_DEBOUNCE: Duration = Duration::from_millis(1000);
+
+/// The size and modification time of every file in a folder.
+type Snapshot = BTreeMap<PathBuf, (u64, Option<SystemTime>)>;
+
+fn snapshot(root: &Path) -> Snapshot {
+    WalkDir::new(root)
+        .into_iter()
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_type().is_file())
+        .filter_map(|entry| {
+            let metadata = entry.metadata().ok()?;
+            Some((entry.path(), (metadata.len(), metadata.modified().ok())))
+        })
+        .collect()
+}
+
+/// Watches a folder for changes by polling it, which works the same on every
+/// platform and file system.
+#[derive(Debug)]
+pub struct SourceWatcher {
+    root:     PathBuf,
+    debounce: Duration,
+    snapshot: Snapshot,
+}
+
+impl SourceWatcher {
+    pub fn new(root: impl Into<PathBuf>, debounce: Duration) -> Self {
+        let root = root.into();
+        let snapshot = snapshot(&root);
+        Self {
+            root,
+            debounce,
+            snapshot,
+        }
+    }
+
+    /// Wait for the folder to change, returning once it has been left alone
+    /// for the debounce interval, so that a burst of saves is handled as one
+    /// change. Returns `false` as soon as `stop` returns `true`.
+    pub fn wait(&mut self, stop: impl Fn() -> bool) -> bool {
+        let mut changed_at: Option<Instant> = None;
+        loop {
+            if stop() {
+                return false;
+            }
+            std::thread::sleep(POLL_INTERVAL);
+            let current = snapshot(&self.root);
+            if current != self.snapshot {
+                self.snapshot = current;
+                changed_at = Some(Instant::now());
+            } else if changed_at.is_some_and(|at| at.elapsed() >= self.debounce) {
+                return true;
+            }
+        }
+    }
+}
+
+/// Repackage a mod from a source folder and merge the files it changed each
+/// time the folder changes, deploying as well if asked, until `stop` returns
+/// `true`. The folder is remembered as the mod's source. Each update's result
+/// goes to `on_update`. A failed update does not end the watch, since the next
+/// change may well fix it.
+pub fn watch_mod(
+    core: &Manager,
+    mod_: impl LookupMod,
+    source: &Path,
+    debounce: Duration,
+    deploy: bool,
+    stop: impl Fn() -> bool,
+    mut on_update: impl FnMut(Result<Manifest>),
+) -> Result<()> {
+    let hash = mod_.as_map_id();
+    if !source.is_dir() {
+        anyhow_ext::bail!("Source folder {} does not exist", source.display());
+    }
+    core.mod_manager()
+        .set_source(hash, Some(source.to_path_buf()))?;
+    let mut watcher = SourceWatcher::new(source, debounce);
+    log::info!("Watching {} for changes", source.display());
+    while watcher.wait(&stop) {
+        log::info!("Changes found in {}", source.display());
+        on_update(update(core, hash, source, deploy));
+    }
+    Ok(())
+}
+
+fn update(core: &Manager, hash: usize, source: &Path, deploy: bool) -> Result<Manifest> {
+    let dirty = core.mod_manager().dev_update(hash, source)?;
+    let deploy_manager = core.deploy_manager();
+    deploy_manager
+        .apply(Some(dirty.clone()))
+        .context("Failed to apply mod changes")?;
+    if deploy {
+        deploy_manager
+            .deploy_auto()
+            .context("Failed to deploy update to merged mod(s)")?;
+    }
+    Ok(dirty)
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn debounced_changes() {
+        let dir = tempfile::tempdir().unwrap();
+        fs_err::write(dir.path().join("a.txt"), "a").unwrap();
+        let mut watcher = SourceWatcher::new(dir.path(), Duration::from_millis(500));
+        let start = Instant::now();
+        assert!(!watcher.wait(|| start.elapsed() > Duration::from_millis(600)));
+        let path = dir.path().join("b.txt");
+        let writer = std::thread::spawn(move || {
+            for i in 0..3 {
+                fs_err::write(&path, i.to_string()).unwrap();
+                std::thread::sleep(Duration::from_millis(100));
+            }
+        });
+        let start = Instant::now();
+        assert!(watcher.wait(|| start.elapsed() > Duration::from_secs(10)));
+        writer.join().unwrap();
+        // The burst of writes was handled as one change
+        let start = Instant::now();
+        assert!(!watcher.wait(|| start.elapsed() > Duration::from_millis(600)));
+    }
+}
@@ -0,0 +1,345 @@
+//! Conversion of platform-specific mods for the other console. Stored
+//! resources use canonical paths and the mergeable ones are not tied to a
+//! byte order, so most of a mod carries across once its diffs are rebuilt
+//! against the other platform's game files. Binary files are only carried
+//! across when they do not depend on the byte order.
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    io::Write,
+    path::Path,
+};
+
+use anyhow_ext::{bail, Context, Result};
+use fs_err as fs;
+use path_slash::PathExt;
+use roead::byml::Byml;
+use serde::Serialize;
+use smartstring::alias::String;
+use uk_content::{
+    canonicalize, platform_prefixes,
+    prelude::{Endian, Mergeable},
+    resource::{MergeableResource, ResourceData},
+};
+use uk_reader::ResourceReader;
+use zip::{write::SimpleFileOptions, ZipWriter};
+
+use crate::{
+    migrate, pack::BUILD_INDEX, unpack::init_decompressor, verify::Source, Manifest, Meta,
+    ModPlatform,
+};
+
+/// What a binary file which cannot be converted holds, judging by its name.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BinaryKind {
+    Texture,
+    Model,
+    Havok,
+    Audio,
+    Other,
+}
+
+impl BinaryKind {
+    fn from_name(canon: &str) -> Self {
+        let ext = Path::new(canon)
+            .extension()
+            .and_then(|e| e.to_str())
+            .unwrap_or_default();
+        match ext {
+            "bfres" if canon.contains(".Tex") => Self::Texture,
+            "bntx" | "bflim" | "bftex" => Self::Texture,
+            "bfres" | "bfsha" => Self::Model,
+            "hkx" | "hkcl" | "hkrb" | "hkrg" | "hksc" | "hktmrb" => Self::Havok,
+            "bfstm" | "bfwav" | "bars" | "bfsar" | "bfstp" => Self::Audio,
+            _ => Self::Other,
+        }
+    }
+}
+
+impl std::fmt::Display for BinaryKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BinaryKind::Texture => "texture".fmt(f),
+            BinaryKind::Model => "model".fmt(f),
+            BinaryKind::Havok => "Havok data".fmt(f),
+            BinaryKind::Audio => "audio".fmt(f),
+            BinaryKind::Other => "binary file".fmt(f),
+        }
+    }
+}
+
+/// A binary file which was left out of a converted mod.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct UnconvertedFile {
+    /// Where the file would go in the target platform's layout, with the
+    /// option it belongs to first, if any.
+    pub path: String,
+    pub kind: BinaryKind,
+}
+
+/// The result of converting a mod for the other platform.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ConvertReport {
+    /// How many mergeable resources and SARCs were rebuilt.
+    pub rebuilt: usize,
+    /// How many binary files were carried across.
+    pub copied: usize,
+    /// Binary files which depend on the byte order, and were left out. The
+    /// converted mod uses the stock versions of these.
+    pub unconverted: Vec<UnconvertedFile>,
+}
+
+enum Converted {
+    Rebuilt(ResourceData),
+    Copied(ResourceData),
+    Dropped(BinaryKind),
+}
+
+/// Convert a binary file for the target byte order, if it can be.
+fn convert_binary(canon: &str, data: &[u8], endian: Endian) -> Result<Option<Vec<u8>>> {
+    if data.len() > 4 && matches!(&data[..2], b"BY" | b"YB") {
+        Ok(Some(Byml::from_binary(data)?.to_binary(endian.into())))
+    } else if data.starts_with(b"AAMP") {
+        // Parameter archives are little endian on both consoles
+        Ok(Some(data.to_vec()))
+    } else if BinaryKind::from_name(canon) == BinaryKind::Other
+        && !data.contains(&0)
+        && std::str::from_utf8(data).is_ok()
+    {
+        Ok(Some(data.to_vec()))
+    } else {
+        Ok(None)
+    }
+}
+
+/// Rebuild a stored resource for the target platform. Diffs are applied to
+/// the target's stock version and taken again, so that they only hold real
+/// changes on that platform.
+fn convert_resource(
+    canon: &str,
+    resource: ResourceData,
+    stock: Option<&ResourceData>,
+    endian: Endian,
+) -> Result<Converted> {
+    Ok(match (resource, stock) {
+        (ResourceData::Binary(data), _) => {
+            match convert_binary(canon, &data, endian)? {
+                Some(data) => Converted::Copied(ResourceData::Binary(data)),
+                None => Converted::Dropped(BinaryKind::from_name(canon)),
+            }
+        }
+        (ResourceData::Mergeable(MergeableResource::BinaryOverride(bin)), _) => {
+            match convert_binary(canon, &bin.0, endian)? {
+                Some(data) => {
+                    Converted::Copied(ResourceData::Mergeable(MergeableResource::BinaryOverride(
+                        Box::new((data, bin.1)),
+                    )))
+                }
+                None => Converted::Dropped(BinaryKind::from_name(canon)),
+            }
+        }
+        (ResourceData::Mergeable(diff), Some(ResourceData::Mergeable(stock)))
+            if std::mem::discriminant(&diff) == std::mem::discriminant(stock) =>
+        {
+            Converted::Rebuilt(ResourceData::Mergeable(stock.diff(&stock.merge(&diff))))
+        }
+        (ResourceData::Sarc(diff), Some(ResourceData::Sarc(stock))) => {
+            Converted::Rebuilt(ResourceData::Sarc(stock.diff(&stock.merge(&diff))))
+        }
+        (resource, _) => Converted::Rebuilt(resource),
+    })
+}
+
+/// Convert a platform-specific mod ZIP or unpacked mod folder for the other
+/// console, using that platform's game files from `target`, and write it as
+/// a mod ZIP at `dest`. Binary files which depend on the byte order are left
+/// out and listed in the report, along with any nested SARC entries and
+/// manifest entries for them.
+pub fn convert_mod(path: &Path, dest: &Path, target: &ResourceReader) -> Result<ConvertReport> {
+    let source = Source::open(path)?;
+    let mut meta: Meta = serde_yaml::from_slice(&source.read("meta.yml")?)
+        .context("Failed to parse meta file from mod")?;
+    let endian = match meta.platform {
+        ModPlatform::Specific(Endian::Big) => Endian::Little,
+        ModPlatform::Specific(Endian::Little) => Endian::Big,
+        ModPlatform::Universal => bail!("{} already works on both platforms", meta.name),
+    };
+    let option_roots = meta
+        .options
+        .iter()
+        .flat_map(|g| g.options().iter())
+        .map(|o| format!("options/{}/", o.path.to_slash_lossy()))
+        .collect::<Vec<_>>();
+    let split = |file: &str| -> (std::string::String, std::string::String) {
+        option_roots
+            .iter()
+            .find_map(|root| {
+                file.strip_prefix(root.as_str())
+                    .map(|canon| (root.clone(), canon.to_owned()))
+            })
+            .unwrap_or_else(|| (std::string::String::new(), file.to_owned()))
+    };
+    let mut files = source.files();
+    files.sort();
+    let stored = files.iter().map(|f| f.as_str()).collect::<BTreeSet<_>>();
+    let decompressor = init_decompressor();
+    let mut report = ConvertReport::default();
+    let mut converted = BTreeMap::new();
+    let mut dropped = BTreeSet::new();
+    for file in files.iter() {
+        if file == "meta.yml"
+            || file.ends_with("manifest.yml")
+            || file == BUILD_INDEX
+            || file.starts_with("thumb.")
+        {
+            continue;
+        }
+        let (root, canon) = split(file.as_str());
+        let data = source.read_resource(&mut decompressor.lock(), file)?;
+        let stock = target.get_data(canon.as_str()).ok();
+        let resource = migrate::read_resource(&meta, &canon, &data, stock.as_deref())?;
+        match convert_resource(&canon, resource, stock.as_deref(), endian)
+            .with_context(|| format!("Failed to convert {file}"))?
+        {
+            Converted::Rebuilt(resource) => {
+                report.rebuilt += 1;
+                converted.insert(file.as_str(), resource);
+            }
+            Converted::Copied(resource) => {
+                report.copied += 1;
+                converted.insert(file.as_str(), resource);
+            }
+            Converted::Dropped(kind) => {
+                let (content, aoc) = platform_prefixes(endian);
+                let target_path = match canon.strip_prefix("Aoc/0010/") {
+                    Some(rest) => format!("{root}{aoc}/{rest}"),
+                    None => format!("{root}{content}/{canon}"),
+                };
+                log::warn!("Leaving out {target_path}, which cannot be converted");
+                report.unconverted.push(UnconvertedFile {
+                    path: target_path.into(),
+                    kind,
+                });
+                dropped.insert(file.as_str());
+            }
+        }
+    }
+    // A file is gone from a root if it was dropped there, or if the root
+    // relied on the base mod's version and that was dropped.
+    let is_dropped = |root: &str, canon: &str| -> bool {
+        let file = [root, canon].concat();
+        dropped.contains(file.as_str())
+            || (!stored.contains(file.as_str()) && dropped.contains(canon))
+    };
+    for (file, resource) in converted.iter_mut() {
+        let ResourceData::Sarc(sarc) = resource else {
+            continue;
+        };
+        let (root, canon) = split(*file);
+        let aoc = canon.starts_with("Aoc/0010/");
+        let gone = sarc
+            .files
+            .iter()
+            .filter(|name| {
+                let nested = match aoc {
+                    true => canonicalize(format!("Aoc/0010/{name}")),
+                    false => canonicalize(name.as_str()),
+                };
+                is_dropped(root.as_str(), nested.as_str())
+                    && target.get_data(nested.as_str()).is_err()
+            })
+            .cloned()
+            .collect::<Vec<_>>();
+        for name in gone {
+            sarc.files.set_delete(name);
+        }
+    }
+    let mut compressor = zstd::bulk::Compressor::with_dictionary(8, crate::DICTIONARY)?;
+    meta.platform = ModPlatform::Specific(endian);
+    meta.api = env!("CARGO_PKG_VERSION").into();
+    dest.parent().map(fs::create_dir_all).transpose()?;
+    let mut zip = ZipWriter::new(fs::File::create(dest)?);
+    let opts = SimpleFileOptions::default().compression_method(zip::CompressionMethod::Stored);
+    for file in files.iter() {
+        // The build index describes the source of the original build
+        if dropped.contains(file.as_str()) || file == BUILD_INDEX {
+            continue;
+        }
+        if file == "meta.yml" {
+            zip.start_file(file.as_str(), opts)?;
+            zip.write_all(serde_yaml::to_string(&meta)?.as_bytes())?;
+        } else if file.ends_with("manifest.yml") {
+            let root = file.trim_end_matches("manifest.yml");
+            let mut manifest: Manifest = serde_yaml::from_slice(&source.read(file)?)
+                .with_context(|| format!("Failed to parse {file}"))?;
+            manifest
+                .content_files
+                .retain(|f| !is_dropped(root, canonicalize(f.as_str()).as_str()));
+            manifest.aoc_files.retain(|f| {
+                !is_dropped(
+                    root,
+                    canonicalize(format!("Aoc/0010/{}", f.trim_start_matches("Aoc/0010/")))
+                        .as_str(),
+                )
+            });
+            zip.start_file(file.as_str(), opts)?;
+            zip.write_all(serde_yaml::to_string(&manifest)?.as_bytes())?;
+        } else if let Some(resource) = converted.get(file.as_str()) {
+            let data = minicbor_ser::to_vec(resource)
+                .map_err(|e| anyhow::format_err!("{:?}", e))
+                .with_context(|| format!("Failed to serialize {file}"))?;
+            zip.start_file(file.as_str(), opts)?;
+            zip.write_all(&compressor.compress(&data)?)?;
+        } else {
+            zip.start_file(file.as_str(), opts)?;
+            zip.write_all(&source.read(file)?)?;
+        }
+    }
+    zip.finish()?;
+    log::info!(
+        "Converted {} for {}, leaving out {} files",
+        meta.name,
+        meta.platform,
+        report.unconverted.len()
+    );
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn binary_kinds() {
+        assert_eq!(
+            BinaryKind::from_name("Model/Foo.Tex1.bfres"),
+            BinaryKind::Texture
+        );
+        assert_eq!(BinaryKind::from_name("Model/Foo.bfres"), BinaryKind::Model);
+        assert_eq!(
+            BinaryKind::from_name("Physics/RigidBody/Foo.hkrb"),
+            BinaryKind::Havok
+        );
+        assert_eq!(BinaryKind::from_name("Sound/Foo.bfstm"), BinaryKind::Audio);
+    }
+
+    #[test]
+    fn neutral_binaries() {
+        let text = b"Some notes".as_slice();
+        assert!(
+            convert_binary("Foo.txt", text, Endian::Little)
+                .unwrap()
+                .is_some()
+        );
+        let byml = Byml::Map(Default::default()).to_binary(Endian::Big.into());
+        let converted = convert_binary("Foo.byml", &byml, Endian::Little)
+            .unwrap()
+            .unwrap();
+        assert_eq!(&converted[..2], b"YB");
+        assert!(
+            convert_binary("Model/Foo.bfres", b"FRES\0\0\0\0", Endian::Little)
+                .unwrap()
+                .is_none()
+        );
+    }
+}
@@ -12,8 +12,12 @@ use uk_content::{
     prelude::Endian,
     util::{HashSet, IndexMap},
 };
+pub mod convert;
+pub mod migrate;
 pub mod pack;
+pub mod source;
 pub mod unpack;
+pub mod verify;
 pub use zstd;
 
 static DICTIONARY: &[u8] = include_bytes!("../data/zsdic");
@@ -144,10 +148,19 @@ pub struct MultipleOptionGroup {
     pub name: String,
     pub description: String,
     pub required: bool,
+    #[serde(serialize_with = "serialize_sorted")]
     pub defaults: HashSet<PathBuf>,
     pub options: Vec<ModOption>,
 }
 
+/// Write a set in a stable order, so the same meta always serializes the same.
+fn serialize_sorted<S: serde::Serializer>(
+    set: &HashSet<PathBuf>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.collect_seq(set.iter().collect::<BTreeSet<_>>())
+}
+
 impl ModOptionGroup for MultipleOptionGroup {
     #[inline(always)]
     fn name(&self) -> &str {
@@ -198,6 +211,19 @@ pub enum OptionGroup {
     Multiple(MultipleOptionGroup),
 }
 
+impl OptionGroup {
+    /// Iterate the options of this group which are enabled by default.
+    pub fn defaults(&self) -> impl Iterator<Item = &ModOption> {
+        let defaults: Vec<&PathBuf> = match self {
+            OptionGroup::Exclusive(group) => group.default.iter().collect(),
+            OptionGroup::Multiple(group) => group.defaults.iter().collect(),
+        };
+        self.options()
+            .iter()
+            .filter(move |o| defaults.contains(&&o.path))
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum ModCategory {
     #[serde(alias = "")]
@@ -0,0 +1,395 @@
+//! Migrations for mods built with older versions of UKMM. Each breaking
+//! change to the encoding of a resource is registered here along with the
+//! API version which introduced it, so that resources from older mods can be
+//! rewritten into the current encoding when they are read, or the mod can be
+//! re-saved in place with [`migrate_mod`].
+use std::{io::Write, path::Path};
+
+use anyhow_ext::{bail, Context, Result};
+use fs_err as fs;
+use lenient_semver::Version;
+use path_slash::PathExt;
+use roead::{aamp::ParameterIO, byml::Byml};
+use uk_content::{
+    prelude::{Endian, Mergeable},
+    resource::{MergeableResource, ResourceData},
+};
+use uk_reader::ResourceReader;
+use zip::{write::SimpleFileOptions, ZipWriter};
+
+use crate::{pack::BUILD_INDEX, unpack::init_decompressor, verify::Source, Meta};
+
+/// The oldest mod API version which can still be read. Mods built before it
+/// predate mod API versioning and the current BYML encoding.
+pub const MIN_API: &str = "0.10.0";
+
+type MigrateFn = fn(&str, ResourceData, &ResourceData) -> Result<ResourceData>;
+
+/// A breaking change to the encoding of some resources.
+pub struct Migration {
+    /// The API version which introduced the change.
+    pub version: &'static str,
+    /// What changed, for messages.
+    pub description: &'static str,
+    /// Whether a resource, by canonical path, is affected.
+    matches: fn(&str) -> bool,
+    /// Rewrite a resource from the old encoding using its stock version, if
+    /// the old encoding can be migrated at all.
+    migrate: Option<MigrateFn>,
+}
+
+impl std::fmt::Debug for Migration {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Migration")
+            .field("version", &self.version)
+            .field("description", &self.description)
+            .field("can_migrate", &self.can_migrate())
+            .finish()
+    }
+}
+
+impl Migration {
+    #[inline]
+    pub fn matches(&self, canon: &str) -> bool {
+        (self.matches)(canon)
+    }
+
+    #[inline]
+    pub fn can_migrate(&self) -> bool {
+        self.migrate.is_some()
+    }
+
+    fn message(&self, meta: &Meta, canon: &str) -> std::string::String {
+        format!(
+            "{} was built with UKMM v{}, but the {} changed in v{} and {canon} cannot be \
+             migrated. Reinstall the mod from its original files.",
+            meta.name, meta.api, self.description, self.version
+        )
+    }
+
+    fn apply(
+        &self,
+        meta: &Meta,
+        canon: &str,
+        resource: ResourceData,
+        stock: Option<&ResourceData>,
+    ) -> Result<ResourceData> {
+        let Some(migrate) = self.migrate else {
+            bail!(self.message(meta, canon));
+        };
+        let stock = stock.with_context(|| {
+            format!(
+                "{canon} from {} needs its stock version to migrate the {}",
+                meta.name, self.description
+            )
+        })?;
+        log::debug!(
+            "Migrating {canon} in {} from v{} for the {}",
+            meta.name,
+            meta.api,
+            self.description
+        );
+        migrate(canon, resource, stock).with_context(|| {
+            format!(
+                "Failed to migrate {canon} in {} to the {}",
+                meta.name, self.description
+            )
+        })
+    }
+}
+
+/// Every breaking change to resource encodings since [`MIN_API`], oldest
+/// first.
+pub static MIGRATIONS: &[Migration] = &[
+    Migration {
+        version:     "0.15.0",
+        description: "StatusEffectList merger",
+        matches:     |canon| file_stem(canon) == Some("StatusEffectList"),
+        migrate:     None,
+    },
+    Migration {
+        version:     "0.15.0",
+        description: "recipe format",
+        matches:     |canon| canon.ends_with(".brecipe"),
+        migrate:     None,
+    },
+    Migration {
+        version:     "0.16.0",
+        description: "LevelSensor merger",
+        matches:     |canon| file_stem(canon) == Some("LevelSensor"),
+        migrate:     None,
+    },
+    Migration {
+        version:     "0.17.0",
+        description: "MainField Static merger",
+        matches:     |canon| canon.ends_with("Map/MainField/Static.mubin"),
+        migrate:     Some(rebuild_generic),
+    },
+    Migration {
+        version:     "0.17.0",
+        description: "AnimSeq merger",
+        matches:     |canon| canon.ends_with(".bas"),
+        migrate:     Some(rebuild_generic),
+    },
+];
+
+#[inline]
+fn file_stem(canon: &str) -> Option<&str> {
+    Path::new(canon).file_stem().and_then(|s| s.to_str())
+}
+
+/// Resources which used to be stored as generic BYML or AAMP diffs are
+/// applied to the stock file and diffed again with their current merger.
+/// Resources already in the current encoding are left alone.
+fn rebuild_generic(
+    canon: &str,
+    resource: ResourceData,
+    stock: &ResourceData,
+) -> Result<ResourceData> {
+    let Some(stock) = stock.as_mergeable() else {
+        return Ok(resource);
+    };
+    let stock_data = stock.clone().into_binary(Endian::Big);
+    let merged = match resource {
+        ResourceData::Mergeable(MergeableResource::GenericByml(diff)) => {
+            Byml::from_binary(&stock_data)?
+                .merge(&diff)
+                .to_binary(Endian::Big.into())
+        }
+        ResourceData::Mergeable(MergeableResource::GenericAamp(diff)) => {
+            ParameterIO::from_binary(&stock_data)?
+                .merge(&diff)
+                .to_binary()
+        }
+        resource => return Ok(resource),
+    };
+    match MergeableResource::from_binary(Path::new(canon), &merged)? {
+        Some(MergeableResource::BinaryOverride(error)) => bail!("{}", error.1),
+        Some(resource) => Ok(ResourceData::Mergeable(stock.diff(&resource))),
+        None => bail!("{canon} is not a mergeable resource"),
+    }
+}
+
+fn parse_api(api: &str) -> Result<(u64, u64, u64)> {
+    let version = Version::parse(api)
+        .map_err(|e| anyhow_ext::anyhow!("{e}"))
+        .context("Invalid API version for mod")?;
+    Ok((version.major, version.minor, version.patch))
+}
+
+/// Check that a mod is not too old to migrate, and find the migrations which
+/// apply to it, oldest first. Prereleases count as their release, since the
+/// format does not change after one.
+pub fn pending(meta: &Meta) -> Result<Vec<&'static Migration>> {
+    let api = parse_api(&meta.api)?;
+    if api < parse_api(MIN_API)? {
+        bail!(
+            "{} was built with UKMM v{}, which is too old to migrate. Mods built before \
+             v{MIN_API} need to be reinstalled from their original files.",
+            meta.name,
+            meta.api
+        );
+    }
+    let mut migrations = vec![];
+    for migration in MIGRATIONS {
+        if parse_api(migration.version)? > api {
+            migrations.push(migration);
+        }
+    }
+    Ok(migrations)
+}
+
+/// The first pending migration for a resource which cannot be applied, as
+/// an error.
+pub(crate) fn check_resource(
+    meta: &Meta,
+    migrations: &[&'static Migration],
+    canon: &str,
+) -> Result<()> {
+    match migrations
+        .iter()
+        .find(|m| !m.can_migrate() && m.matches(canon))
+    {
+        Some(migration) => bail!(migration.message(meta, canon)),
+        None => Ok(()),
+    }
+}
+
+/// Deserialize a stored resource from a mod, applying any pending
+/// migrations for it. Migrations which rebuild a resource need its stock
+/// version.
+pub(crate) fn read_resource(
+    meta: &Meta,
+    canon: &str,
+    data: &[u8],
+    stock: Option<&ResourceData>,
+) -> Result<ResourceData> {
+    let migrations = pending(meta)?
+        .into_iter()
+        .filter(|m| m.matches(canon))
+        .collect::<Vec<_>>();
+    let resource = minicbor_ser::from_slice::<ResourceData>(data);
+    let Some(first) = migrations.first() else {
+        return resource.map_err(|e| {
+            let msg = format!("{}", e);
+            if msg.contains("unknown variant") {
+                anyhow_ext::anyhow!(
+                    "Error deserializing resource {canon} from mod {}. This is probably because \
+                     this mod was built with an old, incompatible beta of UKMM.",
+                    meta.name
+                )
+            } else {
+                anyhow_ext::anyhow!(
+                    "Error deserializing resource {canon} from mod {}. Error: {e}",
+                    meta.name
+                )
+            }
+        });
+    };
+    let mut resource =
+        resource.map_err(|e| anyhow_ext::anyhow!("{} Error: {e}", first.message(meta, canon)))?;
+    for migration in migrations {
+        resource = migration.apply(meta, canon, resource, stock)?;
+    }
+    Ok(resource)
+}
+
+/// Re-save a mod ZIP or unpacked mod folder in place, rewriting every
+/// resource with a pending migration into the current encoding using the
+/// stock resources from `dump`, and updating its API version. Returns how
+/// many resources were rewritten. Nothing is written if any resource cannot
+/// be migrated.
+pub fn migrate_mod(path: &Path, dump: &ResourceReader) -> Result<usize> {
+    let source = Source::open(path)?;
+    let mut meta: Meta = serde_yaml::from_slice(&source.read("meta.yml")?)
+        .context("Failed to parse meta file from mod")?;
+    let migrations = pending(&meta)?;
+    let option_roots = meta
+        .options
+        .iter()
+        .flat_map(|g| g.options().iter())
+        .map(|o| format!("options/{}/", o.path.to_slash_lossy()))
+        .collect::<Vec<_>>();
+    let mut files = source.files();
+    files.sort();
+    let decompressor = init_decompressor();
+    let mut compressor = zstd::bulk::Compressor::with_dictionary(8, crate::DICTIONARY)?;
+    let mut rewritten = std::collections::BTreeMap::new();
+    for file in files.iter() {
+        if file == "meta.yml"
+            || file.ends_with("manifest.yml")
+            || file == BUILD_INDEX
+            || file.starts_with("thumb.")
+        {
+            continue;
+        }
+        let canon = option_roots
+            .iter()
+            .find_map(|root| file.strip_prefix(root.as_str()))
+            .unwrap_or(file);
+        if !migrations.iter().any(|m| m.matches(canon)) {
+            continue;
+        }
+        let data = source.read_resource(&mut decompressor.lock(), file)?;
+        let stock = dump.get_data(canon).ok();
+        let resource = read_resource(&meta, canon, &data, stock.as_deref())?;
+        let data = minicbor_ser::to_vec(&resource)
+            .map_err(|e| anyhow::format_err!("{:?}", e))
+            .with_context(|| format!("Failed to serialize {canon}"))?;
+        let data = match source.compressed() {
+            true => compressor.compress(&data)?,
+            false => data,
+        };
+        rewritten.insert(file.as_str(), data);
+    }
+    let count = rewritten.len();
+    meta.api = env!("CARGO_PKG_VERSION").into();
+    let meta_text = serde_yaml::to_string(&meta)?;
+    match source {
+        Source::Folder(root) => {
+            for (file, data) in rewritten {
+                fs::write(root.join(file), data)?;
+            }
+            fs::write(root.join("meta.yml"), meta_text)?;
+        }
+        Source::Zip(zip) => {
+            let temp = path.with_extension("migrating");
+            let write = || -> Result<()> {
+                let mut writer = ZipWriter::new(fs::File::create(&temp)?);
+                let opts =
+                    SimpleFileOptions::default().compression_method(zip::CompressionMethod::Stored);
+                for file in files.iter() {
+                    writer.start_file(file.as_str(), opts)?;
+                    if file == "meta.yml" {
+                        writer.write_all(meta_text.as_bytes())?;
+                    } else if let Some(data) = rewritten.get(file.as_str()) {
+                        writer.write_all(data)?;
+                    } else {
+                        writer.write_all(&zip.get_file(file)?)?;
+                    }
+                }
+                writer.finish()?;
+                Ok(())
+            };
+            // The old ZIP may be memory mapped, so close it before replacing
+            // it.
+            let result = write().and_then(|_| {
+                drop(zip);
+                Ok(fs::rename(&temp, path)?)
+            });
+            if let Err(e) = result {
+                if temp.exists() {
+                    let _ = fs::remove_file(&temp);
+                }
+                return Err(e);
+            }
+        }
+    }
+    log::info!(
+        "Migrated {} to UKMM v{}, rewriting {count} resources",
+        meta.name,
+        meta.api
+    );
+    Ok(count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn meta(api: &str) -> Meta {
+        Meta {
+            api: api.into(),
+            name: "Test".into(),
+            version: "1.0.0".into(),
+            author: "Tester".into(),
+            category: crate::ModCategory::Other,
+            description: Default::default(),
+            platform: crate::ModPlatform::Universal,
+            url: None,
+            masters: Default::default(),
+            options: vec![],
+        }
+    }
+
+    #[test]
+    fn pending_migrations() {
+        assert!(
+            pending(&meta(env!("CARGO_PKG_VERSION")))
+                .unwrap()
+                .is_empty()
+        );
+        assert!(pending(&meta("0.9.2")).is_err());
+        let versions = pending(&meta("0.16.0"))
+            .unwrap()
+            .into_iter()
+            .map(|m| m.version)
+            .collect::<Vec<_>>();
+        assert_eq!(versions, ["0.17.0", "0.17.0"]);
+        let migrations = pending(&meta("0.15.3")).unwrap();
+        assert!(check_resource(&meta("0.15.3"), &migrations, "Actor/AS/Foo.bas").is_ok());
+        assert!(
+            check_resource(&meta("0.15.3"), &migrations, "Ecosystem/LevelSensor.byml").is_err()
+        );
+    }
+}
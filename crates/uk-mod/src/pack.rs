@@ -1,6 +1,6 @@
 use std::{
-    collections::{BTreeSet, HashSet},
-    io::Write,
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
+    io::{BufReader, Read, Write},
     path::{Path, PathBuf},
     sync::{atomic::AtomicUsize, Arc, LazyLock},
 };
@@ -9,31 +9,31 @@ use anyhow_ext::{Context, Result};
 use botw_utils::hashes::StockHashTable;
 use fs_err as fs;
 use join_str::jstr;
-use jwalk::WalkDir;
 use parking_lot::Mutex;
 use path_slash::PathExt;
 use rayon::prelude::*;
 use roead::{sarc::Sarc, yaz0::decompress_if};
 pub use sanitise_file_name::sanitise;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use serde_with::{serde_as, DefaultOnError};
+use sha2::{Digest, Sha256};
 use smartstring::alias::String;
 use uk_content::{
     canonicalize,
     constants::Language,
     platform_prefixes,
     prelude::{Endian, Mergeable},
-    resource::{is_mergeable_sarc, ResourceData},
+    resource::{is_mergeable_sarc, MergeableResource, ResourceData},
+    util::IndexMap,
 };
-use uk_util::PathExt as UkPathExt;
 use zip::{
     write::{FileOptions, SimpleFileOptions},
     ZipWriter as ZipW,
 };
 
 use crate::{
-    ExclusiveOptionGroup, Manifest, Meta, ModOption, ModOptionGroup, ModPlatform,
-    MultipleOptionGroup, OptionGroup,
+    source::ModSource, ExclusiveOptionGroup, Manifest, Meta, ModOption, ModOptionGroup,
+    ModPlatform, MultipleOptionGroup, OptionGroup,
 };
 
 pub type ZipWriter = Arc<Mutex<ZipW<fs::File>>>;
@@ -44,6 +44,7 @@ static WIIU_HASH_TABLE: LazyLock<StockHashTable> =
     LazyLock::new(|| StockHashTable::new(&botw_utils::hashes::Platform::WiiU));
 
 pub struct ModPacker {
+    source: ModSource,
     source_dir: PathBuf,
     current_root: PathBuf,
     meta: Meta,
@@ -53,13 +54,38 @@ pub struct ModPacker {
     masters: Vec<Arc<uk_reader::ResourceReader>>,
     hash_table: &'static StockHashTable,
     compressor: Arc<Mutex<zstd::bulk::Compressor<'static>>>,
+    reproducible: bool,
+    incremental: bool,
+    previous: Option<PreviousBuild>,
+    sources: Mutex<BTreeMap<String, SourceEntry>>,
+    changed: Mutex<Manifest>,
     _zip_opts: SimpleFileOptions,
     _out_file: PathBuf,
+    // Declared last so the ZIP is closed before this is removed
+    _partial: PartialFile,
+}
+
+/// The file a build is written to until it is complete, so the destination
+/// keeps the last build if packaging fails or is abandoned.
+struct PartialFile(PathBuf);
+
+impl Drop for PartialFile {
+    fn drop(&mut self) {
+        if self.0.exists() {
+            if let Err(e) = fs::remove_file(&self.0) {
+                log::warn!(
+                    "Failed to remove partial build at {}: {e}",
+                    self.0.display()
+                );
+            }
+        }
+    }
 }
 
 impl std::fmt::Debug for ModPacker {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("ModBuilder")
+            .field("source", &self.source)
             .field("source_dir", &self.source_dir)
             .field("current_root", &self.current_root)
             .field("meta", &self.meta)
@@ -70,6 +96,8 @@ impl std::fmt::Debug for ModPacker {
                 &jstr!("zip::ZipWriter at {&self._out_file.to_string_lossy()}"),
             )
             .field("built_resources", &self.built_resources)
+            .field("reproducible", &self.reproducible)
+            .field("incremental", &self.incremental)
             .finish()
     }
 }
@@ -162,12 +190,186 @@ impl From<BnpGroup> for ExclusiveOptionGroup {
     }
 }
 
+/// Name of the folder in a mod root holding resource diffs written as YAML.
+pub const DIFFS_FOLDER: &str = "diffs";
+
+/// A change to a game resource written directly as YAML in the `diffs`
+/// folder of a mod root, instead of as a modified game file. A YAML file in
+/// the folder can hold one diff or a list of them.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ResourceDiff {
+    /// The game file to change, relative to the content or DLC folder, for
+    /// example `Actor/Pack/Enemy_Bokoblin_Junior.sbactorpack`.
+    pub file: String,
+    /// The resource to change inside `file`, if it is nested in a SARC, for
+    /// example `Actor/DropTable/Enemy_Bokoblin_Junior.bdrop`.
+    #[serde(default)]
+    pub resource: Option<String>,
+    /// Whether `file` is a DLC file.
+    #[serde(default)]
+    pub dlc: bool,
+    /// The changes, in the same form as the `resource` of a mergeable
+    /// resource shown by `ukmm dump`.
+    pub diff: MergeableResource,
+}
+
+impl ResourceDiff {
+    /// Parse a YAML file holding one diff or a list of them.
+    pub fn parse_file(text: &str) -> Result<Vec<Self>> {
+        let value: serde_yaml::Value = serde_yaml::from_str(text)?;
+        Ok(if value.is_sequence() {
+            serde_yaml::from_value(value)?
+        } else {
+            vec![serde_yaml::from_value(value)?]
+        })
+    }
+
+    /// The file to change, as listed in the manifest.
+    fn file(&self) -> String {
+        self.file.replace('\\', "/").trim_start_matches('/').into()
+    }
+
+    /// The canonical path of the changed resource.
+    fn canon(&self) -> String {
+        let name = self.resource.as_ref().unwrap_or(&self.file);
+        match self.dlc {
+            true => canonicalize(format!("Aoc/0010/{}", name.trim_start_matches('/'))),
+            false => canonicalize(name.as_str()),
+        }
+    }
+
+    /// Make sure the diff is the same kind of resource as the stock file it
+    /// changes, since merging would otherwise fail or silently do nothing.
+    fn check_type(&self, stock: &ResourceData) -> Result<()> {
+        match stock.as_mergeable() {
+            Some(resource)
+                if std::mem::discriminant(resource) == std::mem::discriminant(&self.diff) =>
+            {
+                Ok(())
+            }
+            Some(resource) => {
+                anyhow_ext::bail!(
+                    "Diff for {} is a {} resource, but the stock file is a {resource} resource",
+                    self.canon(),
+                    self.diff
+                )
+            }
+            None => {
+                anyhow_ext::bail!(
+                    "Diff for {} is a {} resource, but the stock file is not mergeable",
+                    self.canon(),
+                    self.diff
+                )
+            }
+        }
+    }
+}
+
+/// Name of the entry in a packaged mod recording which resources each source
+/// file produced, so that later builds can reuse them.
+pub const BUILD_INDEX: &str = "build.yml";
+
+#[derive(Debug, Serialize, Deserialize)]
+struct BuildIndex {
+    api: String,
+    platform: ModPlatform,
+    /// The masters of the mod when it was built.
+    #[serde(default)]
+    masters: IndexMap<usize, (String, String)>,
+    /// The game dumps the build was diffed against.
+    #[serde(default)]
+    dump: Vec<std::string::String>,
+    sources: BTreeMap<String, SourceEntry>,
+}
+
+/// What one source file produced in a build.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+struct SourceEntry {
+    /// SHA-256 hash of the file's contents.
+    hash:     std::string::String,
+    /// The manifest entry for the file, if it belongs in the mod.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    manifest: Option<String>,
+    /// Resources written from the file.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    written:  Vec<String>,
+    /// Resources in the file which were already written from another one.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    shared:   Vec<String>,
+}
+
+/// A previous build of a mod, whose resources can be reused for source files
+/// which have not changed since.
+struct PreviousBuild {
+    zip:   Mutex<zip::ZipArchive<BufReader<fs::File>>>,
+    index: BuildIndex,
+}
+
+impl PreviousBuild {
+    /// Open a previous build, if it has a build index from this version of
+    /// UKMM for the same platform, masters and game dump, since every
+    /// resource is diffed against those.
+    fn open(path: &Path, meta: &Meta, dump: &[std::string::String]) -> Result<Option<Self>> {
+        let mut zip = zip::ZipArchive::new(BufReader::new(fs::File::open(path)?))?;
+        let index: BuildIndex = match zip.by_name(BUILD_INDEX) {
+            Ok(file) => serde_yaml::from_reader(file)?,
+            Err(zip::result::ZipError::FileNotFound) => {
+                log::debug!("Previous build has no build index");
+                return Ok(None);
+            }
+            Err(e) => return Err(e.into()),
+        };
+        if index.api.as_str() != env!("CARGO_PKG_VERSION") || index.platform != meta.platform {
+            log::debug!(
+                "Previous build is from UKMM v{} for {:?}, not reusing it",
+                index.api,
+                index.platform
+            );
+            return Ok(None);
+        }
+        if index.masters != meta.masters || index.dump != dump {
+            log::debug!("Previous build has other masters or game dump, not reusing it");
+            return Ok(None);
+        }
+        Ok(Some(Self {
+            zip: Mutex::new(zip),
+            index,
+        }))
+    }
+
+    fn read(&self, name: &str) -> Result<Vec<u8>> {
+        let mut zip = self.zip.lock();
+        let mut file = zip.by_name(name)?;
+        let mut data = Vec::with_capacity(file.size() as usize);
+        file.read_to_end(&mut data)?;
+        Ok(data)
+    }
+}
+
 impl ModPacker {
     pub fn parse_rules(path: PathBuf) -> Result<Meta> {
+        let parent = path.parent().context("No parent path???")?;
+        Self::parse_rules_text(
+            fs::read_to_string(&path)?,
+            parent.join("content").exists() || parent.join("aoc").exists(),
+        )
+    }
+
+    /// Parse the meta info of a mod from the `rules.txt` file in its source.
+    pub fn parse_source_rules(source: &ModSource) -> Result<Meta> {
+        let root = source.root();
+        Self::parse_rules_text(
+            source.read_to_string(&root.join("rules.txt"))?,
+            source.exists(&root.join("content")) || source.exists(&root.join("aoc")),
+        )
+    }
+
+    /// Wii U mods are told apart by their `content` or `aoc` folder.
+    fn parse_rules_text(text: std::string::String, wiiu: bool) -> Result<Meta> {
         use configparser::ini::Ini;
         let mut rules = Ini::new();
-        let parent = path.parent().context("No parent path???")?;
-        rules.load(&path).map_err(|e| anyhow_ext::anyhow!(e))?;
+        rules.read(text).map_err(|e| anyhow_ext::anyhow!(e))?;
         Ok(Meta {
             api: env!("CARGO_PKG_VERSION").into(),
             name: rules
@@ -184,7 +386,7 @@ impl ModPacker {
             author: Default::default(),
             masters: Default::default(),
             options: vec![],
-            platform: if parent.join("content").exists() || parent.join("aoc").exists() {
+            platform: if wiiu {
                 ModPlatform::Specific(Endian::Big)
             } else {
                 ModPlatform::Specific(Endian::Little)
@@ -195,7 +397,16 @@ impl ModPacker {
     }
 
     pub fn parse_info(path: PathBuf) -> Result<Meta> {
-        let info: InfoJson = serde_json::from_reader(fs::File::open(path)?)?;
+        Self::parse_info_text(&fs::read_to_string(path)?)
+    }
+
+    /// Parse the meta info of a BNP from the `info.json` file in its source.
+    pub fn parse_source_info(source: &ModSource) -> Result<Meta> {
+        Self::parse_info_text(&source.read_to_string(&source.root().join("info.json"))?)
+    }
+
+    fn parse_info_text(text: &str) -> Result<Meta> {
+        let info: InfoJson = serde_json::from_str(text)?;
         Ok(Meta {
             api: env!("CARGO_PKG_VERSION").into(),
             name: info.name,
@@ -223,35 +434,44 @@ impl ModPacker {
         })
     }
 
+    /// Package the mod in a folder, or in a ZIP, 7z or RAR archive, which is
+    /// read in place.
     pub fn new(
         source: impl AsRef<Path>,
         dest: impl AsRef<Path>,
         meta: Option<Meta>,
         masters: Vec<Arc<uk_reader::ResourceReader>>,
+    ) -> Result<Self> {
+        Self::from_source(ModSource::open(source)?, dest, meta, masters)
+    }
+
+    pub fn from_source(
+        source: ModSource,
+        dest: impl AsRef<Path>,
+        meta: Option<Meta>,
+        masters: Vec<Arc<uk_reader::ResourceReader>>,
     ) -> Result<Self> {
         fn inner(
-            source: &Path,
+            source: ModSource,
             dest: &Path,
             meta: Option<Meta>,
             masters: Vec<Arc<uk_reader::ResourceReader>>,
         ) -> Result<ModPacker> {
-            log::info!("Attempting to package mod at {}", source.display());
-            let source_dir = source.to_path_buf();
-            if !(source_dir.exists() && source_dir.is_dir()) {
-                anyhow_ext::bail!("Source directory does not exist: {}", source_dir.display());
-            }
+            let source_dir = source.root().to_path_buf();
+            log::info!("Attempting to package mod at {}", source_dir.display());
+            let exists = |name: &str| source.exists(&source_dir.join(name));
             let meta = if let Some(meta) = meta {
                 log::debug!("Using providing meta info:\n{:#?}", &meta);
                 meta
-            } else if let Some(rules) = source.join("rules.txt").exists_then() {
+            } else if source.is_file(&source_dir.join("rules.txt")) {
                 log::debug!("Attempting to parse existing rules.txt");
-                ModPacker::parse_rules(rules)?
-            } else if let Some(info) = source.join("info.json").exists_then() {
+                ModPacker::parse_source_rules(&source)?
+            } else if source.is_file(&source_dir.join("info.json")) {
                 log::debug!("Attempting to parse existing info.json");
                 log::warn!(
                     "`info.json` found. If this is a BNP, conversion will not work properly!"
                 );
-                ModPacker::parse_info(info)?
+                ModPacker::parse_source_info(&source)?
             } else {
                 anyhow_ext::bail!("No meta info provided or meta file available");
             };
@@ -259,14 +479,20 @@ impl ModPacker {
                 platform_prefixes(Endian::Big),
                 platform_prefixes(Endian::Little),
             );
-            let endian = if source.join(content_u).exists() || source.join(dlc_u).exists() {
+            let endian = if exists(content_u) || exists(dlc_u) {
                 Endian::Big
-            } else if source.join(content_nx).exists() || source.join(dlc_nx).exists() {
+            } else if exists(content_nx) || exists(dlc_nx) {
                 Endian::Little
+            } else if exists(DIFFS_FOLDER) {
+                // Diffs are the same on either platform
+                match meta.platform {
+                    ModPlatform::Specific(endian) => endian,
+                    ModPlatform::Universal => Endian::Big,
+                }
             } else {
                 anyhow_ext::bail!(
-                    "No content or DLC folder found in source at {}",
-                    source.display()
+                    "No content, DLC or diffs folder found in source at {}",
+                    source_dir.display()
                 );
             };
             let dest_file = if dest.is_dir() {
@@ -274,14 +500,13 @@ impl ModPacker {
             } else {
                 dest.to_path_buf()
             };
-            log::debug!("Using temp file at {}", dest_file.display());
-            if dest_file.exists() {
-                fs::remove_file(&dest_file)?;
-            }
+            let partial = PartialFile(dest_file.with_extension("partial"));
+            log::debug!("Using temp file at {}", partial.0.display());
             log::debug!("Creating ZIP file");
-            let zip = Arc::new(Mutex::new(ZipW::new(fs::File::create(&dest_file)?)));
+            let zip = Arc::new(Mutex::new(ZipW::new(fs::File::create(&partial.0)?)));
             Ok(ModPacker {
                 current_root: source_dir.clone(),
+                source,
                 source_dir,
                 endian,
                 zip,
@@ -295,134 +520,288 @@ impl ModPacker {
                 compressor: Arc::new(Mutex::new(
                     zstd::bulk::Compressor::with_dictionary(8, super::DICTIONARY)?,
                 )),
+                reproducible: false,
+                incremental: false,
+                previous: None,
+                sources: Default::default(),
+                changed: Default::default(),
                 _zip_opts: FileOptions::default()
                     .compression_method(zip::CompressionMethod::Stored),
                 _out_file: dest_file,
+                _partial: partial,
             })
         }
-        inner(source.as_ref(), dest.as_ref(), meta, masters)
+        inner(source, dest.as_ref(), meta, masters)
     }
 
-    fn write_resource(&self, canon: &str, resource: &ResourceData) -> Result<()> {
+    /// Package the mod so that the same source always gives a byte-identical
+    /// archive. Resources are processed one at a time in path order instead
+    /// of in parallel, and every ZIP entry gets the same fixed timestamp, so
+    /// this is slower than the default.
+    pub fn reproducible(mut self, reproducible: bool) -> Self {
+        self.reproducible = reproducible;
+        if reproducible {
+            self._zip_opts = self._zip_opts.last_modified_time(zip::DateTime::default());
+        }
+        self
+    }
+
+    /// Reuse the resources of source files which have not changed since the
+    /// previous build at the destination, instead of processing them again.
+    /// This needs a previous build from the same version of UKMM.
+    pub fn incremental(mut self, incremental: bool) -> Self {
+        self.incremental = incremental;
+        self
+    }
+
+    /// Write a resource to the current root of the mod, returning whether it
+    /// was written or skipped as a duplicate.
+    fn write_resource(&self, canon: &str, resource: &ResourceData) -> Result<bool> {
         let data = minicbor_ser::to_vec(&resource)
             .map_err(|e| anyhow::format_err!("{:?}", e))
             .with_context(|| jstr!("Failed to serialize {canon}"))?;
+        let data = self.compressor.lock().compress(&data)?;
+        self.write_entry(canon, &data)
+    }
+
+    fn write_entry(&self, canon: &str, data: &[u8]) -> Result<bool> {
         let zip_path = self
             .current_root
             .strip_prefix(&self.source_dir)?
             .join(canon);
-        {
+        let written = {
             log::trace!("Writing {} to ZIP", canon);
             let mut zip = self.zip.lock();
             match zip.start_file(zip_path.to_slash_lossy(), self._zip_opts) {
-                Ok(_) => zip.write_all(&self.compressor.lock().compress(&data)?)?,
+                Ok(_) => {
+                    zip.write_all(data)?;
+                    true
+                }
                 Err(zip::result::ZipError::InvalidArchive("Duplicate filename")) => {
                     log::warn!("Attempted to duplicate resource {}, skipping", canon);
+                    false
                 }
                 e => return Err(e.unwrap_err().into()),
             }
-        }
+        };
         self.built_resources.insert(canon.into());
-        Ok(())
+        Ok(written)
+    }
+
+    /// The path of a source file relative to the mod root, as used in the
+    /// build index.
+    fn source_key(&self, path: &Path) -> String {
+        path.strip_prefix(&self.source_dir)
+            .unwrap_or(path)
+            .to_slash_lossy()
+            .into()
+    }
+
+    /// Copy across the resources of source files which have not changed
+    /// since the previous build, returning the manifest entries of the files
+    /// reused.
+    fn reuse_sources(
+        &self,
+        previous: &PreviousBuild,
+        files: &[PathBuf],
+    ) -> Result<HashMap<PathBuf, Option<String>>> {
+        let mut candidates = files
+            .par_iter()
+            .map(|path| -> Result<Option<(&PathBuf, &SourceEntry)>> {
+                let Some(entry) = previous.index.sources.get(&self.source_key(path)) else {
+                    return Ok(None);
+                };
+                let hash = format!("{:x}", Sha256::digest(self.source.read(path)?));
+                Ok((entry.hash == hash).then_some((path, entry)))
+            })
+            .collect::<Result<Vec<_>>>()?
+            .into_iter()
+            .flatten()
+            .collect::<BTreeMap<_, _>>();
+        // A file holding resources which another file wrote first can only be
+        // reused if that file is reused as well.
+        loop {
+            let available = candidates
+                .values()
+                .copied()
+                .flat_map(|entry| entry.written.iter())
+                .collect::<HashSet<_>>();
+            let count = candidates.len();
+            candidates.retain(|_, entry| {
+                entry
+                    .shared
+                    .iter()
+                    .all(|canon| available.contains(canon) || self.built_resources.contains(canon))
+            });
+            if candidates.len() == count {
+                break;
+            }
+        }
+        let zip_root = self.current_root.strip_prefix(&self.source_dir)?;
+        let mut reused = HashMap::with_capacity(candidates.len());
+        for (path, entry) in candidates {
+            log::trace!("Reusing {} from the previous build", path.display());
+            for canon in entry.written.iter() {
+                let data = previous
+                    .read(&zip_root.join(canon.as_str()).to_slash_lossy())
+                    .with_context(|| jstr!("Failed to reuse {canon} from the previous build"))?;
+                self.write_entry(canon, &data)?;
+            }
+            self.sources
+                .lock()
+                .insert(self.source_key(path), entry.clone());
+            reused.insert(path.clone(), entry.manifest.clone());
+        }
+        Ok(reused)
     }
 
     fn collect_resources(&self, root: PathBuf) -> Result<BTreeSet<String>> {
-        let files = WalkDir::new(&root)
+        let files = self.source.files(&root);
+        log::debug!("Resources found in root {}:\n{:#?}", root.display(), &files);
+        let reused = match self.previous.as_ref() {
+            Some(previous) => self.reuse_sources(previous, &files)?,
+            None => HashMap::new(),
+        };
+        if !reused.is_empty() {
+            log::info!(
+                "Reusing {} unchanged files from the previous build",
+                reused.len()
+            );
+        }
+        let files = files
             .into_iter()
-            .filter_map(|f| {
-                f.ok()
-                    .and_then(|f| f.file_type().is_file().then(|| f.path()))
-            })
-            .collect::<Vec<PathBuf>>();
+            .filter(|path| !reused.contains_key(path))
+            .collect::<Vec<_>>();
         let total_files = files.len();
         let current_file = AtomicUsize::new(0);
-        log::debug!("Resources found in root {}:\n{:#?}", root.display(), &files);
+        let is_aoc = root.ends_with(platform_prefixes(self.endian).1);
+        let process = |path: PathBuf| -> Result<Option<String>> {
+            let mut source = SourceEntry::default();
+            let manifest = self.process_file(&path, &root, &mut source)?;
+            if let Some(file) = manifest.as_ref() {
+                let mut changed = self.changed.lock();
+                match is_aoc {
+                    true => changed.aoc_files.insert(file.clone()),
+                    false => changed.content_files.insert(file.clone()),
+                };
+            }
+            source.manifest.clone_from(&manifest);
+            source.written.sort();
+            source.shared.sort();
+            self.sources.lock().insert(self.source_key(&path), source);
+
+            let progress = current_file.load(std::sync::atomic::Ordering::Relaxed) + 1;
+            current_file.store(progress, std::sync::atomic::Ordering::Relaxed);
+            let percent = (progress as f64 / total_files as f64) * 100.0;
+            let fract = percent.fract();
+            if fract <= 0.1 || fract >= 0.95 {
+                log::trace!(
+                    "PROGRESSBuilding {} files: {}%",
+                    total_files,
+                    percent as usize
+                );
+            }
+            Ok(manifest)
+        };
+        // Which copy of a resource is kept when several files hold it depends
+        // on which is processed first, so reproducible output needs a fixed order
+        let files = if self.reproducible {
+            files
+                .into_iter()
+                .map(process)
+                .collect::<Result<Vec<Option<_>>>>()?
+        } else {
+            files
+                .into_par_iter()
+                .map(process)
+                .collect::<Result<Vec<Option<_>>>>()?
+        };
         Ok(files
-            .into_par_iter()
-            .map(|path| -> Result<Option<String>> {
-                log::trace!("Processing resource at {}", path.display());
-                let name: String = path
-                    .strip_prefix(&self.current_root)
-                    .unwrap()
-                    .to_slash_lossy()
-                    .into();
-                // We know this is sound because we got `path` by iterating the contents of `root`.
-                let canon = canonicalize(name.as_str());
-                let file_data = fs::read(&path)?;
-                let file_data = decompress_if(&file_data);
-
-                if path
-                    .file_name()
-                    .and_then(|s| s.to_str())
-                    .map(|s| s == "AocMainField.pack")
-                    .unwrap_or(false)
-                    && file_data.is_empty()
-                {
-                    self.write_resource(
-                        "Aoc/0010/Pack/AocMainField.pack",
-                        &ResourceData::Sarc(Default::default()),
-                    )?;
-                    return Ok(Some("Pack/AocMainField.pack".into()));
-                }
+            .into_iter()
+            .chain(reused.into_values())
+            .flatten()
+            .collect())
+    }
 
-                if name.ends_with("sizetable") || file_data.len() < 4 {
-                    return Ok(None);
-                }
+    /// Process one source file, recording what it holds in `source`, and
+    /// return its manifest entry if it belongs in the mod.
+    fn process_file(
+        &self,
+        path: &Path,
+        root: &Path,
+        source: &mut SourceEntry,
+    ) -> Result<Option<String>> {
+        log::trace!("Processing resource at {}", path.display());
+        let name: String = path
+            .strip_prefix(&self.current_root)
+            .unwrap()
+            .to_slash_lossy()
+            .into();
+        // We know this is sound because we got `path` by iterating the contents of `root`.
+        let canon = canonicalize(name.as_str());
+        let file_data = self.source.read(path)?;
+        source.hash = format!("{:x}", Sha256::digest(&file_data));
+        let file_data = decompress_if(&file_data);
 
-                if !self.hash_table.is_file_modded(&canon, &*file_data, true) {
-                    log::trace!("Resource {} not modded, ignoring", &canon);
-                    return Ok(None);
-                }
+        if path
+            .file_name()
+            .and_then(|s| s.to_str())
+            .map(|s| s == "AocMainField.pack")
+            .unwrap_or(false)
+            && file_data.is_empty()
+        {
+            if self.write_resource(
+                "Aoc/0010/Pack/AocMainField.pack",
+                &ResourceData::Sarc(Default::default()),
+            )? {
+                source
+                    .written
+                    .push("Aoc/0010/Pack/AocMainField.pack".into());
+            }
+            return Ok(Some("Pack/AocMainField.pack".into()));
+        }
 
-                let resource = ResourceData::from_binary(name.as_str(), &*file_data)
-                    .with_context(|| jstr!("Failed to parse resource {&name}"))?;
-                let is_mergeable = matches!(resource, ResourceData::Mergeable(_));
-                if let ResourceData::Mergeable(
-                    uk_content::resource::MergeableResource::BinaryOverride(v),
-                ) = &resource
-                {
-                    log::error!(
-                        "There was an error processing {name}. It will not be processed but will \
-                         be stored as-is, overriding anything else. Error details:\n{}",
-                        v.1
-                    );
-                }
-                self.process_resource(name.clone(), canon.clone(), resource, false)
-                    .with_context(|| jstr!("Failed to process resource {&canon}"))?;
-                if !is_mergeable && is_mergeable_sarc(canon.as_str(), file_data.as_ref()) {
-                    log::trace!(
-                        "Resource {} is a mergeable SARC, processing contents",
-                        &canon
-                    );
-                    self.process_sarc(
-                        Sarc::new(file_data.as_ref())?,
-                        name.as_str().as_ref(),
-                        self.hash_table.is_file_new(&canon),
-                        canon.starts_with("Aoc"),
-                    )
-                    .with_context(|| jstr!("Failed to process SARC file {&canon}"))?;
-                }
+        if name.ends_with("sizetable") || file_data.len() < 4 {
+            return Ok(None);
+        }
 
-                let progress = current_file.load(std::sync::atomic::Ordering::Relaxed) + 1;
-                current_file.store(progress, std::sync::atomic::Ordering::Relaxed);
-                let percent = (progress as f64 / total_files as f64) * 100.0;
-                let fract = percent.fract();
-                if fract <= 0.1 || fract >= 0.95 {
-                    log::trace!(
-                        "PROGRESSBuilding {} files: {}%",
-                        total_files,
-                        percent as usize
-                    );
-                }
+        if !self.hash_table.is_file_modded(&canon, &*file_data, true) {
+            log::trace!("Resource {} not modded, ignoring", &canon);
+            return Ok(None);
+        }
 
-                Ok(Some(
-                    path.strip_prefix(&root).unwrap().to_slash_lossy().into(),
-                ))
-            })
-            .collect::<Result<Vec<Option<_>>>>()?
-            .into_par_iter()
-            .filter_map(|x| x)
-            .collect())
+        let resource = ResourceData::from_binary(name.as_str(), &*file_data)
+            .with_context(|| jstr!("Failed to parse resource {&name}"))?;
+        let is_mergeable = matches!(resource, ResourceData::Mergeable(_));
+        if let ResourceData::Mergeable(uk_content::resource::MergeableResource::BinaryOverride(v)) =
+            &resource
+        {
+            log::error!(
+                "There was an error processing {name}. It will not be processed but will be \
+                 stored as-is, overriding anything else. Error details:\n{}",
+                v.1
+            );
+        }
+        self.process_resource(name.clone(), canon.clone(), resource, false, source)
+            .with_context(|| jstr!("Failed to process resource {&canon}"))?;
+        if !is_mergeable && is_mergeable_sarc(canon.as_str(), file_data.as_ref()) {
+            log::trace!(
+                "Resource {} is a mergeable SARC, processing contents",
+                &canon
+            );
+            self.process_sarc(
+                Sarc::new(file_data.as_ref())?,
+                name.as_str().as_ref(),
+                self.hash_table.is_file_new(&canon),
+                canon.starts_with("Aoc"),
+                source,
+            )
+            .with_context(|| jstr!("Failed to process SARC file {&canon}"))?;
+        }
+
+        Ok(Some(
+            path.strip_prefix(root).unwrap().to_slash_lossy().into(),
+        ))
     }
 
     fn process_resource(
@@ -431,9 +810,11 @@ impl ModPacker {
         canon: String,
         mut resource: ResourceData,
         in_new_sarc: bool,
+        source: &mut SourceEntry,
     ) -> Result<()> {
         if self.built_resources.contains(&canon) {
             log::trace!("Already processed {}, skipping", &canon);
+            source.shared.push(canon);
             return Ok(());
         }
         if canon.starts_with("Pack/Bootup_") {
@@ -510,12 +891,22 @@ impl ModPacker {
             }
         }
 
-        self.write_resource(&canon, &resource)?;
+        match self.write_resource(&canon, &resource)? {
+            true => source.written.push(canon),
+            false => source.shared.push(canon),
+        }
 
         Ok(())
     }
 
-    fn process_sarc(&self, sarc: Sarc, path: &Path, is_new_sarc: bool, is_aoc: bool) -> Result<()> {
+    fn process_sarc(
+        &self,
+        sarc: Sarc,
+        path: &Path,
+        is_new_sarc: bool,
+        is_aoc: bool,
+        source: &mut SourceEntry,
+    ) -> Result<()> {
         for file in sarc.files() {
             if file.data.is_empty() {
                 continue;
@@ -548,7 +939,7 @@ impl ModPacker {
                     v.1
                 );
             }
-            self.process_resource((&name).into(), canon.clone(), resource, is_new_sarc)?;
+            self.process_resource((&name).into(), canon.clone(), resource, is_new_sarc, source)?;
             if is_mergeable_sarc(canon.as_str(), file_data.as_ref()) {
                 log::trace!(
                     "Resource {} in SARC {} is a mergeable SARC, processing contents",
@@ -560,6 +951,7 @@ impl ModPacker {
                     name.as_ref(),
                     is_new_sarc,
                     is_aoc,
+                    source,
                 )
                 .with_context(|| {
                     jstr!("Failed to process {&canon} in SARC {&path.display().to_string()}")
@@ -569,6 +961,84 @@ impl ModPacker {
         Ok(())
     }
 
+    /// Write the resource diffs in the `diffs` folder of a root straight to
+    /// the mod, adding the game files they change to the manifest.
+    fn pack_diffs(&self, root: &Path, manifest: &mut Manifest) -> Result<()> {
+        let diffs_dir = root.join(DIFFS_FOLDER);
+        if !self.source.exists(&diffs_dir) {
+            return Ok(());
+        }
+        log::info!("Collecting resource diffs");
+        let files = self
+            .source
+            .files(&diffs_dir)
+            .into_iter()
+            .filter(|path| {
+                path.extension()
+                    .is_some_and(|ext| ext == "yml" || ext == "yaml")
+            })
+            .collect::<Vec<PathBuf>>();
+        for path in files {
+            let diffs = ResourceDiff::parse_file(&self.source.read_to_string(&path)?)
+                .with_context(|| {
+                    jstr!("Failed to parse resource diffs in {&path.display().to_string()}")
+                })?;
+            for diff in diffs {
+                let file = diff.file();
+                let file_canon = match diff.dlc {
+                    true => canonicalize(jstr!("Aoc/0010/{&file}")),
+                    false => canonicalize(file.as_str()),
+                };
+                let canon = diff.canon();
+                for name in [&file_canon, &canon] {
+                    if self.hash_table.is_file_new(name) {
+                        anyhow_ext::bail!(
+                            "Resource diff in {} changes {name}, which is not a game file. Diffs \
+                             can only change existing resources.",
+                            path.display()
+                        );
+                    }
+                }
+                if self.built_resources.contains(&canon) {
+                    anyhow_ext::bail!(
+                        "Resource diff in {} changes {canon}, which is already changed by another \
+                         diff or file in the mod",
+                        path.display()
+                    );
+                }
+                let stock = self
+                    .masters
+                    .iter()
+                    .rev()
+                    .find_map(|master| master.get_data(canon.as_str()).ok())
+                    .with_context(|| {
+                        format!(
+                            "Resource diff in {} changes {canon}, which could not be loaded from \
+                             the game dump",
+                            path.display()
+                        )
+                    })?;
+                diff.check_type(&stock)
+                    .with_context(|| format!("Bad resource diff in {}", path.display()))?;
+                log::trace!("Writing diff for {} from {}", &canon, path.display());
+                self.write_resource(&canon, &ResourceData::Mergeable(diff.diff))?;
+                let mut changed = self.changed.lock();
+                match diff.dlc {
+                    true => {
+                        changed.aoc_files.insert(file.clone());
+                        manifest.aoc_files.insert(file)
+                    }
+                    false => {
+                        changed.content_files.insert(file.clone());
+                        manifest.content_files.insert(file)
+                    }
+                };
+            }
+        }
+        log::info!("Finished collecting resource diffs");
+        Ok(())
+    }
+
     fn pack_root(&self, root: impl AsRef<Path>) -> Result<()> {
         fn inner(self_: &ModPacker, root: &Path) -> Result<()> {
             log::debug!("Packing from root of {}", root.display());
@@ -576,7 +1046,7 @@ impl ModPacker {
             let (content, aoc) = platform_prefixes(self_.endian);
             let content_dir = root.join(content);
             log::debug!("Checking for content folder at {}", content_dir.display());
-            let content_dir = if content_dir.exists() {
+            let content_dir = if self_.source.exists(&content_dir) {
                 log::debug!("Found content folder at {}", content_dir.display());
                 Some(content_dir)
             } else {
@@ -584,7 +1054,7 @@ impl ModPacker {
             };
             let aoc_dir = root.join(aoc);
             log::debug!("Checking for DLC folder at {}", aoc_dir.display());
-            let aoc_dir = if aoc_dir.exists() {
+            let aoc_dir = if self_.source.exists(&aoc_dir) {
                 log::debug!("Found DLC folder at {}", aoc_dir.display());
                 Some(aoc_dir)
             } else {
@@ -611,6 +1081,7 @@ impl ModPacker {
                 content_files,
                 aoc_files,
             };
+            self_.pack_diffs(root, &mut manifest)?;
             log::trace!("CLEARPROGRESS");
             if manifest
                 .aoc_files
@@ -623,6 +1094,11 @@ impl ModPacker {
                     &ResourceData::Sarc(Default::default()),
                 )?;
                 manifest.aoc_files.insert("Pack/AocMainField.pack".into());
+                self_
+                    .changed
+                    .lock()
+                    .aoc_files
+                    .insert("Pack/AocMainField.pack".into());
             }
             let manifest = serde_yaml::to_string(&manifest)?;
             log::info!("Writing manifest");
@@ -642,7 +1118,7 @@ impl ModPacker {
 
     fn collect_roots(&self) -> Vec<PathBuf> {
         let opt_root = self.source_dir.join("options");
-        let mut roots = HashSet::new();
+        let mut roots = BTreeSet::new();
         for group in &self.meta.options {
             roots.extend(group.options().iter().map(|opt| opt_root.join(&opt.path)))
         }
@@ -654,10 +1130,10 @@ impl ModPacker {
         for name in ["thumb", "thumbnail", "preview"] {
             for ext in ["jpg", "jpeg", "png", "svg"] {
                 let path = self.source_dir.join(name).with_extension(ext);
-                if path.exists() {
+                if self.source.is_file(&path) {
                     let mut zip = self.zip.lock();
                     zip.start_file(format!("thumb.{}", ext), self._zip_opts)?;
-                    zip.write_all(&fs::read(path)?)?;
+                    zip.write_all(&self.source.read(&path)?)?;
                     return Ok(());
                 }
             }
@@ -665,7 +1141,74 @@ impl ModPacker {
         Ok(())
     }
 
-    pub fn pack(mut self) -> Result<PathBuf> {
+    /// Whether any source file of the main mod root changed since the
+    /// previous build.
+    fn base_changed(&self, previous: &PreviousBuild) -> bool {
+        let is_base = |key: &str| !key.starts_with("options/");
+        let sources = self.sources.lock();
+        !sources
+            .iter()
+            .filter(|(key, _)| is_base(key.as_str()))
+            .map(|(key, entry)| (key, &entry.hash))
+            .eq(previous
+                .index
+                .sources
+                .iter()
+                .filter(|(key, _)| is_base(key.as_str()))
+                .map(|(key, entry)| (key, &entry.hash)))
+    }
+
+    /// The game files changed since a previous build: those of every source
+    /// file which was processed again, and of every one which is gone.
+    fn changes(&self, previous: BTreeMap<String, SourceEntry>) -> Manifest {
+        let mut changed = std::mem::take(&mut *self.changed.lock());
+        let (_, aoc) = platform_prefixes(self.endian);
+        let aoc_dirs = std::iter::once(self.source_dir.clone())
+            .chain(self.collect_roots())
+            .map(|root| root.join(aoc))
+            .collect::<Vec<_>>();
+        let sources = self.sources.lock();
+        for (key, entry) in previous {
+            let Some(file) = entry.manifest.filter(|_| !sources.contains_key(&key)) else {
+                continue;
+            };
+            let path = self.source_dir.join(key.as_str());
+            match aoc_dirs.iter().any(|dir| path.starts_with(dir)) {
+                true => changed.aoc_files.insert(file),
+                false => changed.content_files.insert(file),
+            };
+        }
+        changed
+    }
+
+    pub fn pack(self) -> Result<PathBuf> {
+        self.pack_changes().map(|(path, _)| path)
+    }
+
+    /// The sources of the game dumps used as masters, to tell whether a
+    /// previous build was diffed against the same files.
+    fn dump_ids(&self) -> Vec<std::string::String> {
+        self.masters.iter().map(|m| m.source_ser()).collect()
+    }
+
+    /// Package the mod, also returning the game files which changed since
+    /// the previous build if it could be reused, so that only those need to
+    /// be merged again.
+    pub fn pack_changes(mut self) -> Result<(PathBuf, Option<Manifest>)> {
+        let dump = self.dump_ids();
+        // The destination is only replaced once packaging is done, so the
+        // last build there can be reused until then.
+        if self.incremental && self._out_file.exists() {
+            self.previous =
+                PreviousBuild::open(&self._out_file, &self.meta, &dump).unwrap_or_else(|e| {
+                    log::warn!("Could not read previous build, packaging everything: {e:?}");
+                    None
+                });
+        }
+        let previous_sources = self
+            .previous
+            .as_ref()
+            .map(|previous| previous.index.sources.clone());
         self.pack_root(&self.source_dir).with_context(|| {
             format!(
                 "Failed to package mod root at {} for mod {}",
@@ -673,12 +1216,23 @@ impl ModPacker {
                 self.meta.name
             )
         })?;
-        if self.source_dir.join("options").exists() {
+        if self.source.exists(&self.source_dir.join("options")) {
             log::debug!("Mod contains options");
+            // Options are diffed against the base mod, so nothing can be
+            // reused for them if it changed.
+            if self
+                .previous
+                .as_ref()
+                .is_some_and(|previous| self.base_changed(previous))
+            {
+                log::debug!("Base mod changed, not reusing options from the previous build");
+                self.previous = None;
+            }
             self.masters
-                .push(Arc::new(uk_reader::ResourceReader::from_unpacked_mod(
-                    &self.source_dir,
-                )?));
+                .push(Arc::new(uk_reader::ResourceReader::from_loader(
+                    Box::new(self.source.clone()),
+                    self.endian,
+                )));
             log::info!("Collecting resources for options");
             for root in self.collect_roots() {
                 self.current_root.clone_from(&root);
@@ -692,19 +1246,35 @@ impl ModPacker {
             }
         }
         self.pack_thumbnail()?;
+        let changed = previous_sources.map(|previous| self.changes(previous));
         match Arc::try_unwrap(self.zip).map(|z| z.into_inner()) {
             Ok(mut zip) => {
                 log::info!("Writing meta");
                 zip.start_file("meta.yml", self._zip_opts)?;
                 zip.write_all(serde_yaml::to_string(&self.meta)?.as_bytes())?;
+                // The index is only of use to later incremental builds
+                if self.incremental {
+                    let index = BuildIndex {
+                        api: env!("CARGO_PKG_VERSION").into(),
+                        platform: self.meta.platform,
+                        masters: self.meta.masters.clone(),
+                        dump,
+                        sources: std::mem::take(&mut *self.sources.lock()),
+                    };
+                    zip.start_file(BUILD_INDEX, self._zip_opts)?;
+                    zip.write_all(serde_yaml::to_string(&index)?.as_bytes())?;
+                }
                 zip.finish()?
             }
             Err(_) => {
                 anyhow_ext::bail!("Failed to finish writing zip, this is probably a big deal")
             }
         };
+        // Close the previous build before replacing it
+        self.previous = None;
+        fs::rename(&self._partial.0, &self._out_file)?;
         log::info!("Completed packaging mod");
-        Ok(self._out_file)
+        Ok((self._out_file, changed))
     }
 }
 
@@ -715,46 +1285,166 @@ mod tests {
 
     use super::*;
     use crate::{ModOption, MultipleOptionGroup, OptionGroup};
+    fn test_meta() -> Meta {
+        Meta {
+            api: env!("CARGO_PKG_VERSION").into(),
+            platform: ModPlatform::Specific(Endian::Big),
+            name: "Test Mod".into(),
+            version: "0.1.0".into(),
+            category: crate::ModCategory::Overhaul,
+            author: "Lord Caleb".into(),
+            description: "A test mod".into(),
+            masters: IndexMap::default(),
+            url: None,
+            options: vec![OptionGroup::Multiple(MultipleOptionGroup {
+                name: "Test Option Group".into(),
+                description: "A test option group".into(),
+                defaults: ["option1".into()].into_iter().collect(),
+                options: [ModOption {
+                    name: "Test Option".into(),
+                    description: "An option".into(),
+                    path: "option1".into(),
+                    requires: vec![],
+                }]
+                .into_iter()
+                .collect(),
+                required: false,
+            })],
+        }
+    }
+
+    fn test_dump() -> Arc<ResourceReader> {
+        Arc::new(
+            serde_yaml::from_str::<ResourceReader>(
+                &std::fs::read_to_string("../.vscode/dump.yml").unwrap(),
+            )
+            .unwrap(),
+        )
+    }
+
     #[test]
     fn pack_mod() {
         env_logger::init();
         let source = Path::new("test/wiiu");
         let dest = Path::new("test/wiiu.zip");
-        let rom_reader = serde_yaml::from_str::<ResourceReader>(
-            &std::fs::read_to_string("../.vscode/dump.yml").unwrap(),
+        let builder = ModPacker::new(source, dest, Some(test_meta()), vec![test_dump()]).unwrap();
+        builder.pack().unwrap();
+    }
+
+    #[test]
+    fn pack_reproducible() {
+        let source = Path::new("test/wiiu");
+        let dir = tempfile::tempdir().unwrap();
+        let dump = test_dump();
+        let builds = ["first.zip", "second.zip"].map(|name| {
+            let dest = dir.path().join(name);
+            ModPacker::new(source, &dest, Some(test_meta()), vec![dump.clone()])
+                .unwrap()
+                .reproducible(true)
+                .pack()
+                .unwrap();
+            fs::read(&dest).unwrap()
+        });
+        assert!(builds[0] == builds[1], "Reproducible builds differ");
+    }
+
+    #[test]
+    fn parse_diffs() {
+        let one = ResourceDiff::parse_file(
+            "{file: Actor/Pack/Foo.sbactorpack, resource: Actor/DropTable/Foo.bdrop, diff: \
+             !DropTable {}}",
         )
         .unwrap();
-        let builder = ModPacker::new(
-            source,
-            dest,
-            Some(Meta {
-                api: env!("CARGO_PKG_VERSION").into(),
-                platform: ModPlatform::Specific(Endian::Big),
-                name: "Test Mod".into(),
-                version: "0.1.0".into(),
-                category: crate::ModCategory::Overhaul,
-                author: "Lord Caleb".into(),
-                description: "A test mod".into(),
-                masters: IndexMap::default(),
-                url: None,
-                options: vec![OptionGroup::Multiple(MultipleOptionGroup {
-                    name: "Test Option Group".into(),
-                    description: "A test option group".into(),
-                    defaults: ["option1".into()].into_iter().collect(),
-                    options: [ModOption {
-                        name: "Test Option".into(),
-                        description: "An option".into(),
-                        path: "option1".into(),
-                        requires: vec![],
-                    }]
-                    .into_iter()
-                    .collect(),
-                    required: false,
-                })],
-            }),
-            vec![Arc::new(rom_reader)],
+        assert_eq!(one.len(), 1);
+        assert_eq!(one[0].file().as_str(), "Actor/Pack/Foo.sbactorpack");
+        assert_eq!(one[0].canon().as_str(), "Actor/DropTable/Foo.bdrop");
+        let many = ResourceDiff::parse_file(
+            "[{file: Actor/ActorInfo.product.sbyml, diff: !DropTable {}}, {file: \
+             Pack/AocMainField.pack, resource: Map/MainField/Static.smubin, dlc: true, diff: \
+             !DropTable {}}]",
         )
         .unwrap();
-        builder.pack().unwrap();
+        assert_eq!(many[0].canon().as_str(), "Actor/ActorInfo.product.byml");
+        let actor_info = ResourceData::Mergeable(MergeableResource::ActorInfo(Default::default()));
+        assert!(many[0].check_type(&actor_info).is_err());
+        assert!(
+            many[0]
+                .check_type(&ResourceData::Binary(b"abc".to_vec()))
+                .is_err()
+        );
+        let drop_table = ResourceData::Mergeable(MergeableResource::DropTable(Default::default()));
+        assert!(one[0].check_type(&drop_table).is_ok());
+        assert_eq!(
+            many[1].canon().as_str(),
+            "Aoc/0010/Map/MainField/Static.mubin"
+        );
+        assert!(ResourceDiff::parse_file("{file: Pack/Bootup.pack, extra: 1}").is_err());
+    }
+
+    #[test]
+    fn previous_build() {
+        let dir = tempfile::tempdir().unwrap();
+        let meta = Meta {
+            platform: ModPlatform::Specific(Endian::Little),
+            ..test_meta()
+        };
+        let dump = vec!["dump".to_string()];
+        let write = |name: &str, api: &str| {
+            let path = dir.path().join(name);
+            let mut zip = ZipW::new(fs::File::create(&path).unwrap());
+            let index = BuildIndex {
+                api: api.into(),
+                platform: ModPlatform::Specific(Endian::Little),
+                masters: Default::default(),
+                dump: dump.clone(),
+                sources: [(
+                    "content/Actor/ActorInfo.product.sbyml".into(),
+                    SourceEntry {
+                        hash:     "abc".into(),
+                        manifest: Some("Actor/ActorInfo.product.sbyml".into()),
+                        written:  vec!["Actor/ActorInfo.product.byml".into()],
+                        shared:   vec![],
+                    },
+                )]
+                .into_iter()
+                .collect(),
+            };
+            zip.start_file(BUILD_INDEX, SimpleFileOptions::default())
+                .unwrap();
+            zip.write_all(serde_yaml::to_string(&index).unwrap().as_bytes())
+                .unwrap();
+            zip.finish().unwrap();
+            path
+        };
+        let current = write("current.zip", env!("CARGO_PKG_VERSION"));
+        let previous = PreviousBuild::open(&current, &meta, &dump)
+            .unwrap()
+            .unwrap();
+        assert_eq!(previous.index.sources.len(), 1);
+        let wiiu = Meta {
+            platform: ModPlatform::Specific(Endian::Big),
+            ..meta.clone()
+        };
+        assert!(
+            PreviousBuild::open(&current, &wiiu, &dump)
+                .unwrap()
+                .is_none()
+        );
+        let mut with_master = meta.clone();
+        with_master
+            .masters
+            .insert(1, ("Master".into(), "1.0.0".into()));
+        assert!(
+            PreviousBuild::open(&current, &with_master, &dump)
+                .unwrap()
+                .is_none()
+        );
+        assert!(
+            PreviousBuild::open(&current, &meta, &["other".to_string()])
+                .unwrap()
+                .is_none()
+        );
+        let old = write("old.zip", "0.1.0");
+        assert!(PreviousBuild::open(&old, &meta, &dump).unwrap().is_none());
     }
 }
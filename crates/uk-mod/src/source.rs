@@ -0,0 +1,588 @@
+//! The source files of a mod, read from a folder or straight out of a ZIP, 7z
+//! or RAR archive without extracting it first. Files written to a source while
+//! converting it go to an overlay folder, so only those ever touch the disk.
+use std::{
+    collections::{BTreeSet, HashMap},
+    io::Read,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use anyhow_ext::{Context, Result};
+use fs_err as fs;
+use jwalk::WalkDir;
+use parking_lot::{Mutex, RwLock};
+use path_slash::PathExt;
+use serde::{Deserialize, Serialize};
+use uk_content::{platform_prefixes, prelude::Endian};
+use uk_reader::{ROMError, ResourceLoader};
+
+use crate::unpack::ParallelZipReader;
+
+/// Solid 7z blocks have to be decoded from the start to reach a file, so
+/// blocks which unpack to no more than this many bytes are kept in memory after
+/// the first read from them. Larger blocks are decoded again for each file.
+const BLOCK_CACHE_LIMIT: u64 = 64 * 1024 * 1024;
+
+struct SevenZ {
+    archive: sevenz_rust::Archive,
+    /// The index of each file in the archive by its slash path.
+    files:   HashMap<String, usize>,
+    /// The files of the last block read, if it was small enough to keep.
+    block:   Mutex<Option<(usize, HashMap<String, Vec<u8>>)>>,
+}
+
+enum Archive {
+    Zip(ParallelZipReader),
+    SevenZ(SevenZ),
+    Rar,
+}
+
+/// The files of a mod before it is packaged. Paths passed to a source are
+/// joined onto its [`root`](ModSource::root), which for an archive is the
+/// archive path followed by the folder in it holding the mod.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(into = "SourceInfo", try_from = "SourceInfo")]
+pub struct ModSource {
+    path:    PathBuf,
+    root:    PathBuf,
+    /// The folder in the archive holding the mod, empty for the top level.
+    folder:  String,
+    archive: Option<Arc<Archive>>,
+    /// Every file in the archive, as slash paths from the top level.
+    entries: Arc<BTreeSet<String>>,
+    overlay: Option<PathBuf>,
+    /// Files written to the overlay, as slash paths from the root.
+    written: Arc<RwLock<BTreeSet<String>>>,
+}
+
+impl std::fmt::Debug for ModSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ModSource")
+            .field("path", &self.path)
+            .field("root", &self.root)
+            .field("archive", &self.archive.is_some())
+            .field("overlay", &self.overlay)
+            .finish()
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct SourceInfo {
+    path: PathBuf,
+    root: PathBuf,
+}
+
+impl From<ModSource> for SourceInfo {
+    fn from(source: ModSource) -> Self {
+        Self {
+            path: source.path,
+            root: source.root,
+        }
+    }
+}
+
+impl TryFrom<SourceInfo> for ModSource {
+    type Error = anyhow_ext::Error;
+
+    fn try_from(info: SourceInfo) -> Result<Self> {
+        let source = Self::open(&info.path)?;
+        match info.root == info.path {
+            true => Ok(source),
+            false => source.rooted_at(&info.root),
+        }
+    }
+}
+
+/// The entries of a set at or below a slash path.
+fn entries_under<'a>(
+    entries: &'a BTreeSet<String>,
+    dir: &str,
+) -> impl Iterator<Item = &'a String> + 'a {
+    let prefix = match dir.is_empty() {
+        true => String::new(),
+        false => format!("{dir}/"),
+    };
+    entries
+        .range(prefix.clone()..)
+        .take_while(move |entry| entry.starts_with(&prefix))
+}
+
+fn contains_path(entries: &BTreeSet<String>, path: &str) -> bool {
+    entries.contains(path) || entries_under(entries, path).next().is_some()
+}
+
+fn extension(path: &Path) -> String {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or_default()
+        .to_lowercase()
+}
+
+fn read_zip(path: &Path) -> Result<(Archive, BTreeSet<String>)> {
+    let zip = ParallelZipReader::open(path, true).context("Failed to open ZIP")?;
+    let names = zip
+        .iter()
+        .map(|name| name.to_slash_lossy().trim_end_matches('/').to_owned())
+        .collect::<BTreeSet<_>>();
+    // Folders are not always stored in a ZIP, so anything with files under it
+    // is taken to be one
+    let entries = names
+        .iter()
+        .filter(|name| entries_under(&names, name).next().is_none())
+        .cloned()
+        .collect();
+    Ok((Archive::Zip(zip), entries))
+}
+
+fn read_7z(path: &Path) -> Result<(Archive, BTreeSet<String>)> {
+    let archive = sevenz_rust::Archive::open(path).context("Failed to open 7Z")?;
+    let files = archive
+        .files
+        .iter()
+        .enumerate()
+        .filter(|(_, entry)| !entry.is_directory)
+        .map(|(i, entry)| (entry.name.replace('\\', "/"), i))
+        .collect::<HashMap<_, _>>();
+    let entries = files.keys().cloned().collect();
+    Ok((
+        Archive::SevenZ(SevenZ {
+            archive,
+            files,
+            block: Mutex::new(None),
+        }),
+        entries,
+    ))
+}
+
+fn read_rar(path: &Path) -> Result<(Archive, BTreeSet<String>)> {
+    let mut entries = BTreeSet::new();
+    for header in unrar::Archive::new(path)
+        .open_for_listing()
+        .context("Failed to open RAR")?
+    {
+        let header = header.context("Failed to read RAR")?;
+        if header.is_file() {
+            entries.insert(header.filename.to_slash_lossy().into_owned());
+        }
+    }
+    Ok((Archive::Rar, entries))
+}
+
+/// Decode a file from a 7z archive, reading only the block holding it.
+fn get_7z_file(path: &Path, sz: &SevenZ, name: &str) -> Result<Vec<u8>> {
+    let index = *sz
+        .files
+        .get(name)
+        .with_context(|| format!("File {name} not found in {}", path.display()))?;
+    let Some(folder) = sz.archive.stream_map.file_folder_index[index] else {
+        return Ok(vec![]);
+    };
+    let mut block = sz.block.lock();
+    if let Some((_, files)) = block.as_ref().filter(|(cached, _)| *cached == folder) {
+        return files
+            .get(name)
+            .cloned()
+            .with_context(|| format!("File {name} not found in {}", path.display()));
+    }
+    let keep = sz.archive.folders[folder].get_unpack_size() <= BLOCK_CACHE_LIMIT;
+    let mut kept = HashMap::new();
+    let mut data = None;
+    let mut file = fs::File::open(path)?;
+    let password = sevenz_rust::Password::empty();
+    sevenz_rust::BlockDecoder::new(folder, &sz.archive, password.as_slice(), &mut file)
+        .for_each_entries(&mut |entry, reader| {
+            let entry_name = entry.name.replace('\\', "/");
+            if entry.is_directory || (!keep && entry_name != name) {
+                std::io::copy(reader, &mut std::io::sink())?;
+                return Ok(true);
+            }
+            let mut buf = Vec::with_capacity(entry.size as usize);
+            reader.read_to_end(&mut buf)?;
+            if !keep {
+                data = Some(buf);
+                return Ok(false);
+            }
+            if entry_name == name {
+                data = Some(buf.clone());
+            }
+            kept.insert(entry_name, buf);
+            Ok(true)
+        })
+        .context("Failed to read 7Z")?;
+    if keep {
+        *block = Some((folder, kept));
+    }
+    data.with_context(|| format!("File {name} not found in {}", path.display()))
+}
+
+/// Read a file from a RAR archive, skipping the entries before it.
+fn get_rar_file(path: &Path, name: &str) -> Result<Vec<u8>> {
+    let mut archive = unrar::Archive::new(path)
+        .open_for_processing()
+        .context("Failed to open RAR")?;
+    while let Some(header) = archive.read_header().context("Failed to read header")? {
+        if header.entry().is_file() && header.entry().filename.to_slash_lossy() == name {
+            let (data, _) = header
+                .read()
+                .with_context(|| format!("Failed to read {name} from RAR"))?;
+            return Ok(data);
+        }
+        archive = header.skip().context("Failed to read RAR")?;
+    }
+    anyhow_ext::bail!("File {name} not found in {}", path.display())
+}
+
+impl ModSource {
+    /// Open a mod folder, or a ZIP, 7z (including BNPs) or RAR archive
+    /// holding one. Files in an archive are only decompressed when read.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        fn inner(path: &Path) -> Result<ModSource> {
+            if !path.exists() {
+                anyhow_ext::bail!("Mod source does not exist: {}", path.display());
+            }
+            let (archive, entries) = if path.is_dir() {
+                (None, BTreeSet::new())
+            } else {
+                let (archive, entries) = match extension(path).as_str() {
+                    "zip" => read_zip(path)?,
+                    "7z" | "bnp" => read_7z(path)?,
+                    "rar" => read_rar(path)?,
+                    ext => anyhow_ext::bail!("{} files are not supported", ext.to_uppercase()),
+                };
+                (Some(Arc::new(archive)), entries)
+            };
+            Ok(ModSource {
+                path: path.to_path_buf(),
+                root: path.to_path_buf(),
+                folder: String::new(),
+                archive,
+                entries: Arc::new(entries),
+                overlay: None,
+                written: Default::default(),
+            })
+        }
+        inner(path.as_ref())
+    }
+
+    /// Use a folder inside this source as the root of the mod.
+    pub fn rooted_at(&self, root: &Path) -> Result<Self> {
+        let rel = self
+            .relative(root)
+            .with_context(|| format!("{} is not in {}", root.display(), self.root.display()))?;
+        let skip = match rel.is_empty() {
+            true => 0,
+            false => rel.len() + 1,
+        };
+        let written = entries_under(&self.written.read(), &rel)
+            .map(|file| file[skip..].to_owned())
+            .collect();
+        Ok(Self {
+            root: root.to_path_buf(),
+            folder: self.member(&rel),
+            overlay: self.overlay.as_ref().map(|overlay| overlay.join(&rel)),
+            written: Arc::new(RwLock::new(written)),
+            ..self.clone()
+        })
+    }
+
+    /// Write any files changed in this source to a folder, leaving the
+    /// original files alone.
+    pub fn with_overlay(mut self, overlay: impl Into<PathBuf>) -> Self {
+        self.overlay = Some(overlay.into());
+        self
+    }
+
+    /// The folder or archive this source was opened from.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// The root of the mod. This is only a real path if the source is a
+    /// folder.
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    pub fn is_archive(&self) -> bool {
+        self.archive.is_some()
+    }
+
+    fn relative(&self, path: &Path) -> Option<String> {
+        path.strip_prefix(&self.root)
+            .ok()
+            .map(|rel| rel.to_slash_lossy().trim_end_matches('/').to_owned())
+    }
+
+    /// The name of an archive entry from its path relative to the root.
+    fn member(&self, rel: &str) -> String {
+        match (self.folder.is_empty(), rel.is_empty()) {
+            (true, _) => rel.to_owned(),
+            (false, true) => self.folder.clone(),
+            (false, false) => format!("{}/{rel}", self.folder),
+        }
+    }
+
+    /// Whether a file or folder exists in the source.
+    pub fn exists(&self, path: &Path) -> bool {
+        let Some(rel) = self.relative(path) else {
+            return false;
+        };
+        contains_path(&self.written.read(), &rel)
+            || match self.archive {
+                None => path.exists(),
+                Some(_) => {
+                    let member = self.member(&rel);
+                    member.is_empty() || contains_path(&self.entries, &member)
+                }
+            }
+    }
+
+    pub fn is_file(&self, path: &Path) -> bool {
+        let Some(rel) = self.relative(path) else {
+            return false;
+        };
+        self.written.read().contains(&rel)
+            || match self.archive {
+                None => path.is_file(),
+                Some(_) => self.entries.contains(&self.member(&rel)),
+            }
+    }
+
+    /// Every file in a folder of the source and its subfolders, sorted by
+    /// path.
+    pub fn files(&self, dir: &Path) -> Vec<PathBuf> {
+        let Some(rel) = self.relative(dir) else {
+            return vec![];
+        };
+        let mut files = match self.archive {
+            None => {
+                WalkDir::new(dir)
+                    .into_iter()
+                    .filter_map(|entry| entry.ok())
+                    .filter(|entry| entry.file_type().is_file())
+                    .map(|entry| entry.path())
+                    .collect::<BTreeSet<_>>()
+            }
+            Some(_) => {
+                let skip = match self.folder.is_empty() {
+                    true => 0,
+                    false => self.folder.len() + 1,
+                };
+                entries_under(&self.entries, &self.member(&rel))
+                    .map(|entry| self.root.join(&entry[skip..]))
+                    .collect()
+            }
+        };
+        files.extend(entries_under(&self.written.read(), &rel).map(|file| self.root.join(file)));
+        files.into_iter().collect()
+    }
+
+    /// The folders directly inside a folder of the source.
+    pub fn subfolders(&self, dir: &Path) -> Vec<PathBuf> {
+        self.files(dir)
+            .into_iter()
+            .filter_map(|file| {
+                let mut components = file.strip_prefix(dir).ok()?.components();
+                let folder = components.next()?;
+                components.next().map(|_| dir.join(folder))
+            })
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .collect()
+    }
+
+    pub fn read(&self, path: &Path) -> Result<Vec<u8>> {
+        let rel = self
+            .relative(path)
+            .with_context(|| format!("{} is not in the mod source", path.display()))?;
+        if self.written.read().contains(&rel) {
+            if let Some(overlay) = self.overlay.as_ref() {
+                return Ok(fs::read(overlay.join(&rel))?);
+            }
+        }
+        match self.archive.as_deref() {
+            None => Ok(fs::read(path)?),
+            Some(Archive::Zip(zip)) => zip.get_file(self.member(&rel)),
+            Some(Archive::SevenZ(sz)) => get_7z_file(&self.path, sz, &self.member(&rel)),
+            Some(Archive::Rar) => get_rar_file(&self.path, &self.member(&rel)),
+        }
+    }
+
+    pub fn read_to_string(&self, path: &Path) -> Result<std::string::String> {
+        std::string::String::from_utf8(self.read(path)?)
+            .with_context(|| format!("{} is not valid UTF-8", path.display()))
+    }
+
+    /// Write a file to the overlay folder, after which it is read from there
+    /// instead of from the original source.
+    pub fn write(&self, path: &Path, data: impl AsRef<[u8]>) -> Result<()> {
+        let overlay = self
+            .overlay
+            .as_ref()
+            .context("Cannot change a mod source without an overlay folder")?;
+        let rel = self
+            .relative(path)
+            .with_context(|| format!("{} is not in the mod source", path.display()))?;
+        let dest = overlay.join(&rel);
+        if let Some(parent) = dest.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(dest, data)?;
+        self.written.write().insert(rel);
+        Ok(())
+    }
+
+    /// The platform of the mod, by its content folder.
+    fn endian(&self) -> Endian {
+        match self.exists(&self.root.join("content")) {
+            true => Endian::Big,
+            false => Endian::Little,
+        }
+    }
+
+    fn read_game_file(&self, dir: &str, name: &Path) -> uk_reader::Result<Vec<u8>> {
+        let path = self.root.join(dir).join(name);
+        match self.is_file(&path) {
+            true => Ok(self.read(&path)?),
+            false => {
+                Err(ROMError::FileNotFound(
+                    name.to_string_lossy().into(),
+                    self.path.clone(),
+                ))
+            }
+        }
+    }
+}
+
+#[typetag::serde]
+impl ResourceLoader for ModSource {
+    fn get_base_file_data(&self, name: &Path) -> uk_reader::Result<Vec<u8>> {
+        self.read_game_file(platform_prefixes(self.endian()).0, name)
+    }
+
+    fn get_update_file_data(&self, name: &Path) -> uk_reader::Result<Vec<u8>> {
+        match self.endian() {
+            Endian::Big => self.get_base_file_data(name),
+            Endian::Little => Err(ROMError::MissingDumpDir("Update", self.path.clone())),
+        }
+    }
+
+    fn get_aoc_file_data(&self, name: &Path) -> uk_reader::Result<Vec<u8>> {
+        self.read_game_file(platform_prefixes(self.endian()).1, name)
+    }
+
+    fn file_exists(&self, name: &Path) -> bool {
+        let (content, aoc) = platform_prefixes(self.endian());
+        [content, aoc]
+            .into_iter()
+            .any(|dir| self.is_file(&self.root.join(dir).join(name)))
+    }
+
+    fn host_path(&self) -> &Path {
+        &self.path
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use super::*;
+
+    #[test]
+    fn zip_source() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("mod.zip");
+        let mut zip = zip::ZipWriter::new(fs::File::create(&path).unwrap());
+        let opts = zip::write::SimpleFileOptions::default();
+        for (name, data) in [
+            ("My Mod/rules.txt", "[Definition]"),
+            ("My Mod/content/Actor/ActorInfo.product.sbyml", "actors"),
+            ("My Mod/content/Pack/Bootup.pack", "bootup"),
+        ] {
+            zip.start_file(name, opts).unwrap();
+            zip.write_all(data.as_bytes()).unwrap();
+        }
+        zip.finish().unwrap();
+
+        let source = ModSource::open(&path).unwrap();
+        assert!(source.is_archive());
+        let root = source.root().join("My Mod");
+        assert!(source.exists(&root));
+        let source = source
+            .rooted_at(&root)
+            .unwrap()
+            .with_overlay(dir.path().join("overlay"));
+        let content = source.root().join("content");
+        assert!(source.exists(&content));
+        assert!(source.is_file(&content.join("Pack/Bootup.pack")));
+        assert!(!source.is_file(&content.join("Pack")));
+        assert_eq!(source.subfolders(&content), vec![
+            content.join("Actor"),
+            content.join("Pack")
+        ]);
+        assert_eq!(
+            source
+                .read_to_string(&source.root().join("rules.txt"))
+                .unwrap(),
+            "[Definition]"
+        );
+
+        // Written files are read from the overlay, and the archive is left
+        // alone
+        let actorinfo = content.join("Actor/ActorInfo.product.sbyml");
+        source.write(&actorinfo, "merged").unwrap();
+        source
+            .write(&content.join("Pack/TitleBG.pack"), "title")
+            .unwrap();
+        assert_eq!(source.read(&actorinfo).unwrap(), b"merged");
+        assert_eq!(source.files(&content).len(), 3);
+        assert!(
+            dir.path()
+                .join("overlay/content/Pack/TitleBG.pack")
+                .exists()
+        );
+        assert_eq!(
+            ModSource::open(&path)
+                .unwrap()
+                .read(&path.join("My Mod/content/Actor/ActorInfo.product.sbyml"))
+                .unwrap(),
+            b"actors"
+        );
+    }
+
+    #[test]
+    fn sevenz_source() {
+        let dir = tempfile::tempdir().unwrap();
+        let src = dir.path().join("src");
+        for (name, data) in [
+            ("My Mod/rules.txt", "[Definition]"),
+            ("My Mod/content/Actor/ActorInfo.product.sbyml", "actors"),
+            ("My Mod/content/Pack/Bootup.pack", "bootup"),
+        ] {
+            let file = src.join(name);
+            fs::create_dir_all(file.parent().unwrap()).unwrap();
+            fs::write(file, data).unwrap();
+        }
+        let path = dir.path().join("mod.7z");
+        sevenz_rust::compress_to_path(&src, &path).unwrap();
+
+        let source = ModSource::open(&path).unwrap();
+        let root = source.root().join("My Mod");
+        let content = root.join("content");
+        assert_eq!(source.files(&content).len(), 2);
+        // The block is decoded on the first read and kept for the second
+        for _ in 0..2 {
+            assert_eq!(
+                source.read(&content.join("Pack/Bootup.pack")).unwrap(),
+                b"bootup"
+            );
+            assert_eq!(
+                source.read_to_string(&root.join("rules.txt")).unwrap(),
+                "[Definition]"
+            );
+        }
+        assert!(source.read(&content.join("Pack/TitleBG.pack")).is_err());
+    }
+}
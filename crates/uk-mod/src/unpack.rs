@@ -1,4 +1,6 @@
+mod cache;
 mod de;
+mod dump;
 use std::{
     collections::BTreeSet,
     io::{BufReader, Read, Write},
@@ -39,6 +41,8 @@ use uk_content::{
 use uk_reader::{ResourceLoader, ResourceReader};
 use uk_util::PathExt as UkPathExt;
 
+use self::cache::{CacheEntry, CacheSources, MergeCache};
+pub use self::dump::{DumpedResource, ResourceDump};
 use crate::{Manifest, Meta, ModOption};
 
 pub enum ZipData {
@@ -130,6 +134,10 @@ impl ParallelZipReader {
         self.borrow_files().iter().map(|(f, _)| f)
     }
 
+    pub fn contains(&self, file: impl AsRef<Path>) -> bool {
+        self.borrow_files().contains_key(file.as_ref())
+    }
+
     pub fn get_file(&self, file: impl AsRef<Path>) -> Result<Vec<u8>> {
         fn inner(self_: &ParallelZipReader, file: &Path) -> Result<Vec<u8>> {
             self_
@@ -169,6 +177,8 @@ pub struct ModReader {
     decompressor: Arc<Mutex<zstd::bulk::Decompressor<'static>>>,
     #[serde(skip_serializing)]
     zip: Arc<Option<ParallelZipReader>>,
+    #[serde(skip)]
+    content_hash: Option<String>,
 }
 
 impl std::fmt::Debug for ModReader {
@@ -180,6 +190,7 @@ impl std::fmt::Debug for ModReader {
             .field("manifest", &self.manifest)
             .field("decompressor", &"zstd::bulk::Decompressor<'static>")
             .field("zip", &self.zip)
+            .field("content_hash", &self.content_hash)
             .finish()
     }
 }
@@ -269,6 +280,18 @@ impl ModReader {
         let current_api = Version::parse(env!("CARGO_PKG_VERSION"))?;
         if current_api.major != mod_api.major {
             bail!("{name} build with unsupported UKMM version: {mod_api}")
+        }
+        let migrations = crate::migrate::pending(&result.meta)?;
+        if !migrations.is_empty() {
+            log::debug!(
+                "{name} is from UKMM v{mod_api}, resources affected by these changes will be \
+                 migrated: {}",
+                migrations
+                    .iter()
+                    .map(|m| m.description)
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )
         } else if current_api.minor > mod_api.minor && current_api.major == 0 {
             log::warn!(
                 "{name} is from an older UKMM prerelease (v{mod_api}), compatibility not \
@@ -291,6 +314,13 @@ impl ModReader {
         Self::open_inner(path.as_ref(), options.into(), true)
     }
 
+    /// Set the content hash of the mod, which the merge cache uses to tell
+    /// whether it has changed since files were last built from it.
+    pub fn with_content_hash(mut self, hash: impl Into<String>) -> Self {
+        self.content_hash = Some(hash.into());
+        self
+    }
+
     fn open_unzipped(path: PathBuf, options: Vec<ModOption>) -> Result<Self> {
         let meta: Meta = serde_yaml::from_str(&fs::read_to_string(path.join("meta.yml"))?)?;
         let mut manifest: Manifest =
@@ -308,6 +338,7 @@ impl ModReader {
             meta,
             manifest,
             zip: Arc::new(None),
+            content_hash: None,
         })
     }
 
@@ -370,6 +401,7 @@ impl ModReader {
             meta,
             manifest,
             zip: Arc::new(Some(zip)),
+            content_hash: None,
         })
     }
 
@@ -387,6 +419,26 @@ impl ModReader {
         &self.manifest
     }
 
+    /// Whether [`get_versions`](ModReader::get_versions) would find any
+    /// version of a resource in the mod, without reading it.
+    pub fn has_versions(&self, name: &Path) -> bool {
+        let aoc = format!("Aoc/0010/{}", name.to_string_lossy());
+        [canonicalize(name), canonicalize(aoc)].iter().any(|canon| {
+            std::iter::once(PathBuf::from(canon.as_str()))
+                .chain(
+                    self.options
+                        .iter()
+                        .map(|opt| Path::new("options").join(&opt.path).join(canon.as_str())),
+                )
+                .any(|path| {
+                    match self.zip.as_ref() {
+                        Some(zip) => zip.contains(path),
+                        None => self.path.join(path).exists(),
+                    }
+                })
+        })
+    }
+
     pub fn get_versions(&self, name: &Path) -> Result<Vec<Vec<u8>>> {
         let canon = canonicalize(name);
         let mut versions = Vec::with_capacity(1);
@@ -426,6 +478,41 @@ impl ModReader {
         }
         Ok(versions)
     }
+
+    /// Deserialize a version of a resource from the mod, migrating it to the
+    /// current format if the mod was built before a breaking change to it.
+    pub fn read_resource(
+        &self,
+        canon: &str,
+        data: &[u8],
+        stock: Option<&ResourceData>,
+    ) -> Result<ResourceData> {
+        crate::migrate::read_resource(&self.meta, canon, data, stock)
+    }
+
+    /// Get every version of a resource in the mod (including enabled
+    /// options), deserialized into [`ResourceData`] and migrated like
+    /// [`read_resource`](ModReader::read_resource), which needs the stock
+    /// version for some older resources.
+    pub fn get_resources(
+        &self,
+        name: &Path,
+        stock: Option<&ResourceData>,
+    ) -> Result<Vec<ResourceData>> {
+        let canon = canonicalize(name);
+        self.get_versions(name)?
+            .into_iter()
+            .map(|data| {
+                self.read_resource(&canon, &data, stock).with_context(|| {
+                    format!(
+                        "Error deserializing resource {} from mod {}",
+                        name.display(),
+                        self.meta.name
+                    )
+                })
+            })
+            .collect()
+    }
 }
 
 static RSTB_EXCLUDE_EXTS: &[&str] = &[
@@ -433,6 +520,44 @@ static RSTB_EXCLUDE_EXTS: &[&str] = &[
 ];
 static RSTB_EXCLUDE_NAMES: &[&str] = &["ActorInfo.product.byml"];
 
+/// How a file in the merged output would be affected by an unpack.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FileChange {
+    Create,
+    Change,
+    Unchanged,
+}
+
+impl FileChange {
+    fn compare(out_file: &Path, data: &[u8]) -> Self {
+        match std::fs::read(out_file) {
+            Ok(existing) if existing == data => Self::Unchanged,
+            Ok(_) => Self::Change,
+            Err(_) => Self::Create,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PlannedFile {
+    /// Path relative to the output folder, including the platform prefix.
+    pub path:   String,
+    pub change: FileChange,
+    /// Names of the mods which edit the file, in load order.
+    pub mods:   Vec<String>,
+}
+
+/// The result of [`ModUnpacker::plan`], describing what an unpack would do
+/// without having written anything.
+#[derive(Debug, Clone, Serialize)]
+pub struct UnpackPlan {
+    pub files: Vec<PlannedFile>,
+    /// The RSTB updates the unpack would produce, as for
+    /// [`ModUnpacker::unpack`].
+    pub rstb:  std::collections::BTreeMap<String, Option<u32>>,
+}
+
 // #[derive(Debug)]
 pub struct ModUnpacker {
     dump:     Arc<ResourceReader>,
@@ -443,6 +568,8 @@ pub struct ModUnpacker {
     rstb:     DashMap<String, Option<u32>>,
     hashes:   StockHashTable,
     out_dir:  PathBuf,
+    cache:    Option<(PathBuf, MergeCache, CacheSources)>,
+    cached:   DashMap<String, CacheEntry>,
 }
 
 impl ModUnpacker {
@@ -465,6 +592,8 @@ impl ModUnpacker {
                 Endian::Big => botw_utils::hashes::Platform::WiiU,
             }),
             out_dir,
+            cache: None,
+            cached: DashMap::new(),
         }
     }
 
@@ -473,10 +602,20 @@ impl ModUnpacker {
         self
     }
 
-    pub fn unpack(self) -> Result<DashMap<String, Option<u32>>> {
-        if !self.out_dir.exists() {
-            fs::create_dir_all(&self.out_dir)?;
-        }
+    /// Use a persistent merge cache at the given path. Files whose stock and
+    /// mod inputs have not changed since they were last written to the output
+    /// folder are then reused instead of rebuilt. When unpacking without a
+    /// manifest, any other files left in the output folder are removed, so
+    /// it does not need to be cleared first.
+    pub fn with_cache(mut self, path: impl Into<PathBuf>) -> Self {
+        let path = path.into();
+        let sources = CacheSources::new(&self.dump, &self.mods);
+        self.cache = Some((path.clone(), MergeCache::load(&path), sources));
+        self
+    }
+
+    #[allow(clippy::type_complexity)]
+    fn collect_files(&self) -> (BTreeSet<&String>, BTreeSet<&String>, IndexSet<Language>) {
         let mut content_files: BTreeSet<&String>;
         let aoc_files: BTreeSet<&String>;
         if let Some(manifest) = self.manifest.as_ref() {
@@ -499,6 +638,15 @@ impl ModUnpacker {
             modded_langs.insert(lang);
             content_files.remove(&lang.bootup_path());
         }
+        (content_files, aoc_files, modded_langs)
+    }
+
+    pub fn unpack(mut self) -> Result<DashMap<String, Option<u32>>> {
+        if !self.out_dir.exists() {
+            fs::create_dir_all(&self.out_dir)?;
+        }
+        let (content_files, aoc_files, modded_langs) = self.collect_files();
+        let has_texts = !modded_langs.is_empty();
         let (content, aoc) = platform_prefixes(self.endian);
         let total = content_files.len() + aoc_files.len();
         let current = AtomicUsize::new(0);
@@ -549,10 +697,174 @@ impl ModUnpacker {
             log::trace!("CLEARPROGRESS");
             Ok(())
         })?;
+        if let Some((path, mut cache, _)) = self.cache.take() {
+            if self.manifest.is_none() {
+                self.remove_stale(has_texts)?;
+                cache.files.clear();
+            }
+            cache.files.extend(std::mem::take(&mut self.cached));
+            if let Err(e) = cache.save(&path) {
+                log::warn!("{e:?}");
+            }
+        }
         Ok(self.rstb)
     }
 
-    fn unpack_texts(&self, mut langs: IndexSet<Language>) -> Result<()> {
+    /// Remove every file in the output folder which was not written or
+    /// reused by this unpack.
+    fn remove_stale(&self, has_texts: bool) -> Result<()> {
+        let mut keep: BTreeSet<String> = self.cached.iter().map(|e| e.key().clone()).collect();
+        if has_texts {
+            keep.insert(
+                Path::new(platform_content(self.endian))
+                    .join(self.lang.bootup_path().as_str())
+                    .to_slash_lossy()
+                    .into(),
+            );
+        }
+        for entry in WalkDir::new(&self.out_dir)
+            .into_iter()
+            .filter_map(std::result::Result::ok)
+            .filter(|e| e.file_type.is_file())
+        {
+            let path = entry.path();
+            let Ok(rel) = path.strip_prefix(&self.out_dir) else {
+                continue;
+            };
+            if !keep.contains(&*rel.to_slash_lossy()) {
+                log::debug!("Removing stale merged file {}", rel.display());
+                fs::remove_file(&path)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Reuse the existing output for a file if the cache shows that none of
+    /// its inputs have changed since it was built, and the output is still
+    /// what was written then, restoring its RSTB updates.
+    fn reuse_cached(&self, key: &str, out_file: &Path) -> bool {
+        let Some((entry, sources)) = self
+            .cache
+            .as_ref()
+            .and_then(|(_, cache, sources)| cache.files.get(key).map(|e| (e, sources)))
+        else {
+            return false;
+        };
+        if entry.key != sources.key(self.endian, &self.mods, &entry.inputs) {
+            return false;
+        }
+        match fs::read(out_file) {
+            Ok(data) if cache::hash_output(&data) == entry.output => (),
+            _ => {
+                log::debug!("Cached output for {key} is missing or was modified");
+                return false;
+            }
+        }
+        for (canon, size) in entry.rstb.iter() {
+            self.rstb.insert(canon.clone(), *size);
+        }
+        self.cached.insert(key.into(), entry.clone());
+        true
+    }
+
+    /// Apply the RSTB updates from building a file and, if it was written to
+    /// the output folder, record it in the cache, if there is one.
+    fn finish_entry(&self, key: &str, mut entry: CacheEntry, written: Option<&[u8]>) {
+        for (canon, size) in entry.rstb.iter() {
+            self.rstb.insert(canon.clone(), *size);
+        }
+        if let (Some((_, _, sources)), Some(data)) = (self.cache.as_ref(), written) {
+            entry.inputs.sort();
+            entry.inputs.dedup();
+            entry.key = sources.key(self.endian, &self.mods, &entry.inputs);
+            entry.output = cache::hash_output(data);
+            self.cached.insert(key.into(), entry);
+        }
+    }
+
+    /// Build every file that [`unpack`](Self::unpack) would write, but only
+    /// compare the results to the current contents of the output folder
+    /// instead of writing them. Files which the merge cache shows to be up to
+    /// date are reported as unchanged without being rebuilt.
+    pub fn plan(self) -> Result<UnpackPlan> {
+        let (content_files, aoc_files, modded_langs) = self.collect_files();
+        let (content, aoc) = platform_prefixes(self.endian);
+        log::info!(
+            "Checking {} files for changes…",
+            content_files.len() + aoc_files.len()
+        );
+        let mut files = Vec::with_capacity(content_files.len() + aoc_files.len() + 1);
+        for (list, dir, is_aoc) in [(content_files, content, false), (aoc_files, aoc, true)] {
+            files.extend(
+                list
+                    .into_par_iter()
+                    .map(|file| -> Result<PlannedFile> {
+                        let path = Path::new(dir).join(file.as_str());
+                        let key = path.to_slash_lossy();
+                        let out_file = self.out_dir.join(&path);
+                        let change = if self.reuse_cached(&key, &out_file) {
+                            FileChange::Unchanged
+                        } else {
+                            let mut entry = CacheEntry::default();
+                            let data = self.build_file(file.as_str(), is_aoc, &mut entry)?;
+                            self.finish_entry(&key, entry, None);
+                            FileChange::compare(&out_file, &compress_if(data.as_ref(), &out_file))
+                        };
+                        Ok(PlannedFile {
+                            path: key.into(),
+                            change,
+                            mods: self
+                                .mods
+                                .iter()
+                                .filter(|m| {
+                                    if is_aoc {
+                                        m.manifest.aoc_files.contains(*file)
+                                    } else {
+                                        m.manifest.content_files.contains(*file)
+                                    }
+                                })
+                                .map(|m| m.meta.name.clone())
+                                .collect(),
+                        })
+                    })
+                    .collect::<Result<Vec<_>>>()?,
+            );
+        }
+        if let Some(data) = self.build_texts(modded_langs)? {
+            let path =
+                Path::new(platform_content(self.endian)).join(self.lang.bootup_path().as_str());
+            files.push(PlannedFile {
+                change: FileChange::compare(&self.out_dir.join(&path), &data),
+                path:   path.to_slash_lossy().into(),
+                mods:   self
+                    .mods
+                    .iter()
+                    .filter(|m| !m.manifest.languages().is_empty())
+                    .map(|m| m.meta.name.clone())
+                    .collect(),
+            });
+        }
+        files.sort_by(|a, b| a.path.cmp(&b.path));
+        Ok(UnpackPlan {
+            files,
+            rstb: self.rstb.into_iter().collect(),
+        })
+    }
+
+    fn unpack_texts(&self, langs: IndexSet<Language>) -> Result<()> {
+        if let Some(data) = self.build_texts(langs)? {
+            let out = self
+                .out_dir
+                .join(platform_content(self.endian))
+                .join(self.lang.bootup_path().as_str());
+            out.parent().map(fs::create_dir_all).transpose()?;
+            replace_file(&out)?;
+            fs::write(out, data)?;
+        }
+        Ok(())
+    }
+
+    fn build_texts(&self, mut langs: IndexSet<Language>) -> Result<Option<Vec<u8>>> {
         if !langs.is_empty() {
             log::info!("Unpacking game texts");
             let Some(MergeableResource::MessagePack(mut base)) = ResourceData::clone(
@@ -583,11 +895,6 @@ impl ModUnpacker {
                     }
                 }
             }
-            let out = self
-                .out_dir
-                .join(platform_content(self.endian))
-                .join(self.lang.bootup_path().as_str());
-            out.parent().map(fs::create_dir_all).transpose()?;
             let data = base.into_binary(self.endian);
             self.rstb.insert(
                 format!("Message/Msg_{}.product.sarc", self.lang).into(),
@@ -595,9 +902,10 @@ impl ModUnpacker {
             );
             let mut sarc = SarcWriter::new(self.endian.into())
                 .with_file(self.lang.message_path(), compress(data));
-            fs::write(out, sarc.to_binary())?;
+            Ok(Some(sarc.to_binary()))
+        } else {
+            Ok(None)
         }
-        Ok(())
     }
 
     fn unpack_files(
@@ -609,11 +917,21 @@ impl ModUnpacker {
         aoc: bool,
     ) -> Result<()> {
         files.into_par_iter().try_for_each(|file| -> Result<()> {
-            let data = self.build_file(file.as_str(), aoc)?;
             let out_file = dir.join(file.as_str());
-            out_file.parent().map(fs::create_dir_all).transpose()?;
-            let mut writer = std::io::BufWriter::new(fs::File::create(&out_file)?);
-            writer.write_all(&compress_if(data.as_ref(), &out_file))?;
+            let key = out_file
+                .strip_prefix(&self.out_dir)
+                .unwrap_or(&out_file)
+                .to_slash_lossy();
+            if !self.reuse_cached(&key, &out_file) {
+                let mut entry = CacheEntry::default();
+                let data = self.build_file(file.as_str(), aoc, &mut entry)?;
+                out_file.parent().map(fs::create_dir_all).transpose()?;
+                replace_file(&out_file)?;
+                let data = compress_if(data.as_ref(), &out_file);
+                let mut writer = std::io::BufWriter::new(fs::File::create(&out_file)?);
+                writer.write_all(&data)?;
+                self.finish_entry(&key, entry, Some(&*data));
+            }
             let progress = 1 + current_file.load(Ordering::Relaxed);
             current_file.store(progress, Ordering::Relaxed);
             let percent = (progress as f64 / total_files as f64) * 100.0;
@@ -629,7 +947,7 @@ impl ModUnpacker {
         })
     }
 
-    fn build_file(&self, file: &str, aoc: bool) -> Result<Vec<u8>> {
+    fn build_file(&self, file: &str, aoc: bool, entry: &mut CacheEntry) -> Result<Vec<u8>> {
         let mut versions = std::collections::VecDeque::with_capacity(
             (self.mods.len() as f32 / 2.).ceil() as usize,
         );
@@ -637,6 +955,7 @@ impl ModUnpacker {
             (true, false) => jstr!("Aoc/0010/{file}"),
             _ => file.to_owned(),
         };
+        entry.inputs.push(filepath.as_str().into());
         let canon = canonicalize(&filepath);
         let canon_path = Path::new(canon.as_str());
         let mut rstb_val = None;
@@ -660,34 +979,23 @@ impl ModUnpacker {
                 dump_error.push(e.into());
             }
         }
+        // Older mods may need the stock version to migrate their resources
+        let stock = versions.front().cloned();
         for (data, mod_) in self
             .mods
             .iter()
             .filter_map(|mod_| {
                 mod_.get_versions(filepath.as_ref())
                     .ok()
-                    .map(|d| d.into_iter().map(|d| (d, &mod_.meta.name)))
+                    .map(|d| d.into_iter().map(move |d| (d, mod_)))
             })
             .flatten()
         {
-            let res = minicbor_ser::from_slice(&data);
-            match res {
-                Ok(res) => versions.push_back(Arc::new(res)),
-                Err(e) => {
-                    let msg = format!("{}", e);
-                    if msg.contains("unknown variant") {
-                        bail!(
-                            "Error deserializing resource {canon} from mod {mod_}. This is \
-                             probably because this mod was built with an old, incompatible beta \
-                             of UKMM."
-                        );
-                    } else {
-                        bail!(
-                            "Error deserializing resource {canon} from mod {mod_}. Error: {e}"
-                        );
-                    }
-                }
-            }
+            versions.push_back(Arc::new(mod_.read_resource(
+                &canon,
+                &data,
+                stock.as_deref(),
+            )?));
         }
         let base_version = versions
             .pop_front()
@@ -753,7 +1061,7 @@ impl ModUnpacker {
                         res
                     });
                 let data = self
-                    .build_sarc(merged, aoc)
+                    .build_sarc(merged, aoc, entry)
                     .with_context(|| jstr!("Failed to build SARC file {&file}"))?;
                 if can_rstb {
                     rstb_val = Some(rstb::calc::calc_from_size_and_name(
@@ -766,16 +1074,16 @@ impl ModUnpacker {
             }
         };
         if let Some(val) = rstb_val {
-            self.rstb.insert(canon, val);
+            entry.rstb.push((canon, val));
         }
         Ok(data)
     }
 
-    fn build_sarc(&self, sarc: SarcMap, aoc: bool) -> Result<Vec<u8>> {
+    fn build_sarc(&self, sarc: SarcMap, aoc: bool, entry: &mut CacheEntry) -> Result<Vec<u8>> {
         let mut writer = SarcWriter::new(self.endian.into()).with_min_alignment(sarc.alignment);
         for file in sarc.files.into_iter() {
             let data = self
-                .build_file(&file, aoc)
+                .build_file(&file, aoc, entry)
                 .with_context(|| jstr!("Failed to build file {&file} for SARC"))?;
             writer.add_file(
                 file.as_str(),
@@ -786,6 +1094,17 @@ impl ModUnpacker {
     }
 }
 
+/// Remove an output file about to be written. The output folder may share its
+/// files with an earlier merge through hard links, so they have to be replaced
+/// rather than written through.
+fn replace_file(path: &Path) -> Result<()> {
+    if path.exists() {
+        fs::remove_file(path)
+            .with_context(|| format!("Failed to replace file at {}", path.display()))?;
+    }
+    Ok(())
+}
+
 /// Extract a zipped mod, decompressing the binary files, but otherwise
 /// leaving the format intact.
 pub fn unzip_mod(mod_path: &Path, out_path: &Path) -> Result<()> {
@@ -847,6 +1166,43 @@ mod tests {
         .unwrap();
     }
 
+    #[test]
+    fn plan_mod() {
+        let dump = Arc::new(
+            serde_yaml::from_str::<ResourceReader>(
+                &std::fs::read_to_string("../.vscode/dump.yml").unwrap(),
+            )
+            .unwrap(),
+        );
+        let out_dir = tempfile::tempdir().unwrap();
+        let unpacker = || {
+            ModUnpacker::new(
+                dump.clone(),
+                Endian::Big,
+                Language::USen,
+                vec![ModReader::open("test/wiiu.zip", vec![]).unwrap()],
+                out_dir.path().to_path_buf(),
+            )
+        };
+        let plan = unpacker().plan().unwrap();
+        assert!(!plan.files.is_empty());
+        assert!(plan.files.iter().all(|f| f.change == FileChange::Create));
+        assert!(!out_dir.path().join("content").exists());
+        unpacker().unpack().unwrap();
+        let plan = unpacker().plan().unwrap();
+        assert!(plan.files.iter().all(|f| f.change == FileChange::Unchanged));
+    }
+
+    #[test]
+    fn file_change() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("file.bin");
+        assert_eq!(FileChange::compare(&file, b"data"), FileChange::Create);
+        std::fs::write(&file, b"data").unwrap();
+        assert_eq!(FileChange::compare(&file, b"data"), FileChange::Unchanged);
+        assert_eq!(FileChange::compare(&file, b"other"), FileChange::Change);
+    }
+
     #[test]
     fn unzip_mod() {
         let mod_path = "test/wiiu.zip";
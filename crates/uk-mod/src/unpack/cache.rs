@@ -0,0 +1,273 @@
+use std::{collections::BTreeMap, path::Path, time::UNIX_EPOCH};
+
+use anyhow_ext::{Context, Result};
+use fs_err as fs;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use smartstring::alias::String;
+use uk_content::prelude::Endian;
+use uk_reader::ResourceReader;
+
+use super::ModReader;
+
+/// What went into building one file in the merged output, so that it can be
+/// reused by a later unpack if none of it has changed.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub(super) struct CacheEntry {
+    /// A hash of where every input came from, from [`CacheSources::key`].
+    pub key:    std::string::String,
+    /// The SHA-256 hash of the file as written to the output folder.
+    pub output: std::string::String,
+    /// Every resource read to build the file, including files nested in
+    /// SARCs.
+    pub inputs: Vec<String>,
+    /// The RSTB updates produced while building the file.
+    pub rstb:   Vec<(String, Option<u32>)>,
+}
+
+/// A record of the inputs of every file written by previous unpacks into the
+/// same output folder, keyed by path relative to the output folder.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub(super) struct MergeCache {
+    version:   String,
+    pub files: BTreeMap<String, CacheEntry>,
+}
+
+impl MergeCache {
+    /// Load a merge cache, falling back to an empty one if it is missing,
+    /// unreadable, or was written by another version.
+    pub fn load(path: &Path) -> Self {
+        let cache = fs::read(path)
+            .map_err(anyhow_ext::Error::from)
+            .and_then(|data| {
+                serde_json::from_slice::<MergeCache>(&data).context("Failed to parse merge cache")
+            });
+        match cache {
+            Ok(cache) if cache.version == env!("CARGO_PKG_VERSION") => cache,
+            Ok(_) => {
+                log::debug!("Merge cache is from another version, ignoring");
+                Self::default()
+            }
+            Err(e) => {
+                log::debug!("No usable merge cache: {e}");
+                Self::default()
+            }
+        }
+    }
+
+    pub fn save(mut self, path: &Path) -> Result<()> {
+        self.version = env!("CARGO_PKG_VERSION").into();
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, serde_json::to_vec(&self)?).context("Failed to write merge cache")?;
+        Ok(())
+    }
+}
+
+/// Identifies the game dump and each mod an unpack reads from, so that cache
+/// entries can be checked without reading any of their resources.
+pub(super) struct CacheSources {
+    dump: std::string::String,
+    mods: Vec<std::string::String>,
+}
+
+impl CacheSources {
+    pub fn new(dump: &ResourceReader, mods: &[ModReader]) -> Self {
+        Self {
+            dump: format!(
+                "{}:{}",
+                dump.source_ser(),
+                modified(dump.source().host_path())
+            ),
+            mods: mods.iter().map(mod_identity).collect(),
+        }
+    }
+
+    /// Compute the cache key for a file built from the given inputs, from the
+    /// dump and every mod with a version of any of them, in load order. Mods
+    /// are checked for each input itself rather than by their manifests, as
+    /// a file nested in a SARC may come from a mod which only lists another
+    /// SARC holding it.
+    pub fn key(
+        &self,
+        endian: Endian,
+        mods: &[ModReader],
+        inputs: &[String],
+    ) -> std::string::String {
+        input_key(
+            &self.dump,
+            endian,
+            mods.iter()
+                .zip(self.mods.iter())
+                .filter(|(mod_, _)| {
+                    inputs
+                        .iter()
+                        .any(|input| mod_.has_versions(Path::new(input.as_str())))
+                })
+                .map(|(_, id)| id.as_str()),
+            inputs,
+        )
+    }
+}
+
+fn modified(path: &Path) -> u64 {
+    fs::metadata(path)
+        .and_then(|meta| meta.modified())
+        .ok()
+        .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+        .map(|time| time.as_secs())
+        .unwrap_or_default()
+}
+
+/// Identify a mod by its content hash and enabled options. Mods opened
+/// without a content hash fall back to the size and modified time of their
+/// path.
+fn mod_identity(mod_: &ModReader) -> std::string::String {
+    let mut options = mod_
+        .options
+        .iter()
+        .map(|opt| opt.path.to_string_lossy())
+        .collect::<Vec<_>>();
+    options.sort();
+    let hash = match mod_.content_hash.as_ref() {
+        Some(hash) => hash.to_string(),
+        None => {
+            format!(
+                "{}:{}:{}",
+                mod_.path.display(),
+                fs::metadata(&mod_.path)
+                    .map(|m| m.len())
+                    .unwrap_or_default(),
+                modified(&mod_.path)
+            )
+        }
+    };
+    format!("{hash}|{}", options.join("|"))
+}
+
+/// Hash the identities of the dump and mods a file was built from, along
+/// with its sorted inputs. Every field is prefixed with its length so that
+/// different sources can never hash the same.
+fn input_key<'a>(
+    dump: &str,
+    endian: Endian,
+    mods: impl IntoIterator<Item = &'a str>,
+    inputs: &[String],
+) -> std::string::String {
+    let mut hasher = Sha256::new();
+    let mut field = |data: &[u8]| {
+        hasher.update((data.len() as u64).to_le_bytes());
+        hasher.update(data);
+    };
+    field(dump.as_bytes());
+    field(&[matches!(endian, Endian::Big) as u8]);
+    for mod_ in mods {
+        field(mod_.as_bytes());
+    }
+    let mut inputs = inputs.iter().map(|i| i.as_str()).collect::<Vec<_>>();
+    inputs.sort_unstable();
+    inputs.dedup();
+    for input in inputs {
+        field(input.as_bytes());
+    }
+    format!("{:x}", hasher.finalize())
+}
+
+/// Hash the data of a file as written to the output folder.
+pub(super) fn hash_output(data: &[u8]) -> std::string::String {
+    format!("{:x}", Sha256::digest(data))
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+
+    fn inputs(files: &[&str]) -> Vec<String> {
+        files.iter().map(|f| (*f).into()).collect()
+    }
+
+    #[test]
+    fn key_hit() {
+        let first = input_key(
+            "dump",
+            Endian::Big,
+            ["mod_a", "mod_b"],
+            &inputs(&["Actor/ActorLink/A.bxml", "Actor/Pack/A.sbactorpack"]),
+        );
+        // The order the inputs were read in does not matter
+        let second = input_key(
+            "dump",
+            Endian::Big,
+            ["mod_a", "mod_b"],
+            &inputs(&["Actor/Pack/A.sbactorpack", "Actor/ActorLink/A.bxml"]),
+        );
+        assert_eq!(first, second);
+    }
+
+    #[test]
+    fn key_miss() {
+        let key = |dump, endian, mods: &[&str], files: &[&str]| {
+            input_key(dump, endian, mods.iter().copied(), &inputs(files))
+        };
+        let base = key("dump", Endian::Big, &["mod_a"], &["A.bxml"]);
+        assert_ne!(base, key("dump", Endian::Big, &["mod_a"], &["B.bxml"]));
+        assert_ne!(base, key("dump", Endian::Little, &["mod_a"], &["A.bxml"]));
+        assert_ne!(base, key("dump", Endian::Big, &[], &["A.bxml"]));
+        // Fields are length prefixed, so they cannot run into each other
+        assert_ne!(
+            key("dump", Endian::Big, &["ab", "c"], &["A.bxml"]),
+            key("dump", Endian::Big, &["a", "bc"], &["A.bxml"])
+        );
+    }
+
+    #[test]
+    fn key_invalidation() {
+        let files = inputs(&["A.bxml"]);
+        let base = input_key("dump:1", Endian::Big, ["hash_a|", "hash_b|"], &files);
+        // An updated mod, a changed option or a changed dump invalidate the key
+        assert_ne!(
+            base,
+            input_key("dump:1", Endian::Big, ["hash_a2|", "hash_b|"], &files)
+        );
+        assert_ne!(
+            base,
+            input_key("dump:1", Endian::Big, ["hash_a|opt", "hash_b|"], &files)
+        );
+        assert_ne!(
+            base,
+            input_key("dump:2", Endian::Big, ["hash_a|", "hash_b|"], &files)
+        );
+        // So does a change in load order
+        assert_ne!(
+            base,
+            input_key("dump:1", Endian::Big, ["hash_b|", "hash_a|"], &files)
+        );
+    }
+
+    #[test]
+    fn save_and_load() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("merge_cache.json");
+        let key = input_key("dump", Endian::Big, ["mod_a"], &inputs(&["A.bxml"]));
+        let mut cache = MergeCache::default();
+        cache.files.insert("content/A.bxml".into(), CacheEntry {
+            key:    key.clone(),
+            output: hash_output(b"data"),
+            inputs: inputs(&["A.bxml"]),
+            rstb:   vec![("A.bxml".into(), Some(256))],
+        });
+        cache.save(&path).unwrap();
+        let loaded = MergeCache::load(&path);
+        let entry = &loaded.files["content/A.bxml"];
+        assert_eq!(entry.key, key);
+        assert_eq!(entry.output, hash_output(b"data"));
+        assert_eq!(entry.rstb, [("A.bxml".into(), Some(256))]);
+        assert!(
+            MergeCache::load(&dir.path().join("missing.json"))
+                .files
+                .is_empty()
+        );
+    }
+}
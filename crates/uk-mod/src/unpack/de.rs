@@ -97,6 +97,7 @@ impl<'de> Deserialize<'de> for ModReader {
                             .map_err(serde::de::Error::custom)?,
                     )),
                     path,
+                    content_hash: None,
                 })
             }
         }
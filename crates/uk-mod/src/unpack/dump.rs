@@ -0,0 +1,253 @@
+use std::path::{Path, PathBuf};
+
+use anyhow_ext::{bail, Context, Result};
+use fs_err as fs;
+use jwalk::WalkDir;
+use path_slash::PathExt;
+use serde::Serialize;
+use smartstring::alias::String;
+use uk_content::{
+    canonicalize,
+    resource::{MergeableResource, ResourceData, SarcMap},
+};
+
+use super::ModReader;
+use crate::pack::BUILD_INDEX;
+
+/// A resource from a mod in a form which can be shown as YAML or JSON.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub enum ResourceDump {
+    /// Binary files replace the original outright, so only their size and
+    /// SHA-256 are shown.
+    Binary {
+        size:   usize,
+        sha256: std::string::String,
+    },
+    /// A struct variant, since YAML cannot tag a value twice.
+    Mergeable { resource: MergeableResource },
+    Sarc(SarcMap),
+}
+
+impl From<ResourceData> for ResourceDump {
+    fn from(resource: ResourceData) -> Self {
+        use sha2::{Digest, Sha256};
+        match resource {
+            ResourceData::Binary(data) => {
+                Self::Binary {
+                    size:   data.len(),
+                    sha256: format!("{:x}", Sha256::digest(&data)),
+                }
+            }
+            ResourceData::Mergeable(resource) => Self::Mergeable { resource },
+            ResourceData::Sarc(sarc) => Self::Sarc(sarc),
+        }
+    }
+}
+
+/// One version of a resource in a mod.
+#[derive(Debug, Clone, Serialize)]
+pub struct DumpedResource {
+    /// The canonical path of the resource.
+    pub path:   String,
+    /// The option this version comes from, if not the mod itself.
+    pub option: Option<PathBuf>,
+    pub data:   ResourceDump,
+}
+
+impl ModReader {
+    /// Split a file stored in the mod into the option it belongs to, if
+    /// any, and its canonical path. Files which are not resources, or which
+    /// belong to options that are not selected, are skipped.
+    fn split_source<'s, 'a>(&'s self, file: &'a str) -> Option<(Option<&'s Path>, &'a str)> {
+        if file == "meta.yml"
+            || file == "manifest.yml"
+            || file == BUILD_INDEX
+            || file.starts_with("thumb.")
+        {
+            return None;
+        }
+        match file.strip_prefix("options/") {
+            Some(rest) => {
+                self.options
+                    .iter()
+                    .find_map(|opt| {
+                        rest.strip_prefix(&*opt.path.to_slash_lossy())
+                            .and_then(|canon| canon.strip_prefix('/'))
+                            .map(|canon| (Some(opt.path.as_path()), canon))
+                    })
+                    .filter(|(_, canon)| *canon != "manifest.yml")
+            }
+            None => Some((None, file)),
+        }
+    }
+
+    /// The canonical paths of every resource in the mod and its selected
+    /// options, including files nested in SARCs, sorted.
+    pub fn list_resources(&self) -> Vec<String> {
+        let files: Vec<std::string::String> = if let Some(zip) = self.zip.as_ref() {
+            zip.iter()
+                .map(|f| f.to_slash_lossy().into_owned())
+                .collect()
+        } else {
+            WalkDir::new(&self.path)
+                .into_iter()
+                .filter_map(|e| e.ok())
+                .filter(|e| e.file_type().is_file())
+                .filter_map(|e| {
+                    e.path()
+                        .strip_prefix(&self.path)
+                        .ok()
+                        .map(|p| p.to_slash_lossy().into_owned())
+                })
+                .collect()
+        };
+        let mut resources = files
+            .iter()
+            .filter_map(|file| self.split_source(file).map(|(_, canon)| canon.into()))
+            .collect::<Vec<String>>();
+        resources.sort();
+        resources.dedup();
+        resources
+    }
+
+    /// Every version of a resource in the mod and its selected options,
+    /// deserialized for display.
+    pub fn dump_resource(&self, name: &Path) -> Result<Vec<DumpedResource>> {
+        let canon = canonicalize(name);
+        let mut dumped = vec![];
+        let sources =
+            std::iter::once(None).chain(self.options.iter().map(|opt| Some(opt.path.as_path())));
+        for option in sources {
+            let path = match option {
+                Some(opt) => Path::new("options").join(opt).join(canon.as_str()),
+                None => PathBuf::from(canon.as_str()),
+            };
+            let data = if let Some(zip) = self.zip.as_ref() {
+                match zip.get_file(&path) {
+                    Ok(data) => {
+                        self.decompress(data.as_slice())
+                            .with_context(|| format!("Failed to decompress {canon} from mod"))?
+                    }
+                    Err(_) => continue,
+                }
+            } else {
+                let path = self.path.join(path);
+                if !path.exists() {
+                    continue;
+                }
+                fs::read(path)?
+            };
+            let resource: ResourceData = minicbor_ser::from_slice(&data).with_context(|| {
+                format!(
+                    "Error deserializing resource {canon} from mod {}",
+                    self.meta.name
+                )
+            })?;
+            dumped.push(DumpedResource {
+                path:   canon.clone(),
+                option: option.map(Path::to_path_buf),
+                data:   resource.into(),
+            });
+        }
+        if dumped.is_empty() {
+            bail!("{} has no resource {canon}", self.meta.name);
+        }
+        Ok(dumped)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use roead::byml::Byml;
+
+    use super::*;
+
+    #[test]
+    fn dump_binary() {
+        let dump = ResourceDump::from(ResourceData::Binary(b"abc".to_vec()));
+        assert_eq!(dump, ResourceDump::Binary {
+            size:   3,
+            sha256: "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad".into(),
+        });
+    }
+
+    #[test]
+    fn dump_mod() {
+        let tmp = tempfile::tempdir().unwrap();
+        let meta = crate::Meta {
+            api: env!("CARGO_PKG_VERSION").into(),
+            name: "Test".into(),
+            version: "1.0.0".into(),
+            author: "Tester".into(),
+            category: crate::ModCategory::Other,
+            description: Default::default(),
+            platform: crate::ModPlatform::Universal,
+            url: None,
+            masters: Default::default(),
+            options: vec![],
+        };
+        fs::write(
+            tmp.path().join("meta.yml"),
+            serde_yaml::to_string(&meta).unwrap(),
+        )
+        .unwrap();
+        fs::write(
+            tmp.path().join("manifest.yml"),
+            serde_yaml::to_string(&crate::Manifest::default()).unwrap(),
+        )
+        .unwrap();
+        let byml = Byml::Map(
+            [("Name".into(), Byml::String("Test".into()))]
+                .into_iter()
+                .collect(),
+        );
+        let resources = [
+            (
+                "Test/Data.byml",
+                ResourceData::Mergeable(MergeableResource::GenericByml(Box::new(byml.clone()))),
+            ),
+            ("Test/Data.bin", ResourceData::Binary(b"abc".to_vec())),
+        ];
+        for (name, resource) in resources {
+            let path = tmp.path().join(name);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, minicbor_ser::to_vec(&resource).unwrap()).unwrap();
+        }
+
+        let mod_reader = ModReader::open(tmp.path(), vec![]).unwrap();
+        assert_eq!(
+            mod_reader
+                .list_resources()
+                .iter()
+                .map(|r| r.as_str())
+                .collect::<Vec<_>>(),
+            ["Test/Data.bin", "Test/Data.byml"]
+        );
+        let dumped = mod_reader
+            .dump_resource(Path::new("Test/Data.byml"))
+            .unwrap();
+        assert_eq!(dumped.len(), 1);
+        assert_eq!(dumped[0].path.as_str(), "Test/Data.byml");
+        assert_eq!(dumped[0].option, None);
+        assert_eq!(dumped[0].data, ResourceDump::Mergeable {
+            resource: MergeableResource::GenericByml(Box::new(byml)),
+        });
+        let serde_yaml::Value::Tagged(data) = serde_yaml::to_value(&dumped[0].data).unwrap() else {
+            panic!("Dumped resource is not tagged with its kind");
+        };
+        assert_eq!(data.tag, "Mergeable");
+        let serde_yaml::Value::Tagged(resource) = &data.value["resource"] else {
+            panic!("Mergeable resource is not tagged with its type");
+        };
+        assert_eq!(resource.tag, "GenericByml");
+
+        let dumped = mod_reader
+            .dump_resource(Path::new("Test/Data.bin"))
+            .unwrap();
+        assert_eq!(dumped.len(), 1);
+        assert_eq!(
+            dumped[0].data,
+            ResourceDump::from(ResourceData::Binary(b"abc".to_vec()))
+        );
+    }
+}
@@ -0,0 +1,451 @@
+//! Integrity checks for packaged mods, which find every problem that would
+//! otherwise only show up partway through a merge.
+use std::{
+    collections::{BTreeSet, HashSet},
+    path::{Path, PathBuf},
+};
+
+use anyhow_ext::{Context, Result};
+use fs_err as fs;
+use jwalk::WalkDir;
+use lenient_semver::Version;
+use path_slash::PathExt;
+use rayon::prelude::*;
+use serde::Serialize;
+use smartstring::alias::String;
+use uk_content::{canonicalize, prelude::Endian, resource::ResourceData};
+
+use crate::{
+    migrate,
+    pack::BUILD_INDEX,
+    unpack::{init_decompressor, ParallelZipReader},
+    Manifest, Meta, ModOptionGroup, ModPlatform,
+};
+
+/// A problem found in a mod by [`verify_mod`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum VerifyIssue {
+    /// The meta file is missing, invalid, or from an unsupported version.
+    Meta { error: String },
+    /// A manifest is missing or invalid.
+    Manifest { path: String, error: String },
+    /// A file listed in a manifest has no stored resource.
+    Missing { manifest: String, file: String },
+    /// A stored resource could not be decompressed or deserialized.
+    Resource { path: String, error: String },
+    /// A folder in `options` is not one of the mod's options.
+    UnknownOption { path: String },
+    /// A stored resource is neither listed in a manifest nor part of a
+    /// listed file, so it is never used.
+    Unlisted { path: String },
+}
+
+impl std::fmt::Display for VerifyIssue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            VerifyIssue::Meta { error } => write!(f, "Bad meta file: {error}"),
+            VerifyIssue::Manifest { path, error } => write!(f, "Bad manifest {path}: {error}"),
+            VerifyIssue::Missing { manifest, file } => {
+                write!(
+                    f,
+                    "{file} is listed in {manifest} but not stored in the mod"
+                )
+            }
+            VerifyIssue::Resource { path, error } => write!(f, "Bad resource {path}: {error}"),
+            VerifyIssue::UnknownOption { path } => {
+                write!(f, "{path} is not one of the mod's options")
+            }
+            VerifyIssue::Unlisted { path } => {
+                write!(
+                    f,
+                    "{path} is stored in the mod but not listed in any manifest"
+                )
+            }
+        }
+    }
+}
+
+/// The result of verifying a mod.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct VerifyReport {
+    /// The mod name, if its meta could be read.
+    pub name: Option<String>,
+    /// How many resources were checked.
+    pub resources: usize,
+    pub issues: Vec<VerifyIssue>,
+}
+
+impl VerifyReport {
+    #[inline]
+    pub fn is_ok(&self) -> bool {
+        self.issues.is_empty()
+    }
+}
+
+/// The stored files of a mod, from either a ZIP or an unpacked folder.
+pub(crate) enum Source {
+    Zip(ParallelZipReader),
+    Folder(PathBuf),
+}
+
+impl Source {
+    pub(crate) fn open(path: &Path) -> Result<Self> {
+        if path.is_file() {
+            Ok(Source::Zip(
+                ParallelZipReader::open(path, true)
+                    .with_context(|| format!("Failed to open mod at {}", path.display()))?,
+            ))
+        } else if path.is_dir() {
+            Ok(Source::Folder(path.to_path_buf()))
+        } else {
+            anyhow_ext::bail!("No mod found at {}", path.display());
+        }
+    }
+
+    pub(crate) fn files(&self) -> Vec<std::string::String> {
+        match self {
+            Source::Zip(zip) => {
+                zip.iter()
+                    .map(|f| f.to_slash_lossy().into_owned())
+                    .collect()
+            }
+            Source::Folder(root) => {
+                WalkDir::new(root)
+                    .into_iter()
+                    .filter_map(|e| e.ok())
+                    .filter(|e| e.file_type().is_file())
+                    .filter_map(|e| {
+                        e.path()
+                            .strip_prefix(root)
+                            .ok()
+                            .map(|p| p.to_slash_lossy().into_owned())
+                    })
+                    .collect()
+            }
+        }
+    }
+
+    pub(crate) fn read(&self, file: &str) -> Result<Vec<u8>> {
+        match self {
+            Source::Zip(zip) => zip.get_file(file),
+            Source::Folder(root) => Ok(fs::read(root.join(file))?),
+        }
+    }
+
+    /// Resources in unpacked folders are not compressed.
+    #[inline]
+    pub(crate) fn compressed(&self) -> bool {
+        matches!(self, Source::Zip(_))
+    }
+
+    /// Read a stored resource, decompressing it if needed.
+    pub(crate) fn read_resource(
+        &self,
+        decomp: &mut zstd::bulk::Decompressor<'static>,
+        file: &str,
+    ) -> Result<Vec<u8>> {
+        let data = self.read(file)?;
+        if self.compressed() {
+            let size = zstd::bulk::Decompressor::upper_bound(&data).unwrap_or(data.len() * 1024);
+            decomp
+                .decompress(&data, size)
+                .or_else(|e| zstd::decode_all(data.as_slice()).context(e))
+                .context("Failed to decompress")
+        } else {
+            Ok(data)
+        }
+    }
+}
+
+fn check_resource(
+    source: &Source,
+    decomp: &mut zstd::bulk::Decompressor<'static>,
+    file: &str,
+) -> Result<ResourceData> {
+    let data = source.read_resource(decomp, file)?;
+    minicbor_ser::from_slice::<ResourceData>(&data).map_err(|e| {
+        let msg = e.to_string();
+        if msg.contains("unknown variant") {
+            anyhow_ext::anyhow!(
+                "{msg}. The mod was probably built with an old, incompatible beta of UKMM."
+            )
+        } else {
+            anyhow_ext::anyhow!("Failed to deserialize: {msg}")
+        }
+    })
+}
+
+fn check_meta(text: &[u8]) -> std::result::Result<Meta, String> {
+    let meta: Meta = serde_yaml::from_slice(text).map_err(|e| e.to_string())?;
+    let mod_api = Version::parse(&meta.api).map_err(|e| format!("Invalid API version: {e}"))?;
+    let current_api = Version::parse(env!("CARGO_PKG_VERSION")).map_err(|e| e.to_string())?;
+    if mod_api.major != current_api.major {
+        return Err(format!("Built with unsupported UKMM version {mod_api}").into());
+    }
+    migrate::pending(&meta).map_err(|e| e.to_string())?;
+    Ok(meta)
+}
+
+/// The canonical path of a stored resource, without its option folder.
+fn canon_of<'f>(option_roots: &[std::string::String], file: &'f str) -> &'f str {
+    option_roots
+        .iter()
+        .find_map(|root| file.strip_prefix(root.as_str()))
+        .unwrap_or(file)
+}
+
+/// Check a mod ZIP or unpacked mod folder: its meta, the manifests of the mod
+/// and every option, that every file listed in a manifest is stored, that
+/// every stored resource is used and can be decompressed, deserialized and
+/// migrated.
+/// Every problem is collected into the report instead of stopping at the
+/// first one, so this only fails if the mod cannot be opened at all.
+pub fn verify_mod(path: &Path) -> Result<VerifyReport> {
+    let source = Source::open(path)?;
+    let mut report = VerifyReport::default();
+    let meta = match source.read("meta.yml") {
+        Ok(text) => {
+            match check_meta(&text) {
+                Ok(meta) => Some(meta),
+                Err(error) => {
+                    report.issues.push(VerifyIssue::Meta { error });
+                    None
+                }
+            }
+        }
+        Err(e) => {
+            report.issues.push(VerifyIssue::Meta {
+                error: e.to_string().into(),
+            });
+            None
+        }
+    };
+    report.name = meta.as_ref().map(|m| m.name.clone());
+    let option_roots = meta
+        .iter()
+        .flat_map(|m| m.options.iter().flat_map(|g| g.options().iter()))
+        .map(|o| format!("options/{}/", o.path.to_slash_lossy()))
+        .collect::<Vec<_>>();
+    let files = source.files();
+    let mut resources = BTreeSet::new();
+    let mut unknown_options = BTreeSet::new();
+    for file in files.iter() {
+        if file == "meta.yml"
+            || file == "manifest.yml"
+            || file == BUILD_INDEX
+            || file.starts_with("thumb.")
+        {
+            continue;
+        }
+        if let Some(rest) = file.strip_prefix("options/") {
+            if rest.ends_with("/manifest.yml") {
+                continue;
+            }
+            if meta.is_some() && !option_roots.iter().any(|root| file.starts_with(root)) {
+                if let Some(folder) = rest.split('/').next() {
+                    unknown_options.insert(format!("options/{folder}"));
+                }
+                continue;
+            }
+        }
+        resources.insert(file.as_str());
+    }
+    report.issues.extend(
+        unknown_options
+            .into_iter()
+            .map(|path| VerifyIssue::UnknownOption { path: path.into() }),
+    );
+    let mut listed_canons = HashSet::new();
+    let mut manifests_ok = true;
+    // Files listed in an option's manifest may be stored in the base mod
+    // instead, if the option does not change them.
+    for root in std::iter::once(std::string::String::new()).chain(option_roots.iter().cloned()) {
+        let manifest_path = format!("{root}manifest.yml");
+        let manifest: Manifest = match source
+            .read(&manifest_path)
+            .and_then(|text| Ok(serde_yaml::from_slice(&text)?))
+        {
+            Ok(manifest) => manifest,
+            Err(e) => {
+                report.issues.push(VerifyIssue::Manifest {
+                    path:  manifest_path.into(),
+                    error: e.to_string().into(),
+                });
+                manifests_ok = false;
+                continue;
+            }
+        };
+        let listed = manifest
+            .content_files
+            .iter()
+            .map(|f| (f.as_str(), canonicalize(f.as_str())))
+            .chain(manifest.aoc_files.iter().map(|f| {
+                (f.as_str(), match f.starts_with("Aoc/0010/") {
+                    true => canonicalize(f.as_str()),
+                    false => canonicalize(format!("Aoc/0010/{f}")),
+                })
+            }));
+        for (file, canon) in listed {
+            listed_canons.insert(canon.clone());
+            // SARCs only need to be stored if their file list changes, since
+            // their contents are stored separately.
+            let is_sarc = Path::new(file)
+                .extension()
+                .and_then(|e| e.to_str())
+                .is_some_and(|ext| botw_utils::extensions::SARC_EXTS.contains(&ext));
+            if !is_sarc
+                && !resources.contains([root.as_str(), canon.as_str()].concat().as_str())
+                && !resources.contains(canon.as_str())
+            {
+                report.issues.push(VerifyIssue::Missing {
+                    manifest: manifest_path.as_str().into(),
+                    file:     file.into(),
+                });
+            }
+        }
+    }
+    report.resources = resources.len();
+    let migrations = meta
+        .as_ref()
+        .and_then(|m| migrate::pending(m).ok())
+        .unwrap_or_default();
+    let checked = resources
+        .into_par_iter()
+        .map_init(init_decompressor, |decomp, file| {
+            let result = check_resource(&source, &mut decomp.lock(), file).and_then(|resource| {
+                if let Some(meta) = meta.as_ref() {
+                    migrate::check_resource(meta, &migrations, canon_of(&option_roots, file))?;
+                }
+                // Only the file lists of SARCs are needed from here on
+                Ok(match resource {
+                    ResourceData::Sarc(sarc) => sarc.files.iter().cloned().collect::<Vec<String>>(),
+                    _ => vec![],
+                })
+            });
+            (file, result)
+        })
+        .collect::<Vec<_>>();
+    // Files nested in a SARC are not listed in the manifest themselves, so
+    // they are used if their SARC is
+    let mut nested = HashSet::new();
+    for (file, result) in checked.iter() {
+        match result {
+            Ok(files) => {
+                let prefix = match canon_of(&option_roots, file).starts_with("Aoc/0010/") {
+                    true => "Aoc/0010/",
+                    false => "",
+                };
+                nested.extend(
+                    files
+                        .iter()
+                        .map(|name| canonicalize(format!("{prefix}{name}"))),
+                );
+            }
+            Err(e) => {
+                report.issues.push(VerifyIssue::Resource {
+                    path:  (*file).into(),
+                    error: format!("{e:#}").into(),
+                });
+            }
+        }
+    }
+    // Without the meta or every manifest, there is no telling what is used
+    if let Some(meta) = meta.as_ref().filter(|_| manifests_ok) {
+        let endians = match meta.platform {
+            ModPlatform::Specific(endian) => vec![endian],
+            ModPlatform::Universal => vec![Endian::Little, Endian::Big],
+        };
+        for (file, _) in checked {
+            let canon = canon_of(&option_roots, file);
+            let used = listed_canons.contains(canon)
+                || nested.contains(canon)
+                || endians.iter().any(|endian| {
+                    uk_reader::stock_containers(canon, *endian)
+                        .iter()
+                        .any(|container| listed_canons.contains(container))
+                });
+            if !used {
+                report
+                    .issues
+                    .push(VerifyIssue::Unlisted { path: file.into() });
+            }
+        }
+    }
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn verify_mod() {
+        let report = super::verify_mod("test/wiiu.zip".as_ref()).unwrap();
+        assert!(report.is_ok(), "{:#?}", report.issues);
+    }
+
+    #[test]
+    fn bad_folder() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir_all(dir.path().join("options/extra")).unwrap();
+        fs::write(
+            dir.path().join("manifest.yml"),
+            "content:\n- Actor/ActorInfo.product.sbyml\naoc: []\n",
+        )
+        .unwrap();
+        fs::write(dir.path().join("options/extra/Foo.bdrop"), b"not cbor").unwrap();
+        let report = super::verify_mod(dir.path()).unwrap();
+        assert!(report.name.is_none());
+        assert!(matches!(report.issues[0], VerifyIssue::Meta { .. }));
+        assert!(report.issues.contains(&VerifyIssue::Missing {
+            manifest: "manifest.yml".into(),
+            file:     "Actor/ActorInfo.product.sbyml".into(),
+        }));
+        assert!(report.issues.iter().any(|issue| {
+            matches!(issue, VerifyIssue::Resource { path, .. } if path == "options/extra/Foo.bdrop")
+        }));
+    }
+
+    #[test]
+    fn unlisted() {
+        let dir = tempfile::tempdir().unwrap();
+        let meta = Meta {
+            api: env!("CARGO_PKG_VERSION").into(),
+            name: "Test".into(),
+            version: "1.0.0".into(),
+            author: "Tester".into(),
+            category: crate::ModCategory::Other,
+            description: Default::default(),
+            platform: ModPlatform::Specific(Endian::Little),
+            url: None,
+            masters: Default::default(),
+            options: vec![],
+        };
+        fs::write(
+            dir.path().join("meta.yml"),
+            serde_yaml::to_string(&meta).unwrap(),
+        )
+        .unwrap();
+        fs::write(
+            dir.path().join("manifest.yml"),
+            "content:\n- Actor/ActorInfo.product.sbyml\n- \
+             Actor/Pack/Enemy_Bokoblin_Junior.sbactorpack\naoc: []\n",
+        )
+        .unwrap();
+        let data = minicbor_ser::to_vec(&ResourceData::Binary(b"abc".to_vec())).unwrap();
+        for file in [
+            "Actor/ActorInfo.product.byml",
+            "Actor/DropTable/Enemy_Bokoblin_Junior.bdrop",
+            "Actor/DropTable/Enemy_Lizal_Senior.bdrop",
+        ] {
+            let path = dir.path().join(file);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, &data).unwrap();
+        }
+        let report = super::verify_mod(dir.path()).unwrap();
+        assert_eq!(report.issues, [VerifyIssue::Unlisted {
+            path: "Actor/DropTable/Enemy_Lizal_Senior.bdrop".into(),
+        }]);
+    }
+}
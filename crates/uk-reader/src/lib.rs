@@ -1,5 +1,7 @@
-// mod nsp;
+mod nsp;
+mod region;
 mod unpacked;
+mod wud;
 mod zarchive;
 
 use std::{
@@ -22,7 +24,7 @@ use uk_content::{
 };
 use uk_util::PathExt;
 
-use self::{unpacked::Unpacked, zarchive::ZArchive};
+use self::{nsp::Nsp, unpacked::Unpacked, wud::Wud, zarchive::ZArchive};
 
 #[derive(Debug, thiserror::Error)]
 pub enum ROMError {
@@ -30,6 +32,8 @@ pub enum ROMError {
     FileNotFound(String, PathBuf),
     #[error("Missing required {0} folder in game dump\n(Using ROM at {1})")]
     MissingDumpDir(&'static str, PathBuf),
+    #[error("Missing {0} in keys at {1}")]
+    MissingKey(String, PathBuf),
     #[error("Invalid resource path: {0}")]
     InvalidPath(String),
     #[error(transparent)]
@@ -161,6 +165,31 @@ impl std::fmt::Debug for ResourceReader {
     }
 }
 
+/// The canonical paths of the top-level game files which hold a stock
+/// resource, in any of the dumps. A resource which is not nested in a SARC
+/// holds itself. Empty if the resource is not a stock file.
+pub fn stock_containers(canon: &str, endian: Endian) -> Vec<String> {
+    let file_map = match endian {
+        Endian::Little => FILE_MAP_NX.clone(),
+        Endian::Big => FILE_MAP_U.clone(),
+    };
+    let Some(paths) = file_map.get(canon) else {
+        return vec![];
+    };
+    paths
+        .iter()
+        .enumerate()
+        .filter(|(_, path)| !path.is_empty())
+        .map(|(dump, path)| {
+            let root = path.split("//").next().unwrap_or_default();
+            match dump == 0 && !root.starts_with("Aoc/0010/") {
+                true => canonicalize(format!("Aoc/0010/{root}")),
+                false => canonicalize(root),
+            }
+        })
+        .collect()
+}
+
 impl ResourceReader {
     pub fn clear_cache(&self) {
         self.cache.invalidate_all();
@@ -184,6 +213,40 @@ impl ResourceReader {
         })
     }
 
+    /// Read the game from an NSP or XCI of the base game, and optionally
+    /// NSPs of the update and DLC, using the keys in a `prod.keys` file.
+    pub fn from_nsp(
+        base_path: impl AsRef<Path>,
+        update_path: Option<impl AsRef<Path>>,
+        aoc_path: Option<impl AsRef<Path>>,
+        keys_path: impl AsRef<Path>,
+    ) -> Result<Self> {
+        Ok(Self {
+            source: Box::new(Nsp::new(base_path, update_path, aoc_path, keys_path)?),
+            cache: construct_res_cache(),
+            sarc_cache: construct_sarc_cache(),
+            bin_type: BinType::Nintendo,
+            file_map: FILE_MAP_NX.clone(),
+        })
+    }
+
+    /// Read the base game from a WUD or WUX disc image, which needs the
+    /// `game.key` and `common.key` files dumped with it in the same folder,
+    /// and the update and DLC from unpacked folders.
+    pub fn from_wud(
+        image_path: impl AsRef<Path>,
+        update_dir: impl AsRef<Path>,
+        aoc_dir: Option<impl AsRef<Path>>,
+    ) -> Result<Self> {
+        Ok(Self {
+            source: Box::new(Wud::new(image_path, update_dir, aoc_dir)?),
+            cache: construct_res_cache(),
+            sarc_cache: construct_sarc_cache(),
+            bin_type: BinType::Nintendo,
+            file_map: FILE_MAP_U.clone(),
+        })
+    }
+
     pub fn from_unpacked_dirs(
         content_dir: Option<impl AsRef<Path>>,
         update_dir: Option<impl AsRef<Path>>,
@@ -235,6 +298,21 @@ impl ResourceReader {
         inner(mod_dir.as_ref())
     }
 
+    /// Read game files from any other source, such as a mod which has not
+    /// been packaged yet.
+    pub fn from_loader(source: Box<dyn ResourceLoader>, endian: Endian) -> Self {
+        Self {
+            source,
+            cache: construct_res_cache(),
+            sarc_cache: construct_sarc_cache(),
+            bin_type: BinType::Nintendo,
+            file_map: match endian {
+                Endian::Little => FILE_MAP_NX.clone(),
+                Endian::Big => FILE_MAP_U.clone(),
+            },
+        }
+    }
+
     pub fn get_data(&self, path: impl AsRef<Path>) -> Result<Arc<ResourceData>> {
         let canon = canonicalize(path.as_ref());
         log::trace!("Loading resource {}", &canon);
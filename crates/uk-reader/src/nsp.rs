@@ -0,0 +1,709 @@
+//! Game files read straight out of Switch NSP and XCI files, decrypted with
+//! the keys dumped from the user's console.
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use aes::{
+    cipher::{BlockDecrypt, KeyInit, KeyIvInit, StreamCipher},
+    Aes128,
+};
+use fs_err as fs;
+use serde::{Deserialize, Serialize};
+use xts_mode::Xts128;
+
+use crate::{
+    region::{c_str, key, le_u32, le_u64, Region},
+    ROMError, Result,
+};
+
+type Aes128Ctr = ctr::Ctr128BE<Aes128>;
+
+const NCA_HEADER_SIZE: usize = 0xC00;
+const MEDIA_UNIT: u64 = 0x200;
+const BUCKET_SIZE: usize = 0x4000;
+const CONTENT_PROGRAM: u8 = 0;
+const CONTENT_DATA: u8 = 4;
+const CONTENT_PUBLIC_DATA: u8 = 5;
+const EMPTY_ENTRY: u32 = u32::MAX;
+
+/// Keys from a `prod.keys` file, as dumped by Lockpick_RCM.
+struct Keys {
+    path: PathBuf,
+    keys: HashMap<String, Vec<u8>>,
+}
+
+impl Keys {
+    fn open(path: &Path) -> Result<Self> {
+        let keys = fs::read_to_string(path)?
+            .lines()
+            .filter_map(|line| {
+                let (name, value) = line.split_once('=')?;
+                Some((name.trim().to_owned(), parse_hex(value.trim())?))
+            })
+            .collect();
+        Ok(Self {
+            path: path.to_path_buf(),
+            keys,
+        })
+    }
+
+    fn get<const N: usize>(&self, name: &str) -> Result<[u8; N]> {
+        self.keys
+            .get(name)
+            .and_then(|key| key.as_slice().try_into().ok())
+            .ok_or_else(|| ROMError::MissingKey(name.into(), self.path.clone()))
+    }
+}
+
+fn parse_hex(text: &str) -> Option<Vec<u8>> {
+    (text.len() % 2 == 0)
+        .then(|| {
+            (0..text.len())
+                .step_by(2)
+                .map(|i| u8::from_str_radix(text.get(i..i + 2)?, 16).ok())
+                .collect()
+        })
+        .flatten()
+}
+
+fn decrypt_key(kek: &[u8; 16], key: &[u8; 16]) -> [u8; 16] {
+    let mut block = (*key).into();
+    Aes128::new(kek.into()).decrypt_block(&mut block);
+    block.into()
+}
+
+/// The files in a PFS0 (NSP) or HFS0 (XCI) partition.
+fn partition_files(region: &Region) -> Result<Vec<(String, Region)>> {
+    let header = region.read_vec(0, 0x10)?;
+    let entry_size = match &header[..4] {
+        b"PFS0" => 0x18,
+        b"HFS0" => 0x40,
+        _ => return Err(ROMError::OtherMessage("Not a valid NSP or XCI file")),
+    };
+    let count = le_u32(&header, 0x4)? as usize;
+    let strings_size = le_u32(&header, 0x8)? as usize;
+    let table = region.read_vec(0x10, count * entry_size + strings_size)?;
+    let strings = &table[count * entry_size..];
+    let data_offset = 0x10 + table.len() as u64;
+    (0..count)
+        .map(|i| {
+            let entry = &table[i * entry_size..];
+            let name = c_str(strings, le_u32(entry, 0x10)? as usize)?;
+            let file = region.slice(data_offset + le_u64(entry, 0x0)?, le_u64(entry, 0x8)?)?;
+            Ok((name, file))
+        })
+        .collect()
+}
+
+/// The files in the secure partition of an XCI, which holds the game.
+fn xci_files(region: &Region) -> Result<Vec<(String, Region)>> {
+    let header = region.read_vec(0x100, 0x40)?;
+    if &header[..4] != b"HEAD" {
+        return Err(ROMError::OtherMessage("Not a valid XCI file"));
+    }
+    let root_offset = le_u64(&header, 0x30)?;
+    let root = region.slice(root_offset, region.size().saturating_sub(root_offset))?;
+    let secure = partition_files(&root)?
+        .into_iter()
+        .find_map(|(name, partition)| (name == "secure").then_some(partition))
+        .ok_or(ROMError::OtherMessage("XCI has no secure partition"))?;
+    partition_files(&secure)
+}
+
+/// The NCAs in an NSP or XCI which hold game files, with their content type.
+fn open_ncas(path: &Path, keys: &Keys) -> Result<Vec<Nca>> {
+    let file = Region::open(path)?;
+    let files = match path.extension().and_then(|e| e.to_str()) {
+        Some(ext) if ext.eq_ignore_ascii_case("xci") => xci_files(&file)?,
+        _ => partition_files(&file)?,
+    };
+    // Title keys from the tickets, by rights ID
+    let title_keys = files
+        .iter()
+        .filter(|(name, _)| name.ends_with(".tik"))
+        .map(|(_, ticket)| -> Result<([u8; 16], [u8; 16])> {
+            let data = ticket.read_vec(0x180, 0x130)?;
+            Ok((key(&data, 0x120)?, key(&data, 0x0)?))
+        })
+        .collect::<Result<HashMap<_, _>>>()?;
+    files
+        .into_iter()
+        .filter(|(name, _)| name.ends_with(".nca") && !name.ends_with(".cnmt.nca"))
+        .map(|(_, region)| Nca::open(region, keys, &title_keys))
+        .filter(|nca| {
+            nca.as_ref()
+                .map(|nca| {
+                    matches!(
+                        nca.content_type(),
+                        CONTENT_PROGRAM | CONTENT_DATA | CONTENT_PUBLIC_DATA
+                    )
+                })
+                .unwrap_or(true)
+        })
+        .collect()
+}
+
+/// A Nintendo Content Archive, with its header decrypted.
+struct Nca {
+    region:    Region,
+    header:    Vec<u8>,
+    title_key: Option<[u8; 16]>,
+}
+
+impl Nca {
+    fn open(region: Region, keys: &Keys, title_keys: &HashMap<[u8; 16], [u8; 16]>) -> Result<Self> {
+        let mut header = region.read_vec(0, NCA_HEADER_SIZE)?;
+        let header_key: [u8; 32] = keys.get("header_key")?;
+        let xts = Xts128::new(
+            Aes128::new((&header_key[..16]).into()),
+            Aes128::new((&header_key[16..]).into()),
+        );
+        xts.decrypt_area(&mut header, MEDIA_UNIT as usize, 0, |sector| {
+            sector.to_be_bytes()
+        });
+        if &header[0x200..0x204] != b"NCA3" {
+            return Err(ROMError::OtherMessage(
+                "Could not decrypt NCA header, check that your keys are up to date",
+            ));
+        }
+        let rights_id = key(&header, 0x230)?;
+        let title_key = match rights_id.iter().any(|b| *b != 0) {
+            true => {
+                Some(*title_keys.get(&rights_id).ok_or(ROMError::OtherMessage(
+                    "NCA needs a title key, but no ticket for it was found",
+                ))?)
+            }
+            false => None,
+        };
+        Ok(Self {
+            region,
+            header,
+            title_key,
+        })
+    }
+
+    fn content_type(&self) -> u8 {
+        self.header[0x205]
+    }
+
+    /// The section key for AES-CTR, from the title key or the key area.
+    fn key(&self, keys: &Keys) -> Result<[u8; 16]> {
+        let generation = self.header[0x206].max(self.header[0x220]).saturating_sub(1);
+        match self.title_key {
+            Some(title_key) => {
+                let kek = keys.get(&format!("titlekek_{generation:02x}"))?;
+                Ok(decrypt_key(&kek, &title_key))
+            }
+            None => {
+                let kind = match self.header[0x207] {
+                    0 => "application",
+                    1 => "ocean",
+                    _ => "system",
+                };
+                let kek = keys.get(&format!("key_area_key_{kind}_{generation:02x}"))?;
+                Ok(decrypt_key(&kek, &key(&self.header, 0x320)?))
+            }
+        }
+    }
+
+    fn romfs(&self, keys: &Keys) -> Result<Section> {
+        let (index, start, end) = (0..4)
+            .find_map(|i| {
+                let start = le_u32(&self.header, 0x240 + i * 0x10).ok()? as u64 * MEDIA_UNIT;
+                let end = le_u32(&self.header, 0x244 + i * 0x10).ok()? as u64 * MEDIA_UNIT;
+                let fs_header = &self.header[0x400 + i * 0x200..];
+                // A RomFS partition verified by a hash tree
+                (end > start && fs_header[0x2] == 0 && fs_header[0x3] == 3)
+                    .then_some((i, start, end))
+            })
+            .ok_or(ROMError::OtherMessage("NCA has no RomFS"))?;
+        let fs_header = self.header[0x400 + index * 0x200..0x600 + index * 0x200].to_vec();
+        let mut ctr = [0; 8];
+        ctr.copy_from_slice(&fs_header[0x140..0x148]);
+        ctr.reverse();
+        Ok(Section {
+            region: self.region.slice(start, end - start)?,
+            key: self.key(keys)?,
+            ctr,
+            encryption: fs_header[0x4],
+            fs_header,
+        })
+    }
+}
+
+/// Decrypted bytes holding a RomFS and the hash tree over it.
+trait Storage: std::fmt::Debug + Send + Sync {
+    fn read(&self, offset: u64, buf: &mut [u8]) -> Result<()>;
+}
+
+/// A section of an NCA.
+#[derive(Clone)]
+struct Section {
+    region: Region,
+    key: [u8; 16],
+    ctr: [u8; 8],
+    encryption: u8,
+    fs_header: Vec<u8>,
+}
+
+impl std::fmt::Debug for Section {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Section")
+            .field("region", &self.region)
+            .field("encryption", &self.encryption)
+            .finish()
+    }
+}
+
+impl Section {
+    /// Where the RomFS starts, being the last level of the hash tree.
+    fn romfs_offset(&self) -> Result<u64> {
+        le_u64(&self.fs_header, 0x8 + 0x10 + 5 * 0x18)
+    }
+
+    /// Read from the section, replacing part of the counter with
+    /// `generation` for data an update added.
+    fn read_with(&self, offset: u64, buf: &mut [u8], generation: Option<u32>) -> Result<()> {
+        match self.encryption {
+            1 => self.region.read(offset, buf),
+            3 | 4 => {
+                let start = offset & !0xF;
+                let skip = (offset - start) as usize;
+                let mut data = self
+                    .region
+                    .read_vec(start, (skip + buf.len() + 0xF) & !0xF)?;
+                let mut iv = [0; 16];
+                iv[..8].copy_from_slice(&self.ctr);
+                if let Some(generation) = generation {
+                    iv[4..8].copy_from_slice(&generation.to_be_bytes());
+                }
+                iv[8..].copy_from_slice(&(start >> 4).to_be_bytes());
+                Aes128Ctr::new((&self.key).into(), (&iv).into()).apply_keystream(&mut data);
+                buf.copy_from_slice(&data[skip..skip + buf.len()]);
+                Ok(())
+            }
+            _ => {
+                Err(ROMError::OtherMessage(
+                    "NCA section uses an unsupported encryption type",
+                ))
+            }
+        }
+    }
+}
+
+impl Storage for Section {
+    fn read(&self, offset: u64, buf: &mut [u8]) -> Result<()> {
+        self.read_with(offset, buf, None)
+    }
+}
+
+/// The entries of a bucket tree, along with the offset where the last one
+/// ends.
+fn read_buckets<T>(
+    data: &[u8],
+    entry_size: usize,
+    parse: impl Fn(&[u8]) -> Result<T>,
+) -> Result<(Vec<T>, u64)> {
+    let bucket_count = le_u32(data, 0x4)? as usize;
+    let end = le_u64(data, 0x8)?;
+    let mut entries = vec![];
+    for bucket in 0..bucket_count {
+        let bucket = data
+            .get(BUCKET_SIZE * (bucket + 1)..)
+            .ok_or(ROMError::OtherMessage("Update patch table is corrupted"))?;
+        for i in 0..le_u32(bucket, 0x4)? as usize {
+            let entry = bucket
+                .get(0x10 + i * entry_size..)
+                .ok_or(ROMError::OtherMessage("Update patch table is corrupted"))?;
+            entries.push(parse(entry)?);
+        }
+    }
+    Ok((entries, end))
+}
+
+/// Split a read across a table of entries sorted by offset, reading each part
+/// with the entry it falls in, where that entry starts, and its own offset.
+fn split_read<T: Copy>(
+    table: &[(u64, T)],
+    end: u64,
+    mut offset: u64,
+    buf: &mut [u8],
+    mut read: impl FnMut(T, u64, u64, &mut [u8]) -> Result<()>,
+) -> Result<()> {
+    let mut done = 0;
+    while done < buf.len() {
+        let index = table
+            .partition_point(|(start, _)| *start <= offset)
+            .checked_sub(1)
+            .ok_or(ROMError::OtherMessage("Read outside of update patch"))?;
+        let (start, entry) = table[index];
+        let entry_end = table.get(index + 1).map(|(next, _)| *next).unwrap_or(end);
+        let len = (entry_end.saturating_sub(offset) as usize).min(buf.len() - done);
+        if len == 0 {
+            return Err(ROMError::OtherMessage("Read outside of update patch"));
+        }
+        read(entry, start, offset, &mut buf[done..done + len])?;
+        done += len;
+        offset += len as u64;
+    }
+    Ok(())
+}
+
+/// The RomFS of an update, which takes most of its data from the base game
+/// and the rest from its own section, each part with its own counter.
+#[derive(Debug)]
+struct PatchedSection {
+    base: Section,
+    patch: Section,
+    /// Virtual offset, then the physical offset and whether it is in the
+    /// patch
+    relocations: Vec<(u64, (u64, bool))>,
+    size: u64,
+    /// Physical offset in the patch, then its counter generation
+    subsections: Vec<(u64, u32)>,
+    patch_end: u64,
+}
+
+impl PatchedSection {
+    fn new(base: Section, patch: Section) -> Result<Self> {
+        let header = &patch.fs_header;
+        let mut relocation_table = vec![0; le_u64(header, 0x108)? as usize];
+        patch.read_with(le_u64(header, 0x100)?, &mut relocation_table, None)?;
+        let mut subsection_table = vec![0; le_u64(header, 0x128)? as usize];
+        patch.read_with(le_u64(header, 0x120)?, &mut subsection_table, None)?;
+        let (relocations, size) = read_buckets(&relocation_table, 0x14, |entry| {
+            Ok((
+                le_u64(entry, 0x0)?,
+                (le_u64(entry, 0x8)?, le_u32(entry, 0x10)? != 0),
+            ))
+        })?;
+        let (subsections, patch_end) = read_buckets(&subsection_table, 0x10, |entry| {
+            Ok((le_u64(entry, 0x0)?, le_u32(entry, 0xC)?))
+        })?;
+        Ok(Self {
+            base,
+            patch,
+            relocations,
+            size,
+            subsections,
+            patch_end,
+        })
+    }
+
+    fn read_patch(&self, offset: u64, buf: &mut [u8]) -> Result<()> {
+        split_read(
+            &self.subsections,
+            self.patch_end,
+            offset,
+            buf,
+            |generation, _, offset, buf| self.patch.read_with(offset, buf, Some(generation)),
+        )
+    }
+}
+
+impl Storage for PatchedSection {
+    fn read(&self, offset: u64, buf: &mut [u8]) -> Result<()> {
+        split_read(
+            &self.relocations,
+            self.size,
+            offset,
+            buf,
+            |(physical, in_patch), start, offset, buf| {
+                match in_patch {
+                    true => self.read_patch(physical + offset - start, buf),
+                    false => self.base.read_with(physical + offset - start, buf, None),
+                }
+            },
+        )
+    }
+}
+
+/// The file table of a RomFS, read from its storage.
+#[derive(Clone)]
+struct RomFs {
+    storage: Arc<dyn Storage>,
+    /// The offset and size of each file in the storage, by path
+    files:   Arc<HashMap<String, (u64, u64)>>,
+}
+
+impl std::fmt::Debug for RomFs {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RomFs")
+            .field("storage", &self.storage)
+            .field("files", &self.files.len())
+            .finish()
+    }
+}
+
+impl RomFs {
+    fn new(storage: Arc<dyn Storage>, offset: u64) -> Result<Self> {
+        let mut header = [0; 0x50];
+        storage.read(offset, &mut header)?;
+        let read_table = |table_offset: usize| -> Result<Vec<u8>> {
+            let mut table = vec![0; le_u64(&header, table_offset + 0x8)? as usize];
+            storage.read(offset + le_u64(&header, table_offset)?, &mut table)?;
+            Ok(table)
+        };
+        let dirs = read_table(0x18)?;
+        let file_entries = read_table(0x38)?;
+        let data_offset = offset + le_u64(&header, 0x48)?;
+        let name = |table: &[u8], entry: usize, name_offset: usize| -> Result<String> {
+            let len = le_u32(table, entry + name_offset - 0x4)? as usize;
+            table
+                .get(entry + name_offset..entry + name_offset + len)
+                .map(|name| String::from_utf8_lossy(name).into_owned())
+                .ok_or(ROMError::OtherMessage("RomFS file table is corrupted"))
+        };
+        let join = |parent: &str, name: String| {
+            match parent.is_empty() {
+                true => name,
+                false => format!("{parent}/{name}"),
+            }
+        };
+        let mut files = HashMap::new();
+        let mut stack = vec![(0, String::new())];
+        while let Some((dir, path)) = stack.pop() {
+            let mut file = le_u32(&dirs, dir + 0xC)?;
+            while file != EMPTY_ENTRY {
+                let entry = file as usize;
+                files.insert(
+                    join(&path, name(&file_entries, entry, 0x20)?),
+                    (
+                        data_offset + le_u64(&file_entries, entry + 0x8)?,
+                        le_u64(&file_entries, entry + 0x10)?,
+                    ),
+                );
+                file = le_u32(&file_entries, entry + 0x4)?;
+            }
+            let mut child = le_u32(&dirs, dir + 0x8)?;
+            while child != EMPTY_ENTRY {
+                let entry = child as usize;
+                stack.push((entry, join(&path, name(&dirs, entry, 0x18)?)));
+                child = le_u32(&dirs, entry + 0x4)?;
+            }
+        }
+        Ok(Self {
+            storage,
+            files: Arc::new(files),
+        })
+    }
+
+    fn from_section(section: Section) -> Result<Self> {
+        let offset = section.romfs_offset()?;
+        Self::new(Arc::new(section), offset)
+    }
+
+    fn contains(&self, name: &Path) -> bool {
+        self.files
+            .contains_key(name.to_string_lossy().replace('\\', "/").as_str())
+    }
+
+    fn read(&self, name: &Path) -> Option<Result<Vec<u8>>> {
+        let (offset, size) = *self
+            .files
+            .get(name.to_string_lossy().replace('\\', "/").as_str())?;
+        let mut data = vec![0; size as usize];
+        Some(self.storage.read(offset, &mut data).map(|_| data))
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct NspInfo {
+    host_path:   PathBuf,
+    update_path: Option<PathBuf>,
+    aoc_path:    Option<PathBuf>,
+    keys_path:   PathBuf,
+}
+
+impl From<Nsp> for NspInfo {
+    fn from(nsp: Nsp) -> Self {
+        Self {
+            host_path:   nsp.host_path,
+            update_path: nsp.update_path,
+            aoc_path:    nsp.aoc_path,
+            keys_path:   nsp.keys_path,
+        }
+    }
+}
+
+impl TryFrom<NspInfo> for Nsp {
+    type Error = ROMError;
+
+    fn try_from(info: NspInfo) -> Result<Self> {
+        Self::new(
+            info.host_path,
+            info.update_path,
+            info.aoc_path,
+            info.keys_path,
+        )
+    }
+}
+
+/// Game files read from an NSP or XCI of the base game, with the update and
+/// DLC from NSPs of their own.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(into = "NspInfo", try_from = "NspInfo")]
+pub(crate) struct Nsp {
+    host_path: PathBuf,
+    update_path: Option<PathBuf>,
+    aoc_path: Option<PathBuf>,
+    keys_path: PathBuf,
+    base: RomFs,
+    update: Option<RomFs>,
+    aoc: Vec<RomFs>,
+}
+
+impl Nsp {
+    pub(crate) fn new(
+        host_path: impl AsRef<Path>,
+        update_path: Option<impl AsRef<Path>>,
+        aoc_path: Option<impl AsRef<Path>>,
+        keys_path: impl AsRef<Path>,
+    ) -> Result<Self> {
+        let host_path = host_path.as_ref();
+        let update_path = update_path
+            .as_ref()
+            .map(|p| p.as_ref())
+            .filter(|p| !p.as_os_str().is_empty());
+        let aoc_path = aoc_path
+            .as_ref()
+            .map(|p| p.as_ref())
+            .filter(|p| !p.as_os_str().is_empty());
+        let keys = Keys::open(keys_path.as_ref())?;
+        let program = |path: &Path| -> Result<Section> {
+            open_ncas(path, &keys)?
+                .into_iter()
+                .find(|nca| nca.content_type() == CONTENT_PROGRAM)
+                .ok_or_else(|| ROMError::MissingDumpDir("game program", path.to_path_buf()))?
+                .romfs(&keys)
+        };
+        log::info!("Opening base game at {}", host_path.display());
+        let base_section = program(host_path)?;
+        let base = RomFs::from_section(base_section.clone())?;
+        let update = update_path
+            .map(|path| -> Result<RomFs> {
+                log::info!("Opening update at {}", path.display());
+                let patch = program(path)?;
+                let offset = patch.romfs_offset()?;
+                RomFs::new(
+                    Arc::new(PatchedSection::new(base_section.clone(), patch)?),
+                    offset,
+                )
+            })
+            .transpose()?;
+        let aoc = aoc_path
+            .map(|path| -> Result<Vec<RomFs>> {
+                log::info!("Opening DLC at {}", path.display());
+                open_ncas(path, &keys)?
+                    .into_iter()
+                    .filter(|nca| nca.content_type() != CONTENT_PROGRAM)
+                    .map(|nca| RomFs::from_section(nca.romfs(&keys)?))
+                    .collect()
+            })
+            .transpose()?
+            .unwrap_or_default();
+        Ok(Self {
+            host_path: host_path.to_path_buf(),
+            update_path: update_path.map(|p| p.to_path_buf()),
+            aoc_path: aoc_path.map(|p| p.to_path_buf()),
+            keys_path: keys_path.as_ref().to_path_buf(),
+            base,
+            update,
+            aoc,
+        })
+    }
+
+    fn not_found(&self, name: &Path) -> ROMError {
+        ROMError::FileNotFound(name.to_string_lossy().into(), self.host_path.clone())
+    }
+}
+
+#[typetag::serde]
+impl super::ResourceLoader for Nsp {
+    fn get_base_file_data(&self, name: &Path) -> Result<Vec<u8>> {
+        // The update holds the whole game, patched
+        self.update
+            .as_ref()
+            .unwrap_or(&self.base)
+            .read(name)
+            .unwrap_or_else(|| Err(self.not_found(name)))
+    }
+
+    fn get_update_file_data(&self, name: &Path) -> Result<Vec<u8>> {
+        self.update
+            .as_ref()
+            .map(|update| {
+                update
+                    .read(name)
+                    .unwrap_or_else(|| Err(self.not_found(name)))
+            })
+            .unwrap_or_else(|| Err(ROMError::MissingDumpDir("Update", self.host_path.clone())))
+    }
+
+    fn get_aoc_file_data(&self, name: &Path) -> Result<Vec<u8>> {
+        if self.aoc.is_empty() {
+            return Err(ROMError::MissingDumpDir("DLC", self.host_path.clone()));
+        }
+        self.aoc
+            .iter()
+            .rev()
+            .find_map(|aoc| aoc.read(name))
+            .unwrap_or_else(|| Err(self.not_found(name)))
+    }
+
+    fn file_exists(&self, name: &Path) -> bool {
+        self.update.as_ref().unwrap_or(&self.base).contains(name)
+            || self.aoc.iter().any(|aoc| aoc.contains(name))
+    }
+
+    fn host_path(&self) -> &Path {
+        &self.host_path
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pfs0_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("test.nsp");
+        let strings = b"a.tik\0b.nca\0\0\0\0\0";
+        let mut data = b"PFS0".to_vec();
+        data.extend(2u32.to_le_bytes());
+        data.extend((strings.len() as u32).to_le_bytes());
+        data.extend(0u32.to_le_bytes());
+        for (offset, size, name) in [(0u64, 3u64, 0u32), (3, 5, 6)] {
+            data.extend(offset.to_le_bytes());
+            data.extend(size.to_le_bytes());
+            data.extend(name.to_le_bytes());
+            data.extend(0u32.to_le_bytes());
+        }
+        data.extend(strings);
+        data.extend(b"tikncanca");
+        fs::write(&path, &data).unwrap();
+        let files = partition_files(&Region::open(&path).unwrap()).unwrap();
+        assert_eq!(files.len(), 2);
+        assert_eq!(files[0].0, "a.tik");
+        assert_eq!(files[1].0, "b.nca");
+        assert_eq!(files[1].1.read_vec(0, 5).unwrap(), b"ncanc");
+    }
+
+    #[test]
+    fn patch_table_reads() {
+        let table = [(0, 'a'), (4, 'b'), (6, 'c')];
+        let mut buf = [0; 8];
+        let mut parts = vec![];
+        split_read(&table, 10, 2, &mut buf, |entry, start, offset, buf| {
+            parts.push((entry, offset - start, buf.len()));
+            Ok(())
+        })
+        .unwrap();
+        assert_eq!(parts, vec![('a', 2, 2), ('b', 0, 2), ('c', 0, 4)]);
+        assert!(split_read(&table, 10, 8, &mut buf, |_, _, _, _| Ok(())).is_err());
+    }
+}
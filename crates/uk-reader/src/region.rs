@@ -0,0 +1,102 @@
+use std::{
+    io::{Read, Seek, SeekFrom},
+    path::Path,
+    sync::Arc,
+};
+
+use fs_err as fs;
+use parking_lot::Mutex;
+
+use crate::{ROMError, Result};
+
+/// A span of bytes in a file on disk, such as one file packed inside another.
+#[derive(Debug, Clone)]
+pub(crate) struct Region {
+    file:   Arc<Mutex<fs::File>>,
+    offset: u64,
+    size:   u64,
+}
+
+impl Region {
+    pub(crate) fn open(path: &Path) -> Result<Self> {
+        let file = fs::File::open(path)?;
+        let size = file.metadata()?.len();
+        Ok(Self {
+            file: Arc::new(Mutex::new(file)),
+            offset: 0,
+            size,
+        })
+    }
+
+    pub(crate) fn size(&self) -> u64 {
+        self.size
+    }
+
+    pub(crate) fn slice(&self, offset: u64, size: u64) -> Result<Self> {
+        if offset.saturating_add(size) > self.size {
+            return Err(ROMError::OtherMessage(
+                "Game image is truncated or corrupted",
+            ));
+        }
+        Ok(Self {
+            file: self.file.clone(),
+            offset: self.offset + offset,
+            size,
+        })
+    }
+
+    pub(crate) fn read(&self, offset: u64, buf: &mut [u8]) -> Result<()> {
+        let mut file = self.file.lock();
+        file.seek(SeekFrom::Start(self.offset + offset))?;
+        file.read_exact(buf)?;
+        Ok(())
+    }
+
+    pub(crate) fn read_vec(&self, offset: u64, len: usize) -> Result<Vec<u8>> {
+        let mut buf = vec![0; len];
+        self.read(offset, &mut buf)?;
+        Ok(buf)
+    }
+}
+
+fn bytes<const N: usize>(data: &[u8], offset: usize) -> Result<[u8; N]> {
+    data.get(offset..offset + N)
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or(ROMError::OtherMessage(
+            "Game image is truncated or corrupted",
+        ))
+}
+
+pub(crate) fn le_u32(data: &[u8], offset: usize) -> Result<u32> {
+    bytes(data, offset).map(u32::from_le_bytes)
+}
+
+pub(crate) fn le_u64(data: &[u8], offset: usize) -> Result<u64> {
+    bytes(data, offset).map(u64::from_le_bytes)
+}
+
+pub(crate) fn be_u16(data: &[u8], offset: usize) -> Result<u16> {
+    bytes(data, offset).map(u16::from_be_bytes)
+}
+
+pub(crate) fn be_u32(data: &[u8], offset: usize) -> Result<u32> {
+    bytes(data, offset).map(u32::from_be_bytes)
+}
+
+pub(crate) fn be_u64(data: &[u8], offset: usize) -> Result<u64> {
+    bytes(data, offset).map(u64::from_be_bytes)
+}
+
+pub(crate) fn key(data: &[u8], offset: usize) -> Result<[u8; 16]> {
+    bytes(data, offset)
+}
+
+/// A null-terminated string in a string table.
+pub(crate) fn c_str(data: &[u8], offset: usize) -> Result<String> {
+    data.get(offset..)
+        .and_then(|rest| rest.split(|b| *b == 0).next())
+        .map(|name| String::from_utf8_lossy(name).into_owned())
+        .ok_or(ROMError::OtherMessage(
+            "Game image is truncated or corrupted",
+        ))
+}
@@ -0,0 +1,504 @@
+//! Game files read straight out of a Wii U disc image, either a raw WUD or a
+//! compressed WUX, decrypted with the keys dumped along with it. Discs only
+//! hold the base game, so the update and DLC are still read from folders.
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use aes::{
+    cipher::{block_padding::NoPadding, BlockDecryptMut, KeyIvInit},
+    Aes128,
+};
+use fs_err as fs;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    region::{be_u16, be_u32, be_u64, c_str, key, le_u32, le_u64, Region},
+    unpacked::Unpacked,
+    ROMError, ResourceLoader, Result,
+};
+
+type Aes128Cbc = cbc::Decryptor<Aes128>;
+
+const WUX_MAGIC: &[u8; 8] = b"WUX0\x23\xD0\x99\x10";
+const SECTOR_SIZE: u64 = 0x8000;
+/// Where the encrypted part of the disc starts.
+const DECRYPTED_AREA: u64 = 0x18000;
+const PARTITION_TOC_SIGNATURE: [u8; 4] = [0xCC, 0xA6, 0xE6, 0x7B];
+const BLOCK_SIZE: u64 = 0x10000;
+const HASHED_DATA_SIZE: u64 = 0xFC00;
+const HASHES_SIZE: usize = 0x400;
+const APPLICATION_TITLE: u64 = 0x00050000;
+
+fn decrypt(key: &[u8; 16], iv: &[u8; 16], data: &mut [u8]) {
+    Aes128Cbc::new(key.into(), iv.into())
+        .decrypt_padded_mut::<NoPadding>(data)
+        .expect("Encrypted disc data should always be block aligned");
+}
+
+/// The IV for the start of a title content.
+fn content_iv(index: u16) -> [u8; 16] {
+    let mut iv = [0; 16];
+    iv[..2].copy_from_slice(&index.to_be_bytes());
+    iv
+}
+
+fn read_key(path: &Path, name: &str) -> Result<[u8; 16]> {
+    fs::read(path)
+        .ok()
+        .and_then(|data| data.get(..16)?.try_into().ok())
+        .ok_or_else(|| ROMError::MissingKey(name.into(), path.to_path_buf()))
+}
+
+/// A disc image, where a WUX stores identical sectors only once.
+#[derive(Debug)]
+enum Image {
+    Wud(Region),
+    Wux {
+        region: Region,
+        sector_size: u64,
+        sectors: Vec<u32>,
+        data_offset: u64,
+    },
+}
+
+impl Image {
+    fn open(path: &Path) -> Result<Self> {
+        let region = Region::open(path)?;
+        let header = region.read_vec(0, 0x20)?;
+        if &header[..8] != WUX_MAGIC {
+            return Ok(Self::Wud(region));
+        }
+        let sector_size = le_u32(&header, 0x8)? as u64;
+        if sector_size == 0 {
+            return Err(ROMError::OtherMessage("WUX file is corrupted"));
+        }
+        let count = le_u64(&header, 0x10)?.div_ceil(sector_size) as usize;
+        let sectors = region
+            .read_vec(0x20, count * 4)?
+            .chunks_exact(4)
+            .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+            .collect();
+        Ok(Self::Wux {
+            region,
+            sector_size,
+            sectors,
+            data_offset: (0x20 + count as u64 * 4).next_multiple_of(sector_size),
+        })
+    }
+
+    fn read(&self, offset: u64, buf: &mut [u8]) -> Result<()> {
+        match self {
+            Self::Wud(region) => region.read(offset, buf),
+            Self::Wux {
+                region,
+                sector_size,
+                sectors,
+                data_offset,
+            } => {
+                let mut done = 0;
+                while done < buf.len() {
+                    let pos = offset + done as u64;
+                    let sector = sectors
+                        .get((pos / sector_size) as usize)
+                        .ok_or(ROMError::OtherMessage("Read past the end of the disc"))?;
+                    let within = pos % sector_size;
+                    let len = ((sector_size - within) as usize).min(buf.len() - done);
+                    region.read(
+                        data_offset + *sector as u64 * sector_size + within,
+                        &mut buf[done..done + len],
+                    )?;
+                    done += len;
+                }
+                Ok(())
+            }
+        }
+    }
+
+    fn read_vec(&self, offset: u64, len: usize) -> Result<Vec<u8>> {
+        let mut buf = vec![0; len];
+        self.read(offset, &mut buf)?;
+        Ok(buf)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct FstFile {
+    offset:  u64,
+    size:    u64,
+    content: u16,
+}
+
+/// A file system table, listing the files in a partition or title and the
+/// content holding each of them.
+#[derive(Debug)]
+struct Fst {
+    /// The offset of each content, by its index
+    contents: Vec<u64>,
+    files:    HashMap<String, FstFile>,
+}
+
+impl Fst {
+    fn parse(data: &[u8]) -> Result<Self> {
+        if !data.starts_with(b"FST\0") {
+            return Err(ROMError::OtherMessage(
+                "Could not decrypt the disc file table, check the disc keys",
+            ));
+        }
+        let content_count = be_u32(data, 0x8)? as usize;
+        let contents = (0..content_count)
+            .map(|i| Ok(be_u32(data, 0x20 + i * 0x20)? as u64 * SECTOR_SIZE))
+            .collect::<Result<_>>()?;
+        let entries = 0x20 + content_count * 0x20;
+        let entry_count = be_u32(data, entries + 0x8)? as usize;
+        let names = entries + entry_count * 0x10;
+        // Each folder lists the index after its last entry
+        let mut dirs = vec![(entry_count, String::new())];
+        let mut files = HashMap::new();
+        for i in 1..entry_count {
+            while dirs.last().is_some_and(|(end, _)| i >= *end) {
+                dirs.pop();
+            }
+            let entry = entries + i * 0x10;
+            let kind_name = be_u32(data, entry)?;
+            let name = c_str(data, names + (kind_name & 0xFFFFFF) as usize)?;
+            let path = match dirs.last() {
+                Some((_, parent)) if !parent.is_empty() => format!("{parent}/{name}"),
+                _ => name,
+            };
+            let kind = (kind_name >> 24) as u8;
+            if kind & 0x1 != 0 {
+                dirs.push((be_u32(data, entry + 0x8)? as usize, path));
+            } else if kind & 0x80 == 0 {
+                let offset = be_u32(data, entry + 0x4)? as u64;
+                files.insert(path, FstFile {
+                    offset:  match be_u16(data, entry + 0xC)? & 0x4 {
+                        0 => offset << 5,
+                        _ => offset,
+                    },
+                    size:    be_u32(data, entry + 0x8)? as u64,
+                    content: be_u16(data, entry + 0xE)?,
+                });
+            }
+        }
+        Ok(Self { contents, files })
+    }
+}
+
+/// Where a partition's contents are counted from, and the size of its header.
+fn partition(image: &Image, offset: u64) -> Result<(u64, u64)> {
+    let header = image.read_vec(offset, 0x10)?;
+    Ok((
+        offset + DECRYPTED_AREA - 0x10000,
+        be_u32(&header, 0x4)? as u64,
+    ))
+}
+
+/// The base game title on a Wii U disc.
+struct Disc {
+    image: Image,
+    title_key: [u8; 16],
+    /// Where the title's contents start
+    data_offset: u64,
+    /// Whether each content is hashed, by its index
+    hashed: HashMap<u16, bool>,
+    fst: Fst,
+}
+
+impl std::fmt::Debug for Disc {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Disc")
+            .field("image", &self.image)
+            .field("files", &self.fst.files.len())
+            .finish()
+    }
+}
+
+impl Disc {
+    /// Open a disc image, with the `game.key` and `common.key` files dumped
+    /// with it by wudump.
+    fn open(path: &Path) -> Result<Self> {
+        let dir = path.parent().unwrap_or(Path::new(""));
+        let disc_key = read_key(&dir.join("game.key"), "disc key")?;
+        let common_key = read_key(&dir.join("common.key"), "Wii U common key")?;
+        let image = Image::open(path)?;
+
+        let mut toc = image.read_vec(DECRYPTED_AREA, SECTOR_SIZE as usize)?;
+        decrypt(&disc_key, &[0; 16], &mut toc);
+        if toc[..4] != PARTITION_TOC_SIGNATURE {
+            return Err(ROMError::OtherMessage(
+                "Could not decrypt the disc, check that game.key belongs to it",
+            ));
+        }
+        let partitions = (0..be_u32(&toc, 0x1C)? as usize)
+            .map(|i| -> Result<(String, u64)> {
+                let entry = 0x800 + i * 0x80;
+                Ok((
+                    c_str(&toc, entry)?,
+                    be_u32(&toc, entry + 0x20)? as u64 * SECTOR_SIZE,
+                ))
+            })
+            .collect::<Result<HashMap<_, _>>>()?;
+
+        // The system partition holds the tickets and TMDs for each title
+        let (si_offset, si_header_size) = partition(
+            &image,
+            *partitions
+                .get("SI")
+                .ok_or(ROMError::OtherMessage("Disc has no system partition"))?,
+        )?;
+        let mut fst = image.read_vec(si_offset + si_header_size, SECTOR_SIZE as usize)?;
+        decrypt(&disc_key, &[0; 16], &mut fst);
+        let si_fst = Fst::parse(&fst)?;
+        let read_si_file = |name: &str| -> Result<Vec<u8>> {
+            let file = si_fst
+                .files
+                .get(name)
+                .ok_or(ROMError::OtherMessage("Disc is missing title metadata"))?;
+            let start = si_offset
+                + si_fst
+                    .contents
+                    .get(file.content as usize)
+                    .ok_or(ROMError::OtherMessage("Disc file table is corrupted"))?;
+            let first = file.offset / BLOCK_SIZE;
+            let last = (file.offset + file.size).div_ceil(BLOCK_SIZE);
+            let mut data = image.read_vec(
+                start + first * BLOCK_SIZE,
+                ((last - first) * BLOCK_SIZE) as usize,
+            )?;
+            for (i, block) in data.chunks_mut(BLOCK_SIZE as usize).enumerate() {
+                let mut iv = [0; 16];
+                iv[8..].copy_from_slice(&(first + i as u64).to_be_bytes());
+                decrypt(&disc_key, &iv, block);
+            }
+            let skip = (file.offset - first * BLOCK_SIZE) as usize;
+            Ok(data[skip..skip + file.size as usize].to_vec())
+        };
+        let mut titles = si_fst
+            .files
+            .keys()
+            .filter_map(|name| name.strip_suffix("/title.tmd"))
+            .collect::<Vec<_>>();
+        titles.sort();
+        let (title, tmd) = titles
+            .into_iter()
+            .find_map(|title| {
+                let tmd = read_si_file(&format!("{title}/title.tmd")).ok()?;
+                (be_u64(&tmd, 0x18C).ok()? >> 32 == APPLICATION_TITLE).then_some((title, tmd))
+            })
+            .ok_or(ROMError::OtherMessage("Disc has no game on it"))?;
+        let title_id = be_u64(&tmd, 0x18C)?;
+        let ticket = read_si_file(&format!("{title}/title.tik"))?;
+        let mut title_key = key(&ticket, 0x1BF)?;
+        let mut iv = [0; 16];
+        iv[..8].copy_from_slice(&title_id.to_be_bytes());
+        decrypt(&common_key, &iv, &mut title_key);
+
+        let hashed = (0..be_u16(&tmd, 0x1DE)? as usize)
+            .map(|i| -> Result<(u16, (bool, u64))> {
+                let record = 0xB04 + i * 0x30;
+                Ok((
+                    be_u16(&tmd, record + 0x4)?,
+                    (
+                        be_u16(&tmd, record + 0x6)? & 0x2 != 0,
+                        be_u64(&tmd, record + 0x8)?,
+                    ),
+                ))
+            })
+            .collect::<Result<HashMap<_, _>>>()?;
+        let (game_offset, game_header_size) = partition(
+            &image,
+            *partitions
+                .get(&format!("GM{title_id:016X}"))
+                .ok_or(ROMError::OtherMessage("Disc has no game partition"))?,
+        )?;
+        let data_offset = game_offset + game_header_size;
+        // The first content is the title's own file table
+        let fst_size = hashed
+            .get(&0)
+            .map(|(_, size)| *size)
+            .ok_or(ROMError::OtherMessage("Game has no file table"))?;
+        let mut fst = image.read_vec(data_offset, fst_size.next_multiple_of(16) as usize)?;
+        decrypt(&title_key, &content_iv(0), &mut fst);
+        Ok(Self {
+            fst: Fst::parse(&fst)?,
+            image,
+            title_key,
+            data_offset,
+            hashed: hashed
+                .into_iter()
+                .map(|(index, (hashed, _))| (index, hashed))
+                .collect(),
+        })
+    }
+
+    fn file(&self, name: &Path) -> Option<&FstFile> {
+        let name = name.to_string_lossy().replace('\\', "/");
+        self.fst.files.get(format!("content/{name}").as_str())
+    }
+
+    fn read(&self, file: &FstFile) -> Result<Vec<u8>> {
+        let start = self.data_offset
+            + self
+                .fst
+                .contents
+                .get(file.content as usize)
+                .ok_or(ROMError::OtherMessage("Game file table is corrupted"))?;
+        let end = file.offset + file.size;
+        if self.hashed.get(&file.content).copied().unwrap_or(false) {
+            // Each block starts with hashes, some of which are the IV for the
+            // data after them
+            let mut out = Vec::with_capacity(file.size as usize);
+            let mut offset = file.offset;
+            while offset < end {
+                let block = offset / HASHED_DATA_SIZE;
+                let mut data = self
+                    .image
+                    .read_vec(start + block * BLOCK_SIZE, BLOCK_SIZE as usize)?;
+                let (hashes, rest) = data.split_at_mut(HASHES_SIZE);
+                decrypt(&self.title_key, &content_iv(file.content), hashes);
+                let iv = key(hashes, (block % 16) as usize * 0x14)?;
+                decrypt(&self.title_key, &iv, rest);
+                let within = (offset - block * HASHED_DATA_SIZE) as usize;
+                let len = (HASHED_DATA_SIZE - within as u64).min(end - offset) as usize;
+                out.extend_from_slice(&rest[within..within + len]);
+                offset += len as u64;
+            }
+            Ok(out)
+        } else {
+            let aligned = file.offset & !0xF;
+            let iv = match aligned {
+                0 => content_iv(file.content),
+                _ => key(&self.image.read_vec(start + aligned - 16, 16)?, 0)?,
+            };
+            let mut data = self.image.read_vec(
+                start + aligned,
+                (end - aligned).next_multiple_of(16) as usize,
+            )?;
+            decrypt(&self.title_key, &iv, &mut data);
+            let skip = (file.offset - aligned) as usize;
+            Ok(data[skip..skip + file.size as usize].to_vec())
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct WudInfo {
+    host_path:  PathBuf,
+    update_dir: PathBuf,
+    aoc_dir:    Option<PathBuf>,
+}
+
+impl From<Wud> for WudInfo {
+    fn from(wud: Wud) -> Self {
+        Self {
+            host_path:  wud.host_path,
+            update_dir: wud.update_dir,
+            aoc_dir:    wud.aoc_dir,
+        }
+    }
+}
+
+impl TryFrom<WudInfo> for Wud {
+    type Error = ROMError;
+
+    fn try_from(info: WudInfo) -> Result<Self> {
+        Self::new(info.host_path, info.update_dir, info.aoc_dir)
+    }
+}
+
+/// Game files read from a WUD or WUX disc image of the base game, with the
+/// update and DLC from unpacked folders.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(into = "WudInfo", try_from = "WudInfo")]
+pub(crate) struct Wud {
+    host_path: PathBuf,
+    update_dir: PathBuf,
+    aoc_dir: Option<PathBuf>,
+    disc: Arc<Disc>,
+    folders: Unpacked,
+}
+
+impl Wud {
+    pub(crate) fn new(
+        host_path: impl AsRef<Path>,
+        update_dir: impl AsRef<Path>,
+        aoc_dir: Option<impl AsRef<Path>>,
+    ) -> Result<Self> {
+        let host_path = host_path.as_ref();
+        log::info!("Opening disc image at {}", host_path.display());
+        let aoc_dir = aoc_dir
+            .as_ref()
+            .map(|p| p.as_ref())
+            .filter(|p| !p.as_os_str().is_empty());
+        let folders = Unpacked::new(None::<&Path>, Some(update_dir.as_ref()), aoc_dir, true)?;
+        Ok(Self {
+            host_path: host_path.to_path_buf(),
+            update_dir: update_dir.as_ref().to_path_buf(),
+            aoc_dir: aoc_dir.map(|p| p.to_path_buf()),
+            disc: Arc::new(Disc::open(host_path)?),
+            folders,
+        })
+    }
+}
+
+#[typetag::serde]
+impl super::ResourceLoader for Wud {
+    fn get_base_file_data(&self, name: &Path) -> Result<Vec<u8>> {
+        self.disc
+            .file(name)
+            .map(|file| self.disc.read(file))
+            .unwrap_or_else(|| {
+                Err(ROMError::FileNotFound(
+                    name.to_string_lossy().into(),
+                    self.host_path.clone(),
+                ))
+            })
+    }
+
+    fn get_update_file_data(&self, name: &Path) -> Result<Vec<u8>> {
+        self.folders.get_update_file_data(name)
+    }
+
+    fn get_aoc_file_data(&self, name: &Path) -> Result<Vec<u8>> {
+        self.folders.get_aoc_file_data(name)
+    }
+
+    fn file_exists(&self, name: &Path) -> bool {
+        self.folders.file_exists(name) || self.disc.file(name).is_some()
+    }
+
+    fn host_path(&self) -> &Path {
+        &self.host_path
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wux_sectors() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("game.wux");
+        let mut data = WUX_MAGIC.to_vec();
+        data.extend(0x10u32.to_le_bytes());
+        data.extend(0u32.to_le_bytes());
+        data.extend(0x30u64.to_le_bytes());
+        data.extend([0; 8]);
+        // Three sectors, the last two the same
+        for sector in [0u32, 1, 1] {
+            data.extend(sector.to_le_bytes());
+        }
+        data.resize(0x30, 0);
+        data.extend([b'a'; 0x10]);
+        data.extend([b'b'; 0x10]);
+        fs::write(&path, &data).unwrap();
+        let image = Image::open(&path).unwrap();
+        assert_eq!(image.read_vec(0x8, 0x10).unwrap(), b"aaaaaaaabbbbbbbb");
+        assert_eq!(image.read_vec(0x1C, 0x10).unwrap(), b"bbbbbbbbbbbbbbbb");
+        assert!(image.read_vec(0x28, 0x10).is_err());
+    }
+}
@@ -1,13 +1,26 @@
 use std::{
+    collections::BTreeMap,
     io::{stdin, stdout, Write},
     option::Option,
     path::{Path, PathBuf},
+    time::Duration,
 };
 
 use anyhow_ext::{Context, Result};
+use fs_err as fs;
+use serde::Deserialize;
+use serde_json::json;
 use smartstring::alias::String;
-use uk_manager::{core, mods::LookupMod, settings::Platform};
-use uk_mod::{unpack::ModReader, Manifest, Meta};
+use uk_content::prelude::Endian;
+use uk_manager::{
+    bundle, core, lock,
+    mods::{LookupMod, Mod},
+    settings::Platform,
+    watch,
+};
+use uk_mod::{
+    unpack::ModReader, Manifest, Meta, ModOption, ModOptionGroup, ModPlatform, OptionGroup,
+};
 
 use crate::gui::{package, tasks};
 
@@ -28,6 +41,11 @@ xflags::xflags! {
             required path: PathBuf
             /// The profile to install the mod in
             optional profile: String
+            /// YAML or JSON file selecting the mod's options, mapping group names to option
+            /// paths. Groups left out keep their default options.
+            optional -o, --options file: PathBuf
+            /// Print the result as JSON
+            optional --json
         }
         /// Package a mod
         cmd package {
@@ -37,6 +55,14 @@ xflags::xflags! {
             required output: PathBuf
             /// Path to the meta file for the mod
             required meta: PathBuf
+            /// Build a byte-identical archive every time from the same source, at the cost
+            /// of packaging resources one at a time
+            optional --reproducible
+            /// Reuse the resources of unchanged source files from an existing build at the
+            /// output path
+            optional --incremental
+            /// Print the result as JSON
+            optional --json
         }
         /// Uninstall a mod
         cmd uninstall {
@@ -44,15 +70,244 @@ xflags::xflags! {
             optional index: usize
             /// The profile to uninstall the mod from
             optional profile: String
+            /// The mod to uninstall, by name instead of index
+            optional -n, --name name: String
+            /// Print the result as JSON
+            optional --json
         }
         /// Refresh merge
-        cmd remerge {}
+        cmd remerge {
+            /// Report the changes a remerge would make without writing them
+            optional --dry-run
+            /// Print the result or dry run report as JSON
+            optional --json
+        }
         /// Deploy mods
-        cmd deploy {}
+        cmd deploy {
+            /// Only deploy to the named deploy target, instead of every target
+            optional -t, --target name: String
+            /// Print the result as JSON
+            optional --json
+        }
+        /// List the deploy targets for the current platform
+        cmd targets {
+            /// Print the targets as JSON
+            optional --json
+        }
+        /// Hash every merged file and its deployed copy to find deployed files which are
+        /// missing, changed or left over
+        cmd verify-deploy {
+            /// Only check the named deploy target, instead of every target
+            optional -t, --target name: String
+            /// Copy or link the missing and changed files and delete the extra ones
+            optional --repair
+            /// Print the differences as JSON
+            optional --json
+        }
+        /// Remove every deployed file from the output folder and put back the files which
+        /// were there before UKMM first deployed to it
+        cmd restore-output {
+            /// Only restore the output of the named deploy target, instead of every target
+            optional -t, --target name: String
+            /// Print the result as JSON
+            optional --json
+        }
+        /// List the mods in a profile in load order
+        cmd list {
+            /// The profile to list, instead of the current one
+            optional profile: String
+            /// Print the mods as JSON
+            optional --json
+        }
+        /// Enable mods in the current profile, by number (as shown by `list`) or name
+        cmd enable {
+            repeated mods: String
+            /// Print the result as JSON
+            optional --json
+        }
+        /// Disable mods in the current profile, by number (as shown by `list`) or name
+        cmd disable {
+            repeated mods: String
+            /// Print the result as JSON
+            optional --json
+        }
+        /// Move a mod to a new position in the load order of the current profile
+        cmd reorder {
+            /// The mod to move, by number (as shown by `list`) or name
+            required name: String
+            /// The new position, starting from 1
+            required position: usize
+            /// Print the result as JSON
+            optional --json
+        }
+        /// Show the options of a mod, or select them. Options are given by path, or as
+        /// GROUP=OPTION using the group name and option name or path. Any option not given
+        /// is deselected.
+        cmd options {
+            /// The mod to configure, by number (as shown by `list`) or name
+            required name: String
+            repeated select: String
+            /// Fix the selection (or the current options if none is given) to satisfy the
+            /// rules of the option groups, explaining each change
+            optional --fix
+            /// Print the options as JSON
+            optional --json
+        }
+        /// List profiles
+        cmd profiles {
+            /// Print the profiles as JSON
+            optional --json
+        }
+        /// Create a new, empty profile
+        cmd profile-create {
+            required name: String
+            /// Print the result as JSON
+            optional --json
+        }
+        /// Switch to another profile
+        cmd profile-switch {
+            required name: String
+            /// Print the result as JSON
+            optional --json
+        }
+        /// Copy a profile under a new name
+        cmd profile-copy {
+            required name: String
+            required new_name: String
+            /// Print the result as JSON
+            optional --json
+        }
+        /// Delete a profile other than the current one
+        cmd profile-delete {
+            required name: String
+            /// Print the result as JSON
+            optional --json
+        }
+        /// Export a profile, with its load order, mod states and options, to a single ZIP
+        cmd profile-export {
+            required name: String
+            /// Path of the bundle to write
+            required path: PathBuf
+            /// Store only the meta of each mod instead of the mod files
+            optional --meta-only
+            /// Print the result as JSON
+            optional --json
+        }
+        /// Import a profile exported with `profile-export` as a new profile
+        cmd profile-import {
+            /// Path of the bundle to import
+            required path: PathBuf
+            /// Name of the new profile, if not the exported name
+            optional name: String
+            /// Print the result as JSON
+            optional --json
+        }
+        /// Save a lock snapshot of a profile, recording the content hash, options and order
+        /// of every mod
+        cmd lock {
+            /// The profile to lock, if not the current one
+            optional profile: String
+            /// Print the snapshot as JSON
+            optional --json
+        }
+        /// Check a profile against its lock snapshot for mods which were changed, replaced,
+        /// added or removed
+        cmd verify-lock {
+            /// The profile to check, if not the current one
+            optional profile: String
+            /// Print the differences as JSON
+            optional --json
+        }
+        /// Show the changes made to a profile, newest first
+        cmd history {
+            /// The profile to show, if not the current one
+            optional profile: String
+            /// Print the history as JSON
+            optional --json
+        }
+        /// Undo changes to the current profile, from the newest back to a change in its
+        /// history
+        cmd rollback {
+            /// The number of the oldest change to undo, as shown by `history`. Defaults to
+            /// the newest change.
+            optional number: usize
+            /// Print the result as JSON
+            optional --json
+        }
+        /// Show the current settings
+        cmd settings {
+            /// Print the settings as JSON
+            optional --json
+        }
+        /// Check that every enabled mod's dependencies are installed, enabled and loaded first
+        cmd deps {
+            /// Print the issues as JSON
+            optional --json
+        }
+        /// List resources edited by more than one enabled mod
+        cmd conflicts {
+            /// Print the report as JSON
+            optional --json
+        }
+        /// List the resources in a mod file, or show the changes a mod makes to a resource
+        /// as YAML (or JSON with `--json`). Binary files are shown as their size and hash.
+        cmd dump {
+            /// Path to the mod
+            required path: PathBuf
+            /// The resource to show, by canonical path as listed. Versions from every option
+            /// of the mod are shown.
+            optional resource: String
+            /// Print the list or resource as JSON
+            optional --json
+        }
+        /// Check a mod for a bad meta file or manifests, missing or unreadable resources,
+        /// and unknown options, reporting every problem found
+        cmd verify-mod {
+            /// Path to a mod file or folder, or an installed mod by number (as shown by
+            /// `list`) or name
+            required name: String
+            /// Print the report as JSON
+            optional --json
+        }
+        /// Re-save an installed mod in place with its resources migrated to the current mod
+        /// format. Mods built with older versions of UKMM are otherwise migrated each time
+        /// they are merged.
+        cmd migrate-mod {
+            /// The mod by number (as shown by `list`) or name
+            required name: String
+            /// Print the result as JSON
+            optional --json
+        }
+        /// Convert a Wii U or Switch mod for the other console, using that console's game
+        /// dump. Binary files which depend on the byte order, such as textures, models and
+        /// Havok data, are left out and listed.
+        cmd convert {
+            /// Path to the mod
+            required path: PathBuf
+            /// Path to the converted mod archive
+            required output: PathBuf
+            /// Print the report as JSON
+            optional --json
+        }
+        /// Watch a mod's source folder, repackaging the mod and merging the files it changed
+        /// each time the folder changes, until stopped with Ctrl+C. Use `--deploy` to deploy
+        /// after each update as well.
+        cmd watch {
+            /// The mod by number (as shown by `list`) or name
+            required name: String
+            /// The mod's source folder, if not the one it was last updated from
+            optional source: PathBuf
+            /// Milliseconds to wait after a change for the folder to settle
+            optional --debounce ms: u64
+            /// Print the result of each update as JSON
+            optional --json
+        }
         /// Change current mode (Switch or Wii U)
         cmd mode {
             /// Mode to activate (Switch or Wii U)
             required platform: Platform
+            /// Print the result as JSON
+            optional --json
         }
     }
 }
@@ -77,6 +332,33 @@ pub enum UkmmCmd {
     Package(Package),
     Remerge(Remerge),
     Deploy(Deploy),
+    Targets(Targets),
+    VerifyDeploy(VerifyDeploy),
+    RestoreOutput(RestoreOutput),
+    List(List),
+    Enable(Enable),
+    Disable(Disable),
+    Reorder(Reorder),
+    Options(Options),
+    Profiles(Profiles),
+    ProfileCreate(ProfileCreate),
+    ProfileSwitch(ProfileSwitch),
+    ProfileCopy(ProfileCopy),
+    ProfileDelete(ProfileDelete),
+    ProfileExport(ProfileExport),
+    ProfileImport(ProfileImport),
+    Lock(Lock),
+    VerifyLock(VerifyLock),
+    History(History),
+    Rollback(Rollback),
+    Settings(Settings),
+    Deps(Deps),
+    Conflicts(Conflicts),
+    Dump(Dump),
+    VerifyMod(VerifyMod),
+    MigrateMod(MigrateMod),
+    Convert(Convert),
+    Watch(Watch),
     Mode(Mode),
 }
 
@@ -84,6 +366,8 @@ pub enum UkmmCmd {
 pub struct Install {
     pub path:    PathBuf,
     pub profile: Option<String>,
+    pub options: Option<PathBuf>,
+    pub json:    bool,
 }
 
 #[derive(Debug)]
@@ -91,23 +375,206 @@ pub struct Package {
     pub path:   PathBuf,
     pub output: PathBuf,
     pub meta:   PathBuf,
+
+    pub reproducible: bool,
+    pub incremental:  bool,
+
+    pub json: bool,
 }
 
 #[derive(Debug)]
 pub struct Uninstall {
     pub index:   Option<usize>,
     pub profile: Option<String>,
+    pub name:    Option<String>,
+    pub json:    bool,
+}
+
+#[derive(Debug)]
+pub struct Remerge {
+    pub dry_run: bool,
+    pub json:    bool,
+}
+
+#[derive(Debug)]
+pub struct Deploy {
+    pub target: Option<String>,
+    pub json:   bool,
+}
+
+#[derive(Debug)]
+pub struct Targets {
+    pub json: bool,
+}
+
+#[derive(Debug)]
+pub struct VerifyDeploy {
+    pub target: Option<String>,
+    pub repair: bool,
+    pub json:   bool,
+}
+
+#[derive(Debug)]
+pub struct RestoreOutput {
+    pub target: Option<String>,
+    pub json:   bool,
+}
+
+#[derive(Debug)]
+pub struct List {
+    pub profile: Option<String>,
+    pub json:    bool,
+}
+
+#[derive(Debug)]
+pub struct Enable {
+    pub mods: Vec<String>,
+    pub json: bool,
+}
+
+#[derive(Debug)]
+pub struct Disable {
+    pub mods: Vec<String>,
+    pub json: bool,
+}
+
+#[derive(Debug)]
+pub struct Reorder {
+    pub name:     String,
+    pub position: usize,
+    pub json:     bool,
+}
+
+#[derive(Debug)]
+pub struct Options {
+    pub name:   String,
+    pub select: Vec<String>,
+    pub fix:    bool,
+    pub json:   bool,
+}
+
+#[derive(Debug)]
+pub struct Profiles {
+    pub json: bool,
+}
+
+#[derive(Debug)]
+pub struct ProfileCreate {
+    pub name: String,
+    pub json: bool,
+}
+
+#[derive(Debug)]
+pub struct ProfileSwitch {
+    pub name: String,
+    pub json: bool,
+}
+
+#[derive(Debug)]
+pub struct ProfileCopy {
+    pub name:     String,
+    pub new_name: String,
+    pub json:     bool,
+}
+
+#[derive(Debug)]
+pub struct ProfileDelete {
+    pub name: String,
+    pub json: bool,
+}
+
+#[derive(Debug)]
+pub struct ProfileExport {
+    pub name: String,
+    pub path: PathBuf,
+    pub meta_only: bool,
+    pub json: bool,
+}
+
+#[derive(Debug)]
+pub struct ProfileImport {
+    pub path: PathBuf,
+    pub name: Option<String>,
+    pub json: bool,
+}
+
+#[derive(Debug)]
+pub struct Lock {
+    pub profile: Option<String>,
+    pub json:    bool,
+}
+
+#[derive(Debug)]
+pub struct VerifyLock {
+    pub profile: Option<String>,
+    pub json:    bool,
+}
+
+#[derive(Debug)]
+pub struct History {
+    pub profile: Option<String>,
+    pub json:    bool,
+}
+
+#[derive(Debug)]
+pub struct Rollback {
+    pub number: Option<usize>,
+    pub json:   bool,
+}
+
+#[derive(Debug)]
+pub struct Settings {
+    pub json: bool,
+}
+
+#[derive(Debug)]
+pub struct Deps {
+    pub json: bool,
+}
+
+#[derive(Debug)]
+pub struct Conflicts {
+    pub json: bool,
+}
+
+#[derive(Debug)]
+pub struct Dump {
+    pub path:     PathBuf,
+    pub resource: Option<String>,
+    pub json:     bool,
+}
+
+#[derive(Debug)]
+pub struct VerifyMod {
+    pub name: String,
+    pub json: bool,
+}
+
+#[derive(Debug)]
+pub struct MigrateMod {
+    pub name: String,
+    pub json: bool,
 }
 
 #[derive(Debug)]
-pub struct Remerge;
+pub struct Convert {
+    pub path:   PathBuf,
+    pub output: PathBuf,
+    pub json:   bool,
+}
 
 #[derive(Debug)]
-pub struct Deploy;
+pub struct Watch {
+    pub name:     String,
+    pub source:   Option<PathBuf>,
+    pub debounce: Option<u64>,
+    pub json:     bool,
+}
 
 #[derive(Debug)]
 pub struct Mode {
     pub platform: Platform,
+    pub json:     bool,
 }
 
 impl Ukmm {
@@ -128,6 +595,46 @@ impl Ukmm {
 }
 // generated end
 
+impl UkmmCmd {
+    fn json(&self) -> bool {
+        match self {
+            UkmmCmd::Install(Install { json, .. })
+            | UkmmCmd::Uninstall(Uninstall { json, .. })
+            | UkmmCmd::Package(Package { json, .. })
+            | UkmmCmd::Remerge(Remerge { json, .. })
+            | UkmmCmd::Deploy(Deploy { json, .. })
+            | UkmmCmd::Targets(Targets { json })
+            | UkmmCmd::VerifyDeploy(VerifyDeploy { json, .. })
+            | UkmmCmd::RestoreOutput(RestoreOutput { json, .. })
+            | UkmmCmd::List(List { json, .. })
+            | UkmmCmd::Enable(Enable { json, .. })
+            | UkmmCmd::Disable(Disable { json, .. })
+            | UkmmCmd::Reorder(Reorder { json, .. })
+            | UkmmCmd::Options(Options { json, .. })
+            | UkmmCmd::Profiles(Profiles { json })
+            | UkmmCmd::ProfileCreate(ProfileCreate { json, .. })
+            | UkmmCmd::ProfileSwitch(ProfileSwitch { json, .. })
+            | UkmmCmd::ProfileCopy(ProfileCopy { json, .. })
+            | UkmmCmd::ProfileDelete(ProfileDelete { json, .. })
+            | UkmmCmd::ProfileExport(ProfileExport { json, .. })
+            | UkmmCmd::ProfileImport(ProfileImport { json, .. })
+            | UkmmCmd::Lock(Lock { json, .. })
+            | UkmmCmd::VerifyLock(VerifyLock { json, .. })
+            | UkmmCmd::History(History { json, .. })
+            | UkmmCmd::Rollback(Rollback { json, .. })
+            | UkmmCmd::Settings(Settings { json })
+            | UkmmCmd::Deps(Deps { json })
+            | UkmmCmd::Conflicts(Conflicts { json })
+            | UkmmCmd::Dump(Dump { json, .. })
+            | UkmmCmd::VerifyMod(VerifyMod { json, .. })
+            | UkmmCmd::MigrateMod(MigrateMod { json, .. })
+            | UkmmCmd::Convert(Convert { json, .. })
+            | UkmmCmd::Watch(Watch { json, .. })
+            | UkmmCmd::Mode(Mode { json, .. }) => *json,
+        }
+    }
+}
+
 macro_rules! input {
     () => {{
         stdout().flush()?;
@@ -137,21 +644,142 @@ macro_rules! input {
     }};
 }
 
+/// Print progress messages to stdout, or to stderr when printing JSON, so
+/// that stdout stays machine-readable.
+macro_rules! status {
+    ($self:ident, $($arg:tt)*) => {
+        if $self.json {
+            eprintln!($($arg)*);
+        } else {
+            println!($($arg)*);
+        }
+    };
+}
+
+fn print_json(value: &impl serde::Serialize) -> Result<()> {
+    println!("{}", serde_json::to_string_pretty(value)?);
+    Ok(())
+}
+
+fn mod_json(index: usize, mod_: &Mod) -> serde_json::Value {
+    json!({
+        "index": index + 1,
+        "name": mod_.meta.name,
+        "version": mod_.meta.version,
+        "author": mod_.meta.author,
+        "enabled": mod_.enabled,
+        "options": mod_.enabled_options.iter().map(|o| &o.path).collect::<Vec<_>>(),
+        "path": mod_.path,
+        "content_hash": mod_.content_hash,
+    })
+}
+
+/// Resolve option selections, each given as an option path or as a group
+/// name and an option name or path separated by `=`.
+fn select_options(meta: &Meta, selections: &[String]) -> Result<Vec<ModOption>> {
+    let mut selected: Vec<ModOption> = vec![];
+    for selection in selections {
+        let (group, option) = match selection.split_once('=') {
+            Some((group, option)) => (Some(group.trim()), option.trim()),
+            None => (None, selection.trim()),
+        };
+        let option = meta
+            .options
+            .iter()
+            .filter(|g| group.map(|name| g.name() == name).unwrap_or(true))
+            .flat_map(|g| g.options().iter())
+            .find(|o| o.path == Path::new(option) || (group.is_some() && o.name.as_str() == option))
+            .with_context(|| format!("{} has no option matching {selection}", meta.name))?;
+        if !selected.contains(option) {
+            selected.push(option.clone());
+        }
+    }
+    Ok(selected)
+}
+
+/// The options chosen from one group in an option selection file.
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum GroupSelection {
+    One(PathBuf),
+    Many(Vec<PathBuf>),
+}
+
+/// Read an option selection file in YAML or JSON, mapping group names to the
+/// path of the chosen option or a list of them. Groups which are not listed
+/// keep their default options.
+fn read_option_file(meta: &Meta, path: &Path) -> Result<Vec<ModOption>> {
+    let selection: BTreeMap<std::string::String, GroupSelection> =
+        serde_yaml::from_str(&fs::read_to_string(path)?)
+            .with_context(|| format!("Failed to parse option selection file {}", path.display()))?;
+    if let Some(name) = selection
+        .keys()
+        .find(|name| !meta.options.iter().any(|g| g.name() == name.as_str()))
+    {
+        anyhow_ext::bail!(
+            "{} has no option group named \"{name}\". Its groups are: {}",
+            meta.name,
+            meta.options
+                .iter()
+                .map(|g| format!("\"{}\"", g.name()))
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
+    }
+    let mut selected: Vec<ModOption> = vec![];
+    for group in meta.options.iter() {
+        let paths = match selection.get(group.name()) {
+            None => {
+                selected.extend(group.defaults().cloned());
+                continue;
+            }
+            Some(GroupSelection::One(path)) => std::slice::from_ref(path),
+            Some(GroupSelection::Many(paths)) => paths.as_slice(),
+        };
+        for path in paths {
+            let option = group
+                .options()
+                .iter()
+                .find(|o| o.path == *path)
+                .with_context(|| {
+                    format!(
+                        "Option group \"{}\" has no option {}. Its options are: {}",
+                        group.name(),
+                        path.display(),
+                        group
+                            .options()
+                            .iter()
+                            .map(|o| o.path.display().to_string())
+                            .collect::<Vec<_>>()
+                            .join(", ")
+                    )
+                })?;
+            if !selected.contains(option) {
+                selected.push(option.clone());
+            }
+        }
+    }
+    uk_manager::options::validate_options(meta, &selected)?;
+    Ok(selected)
+}
+
 #[derive(Debug)]
 pub struct Runner {
     core: core::Manager,
     cli:  Ukmm,
+    json: bool,
 }
 
 impl Runner {
     pub fn new(cli: Ukmm) -> Self {
         Self {
             core: core::Manager::init().unwrap(),
+            json: cli.subcommand.json(),
             cli,
         }
     }
 
-    fn check_mod(&self, path: &Path) -> Result<Option<PathBuf>> {
+    fn check_mod(&self, path: &Path) -> Result<Option<(PathBuf, Meta)>> {
         let (mod_, path) = match ModReader::open(path, vec![]) {
             Ok(mod_) => (mod_, path.to_path_buf()),
             Err(e) => {
@@ -175,24 +803,121 @@ impl Runner {
                 }
             }
         };
-        if !mod_.meta.options.is_empty() {
-            anyhow_ext::bail!(
-                "This mod contains configuration options and should be installed via the GUI."
-            );
+        status!(self, "Installing {}...", mod_.meta.name);
+        Ok(Some((path, mod_.meta)))
+    }
+
+    /// Find a mod in the current profile by its number in the `list` output
+    /// or by its name.
+    fn find_mod(&self, query: &str) -> Result<Mod> {
+        self.find_mod_in(query, None)
+    }
+
+    /// Look up a mod in a profile, or the current one, as for
+    /// [`find_mod`](Self::find_mod).
+    fn find_mod_in(&self, query: &str, profile: Option<&String>) -> Result<Mod> {
+        let mod_manager = self.core.mod_manager();
+        let mods =
+            uk_manager::mods::Profile::iter(mod_manager.get_profile(profile)).collect::<Vec<_>>();
+        match query.parse::<usize>() {
+            Ok(number) => {
+                number
+                    .checked_sub(1)
+                    .and_then(|i| mods.get(i))
+                    .cloned()
+                    .with_context(|| format!("Mod {number} does not exist"))
+            }
+            Err(_) => {
+                mods.into_iter()
+                    .find(|m| m.meta.name.eq_ignore_ascii_case(query))
+                    .with_context(|| format!("Mod {query} is not installed"))
+            }
+        }
+    }
+
+    /// The named deploy target, or every target if none is given.
+    fn targets(&self, target: Option<&String>) -> Result<Vec<String>> {
+        let targets = self.core.deploy_manager().targets();
+        match target {
+            Some(target) if !targets.contains(target) => {
+                anyhow_ext::bail!("No deploy target named {target} for current platform")
+            }
+            Some(target) => Ok(vec![target.clone()]),
+            None if targets.is_empty() => {
+                anyhow_ext::bail!("No deployment config for current platform")
+            }
+            None => Ok(targets),
         }
-        println!("Installing {}...", mod_.meta.name);
-        Ok(Some(path))
     }
 
-    fn deploy(&self) -> Result<()> {
+    fn deploy(&self, target: Option<&String>) -> Result<bool> {
+        let targets = self.targets(target)?;
         let deployer = self.core.deploy_manager();
-        if deployer.pending() {
-            println!("Deploying changes...");
-            deployer.deploy()?;
-            println!("Deployment complete");
+        let mut deployed = false;
+        for target in targets {
+            if deployer.target_pending(&target) {
+                status!(self, "Deploying changes to {target}...");
+                deployer.deploy_target(&target)?;
+                deployed = true;
+            } else {
+                status!(self, "No changes pending deployment to {target}");
+            }
+        }
+        if deployed {
+            status!(self, "Deployment complete");
+        }
+        Ok(deployed)
+    }
+
+    /// Deploy if requested by the `--deploy` flag.
+    fn finish(&self) -> Result<bool> {
+        let deployed = if self.cli.deploy {
+            self.deploy(None)?
         } else {
-            println!("No changes pending deployment");
+            false
         };
+        status!(self, "Done!");
+        Ok(deployed)
+    }
+
+    fn set_enabled(&self, names: &[String], enabled: bool) -> Result<()> {
+        let mod_manager = self.core.mod_manager();
+        let mut manifest = Manifest::default();
+        let mut changed = vec![];
+        let action = format!(
+            "{} {}",
+            if enabled { "Enable" } else { "Disable" },
+            names.join(", ")
+        );
+        mod_manager.batch(&action, || -> Result<()> {
+            for name in names {
+                let mod_ = self.find_mod(name)?;
+                if mod_.enabled == enabled {
+                    continue;
+                }
+                status!(
+                    self,
+                    "{} {}...",
+                    if enabled { "Enabling" } else { "Disabling" },
+                    mod_.meta.name
+                );
+                manifest.extend(&mod_manager.set_enabled(mod_.as_map_id(), enabled, None)?);
+                changed.push(mod_.meta.name);
+            }
+            Ok(())
+        })?;
+        mod_manager.save()?;
+        if !changed.is_empty() {
+            status!(self, "Applying changes to merge...");
+            self.core.deploy_manager().apply(Some(manifest))?;
+        }
+        let deployed = self.finish()?;
+        if self.json {
+            print_json(&json!({
+                (if enabled { "enabled" } else { "disabled" }): changed,
+                "deployed": deployed,
+            }))?;
+        }
         Ok(())
     }
 
@@ -202,92 +927,857 @@ impl Runner {
             log::set_max_level(log::LevelFilter::Debug);
         }
         match &self.cli.subcommand {
-            UkmmCmd::Mode(Mode { platform }) => {
+            UkmmCmd::Mode(Mode { platform, json }) => {
                 self.core
                     .settings_mut()
                     .apply(|s| s.current_mode = *platform)?;
                 self.core.reload()?;
-                println!("Mode changed to {:?}", platform);
-                if self.cli.deploy {
-                    self.deploy()?;
+                status!(self, "Mode changed to {:?}", platform);
+                let deployed = self.finish()?;
+                if *json {
+                    print_json(&json!({ "mode": platform, "deployed": deployed }))?;
                 }
-                println!("Done!");
             }
-            UkmmCmd::Install(Install { path, profile }) => {
-                if let Some(path) = self.check_mod(path)? {
+            UkmmCmd::Install(Install {
+                path,
+                profile,
+                options,
+                json,
+            }) => {
+                if let Some((path, meta)) = self.check_mod(path)? {
                     let mods = self.core.mod_manager();
-                    let mod_ = mods.add(&path, profile.as_ref())?;
-                    mods.set_enabled(mod_.as_map_id(), true, profile.as_ref())?;
+                    let current =
+                        profile.is_none() || profile.as_deref() == Some(mods.current_profile());
+                    let selected = match options {
+                        Some(_) if !current => {
+                            anyhow_ext::bail!(
+                                "Options can only be selected when installing to the current \
+                                 profile"
+                            )
+                        }
+                        Some(file) => Some(read_option_file(&meta, file)?),
+                        None => None,
+                    };
+                    let defaults = selected.is_none();
+                    let selected = selected.unwrap_or_else(|| {
+                        meta.options
+                            .iter()
+                            .flat_map(|group| group.defaults())
+                            .cloned()
+                            .collect()
+                    });
+                    // Check the options before storing the mod, so an invalid
+                    // selection does not leave it half installed
+                    if current && !meta.options.is_empty() {
+                        uk_manager::options::validate_options(&meta, &selected)?;
+                    }
+                    let mut mod_ = mods.add(&path, profile.as_ref())?;
+                    let mut manifest =
+                        mods.set_enabled(mod_.as_map_id(), true, profile.as_ref())?;
+                    if !mod_.meta.options.is_empty() {
+                        if current {
+                            mod_.enabled_options = selected;
+                            if defaults {
+                                status!(
+                                    self,
+                                    "Enabled default options. Use the `options` command to change \
+                                     them."
+                                );
+                            }
+                            manifest = mods.set_enabled_options(
+                                mod_.as_map_id(),
+                                mod_.enabled_options.clone(),
+                            )?;
+                        } else {
+                            status!(
+                                self,
+                                "This mod has options, which can be selected with the `options` \
+                                 command after switching to the profile."
+                            );
+                        }
+                    }
                     mods.save()?;
-                    println!("Applying mod to load order...");
+                    status!(self, "Applying mod to load order...");
                     let deployer = self.core.deploy_manager();
-                    deployer.apply(Some(mod_.manifest()?.as_ref().clone()))?;
-                    if self.cli.deploy {
-                        self.deploy()?;
+                    deployer.apply(Some(manifest.as_ref().clone()))?;
+                    drop(deployer);
+                    let deployed = self.finish()?;
+                    if *json {
+                        print_json(&json!({
+                            "installed": mod_.meta.name,
+                            "version": mod_.meta.version,
+                            "options": mod_.enabled_options.iter().map(|o| &o.path).collect::<Vec<_>>(),
+                            "deployed": deployed,
+                        }))?;
                     }
-                    println!("Done!");
                 }
             }
             UkmmCmd::Package(pkg) => {
-                println!("Packaging mod...");
+                status!(self, "Packaging mod...");
                 let builder = package::ModPackerBuilder {
                     source: pkg.path.clone(),
                     dest:   pkg.output.clone(),
                     meta:   Meta::parse(&pkg.meta)?,
+
+                    reproducible: pkg.reproducible,
+                    incremental:  pkg.incremental,
                 };
                 tasks::package_mod(&self.core, builder)?;
-                println!("Done!");
+                status!(self, "Done!");
+                if pkg.json {
+                    print_json(&json!({ "output": pkg.output }))?;
+                }
             }
-            UkmmCmd::Remerge(_) => {
-                println!("Remerging...");
-                tasks::apply_changes(&self.core, vec![], None)?;
-                println!("Done!");
+            UkmmCmd::Remerge(Remerge { dry_run, json }) => {
+                if *dry_run {
+                    let report = self.core.deploy_manager().apply_dry_run(None)?;
+                    if *json {
+                        print_json(&report)?;
+                    } else {
+                        print!("{report}");
+                    }
+                } else {
+                    status!(self, "Remerging...");
+                    tasks::apply_changes(&self.core, vec![], None)?;
+                    let deployed = self.finish()?;
+                    if *json {
+                        print_json(&json!({ "remerged": true, "deployed": deployed }))?;
+                    }
+                }
             }
-            UkmmCmd::Uninstall(Uninstall { index, profile }) => {
+            UkmmCmd::Uninstall(Uninstall {
+                index,
+                profile,
+                name,
+                json,
+            }) => {
+                if let Some(profile) = profile {
+                    if !self
+                        .core
+                        .mod_manager()
+                        .profile_names()
+                        .any(|p| p == *profile)
+                    {
+                        anyhow_ext::bail!("Profile {profile} does not exist");
+                    }
+                }
+                let profile_mods = || {
+                    let mod_manager = self.core.mod_manager();
+                    uk_manager::mods::Profile::iter(mod_manager.get_profile(profile.as_ref()))
+                        .collect::<Vec<_>>()
+                };
+                let mods = match (index, name) {
+                    (Some(_), Some(_)) => {
+                        anyhow_ext::bail!("Give either the index or the name of a mod, not both")
+                    }
+                    (Some(index), None) => {
+                        vec![
+                            profile_mods()
+                                .get(*index)
+                                .cloned()
+                                .with_context(|| format!("Mod {index} does not exist"))?,
+                        ]
+                    }
+                    (None, Some(name)) => {
+                        vec![
+                            profile_mods()
+                                .into_iter()
+                                .find(|m| m.meta.name.eq_ignore_ascii_case(name))
+                                .with_context(|| format!("Mod {name} is not installed"))?,
+                        ]
+                    }
+                    (None, None) if *json => {
+                        anyhow_ext::bail!("A mod is required when printing JSON")
+                    }
+                    (None, None) => {
+                        println!("Installed mods:");
+                        for (i, mod_) in profile_mods().iter().enumerate() {
+                            println!(
+                                "{}. {} (v{}) by {}",
+                                i + 1,
+                                &mod_.meta.name,
+                                &mod_.meta.version,
+                                &mod_.meta.author
+                            );
+                        }
+                        print!("Enter mod(s) to uninstall, separated by commas: ");
+                        // Look up every mod first, so that removing one does
+                        // not renumber the rest
+                        input!()
+                            .split(',')
+                            .map(str::trim)
+                            .filter(|id| !id.is_empty())
+                            .map(|query| self.find_mod_in(query, profile.as_ref()))
+                            .collect::<Result<Vec<_>>>()?
+                    }
+                };
                 let mut manifests = Manifest::default();
                 let mod_manager = self.core.mod_manager();
-                let mods = mod_manager.mods().collect::<Vec<_>>();
-
-                if let Some(index_value) = index {
-                    let mod_ = mods
-                        .get(*index_value)
-                        .with_context(|| format!("Mod {} does not exist", index_value))?;
-                    println!("Removing mod {}...", &mod_.meta.name);
-                    mod_manager.del(mod_, profile.as_ref())?;
+                let mut removed = vec![];
+                for mod_ in mods {
+                    status!(self, "Removing mod {}...", &mod_.meta.name);
+                    mod_manager.del(&mod_, profile.as_ref())?;
                     mod_manager.save()?;
                     manifests.extend(mod_.manifest()?.as_ref());
+                    removed.push(mod_.meta.name.clone());
+                }
+
+                status!(self, "Applying changes to merge...");
+                self.core.deploy_manager().apply(Some(manifests))?;
+                let deployed = self.finish()?;
+                if *json {
+                    print_json(&json!({ "uninstalled": removed, "deployed": deployed }))?;
+                }
+            }
+            UkmmCmd::Deploy(Deploy { target, json }) => {
+                let deployed = self.deploy(target.as_ref())?;
+                if *json {
+                    print_json(&json!({ "deployed": deployed }))?;
+                }
+            }
+            UkmmCmd::Targets(Targets { json }) => {
+                let settings = self.core.settings();
+                let deployer = self.core.deploy_manager();
+                let targets = settings
+                    .platform_config()
+                    .into_iter()
+                    .flat_map(|c| c.targets())
+                    .collect::<Vec<_>>();
+                if *json {
+                    print_json(
+                        &targets
+                            .into_iter()
+                            .map(|(name, config)| {
+                                json!({
+                                    "name": name,
+                                    "output": config.output,
+                                    "method": config.method,
+                                    "layout": config.layout,
+                                    "auto": config.auto,
+                                    "cemu_rules": config.cemu_rules,
+                                    "pending": deployer.target_pending(name),
+                                })
+                            })
+                            .collect::<Vec<_>>(),
+                    )?;
+                } else if targets.is_empty() {
+                    println!("No deployment config for current platform");
+                } else {
+                    for (name, config) in targets {
+                        println!(
+                            "{} {name}: {} ({:?}, {}{})",
+                            if deployer.target_pending(name) {
+                                "*"
+                            } else {
+                                " "
+                            },
+                            config.output.display(),
+                            config.method,
+                            config.layout.name(),
+                            if config.auto { ", auto" } else { "" }
+                        );
+                    }
+                }
+            }
+            UkmmCmd::VerifyDeploy(VerifyDeploy {
+                target,
+                repair,
+                json,
+            }) => {
+                let targets = self.targets(target.as_ref())?;
+                let mut drifts = BTreeMap::new();
+                for target in targets {
+                    status!(self, "Hashing merged and deployed files for {target}...");
+                    let drift = self
+                        .core
+                        .deploy_manager()
+                        .verify_deployment(&target, *repair)?;
+                    if !*json {
+                        if drift.is_empty() {
+                            println!("{target}: deployed files match the merged files");
+                        } else {
+                            print!("{target}:\n{drift}");
+                        }
+                    }
+                    drifts.insert(target, drift);
+                }
+                if *json {
+                    print_json(&drifts)?;
+                }
+                if drifts.values().any(|d| !d.is_empty() && !d.repaired) {
+                    anyhow_ext::bail!("Deployed files differ from the merged files");
+                }
+            }
+            UkmmCmd::RestoreOutput(RestoreOutput { target, json }) => {
+                let mut restored = vec![];
+                for target in self.targets(target.as_ref())? {
+                    if self.core.deploy_manager().restore_output(&target)? {
+                        status!(self, "Restored the original output folder for {target}");
+                        restored.push(target);
+                    } else {
+                        status!(
+                            self,
+                            "Nothing has been deployed to the output folder for {target}"
+                        );
+                    }
+                }
+                if *json {
+                    print_json(&json!({ "restored": restored }))?;
+                }
+            }
+            UkmmCmd::List(List { profile, json }) => {
+                let mod_manager = self.core.mod_manager();
+                if let Some(profile) = profile {
+                    if !mod_manager.profile_names().any(|p| p == *profile) {
+                        anyhow_ext::bail!("Profile {profile} does not exist");
+                    }
+                }
+                let mods =
+                    uk_manager::mods::Profile::iter(mod_manager.get_profile(profile.as_ref()))
+                        .collect::<Vec<_>>();
+                if *json {
+                    print_json(
+                        &mods
+                            .iter()
+                            .enumerate()
+                            .map(|(i, m)| mod_json(i, m))
+                            .collect::<Vec<_>>(),
+                    )?;
+                } else if mods.is_empty() {
+                    println!("No mods installed");
                 } else {
-                    println!("Installed mods:");
                     for (i, mod_) in mods.iter().enumerate() {
                         println!(
-                            "{}. {} (v{}) by {}",
+                            "{:>3}. [{}] {} (v{}) by {}",
                             i + 1,
+                            if mod_.enabled { "x" } else { " " },
                             &mod_.meta.name,
                             &mod_.meta.version,
                             &mod_.meta.author
                         );
+                        for option in mod_.enabled_options.iter() {
+                            println!("       + {}", option.name);
+                        }
+                    }
+                }
+            }
+            UkmmCmd::Enable(Enable { mods, .. }) => self.set_enabled(mods, true)?,
+            UkmmCmd::Disable(Disable { mods, .. }) => self.set_enabled(mods, false)?,
+            UkmmCmd::Reorder(Reorder {
+                name,
+                position,
+                json,
+            }) => {
+                let mod_ = self.find_mod(name)?;
+                let mod_manager = self.core.mod_manager();
+                let mut order = mod_manager.all_mods().map(|m| m.hash()).collect::<Vec<_>>();
+                order.retain(|h| *h != mod_.hash());
+                order.insert(position.saturating_sub(1).min(order.len()), mod_.hash());
+                mod_manager.set_order(order.clone());
+                mod_manager.save()?;
+                let final_order = mod_manager.all_mods().map(|m| m.hash()).collect::<Vec<_>>();
+                let new_position = final_order.iter().position(|h| *h == mod_.hash());
+                if mod_.enabled {
+                    status!(self, "Applying changes to merge...");
+                    // Only files edited by the moved mod change, unless other
+                    // mods had to be moved after their masters as well.
+                    let dirty = (final_order == order)
+                        .then(|| mod_.manifest())
+                        .transpose()?
+                        .map(|m| m.as_ref().clone());
+                    self.core.deploy_manager().apply(dirty)?;
+                }
+                let deployed = self.finish()?;
+                if *json {
+                    print_json(&json!({
+                        "mod": mod_.meta.name,
+                        "position": new_position.map(|p| p + 1),
+                        "deployed": deployed,
+                    }))?;
+                } else if let Some(pos) = new_position {
+                    println!("{} is now at position {}", mod_.meta.name, pos + 1);
+                }
+            }
+            UkmmCmd::Options(Options {
+                name,
+                select,
+                fix,
+                json,
+            }) => {
+                let mod_ = self.find_mod(name)?;
+                if select.is_empty() && !fix {
+                    if *json {
+                        print_json(&json!({
+                            "mod": mod_.meta.name,
+                            "groups": mod_.meta.options,
+                            "enabled": mod_.enabled_options.iter().map(|o| &o.path).collect::<Vec<_>>(),
+                        }))?;
+                    } else if mod_.meta.options.is_empty() {
+                        println!("{} has no options", mod_.meta.name);
+                    } else {
+                        for group in mod_.meta.options.iter() {
+                            println!(
+                                "{} ({}{})",
+                                group.name(),
+                                match group {
+                                    OptionGroup::Exclusive(_) => "choose one",
+                                    OptionGroup::Multiple(_) => "choose any",
+                                },
+                                if group.required() { ", required" } else { "" }
+                            );
+                            for option in group.options() {
+                                println!(
+                                    "  [{}] {}  {}",
+                                    if mod_.enabled_options.contains(option) {
+                                        "x"
+                                    } else {
+                                        " "
+                                    },
+                                    option.path.display(),
+                                    option.name
+                                );
+                            }
+                        }
                     }
-                    print!("Enter mod(s) to uninstall, separated by commas: ");
-                    let mut manifests = Manifest::default();
-                    for id in input!().replace(' ', "").split(',') {
-                        let mod_ = mods
-                            .get(id.trim().parse::<usize>().context("Invalid mod number")? - 1)
-                            .with_context(|| format!("Mod {} does not exist", id))?;
-                        println!("Removing mod {}...", &mod_.meta.name);
-                        mod_manager.del(mod_, profile.as_ref())?;
-                        mod_manager.save()?;
-                        manifests.extend(mod_.manifest()?.as_ref());
+                } else {
+                    let mut options = if select.is_empty() {
+                        mod_.enabled_options.clone()
+                    } else {
+                        select_options(&mod_.meta, select)?
+                    };
+                    let mut fixed = vec![];
+                    if *fix {
+                        let resolution =
+                            uk_manager::options::resolve_options(&mod_.meta, &options)?;
+                        for issue in resolution.fixed.iter() {
+                            status!(self, "Fixed: {issue}");
+                        }
+                        options = resolution.options;
+                        fixed = resolution.fixed;
+                    }
+                    let mod_manager = self.core.mod_manager();
+                    let mut manifest = Manifest::default();
+                    manifest.extend(mod_.manifest()?.as_ref());
+                    manifest.extend(
+                        mod_manager
+                            .set_enabled_options(mod_.as_map_id(), options.clone())?
+                            .as_ref(),
+                    );
+                    mod_manager.save()?;
+                    if mod_.enabled {
+                        status!(self, "Applying changes to merge...");
+                        self.core.deploy_manager().apply(Some(manifest))?;
+                    }
+                    let deployed = self.finish()?;
+                    if *json {
+                        print_json(&json!({
+                            "mod": mod_.meta.name,
+                            "enabled": options.iter().map(|o| &o.path).collect::<Vec<_>>(),
+                            "fixed": fixed,
+                            "deployed": deployed,
+                        }))?;
                     }
                 }
-
-                println!("Applying changes to merge...");
-                self.core.deploy_manager().apply(Some(manifests))?;
-                if self.cli.deploy {
-                    self.deploy()?;
+            }
+            UkmmCmd::Profiles(Profiles { json }) => {
+                let mod_manager = self.core.mod_manager();
+                let current = mod_manager.current_profile();
+                let mut profiles = mod_manager.profile_names().collect::<Vec<_>>();
+                profiles.sort();
+                if *json {
+                    print_json(&json!({ "current": current, "profiles": profiles }))?;
+                } else {
+                    for profile in profiles {
+                        println!(
+                            "{} {profile}",
+                            if profile.as_str() == current {
+                                "*"
+                            } else {
+                                " "
+                            }
+                        );
+                    }
+                }
+            }
+            UkmmCmd::ProfileCreate(ProfileCreate { name, json }) => {
+                let mod_manager = self.core.mod_manager();
+                if mod_manager.profile_names().any(|p| p == *name) {
+                    anyhow_ext::bail!("Profile {name} already exists");
+                }
+                mod_manager.create_profile_if(name)?;
+                status!(self, "Created profile {name}");
+                if *json {
+                    print_json(&json!({ "created": name }))?;
+                }
+            }
+            UkmmCmd::ProfileSwitch(ProfileSwitch { name, json }) => {
+                if !self.core.mod_manager().profile_names().any(|p| p == *name) {
+                    anyhow_ext::bail!("Profile {name} does not exist");
+                }
+                self.core.change_profile(name)?;
+                self.core.settings().save()?;
+                status!(self, "Switched to profile {name}");
+                let deployed = self.finish()?;
+                if *json {
+                    print_json(&json!({ "current": name, "deployed": deployed }))?;
+                }
+            }
+            UkmmCmd::ProfileCopy(ProfileCopy {
+                name,
+                new_name,
+                json,
+            }) => {
+                self.core.mod_manager().copy_profile(name, new_name)?;
+                status!(self, "Copied profile {name} to {new_name}");
+                if *json {
+                    print_json(&json!({ "copied": name, "to": new_name }))?;
+                }
+            }
+            UkmmCmd::ProfileDelete(ProfileDelete { name, json }) => {
+                self.core.mod_manager().delete_profile(name)?;
+                status!(self, "Deleted profile {name}");
+                if *json {
+                    print_json(&json!({ "deleted": name }))?;
+                }
+            }
+            UkmmCmd::ProfileExport(ProfileExport {
+                name,
+                path,
+                meta_only,
+                json,
+            }) => {
+                status!(self, "Exporting profile {name}...");
+                let exported = bundle::export_profile(&self.core, name, path, !meta_only)?;
+                status!(
+                    self,
+                    "Exported {} mods to {}",
+                    exported.mods.len(),
+                    path.display()
+                );
+                if *json {
+                    print_json(&json!({
+                        "profile": exported.profile,
+                        "path": path,
+                        "mods": exported.mods.iter().map(|m| &m.meta.name).collect::<Vec<_>>(),
+                        "included": exported.mods.iter().filter(|m| m.file.is_some()).count(),
+                    }))?;
+                }
+            }
+            UkmmCmd::ProfileImport(ProfileImport { path, name, json }) => {
+                status!(self, "Importing profile from {}...", path.display());
+                let report = bundle::import_profile(&self.core, path, name.as_deref())?;
+                if *json {
+                    print_json(&report)?;
+                } else {
+                    println!(
+                        "Imported {} mods into profile {}",
+                        report.installed.len(),
+                        report.profile
+                    );
+                    for mod_ in report.missing.iter() {
+                        println!("Missing: {mod_}");
+                    }
+                    for mod_ in report.incompatible.iter() {
+                        println!("Wrong platform: {mod_}");
+                    }
+                    for (mod_, reason) in report.failed.iter() {
+                        println!("Failed: {mod_}: {reason}");
+                    }
+                }
+            }
+            UkmmCmd::Lock(Lock { profile, json }) => {
+                status!(self, "Hashing mods...");
+                let lock = lock::lock_profile(&self.core.mod_manager(), profile.as_ref())?;
+                if *json {
+                    print_json(&lock)?;
+                } else {
+                    println!(
+                        "Saved lock snapshot of profile {} with {} mods",
+                        lock.profile,
+                        lock.mods.len()
+                    );
+                }
+            }
+            UkmmCmd::VerifyLock(VerifyLock { profile, json }) => {
+                status!(self, "Hashing mods...");
+                let issues = lock::verify_lock(&self.core.mod_manager(), profile.as_ref())?;
+                if *json {
+                    print_json(&issues)?;
+                } else if issues.is_empty() {
+                    println!("All mods match the lock snapshot");
+                } else {
+                    for issue in &issues {
+                        println!("{issue}");
+                    }
+                }
+                if !issues.is_empty() {
+                    anyhow_ext::bail!("{} differences from the lock snapshot", issues.len());
+                }
+            }
+            UkmmCmd::History(History { profile, json }) => {
+                let mod_manager = self.core.mod_manager();
+                if let Some(profile) = profile {
+                    if !mod_manager.profile_names().any(|p| p == *profile) {
+                        anyhow_ext::bail!("Profile {profile} does not exist");
+                    }
+                }
+                let history = mod_manager.history(profile.as_ref());
+                if *json {
+                    print_json(
+                        &history
+                            .iter()
+                            .enumerate()
+                            .map(|(i, entry)| {
+                                json!({
+                                    "number": i + 1,
+                                    "action": entry.action,
+                                    "timestamp": entry.timestamp,
+                                })
+                            })
+                            .collect::<Vec<_>>(),
+                    )?;
+                } else if history.is_empty() {
+                    println!("No changes recorded");
+                } else {
+                    for (i, entry) in history.iter().enumerate() {
+                        println!("{:>3}. {} ({})", i + 1, entry.action, entry.age());
+                    }
+                }
+            }
+            UkmmCmd::Rollback(Rollback { number, json }) => {
+                let number = number.unwrap_or(1);
+                if number == 0 {
+                    anyhow_ext::bail!("Changes are numbered from 1");
+                }
+                let mod_manager = self.core.mod_manager();
+                let rollback = mod_manager.rollback(None, number - 1)?;
+                mod_manager.save()?;
+                for name in rollback.missing.iter() {
+                    status!(self, "{name} is no longer stored and was left out");
+                }
+                status!(self, "Applying changes to merge...");
+                self.core.deploy_manager().apply(Some(rollback.manifest))?;
+                let deployed = self.finish()?;
+                if *json {
+                    print_json(&json!({
+                        "undone": number,
+                        "missing": rollback.missing,
+                        "deployed": deployed,
+                    }))?;
+                }
+            }
+            UkmmCmd::Settings(Settings { json }) => {
+                let settings = self.core.settings();
+                if *json {
+                    print_json(&*settings)?;
+                } else {
+                    print!("{}", serde_yaml::to_string(&*settings)?);
+                }
+            }
+            UkmmCmd::Deps(Deps { json }) => {
+                let issues = self.core.mod_manager().dependency_issues(None);
+                if *json {
+                    print_json(&issues)?;
+                } else if issues.is_empty() {
+                    println!("All mod dependencies are satisfied");
+                } else {
+                    for issue in &issues {
+                        println!("{issue}");
+                    }
+                }
+            }
+            UkmmCmd::Conflicts(Conflicts { json }) => {
+                let dump = self.core.settings().dump();
+                let report = uk_manager::conflicts::find_conflicts(
+                    self.core.mod_manager().mods(),
+                    dump.as_deref(),
+                )?;
+                if *json {
+                    print_json(&report)?;
+                } else {
+                    print!("{report}");
+                }
+            }
+            UkmmCmd::Dump(Dump {
+                path,
+                resource,
+                json,
+            }) => {
+                let meta = if path.is_file() {
+                    Meta::from_mod(path)?
+                } else {
+                    Meta::parse(path.join("meta.yml"))?
+                };
+                let options = meta
+                    .options
+                    .iter()
+                    .flat_map(|g| g.options().iter().cloned())
+                    .collect::<Vec<_>>();
+                let reader = ModReader::open_peek(path, options)?;
+                match resource {
+                    Some(resource) => {
+                        let dumped = reader.dump_resource(Path::new(resource.as_str()))?;
+                        if *json {
+                            println!(
+                                "{}",
+                                serde_json::to_string_pretty(&dumped).context(
+                                    "This resource cannot be shown as JSON, try YAML instead"
+                                )?
+                            );
+                        } else {
+                            print!("{}", serde_yaml::to_string(&dumped)?);
+                        }
+                    }
+                    None => {
+                        let resources = reader.list_resources();
+                        if *json {
+                            print_json(&resources)?;
+                        } else {
+                            for resource in resources.iter() {
+                                println!("{resource}");
+                            }
+                        }
+                    }
+                }
+            }
+            UkmmCmd::VerifyMod(VerifyMod { name, json }) => {
+                let path = if Path::new(name.as_str()).exists() {
+                    PathBuf::from(name.as_str())
+                } else {
+                    self.find_mod(name)?.path
+                };
+                status!(self, "Verifying {}...", path.display());
+                let report = uk_mod::verify::verify_mod(&path)?;
+                if *json {
+                    print_json(&report)?;
+                } else {
+                    for issue in report.issues.iter() {
+                        println!("{issue}");
+                    }
+                    if report.is_ok() {
+                        println!(
+                            "{} is valid, with {} resources",
+                            report.name.as_deref().unwrap_or("The mod"),
+                            report.resources
+                        );
+                    }
+                }
+                if !report.is_ok() {
+                    anyhow_ext::bail!("Found {} problems in the mod", report.issues.len());
+                }
+            }
+            UkmmCmd::Convert(Convert { path, output, json }) => {
+                let meta = if path.is_file() {
+                    Meta::from_mod(path)?
+                } else {
+                    Meta::parse(path.join("meta.yml"))?
+                };
+                let dump = {
+                    let settings = self.core.settings();
+                    let config = match meta.platform {
+                        ModPlatform::Specific(Endian::Big) => settings.switch_config.as_ref(),
+                        ModPlatform::Specific(Endian::Little) => settings.wiiu_config.as_ref(),
+                        ModPlatform::Universal => {
+                            anyhow_ext::bail!("{} already works on both platforms", meta.name)
+                        }
+                    };
+                    config
+                        .map(|c| c.dump.clone())
+                        .context("The other platform has not been set up")?
+                };
+                status!(self, "Converting {}...", meta.name);
+                let report = uk_mod::convert::convert_mod(path, output, &dump)?;
+                if *json {
+                    print_json(&report)?;
+                } else {
+                    for file in report.unconverted.iter() {
+                        println!("Left out {} ({})", file.path, file.kind);
+                    }
+                    println!(
+                        "Converted {} to {}: {} resources rebuilt, {} files copied, {} left out",
+                        meta.name,
+                        output.display(),
+                        report.rebuilt,
+                        report.copied,
+                        report.unconverted.len()
+                    );
+                }
+            }
+            UkmmCmd::Watch(Watch {
+                name,
+                source,
+                debounce,
+                json,
+            }) => {
+                let mod_ = self.find_mod(name)?;
+                let source = source
+                    .clone()
+                    .or_else(|| mod_.source.clone())
+                    .with_context(|| {
+                        format!(
+                            "{} has not been updated from a source folder yet, so one must be \
+                             given",
+                            mod_.meta.name
+                        )
+                    })?;
+                let debounce = debounce
+                    .map(Duration::from_millis)
+                    .unwrap_or(watch::DEFAULT_DEBOUNCE);
+                status!(
+                    self,
+                    "Watching {} for changes to {}, press Ctrl+C to stop",
+                    source.display(),
+                    mod_.meta.name
+                );
+                watch::watch_mod(
+                    &self.core,
+                    &mod_,
+                    &source,
+                    debounce,
+                    self.cli.deploy,
+                    || false,
+                    |result| {
+                        let printed = match (result, *json) {
+                            (Ok(dirty), true) => {
+                                print_json(&json!({ "mod": mod_.meta.name, "changed": dirty }))
+                            }
+                            (Ok(dirty), false) => {
+                                println!(
+                                    "Updated {}, merging {} changed files",
+                                    mod_.meta.name,
+                                    dirty.content_files.len() + dirty.aoc_files.len()
+                                );
+                                Ok(())
+                            }
+                            (Err(e), true) => {
+                                print_json(
+                                    &json!({ "mod": mod_.meta.name, "error": format!("{e:?}") }),
+                                )
+                            }
+                            (Err(e), false) => {
+                                eprintln!("Failed to update {}: {e:?}", mod_.meta.name);
+                                Ok(())
+                            }
+                        };
+                        if let Err(e) = printed {
+                            log::error!("{e:?}");
+                        }
+                    },
+                )?;
+            }
+            UkmmCmd::MigrateMod(MigrateMod { name, json }) => {
+                let mod_ = self.find_mod(name)?;
+                status!(self, "Migrating {}...", mod_.meta.name);
+                let count = self.core.mod_manager().migrate(&mod_)?;
+                if *json {
+                    print_json(&json!({ "mod": mod_.meta.name, "migrated": count }))?;
+                } else {
+                    println!(
+                        "Migrated {} from UKMM v{}, rewriting {count} resources",
+                        mod_.meta.name, mod_.meta.api
+                    );
                 }
-                println!("Done!");
             }
-            UkmmCmd::Deploy(_) => self.deploy()?,
         };
         Ok(())
     }
@@ -19,7 +19,7 @@ use std::{
     ops::DerefMut,
     path::PathBuf,
     rc::Rc,
-    sync::Arc,
+    sync::{atomic::AtomicBool, Arc},
     thread,
     time::Duration,
 };
@@ -147,6 +147,7 @@ pub enum Message {
     Apply,
     ChangeProfile(String),
     ChangeSort(Sort, bool),
+    CheckDependencies,
     CheckMeta,
     CleanProfile(String),
     ClearDrag,
@@ -155,24 +156,30 @@ pub enum Message {
     CloseConfirm,
     CloseError,
     CloseChangelog,
+    CloseHistory,
     ClosePackagingOptions,
     ClosePackagingDependencies,
     CloseProfiles,
+    CloseReport,
     Confirm(Box<Message>, String),
     DeleteProfile(String),
     Deploy,
+    DeployTarget(String),
     Deselect(usize),
     DoUpdate,
     DuplicateProfile(String),
     Error(anyhow_ext::Error),
+    ExportProfile(String),
     Extract,
     FilePickerBack,
     FilePickerSet(Option<PathBuf>),
     FilePickerUp,
+    FindConflicts,
     GetPackagingOptions,
     HandleMod(Mod),
     HandleSettings,
     ImportCemu,
+    ImportProfile,
     InstallMod(Mod),
     MigrateBcml,
     ModUpdate,
@@ -181,7 +188,9 @@ pub enum Message {
     Noop,
     OfferUpdate(VersionResponse),
     OpenMod(PathBuf),
+    PreviewRemerge,
     PackageMod,
+    ProfileImported(String),
     RefreshModsDisplay,
     Remerge,
     ReloadProfiles,
@@ -194,6 +203,8 @@ pub enum Message {
     ResetPending,
     ResetSettings,
     Restart,
+    RestoreOutput,
+    Rollback(usize),
     SaveSettings,
     SelectAlso(usize),
     SelectFile,
@@ -206,12 +217,16 @@ pub enum Message {
     SetLanguage(LocLang),
     SetTheme(uk_ui::visuals::Theme),
     ShowAbout,
+    ShowHistory,
     ShowPackagingOptions(FxHashSet<PathBuf>),
     ShowPackagingDependencies,
+    ShowReport(String, String),
     StartDrag(usize),
     Toast(String),
     ToggleMods(Option<Vec<Mod>>, bool),
     DevUpdate,
+    DevWatch,
+    DevUnwatch,
     UpdatePackageMeta(Meta),
     UninstallMods(Option<Vec<Mod>>),
     UpdateOptions(Mod),
@@ -269,7 +284,11 @@ pub struct App {
     theme: uk_ui::visuals::Theme,
     dock_style: uk_ui::egui_dock::Style,
     changelog: Option<String>,
+    report: Option<(String, String)>,
+    history: Option<Vec<uk_manager::history::HistoryEntry>>,
     new_version: Option<VersionResponse>,
+    /// The mod being watched for source changes, with the flag to stop it.
+    watch: Option<(usize, Arc<AtomicBool>)>,
 }
 
 impl App {
@@ -322,6 +341,8 @@ impl App {
                     None
                 }
             },
+            report: None,
+            history: None,
             channel: (send, recv),
             closed_tabs: Default::default(),
             focused: FocusedPane::None,
@@ -351,6 +372,7 @@ impl App {
             update_mod: Default::default(),
             error_queue: Default::default(),
             new_version: None,
+            watch: None,
             core,
         }
     }
@@ -394,6 +416,8 @@ impl App {
             || self.opt_folders.is_some()
             || self.meta_input.is_open()
             || self.changelog.is_some()
+            || self.report.is_some()
+            || self.history.is_some()
     }
 
     fn do_update(&self, message: Message) {
@@ -458,6 +482,8 @@ impl eframe::App for App {
         self.render_option_picker(ctx);
         self.profiles_state.borrow_mut().render(self, ctx);
         self.render_changelog(ctx);
+        self.render_report(ctx);
+        self.render_history(ctx);
         self.meta_input.ui(ctx);
         let layer_id = LayerId::background();
         let max_rect = ctx.available_rect();
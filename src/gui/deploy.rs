@@ -3,16 +3,50 @@ use super::*;
 
 impl App {
     pub fn render_deploy_tab(&self, ui: &mut Ui) {
-        match self
+        let targets = self
             .core
             .settings()
             .platform_config()
-            .and_then(|c| c.deploy_config.as_ref())
-        {
-            Some(config) => {
+            .map(|c| {
+                c.targets()
+                    .map(|(name, config)| (name.to_string(), config.clone()))
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default();
+        let id = egui::Id::new("deploy-target");
+        let selected = ui
+            .data(|d| d.get_temp::<std::string::String>(id))
+            .and_then(|name| targets.iter().find(|(target, _)| *target == name))
+            .or_else(|| targets.first());
+        match selected {
+            Some((target, config)) => {
                 egui::Frame::none().inner_margin(4.0).show(ui, |ui| {
                     ui.spacing_mut().item_spacing.y = 8.0;
                     ui.with_layout(Layout::top_down(Align::Center), |ui| {
+                        if targets.len() > 1 {
+                            ui.horizontal(|ui| {
+                                ui.label(
+                                    RichText::new("Deploy_Target".localize())
+                                        .family(egui::FontFamily::Name("Bold".into())),
+                                );
+                                ui.with_layout(Layout::right_to_left(Align::Max), |ui| {
+                                    egui::ComboBox::new(id, "")
+                                        .selected_text(target.as_str())
+                                        .show_ui(ui, |ui| {
+                                            for (name, _) in &targets {
+                                                if ui
+                                                    .selectable_label(name == target, name.as_str())
+                                                    .clicked()
+                                                {
+                                                    ui.data_mut(|d| {
+                                                        d.insert_temp(id, name.clone())
+                                                    });
+                                                }
+                                            }
+                                        });
+                                })
+                            });
+                        }
                         ui.horizontal(|ui| {
                             ui.label(
                                 RichText::new("Settings_Platform_Deploy_Method".localize())
@@ -105,13 +139,24 @@ impl App {
                                                 .spawn();
                                         }
                                     }
+                                    if targets.len() > 1
+                                        && ui
+                                            .add(egui::Button::new("Deploy_All".localize()))
+                                            .clicked()
+                                    {
+                                        self.do_update(super::Message::Deploy);
+                                    }
                                     if ui
                                         .add(egui::Button::new("Tab_Deploy".localize()))
                                         .clicked()
                                     {
-                                        self.do_update(super::Message::Deploy);
+                                        self.do_update(super::Message::DeployTarget(
+                                            target.clone(),
+                                        ));
                                     }
-                                    if config.auto && self.core.deploy_manager().pending() {
+                                    if config.auto
+                                        && self.core.deploy_manager().target_pending(target)
+                                    {
                                         ui.label(
                                             RichText::new(
                                                 "Deploy_Auto_Failed".localize()
@@ -37,10 +37,33 @@ impl App {
             ui.close_menu();
             self.do_update(Message::Remerge);
         }
+        if ui.button("Menu_Tools_PreviewMerge".localize()).clicked() {
+            ui.close_menu();
+            self.do_update(Message::PreviewRemerge);
+        }
         if ui.button("Menu_Tools_ResetPending".localize()).clicked() {
             ui.close_menu();
             self.do_update(Message::ResetPending);
         }
+        if ui.button("Menu_Tools_RestoreOutput".localize()).clicked() {
+            ui.close_menu();
+            self.do_update(Message::Confirm(
+                Message::RestoreOutput.into(),
+                "Menu_Tools_RestoreOutput_Confirm".localize(),
+            ));
+        }
+        if ui.button("Menu_Tools_Conflicts".localize()).clicked() {
+            ui.close_menu();
+            self.do_update(Message::FindConflicts);
+        }
+        if ui.button("Menu_Tools_Dependencies".localize()).clicked() {
+            ui.close_menu();
+            self.do_update(Message::CheckDependencies);
+        }
+        if ui.button("Menu_Tools_History".localize()).clicked() {
+            ui.close_menu();
+            self.do_update(Message::ShowHistory);
+        }
         if ui.button("Menu_Tools_ConfigFolder".localize()).clicked() {
             ui.close_menu();
             open::that(Settings::config_dir()).unwrap_or(());
@@ -510,4 +510,89 @@ impl App {
                 });
         }
     }
+
+    pub fn render_report(&self, ctx: &egui::Context) {
+        if let Some((ref title, ref report)) = self.report {
+            egui::Window::new(title.as_str())
+                .collapsible(false)
+                .scroll([false, true])
+                .anchor(Align2::CENTER_CENTER, Vec2::default())
+                .frame(Frame::window(&ctx.style()).inner_margin(6.))
+                .default_width(600.0)
+                .default_height(500.0)
+                .show(ctx, |ui| {
+                    ui.spacing_mut().item_spacing.y = 6.0;
+                    let md_cache = ui.data_mut(|d| {
+                        d.get_temp_mut_or_default::<Arc<Mutex<egui_commonmark::CommonMarkCache>>>(
+                            egui::Id::new("md_cache_report"),
+                        )
+                        .clone()
+                    });
+                    egui_commonmark::CommonMarkViewer::new("report").show(
+                        ui,
+                        &mut md_cache.lock(),
+                        report,
+                    );
+                    ui.separator();
+                    let width = ui.min_size().x;
+                    ui.horizontal(|ui| {
+                        ui.allocate_ui_with_layout(
+                            Vec2::new(width, ui.min_size().y),
+                            Layout::right_to_left(Align::Center),
+                            |ui| {
+                                if ui.button("Generic_Copy".localize()).clicked() {
+                                    ui.output_mut(|o| o.copied_text = report.clone());
+                                }
+                                if ui.button("Generic_OK".localize()).clicked() {
+                                    self.do_update(Message::CloseReport);
+                                }
+                                ui.shrink_width_to_current();
+                            },
+                        );
+                    });
+                });
+        }
+    }
+
+    pub fn render_history(&self, ctx: &egui::Context) {
+        if let Some(ref history) = self.history {
+            egui::Window::new("Menu_Tools_History".localize())
+                .collapsible(false)
+                .scroll([false, true])
+                .anchor(Align2::CENTER_CENTER, Vec2::default())
+                .frame(Frame::window(&ctx.style()).inner_margin(6.))
+                .default_width(400.0)
+                .show(ctx, |ui| {
+                    ui.spacing_mut().item_spacing.y = 6.0;
+                    if history.is_empty() {
+                        ui.label("History_Empty".localize());
+                    }
+                    for (i, entry) in history.iter().enumerate() {
+                        ui.horizontal(|ui| {
+                            ui.label(entry.action.as_str());
+                            ui.weak(entry.age());
+                            ui.with_layout(Layout::right_to_left(Align::Center), |ui| {
+                                if ui.button("History_Restore".localize()).clicked() {
+                                    self.do_update(Message::Rollback(i));
+                                }
+                            });
+                        });
+                    }
+                    ui.separator();
+                    let width = ui.min_size().x;
+                    ui.horizontal(|ui| {
+                        ui.allocate_ui_with_layout(
+                            Vec2::new(width, ui.min_size().y),
+                            Layout::right_to_left(Align::Center),
+                            |ui| {
+                                if ui.button("Generic_Close".localize()).clicked() {
+                                    self.do_update(Message::CloseHistory);
+                                }
+                                ui.shrink_width_to_current();
+                            },
+                        );
+                    });
+                });
+        }
+    }
 }
@@ -19,6 +19,8 @@ enum ContextMenuMessage {
     Extract,
     Update,
     DevUpdate,
+    Watch,
+    Unwatch,
     Uninstall,
     Toggle(bool),
     Move(usize),
@@ -347,6 +349,10 @@ impl App {
             let mut toggled = false;
             let mut ctx_action = None;
             let menu_mod = mod_.clone();
+            let watched = self
+                .watch
+                .as_ref()
+                .is_some_and(|(hash, _)| *hash == menu_mod.hash());
 
             let mut process_col_res = |res: Response| {
                 clicked = clicked || res.clicked();
@@ -354,7 +360,7 @@ impl App {
                 drag_started = drag_started || res.drag_started();
                 res.context_menu(|ui| {
                     if let Some(action) =
-                        Self::render_mod_context_menu(&self.core, menu_mod.clone(), ui)
+                        Self::render_mod_context_menu(&self.core, menu_mod.clone(), watched, ui)
                     {
                         ctx_action.replace(action);
                     }
@@ -405,6 +411,8 @@ impl App {
                     ContextMenuMessage::DevUpdate => {
                         self.do_update(Message::DevUpdate);
                     }
+                    ContextMenuMessage::Watch => self.do_update(Message::DevWatch),
+                    ContextMenuMessage::Unwatch => self.do_update(Message::DevUnwatch),
                     ContextMenuMessage::Uninstall => {
                         let message = "Mod_Uninstall_Confirmation".localize();
                         let vars = HashMap::from(
@@ -450,6 +458,7 @@ impl App {
     fn render_mod_context_menu(
         core: &uk_manager::core::Manager,
         mod_: Mod,
+        watched: bool,
         ui: &mut Ui,
     ) -> Option<ContextMenuMessage> {
         let mut result = None;
@@ -473,6 +482,15 @@ impl App {
             ui.close_menu();
             result = Some(ContextMenuMessage::DevUpdate);
         }
+        if watched {
+            if ui.button("Mod_Dev_Unwatch".localize()).clicked() {
+                ui.close_menu();
+                result = Some(ContextMenuMessage::Unwatch);
+            }
+        } else if ui.button("Mod_Dev_Watch".localize()).clicked() {
+            ui.close_menu();
+            result = Some(ContextMenuMessage::Watch);
+        }
         if ui.button("Mod_Uninstall".localize()).clicked() {
             ui.close_menu();
             result = Some(ContextMenuMessage::Uninstall);
@@ -20,7 +20,6 @@ impl App {
             .anchor(egui::Align2::CENTER_CENTER, Vec2::default())
             .show(ctx, |ui| {
                 let mod_ = unsafe { &mut self.options_mod.as_mut().unwrap_unchecked().0 };
-                let mut done = true;
                 mod_.meta.options.iter().for_each(|group| {
                     egui::CollapsingHeader::new(group.name())
                         .default_open(true)
@@ -83,17 +82,23 @@ impl App {
                                 }
                             });
                         });
-                    if group.required()
-                        && !group
-                            .options()
-                            .iter()
-                            .any(|opt| mod_.enabled_options.contains(opt))
-                    {
-                        done = false;
-                    }
                 });
+                let issues = uk_manager::options::check_options(&mod_.meta, &mod_.enabled_options);
+                let done = issues.is_empty();
                 if !done {
-                    ui.colored_label(visuals::RED, "Options_Required".localize());
+                    ui.colored_label(visuals::RED, "Options_Invalid".localize());
+                    for issue in issues.iter() {
+                        ui.colored_label(visuals::RED, format!("• {issue}"));
+                    }
+                    if ui.button("Options_Fix".localize()).clicked() {
+                        match uk_manager::options::resolve_options(
+                            &mod_.meta,
+                            &mod_.enabled_options,
+                        ) {
+                            Ok(resolution) => mod_.enabled_options = resolution.options,
+                            Err(e) => self.do_update(Message::Error(e)),
+                        }
+                    }
                 }
                 ui.horizontal(|ui| {
                     ui.add_space(2.);
@@ -28,6 +28,9 @@ pub struct ModPackerBuilder {
     pub source: PathBuf,
     pub dest:   PathBuf,
     pub meta:   Meta,
+
+    pub reproducible: bool,
+    pub incremental:  bool,
 }
 
 impl ModPackerBuilder {
@@ -47,6 +50,9 @@ impl ModPackerBuilder {
                 options: Default::default(),
                 masters: Default::default(),
             },
+
+            reproducible: false,
+            incremental:  false,
         }
     }
 
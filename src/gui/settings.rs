@@ -58,6 +58,17 @@ pub enum DumpType {
         aoc_dir:     Option<PathBuf>,
         host_path:   PathBuf,
     },
+    Nsp {
+        host_path:   PathBuf,
+        update_path: Option<PathBuf>,
+        aoc_path:    Option<PathBuf>,
+        keys_path:   PathBuf,
+    },
+    Wud {
+        host_path:  PathBuf,
+        update_dir: PathBuf,
+        aoc_dir:    Option<PathBuf>,
+    },
 }
 
 impl DumpType {
@@ -65,6 +76,8 @@ impl DumpType {
         match self {
             DumpType::Unpacked { host_path, .. } => host_path.as_path(),
             DumpType::ZArchive { host_path, .. } => host_path.as_path(),
+            DumpType::Nsp { host_path, .. } => host_path.as_path(),
+            DumpType::Wud { host_path, .. } => host_path.as_path(),
         }
     }
 
@@ -89,7 +102,9 @@ impl DumpType {
                         .map(|d| d.as_os_str().is_empty())
                         .unwrap_or(true)
             }
-            DumpType::ZArchive { host_path, .. } => host_path.as_os_str().is_empty(),
+            DumpType::ZArchive { host_path, .. }
+            | DumpType::Nsp { host_path, .. }
+            | DumpType::Wud { host_path, .. } => host_path.as_os_str().is_empty(),
         }
     }
 }
@@ -106,6 +121,7 @@ pub struct PlatformSettingsUI {
     pub profile: String,
     pub dump: DumpType,
     pub deploy_config: DeployConfig,
+    pub deploy_targets: Vec<(String, DeployConfig)>,
 }
 
 impl Default for PlatformSettingsUI {
@@ -120,6 +136,7 @@ impl Default for PlatformSettingsUI {
                 aoc_dir:     Default::default(),
             },
             deploy_config: Default::default(),
+            deploy_targets: Default::default(),
         }
     }
 }
@@ -158,8 +175,35 @@ impl TryFrom<PlatformSettingsUI> for PlatformSettings {
             DumpType::ZArchive { host_path, .. } => {
                 Arc::new(ResourceReader::from_zarchive(host_path)?)
             }
+            DumpType::Nsp {
+                host_path,
+                update_path,
+                aoc_path,
+                keys_path,
+            } => {
+                Arc::new(ResourceReader::from_nsp(
+                    host_path,
+                    update_path,
+                    aoc_path,
+                    keys_path,
+                )?)
+            }
+            DumpType::Wud {
+                host_path,
+                update_dir,
+                aoc_dir,
+            } => Arc::new(ResourceReader::from_wud(host_path, update_dir, aoc_dir)?),
         };
-        Ok(Self {
+        let mut deploy_targets = std::collections::BTreeMap::new();
+        for (name, config) in settings.deploy_targets {
+            let name = name.trim();
+            if config.output.as_os_str().is_empty() {
+                continue;
+            } else if deploy_targets.insert(name.into(), config).is_some() {
+                anyhow::bail!("Deploy target name \"{name}\" is used more than once");
+            }
+        }
+        let settings = Self {
             language: settings.language,
             profile: settings.profile.into(),
             dump,
@@ -168,7 +212,10 @@ impl TryFrom<PlatformSettingsUI> for PlatformSettings {
             } else {
                 Some(settings.deploy_config)
             },
-        })
+            deploy_targets,
+        };
+        settings.validate_targets()?;
+        Ok(settings)
     }
 }
 
@@ -179,6 +226,11 @@ impl From<&PlatformSettings> for PlatformSettingsUI {
             profile: settings.profile.to_string(),
             dump: settings.dump.as_ref().into(),
             deploy_config: settings.deploy_config.as_ref().cloned().unwrap_or_default(),
+            deploy_targets: settings
+                .deploy_targets
+                .iter()
+                .map(|(name, config)| (name.to_string(), config.clone()))
+                .collect(),
         }
     }
 }
@@ -187,6 +239,11 @@ impl PartialEq<PlatformSettings> for PlatformSettingsUI {
     fn eq(&self, other: &PlatformSettings) -> bool {
         self.language == other.language
             && other.deploy_config.contains(&self.deploy_config)
+            && self.deploy_targets.len() == other.deploy_targets.len()
+            && self
+                .deploy_targets
+                .iter()
+                .all(|(name, config)| other.deploy_targets.get(name.as_str()) == Some(config))
             && self.dump.host_path() == other.dump.source().host_path()
     }
 }
@@ -195,7 +252,6 @@ pub static CONFIG: LazyLock<RwLock<FxHashMap<Platform, PlatformSettingsUI>>> =
     LazyLock::new(|| RwLock::new(Default::default()));
 
 fn render_deploy_config(config: &mut DeployConfig, platform: Platform, ui: &mut Ui) -> bool {
-    ui.label("Settings_Platform_Deploy".localize());
     let mut changed = false;
     ui.group(|ui| {
         let width = ui.available_width().max(0.0);
@@ -228,8 +284,50 @@ fn render_deploy_config(config: &mut DeployConfig, platform: Platform, ui: &mut
                         "Settings_Platform_Deploy_Method_Symlink".localize(),
                     )
                     .changed();
+                changed |= ui
+                    .radio_value(
+                        &mut config.method,
+                        uk_manager::settings::DeployMethod::Archive,
+                        "Settings_Platform_Deploy_Method_Archive".localize(),
+                    )
+                    .changed();
             },
         );
+        if config.method == uk_manager::settings::DeployMethod::Archive {
+            name = "Settings_Platform_Deploy_Archive_Format".localize();
+            description = "Settings_Platform_Deploy_Archive_Format_Desc".localize();
+            render_setting(
+                &name,
+                &description,
+                ui,
+                |ui| {
+                    changed |= ui
+                        .radio_value(
+                            &mut config.archive_format,
+                            uk_manager::settings::ArchiveFormat::Zip,
+                            "ZIP",
+                        )
+                        .changed();
+                    changed |= ui
+                        .radio_value(
+                            &mut config.archive_format,
+                            uk_manager::settings::ArchiveFormat::Tar,
+                            "tar",
+                        )
+                        .changed();
+                },
+            );
+            name = "Settings_Platform_Deploy_Archive_Delta".localize();
+            description = "Settings_Platform_Deploy_Archive_Delta_Desc".localize();
+            render_setting(
+                &name,
+                &description,
+                ui,
+                |ui| {
+                    changed |= ui.checkbox(&mut config.archive_delta, "").changed();
+                },
+            );
+        }
         name = "Settings_Platform_Deploy_Layout".localize();
         description = match platform {
             Platform::WiiU => "Settings_Platform_Deploy_Layout_WiiU_Desc".localize(),
@@ -276,6 +374,21 @@ fn render_deploy_config(config: &mut DeployConfig, platform: Platform, ui: &mut
                 changed |= ui.checkbox(&mut config.auto, "").changed();
             },
         );
+        if matches!(
+            config.method,
+            uk_manager::settings::DeployMethod::Copy | uk_manager::settings::DeployMethod::HardLink
+        ) {
+            name = "Settings_Platform_Deploy_Hashes".localize();
+            description = "Settings_Platform_Deploy_Hashes_Desc".localize();
+            render_setting(
+                &name,
+                &description,
+                ui,
+                |ui| {
+                    changed |= ui.checkbox(&mut config.compare_hashes, "").changed();
+                },
+            );
+        }
         if platform == Platform::WiiU {
             name = "Settings_Platform_Deploy_Rules".localize();
             description = "Settings_Platform_Deploy_Rules_Desc".localize();
@@ -315,6 +428,46 @@ fn render_deploy_config(config: &mut DeployConfig, platform: Platform, ui: &mut
     changed
 }
 
+fn render_deploy_targets(
+    targets: &mut Vec<(String, DeployConfig)>,
+    platform: Platform,
+    ui: &mut Ui,
+) -> bool {
+    let mut changed = false;
+    ui.add_space(8.0);
+    ui.label("Settings_Platform_Deploy_Targets".localize())
+        .on_hover_text("Settings_Platform_Deploy_Targets_Desc".localize());
+    let mut removed = None;
+    for (i, (name, config)) in targets.iter_mut().enumerate() {
+        ui.push_id(format!("deploy-target-{platform}-{i}"), |ui| {
+            ui.horizontal(|ui| {
+                ui.label("Settings_Platform_Deploy_Target".localize());
+                changed |= ui.text_edit_singleline(name).changed();
+                if ui
+                    .icon_button(icons::Icon::Delete)
+                    .on_hover_text("Generic_Delete".localize())
+                    .clicked()
+                {
+                    removed = Some(i);
+                }
+            });
+            changed |= render_deploy_config(config, platform, ui);
+        });
+    }
+    if let Some(i) = removed {
+        targets.remove(i);
+        changed = true;
+    }
+    if ui
+        .button("Settings_Platform_Deploy_Target_Add".localize())
+        .clicked()
+    {
+        targets.push((format!("Target {}", targets.len() + 2), Default::default()));
+        changed = true;
+    }
+    changed
+}
+
 fn render_platform_config(
     config: &mut Option<PlatformSettings>,
     platform: Platform,
@@ -348,47 +501,83 @@ fn render_platform_config(
     ui.group(|ui| {
         let width = ui.available_width().max(0.0);
         ui.allocate_space([width, 0.0].into());
-        if platform == Platform::WiiU {
-            name = "Settings_Platform_Dump_Type".localize();
-            description = "Settings_Platform_Dump_Type_Desc".localize();
-            render_setting(
-                &name,
-                &description,
-                ui,
-                |ui| {
-                    if ui
-                        .radio(
-                            matches!(config.dump, DumpType::Unpacked { .. }),
-                            "Settings_Platform_Dump_Type_Unpacked".localize()
-                        )
-                        .clicked()
-                    {
-                        config.dump = DumpType::Unpacked {
-                            host_path:   Default::default(),
-                            content_dir: Default::default(),
-                            update_dir:  Default::default(),
-                            aoc_dir:     Default::default(),
-                        };
-                        changed = true;
+        name = "Settings_Platform_Dump_Type".localize();
+        description = match platform {
+            Platform::WiiU => "Settings_Platform_Dump_Type_Desc".localize(),
+            Platform::Switch => "Settings_Platform_Dump_Type_NX_Desc".localize(),
+        };
+        render_setting(
+            &name,
+            &description,
+            ui,
+            |ui| {
+                if ui
+                    .radio(
+                        matches!(config.dump, DumpType::Unpacked { .. }),
+                        "Settings_Platform_Dump_Type_Unpacked".localize()
+                    )
+                    .clicked()
+                {
+                    config.dump = DumpType::Unpacked {
+                        host_path:   Default::default(),
+                        content_dir: Default::default(),
+                        update_dir:  Default::default(),
+                        aoc_dir:     Default::default(),
+                    };
+                    changed = true;
+                }
+                match platform {
+                    Platform::WiiU => {
+                        if ui
+                            .radio(
+                                matches!(config.dump, DumpType::ZArchive { .. }),
+                                "Settings_Platform_Dump_Type_WUA".localize()
+                            )
+                            .clicked()
+                        {
+                            config.dump = DumpType::ZArchive {
+                                content_dir: Default::default(),
+                                update_dir:  Default::default(),
+                                aoc_dir:     Default::default(),
+                                host_path:   Default::default(),
+                            };
+                            changed = true;
+                        }
+                        if ui
+                            .radio(
+                                matches!(config.dump, DumpType::Wud { .. }),
+                                "Settings_Platform_Dump_Type_WUD".localize()
+                            )
+                            .clicked()
+                        {
+                            config.dump = DumpType::Wud {
+                                host_path:  Default::default(),
+                                update_dir: Default::default(),
+                                aoc_dir:    Default::default(),
+                            };
+                            changed = true;
+                        }
                     }
-                    if ui
-                        .radio(
-                            matches!(config.dump, DumpType::ZArchive { .. }),
-                            "Settings_Platform_Dump_Type_WUA".localize()
-                        )
-                        .clicked()
-                    {
-                        config.dump = DumpType::ZArchive {
-                            content_dir: Default::default(),
-                            update_dir:  Default::default(),
-                            aoc_dir:     Default::default(),
-                            host_path:   Default::default(),
-                        };
-                        changed = true;
+                    Platform::Switch => {
+                        if ui
+                            .radio(
+                                matches!(config.dump, DumpType::Nsp { .. }),
+                                "Settings_Platform_Dump_Type_NSP".localize()
+                            )
+                            .clicked()
+                        {
+                            config.dump = DumpType::Nsp {
+                                host_path:   Default::default(),
+                                update_path: Default::default(),
+                                aoc_path:    Default::default(),
+                                keys_path:   Default::default(),
+                            };
+                            changed = true;
+                        }
                     }
-                },
-            );
-        }
+                }
+            },
+        );
         match &mut config.dump {
             DumpType::Unpacked {
                 host_path,
@@ -472,9 +661,85 @@ fn render_platform_config(
                     },
                 );
             }
+            DumpType::Nsp {
+                host_path,
+                update_path,
+                aoc_path,
+                keys_path,
+            } => {
+                for (label, tooltip, path) in [
+                    (
+                        "Settings_Platform_Dump_NSP_Base",
+                        "Settings_Platform_Dump_NSP_Base_Desc",
+                        host_path,
+                    ),
+                    (
+                        "Settings_Platform_Dump_NSP_Update",
+                        "Settings_Platform_Dump_NSP_Update_Desc",
+                        update_path.get_or_insert_default(),
+                    ),
+                    (
+                        "Settings_Platform_Dump_NSP_DLC",
+                        "Settings_Platform_Dump_NSP_DLC_Desc",
+                        aoc_path.get_or_insert_default(),
+                    ),
+                    (
+                        "Settings_Platform_Dump_NSP_Keys",
+                        "Settings_Platform_Dump_NSP_Keys_Desc",
+                        keys_path,
+                    ),
+                ] {
+                    render_setting(
+                        &label.localize(),
+                        &tooltip.localize(),
+                        ui,
+                        |ui| {
+                            changed |= ui.file_picker(path).changed();
+                        },
+                    );
+                }
+            }
+            DumpType::Wud {
+                host_path,
+                update_dir,
+                aoc_dir,
+            } => {
+                name = "Settings_Platform_Dump_WUD".localize();
+                description = "Settings_Platform_Dump_WUD_Desc".localize();
+                render_setting(
+                    &name,
+                    &description,
+                    ui,
+                    |ui| {
+                        changed |= ui.file_picker(host_path).changed();
+                    },
+                );
+                name = "Settings_Platform_Dump_Update".localize();
+                description = "Settings_Platform_Dump_Update_Desc".localize();
+                render_setting(
+                    &name,
+                    &description,
+                    ui,
+                    |ui| {
+                        changed |= ui.folder_picker(update_dir).changed();
+                    },
+                );
+                name = "Settings_Platform_Dump_DLC".localize();
+                description = "Settings_Platform_Dump_DLC_WiiU_Desc".localize();
+                render_setting(
+                    &name,
+                    &description,
+                    ui,
+                    |ui| {
+                        changed |= ui.folder_picker(aoc_dir.get_or_insert_default()).changed();
+                    },
+                );
+            }
         }
     });
+    ui.label("Settings_Platform_Deploy".localize());
     changed |= render_deploy_config(&mut config.deploy_config, platform, ui);
+    changed |= render_deploy_targets(&mut config.deploy_targets, platform, ui);
     changed
 }
 
@@ -704,6 +969,7 @@ impl App {
                     (Some(config), None) => {
                         !config.dump.is_empty()
                             || !config.deploy_config.output.as_os_str().is_empty()
+                            || !config.deploy_targets.is_empty()
                     }
                     (Some(tmp_config), Some(config)) => tmp_config.ne(config),
                 }
@@ -717,6 +983,7 @@ impl App {
                     (Some(config), None) => {
                         !config.dump.is_empty()
                             || !config.deploy_config.output.as_os_str().is_empty()
+                            || !config.deploy_targets.is_empty()
                     }
                     (Some(tmp_config), Some(config)) => tmp_config.ne(config),
                 }
@@ -16,6 +16,7 @@ use uk_content::constants::Language;
 use uk_manager::{
     bnp::convert_bnp,
     core::Manager,
+    dependencies,
     mods::Mod,
     settings::{DeployConfig, Platform, PlatformSettings, UpdatePreference},
     util::get_temp_file,
@@ -190,34 +191,56 @@ pub fn apply_changes(core: &Manager, mods: Vec<Mod>, dirty: Option<Manifest>) ->
     log::info!("Applying pending changes to mod configuration");
     if !mods.is_empty() {
         log::info!("Updating mod states");
-        mods.iter()
-            .try_for_each(|m| -> Result<()> {
-                let mod_ = mod_manager
-                    .all_mods()
-                    .find(|m2| m2.hash() == m.hash())
-                    .unwrap();
-                if !mod_.state_eq(m) {
-                    mod_manager
-                        .set_enabled(m.hash(), m.enabled, None)
-                        .with_context(|| {
-                            format!(
-                                "Failed to {} {}",
-                                if m.enabled { "enable" } else { "disable" },
-                                m.meta.name.as_str()
-                            )
-                        })?;
-                    mod_manager
-                        .set_enabled_options(m.hash(), m.enabled_options.clone())
-                        .with_context(|| {
-                            format!("Failed to update options on {}", m.meta.name.as_str())
-                        })?;
-                }
-                Ok(())
-            })
-            .context("Failed to update mod state")?;
-        log::info!("Updating load order");
-        let order = mods.iter().map(|m| m.hash()).collect();
-        mod_manager.set_order(order);
+        mod_manager.batch("Apply changes", || -> Result<()> {
+            let by_hash = mods
+                .iter()
+                .map(|m| (m.hash(), m))
+                .collect::<uk_manager::util::HashMap<_, _>>();
+            let order = dependencies::sort_order(
+                &mods.iter().map(|m| m.hash()).collect::<Vec<_>>(),
+                &mods.iter().map(|m| (m.hash(), m.clone())).collect(),
+            );
+            // Enable masters before their dependents, and disable dependents
+            // before their masters
+            order
+                .iter()
+                .map(|h| by_hash[h])
+                .filter(|m| m.enabled)
+                .chain(
+                    order
+                        .iter()
+                        .rev()
+                        .map(|h| by_hash[h])
+                        .filter(|m| !m.enabled),
+                )
+                .try_for_each(|m| -> Result<()> {
+                    let mod_ = mod_manager
+                        .all_mods()
+                        .find(|m2| m2.hash() == m.hash())
+                        .unwrap();
+                    if !mod_.state_eq(m) {
+                        mod_manager
+                            .set_enabled(m.hash(), m.enabled, None)
+                            .with_context(|| {
+                                format!(
+                                    "Failed to {} {}",
+                                    if m.enabled { "enable" } else { "disable" },
+                                    m.meta.name.as_str()
+                                )
+                            })?;
+                        mod_manager
+                            .set_enabled_options(m.hash(), m.enabled_options.clone())
+                            .with_context(|| {
+                                format!("Failed to update options on {}", m.meta.name.as_str())
+                            })?;
+                    }
+                    Ok(())
+                })
+                .context("Failed to update mod state")?;
+            log::info!("Updating load order");
+            mod_manager.set_order(order);
+            Ok(())
+        })?;
         mod_manager
             .save()
             .context("Failed to save mod configuration for current profile")?;
@@ -230,12 +253,38 @@ pub fn apply_changes(core: &Manager, mods: Vec<Mod>, dirty: Option<Manifest>) ->
     if core
         .settings()
         .platform_config()
-        .and_then(|c| c.deploy_config.as_ref().map(|c| c.auto))
-        .unwrap_or(false)
+        .is_some_and(|c| c.targets().any(|(_, config)| config.auto))
+    {
+        log::info!("Deploying changes");
+        deploy_manager
+            .deploy_auto()
+            .context("Failed to deploy update to merged mod(s)")?;
+    }
+    log::info!("Done");
+    Ok(Message::ResetMods(None))
+}
+
+pub fn rollback(core: &Manager, index: usize) -> Result<Message> {
+    let mod_manager = core.mod_manager();
+    let rollback = mod_manager.rollback(None, index)?;
+    for name in rollback.missing.iter() {
+        log::warn!("{name} is no longer installed and could not be restored");
+    }
+    mod_manager
+        .save()
+        .context("Failed to save mod configuration for current profile")?;
+    let deploy_manager = core.deploy_manager();
+    deploy_manager
+        .apply(Some(rollback.manifest))
+        .context("Failed to apply rolled back mod configuration")?;
+    if core
+        .settings()
+        .platform_config()
+        .is_some_and(|c| c.targets().any(|(_, config)| config.auto))
     {
         log::info!("Deploying changes");
         deploy_manager
-            .deploy()
+            .deploy_auto()
             .context("Failed to deploy update to merged mod(s)")?;
     }
     log::info!("Done");
@@ -253,6 +302,8 @@ pub fn package_mod(core: &Manager, builder: ModPackerBuilder) -> Result<Message>
         [dump].into_iter().collect(),
     )
     .context("Failed to initialize mod packager")?
+    .reproducible(builder.reproducible)
+    .incremental(builder.incremental)
     .pack()
     .context("Failed to package mod")?;
     Ok(Message::ResetPacker)
@@ -270,21 +321,7 @@ pub fn dev_update_mods(core: &Manager, mods: Vec<Mod>) -> Result<Message> {
             .set_title(message.format(&vars)?)
             .pick_folder()
         {
-            dirty.extend(&mod_.manifest().unwrap_or_default());
-            let hash = mod_.hash();
-            ModPacker::new(
-                folder,
-                &mod_.path,
-                None,
-                [core.settings().dump().unwrap()].into_iter().collect(),
-            )
-            .context("Failed to initialize mod packager")?
-            .pack()
-            .context("Failed to package mod")?;
-            let new_mod = ModReader::open_peek(mod_.path, vec![])?;
-            dirty.extend(new_mod.manifest());
-            core.mod_manager_mut()
-                .replace(Mod::from_reader(new_mod), hash)?;
+            dirty.extend(&core.mod_manager().dev_update(&mod_, &folder)?);
         } else {
             return Ok(Message::Noop);
         }
@@ -333,6 +370,47 @@ pub fn extract_mods(core: &Manager, mods: Vec<Mod>) -> Result<Message> {
     }
 }
 
+pub fn find_conflicts(core: Arc<Manager>) -> Result<Message> {
+    log::info!("Checking enabled mods for conflicts");
+    let dump = core.settings().dump();
+    let report = uk_manager::conflicts::find_conflicts(core.mod_manager().mods(), dump.as_deref())?;
+    log::info!("Found {} resources edited by multiple mods", report.conflicts.len());
+    Ok(Message::ShowReport(
+        "Report_Conflicts".localize().to_string(),
+        report.to_markdown(),
+    ))
+}
+
+pub fn check_dependencies(core: Arc<Manager>) -> Result<Message> {
+    let issues = core.mod_manager().dependency_issues(None);
+    let report = if issues.is_empty() {
+        "All mod dependencies are satisfied.".to_string()
+    } else {
+        issues.iter().fold(String::new(), |mut acc, issue| {
+            writeln!(acc, "- {issue}").expect("Failed to write to String");
+            acc
+        })
+    };
+    Ok(Message::ShowReport(
+        "Report_Dependencies".localize().to_string(),
+        report,
+    ))
+}
+
+pub fn import_profile(core: Arc<Manager>, path: PathBuf) -> Result<Message> {
+    let report = uk_manager::bundle::import_profile(&core, &path, None)?;
+    Ok(Message::ProfileImported(report.to_markdown()))
+}
+
+pub fn preview_remerge(core: Arc<Manager>) -> Result<Message> {
+    log::info!("Checking what a remerge would change");
+    let report = core.deploy_manager().apply_dry_run(None)?;
+    Ok(Message::ShowReport(
+        "Report_Remerge".localize().to_string(),
+        report.to_markdown(),
+    ))
+}
+
 pub fn parse_meta(file: PathBuf) -> Result<Message> {
     match file.extension().and_then(|x| x.to_str()).unwrap() {
         "txt" => ModPacker::parse_rules(file),
@@ -642,7 +720,11 @@ pub fn import_cemu_settings(core: &Manager, path: &Path) -> Result<Message> {
                 cemu_rules: true,
                 executable: exe_cmd,
                 layout: uk_manager::settings::DeployLayout::WithName,
+                compare_hashes: false,
+                archive_format: Default::default(),
+                archive_delta: false,
             }),
+            deploy_targets: Default::default(),
         })
     };
     settings.save()?;
@@ -708,6 +790,7 @@ pub fn migrate_bcml(core: Arc<Manager>) -> Result<Message> {
                             }
                         })
                     }),
+                deploy_targets: Default::default(),
                 dump: Arc::new(ResourceReader::from_unpacked_dirs(
                     Some(game_dir),
                     Some(update_dir),
@@ -738,6 +821,7 @@ pub fn migrate_bcml(core: Arc<Manager>) -> Result<Message> {
                         ..Default::default()
                     }
                 }),
+                deploy_targets: Default::default(),
                 dump: Arc::new(ResourceReader::from_unpacked_dirs(
                     Some(game_dir),
                     None::<PathBuf>,
@@ -1,6 +1,11 @@
 use strfmt::Format;
 use uk_content::prelude::Endian;
 use uk_localization::string_ext::LocString;
+use uk_manager::{
+    dependencies::{self, IssueKind},
+    watch,
+};
+
 use super::*;
 
 impl App {
@@ -205,6 +210,33 @@ impl App {
                         Ok(Message::ReloadProfiles)
                     });
                 }
+                Message::ExportProfile(profile) => {
+                    if let Some(dest) = rfd::FileDialog::new()
+                        .add_filter("UKMM Profile", &["zip"])
+                        .set_title("Profile_Export".localize())
+                        .set_file_name(format!("{profile}.zip"))
+                        .save_file()
+                    {
+                        self.do_task(move |core| {
+                            uk_manager::bundle::export_profile(&core, &profile, &dest, true)?;
+                            Ok(Message::Noop)
+                        });
+                    }
+                }
+                Message::ImportProfile => {
+                    if let Some(path) = rfd::FileDialog::new()
+                        .add_filter("UKMM Profile", &["zip"])
+                        .set_title("Profile_Import".localize())
+                        .pick_file()
+                    {
+                        self.do_task(move |core| tasks::import_profile(core, path));
+                    }
+                }
+                Message::ProfileImported(report) => {
+                    self.profiles_state.borrow_mut().reload(&self.core);
+                    self.busy.set(false);
+                    self.report = Some(("Report_Profile_Import".localize().to_string(), report));
+                }
                 Message::RenameProfile(profile, rename) => {
                     self.do_task(move |core| {
                         let profiles_dir = core.settings().profiles_dir();
@@ -278,16 +310,24 @@ impl App {
                 Message::HandleMod(mod_) => {
                     self.busy.set(false);
                     log::debug!("{:#?}", &mod_);
-                    for (hash, (name, version)) in mod_.meta.masters.iter() {
-                        if !self.mods.iter().any(|m| m.hash() == *hash) {
-                            self.do_update(Message::Error(anyhow_ext::anyhow!(
-                                "Could not find required mod dependency {} (version {})",
-                                name,
-                                version
-                            )));
-                        }
+                    let (missing, disabled): (Vec<_>, Vec<_>) =
+                        dependencies::check_masters(&mod_.meta, &self.mods)
+                            .into_iter()
+                            .partition(|issue| issue.kind != IssueKind::Disabled);
+                    for issue in disabled {
+                        self.do_update(Message::Toast(issue.to_string()));
                     }
-                    if !matches!(mod_.meta.platform, ModPlatform::Universal)
+                    if !missing.is_empty() {
+                        self.do_update(Message::Error(anyhow_ext::anyhow!(
+                            "Could not install {}. {}",
+                            mod_.meta.name,
+                            missing
+                                .iter()
+                                .map(|i| i.to_string())
+                                .collect::<Vec<_>>()
+                                .join(". ")
+                        )));
+                    } else if !matches!(mod_.meta.platform, ModPlatform::Universal)
                         && mod_.meta.platform != ModPlatform::Specific(self.platform().into())
                     {
                         self.do_update(Message::Error(anyhow_ext::anyhow!(
@@ -317,6 +357,12 @@ impl App {
                             dirty.extend(&mod_.manifest().unwrap_or_default());
                             Ok(Message::ResetMods(Some(dirty)))
                         } else {
+                            if !tmp_mod_.enabled_options.is_empty() {
+                                uk_manager::options::validate_options(
+                                    &tmp_mod_.meta,
+                                    &tmp_mod_.enabled_options,
+                                )?;
+                            }
                             let mod_ = mods.add(&tmp_mod_.path, None)?;
                             let hash = mod_.as_map_id();
                             if !tmp_mod_.enabled_options.is_empty() {
@@ -361,6 +407,62 @@ impl App {
                     let mods = self.selected.clone();
                     self.do_task(move |core| tasks::dev_update_mods(&core, mods));
                 }
+                Message::DevWatch => {
+                    if let Some(mod_) = self.selected.first().cloned() {
+                        let source = mod_.source.clone().or_else(|| {
+                            let vars = std::collections::HashMap::from([(
+                                "mod_name".to_string(),
+                                mod_.meta.name.to_string(),
+                            )]);
+                            rfd::FileDialog::new()
+                                .set_title(
+                                    "Mod_Update_Folder"
+                                        .localize()
+                                        .format(&vars)
+                                        .unwrap_or_default(),
+                                )
+                                .pick_folder()
+                        });
+                        if let Some(source) = source {
+                            if let Some((_, stop)) = self.watch.take() {
+                                stop.store(true, std::sync::atomic::Ordering::Relaxed);
+                            }
+                            let stop = Arc::new(AtomicBool::new(false));
+                            self.watch = Some((mod_.hash(), stop.clone()));
+                            let core = self.core.clone();
+                            let sender = self.channel.0.clone();
+                            thread::spawn(move || {
+                                let deploy = core
+                                    .settings()
+                                    .platform_config()
+                                    .is_some_and(|c| c.targets().any(|(_, config)| config.auto));
+                                let result = watch::watch_mod(
+                                    &core,
+                                    &mod_,
+                                    &source,
+                                    watch::DEFAULT_DEBOUNCE,
+                                    deploy,
+                                    || stop.load(std::sync::atomic::Ordering::Relaxed),
+                                    |result| {
+                                        let message = match result {
+                                            Ok(_) => Message::ResetMods(None),
+                                            Err(e) => Message::Error(e),
+                                        };
+                                        sender.send(message).ok();
+                                    },
+                                );
+                                if let Err(e) = result {
+                                    sender.send(Message::Error(e)).ok();
+                                }
+                            });
+                        }
+                    }
+                }
+                Message::DevUnwatch => {
+                    if let Some((_, stop)) = self.watch.take() {
+                        stop.store(true, std::sync::atomic::Ordering::Relaxed);
+                    }
+                }
                 Message::ToggleMods(mods, enabled) => {
                     let mods = mods.as_ref().unwrap_or(&self.selected);
                     let dirty = mods.iter().try_fold(
@@ -462,6 +564,13 @@ impl App {
                         Ok(Message::ResetMods(None))
                     })
                 }
+                Message::DeployTarget(target) => {
+                    self.do_task(move |core| {
+                        log::info!("Deploying current mod configuration to {target}");
+                        core.deploy_manager().deploy_target(&target)?;
+                        Ok(Message::ResetMods(None))
+                    })
+                }
                 Message::ResetPending => {
                     self.do_task(|core| {
                         log::info!("Resetting pending deployment data");
@@ -470,6 +579,21 @@ impl App {
                         Ok(Message::Noop)
                     })
                 }
+                Message::RestoreOutput => {
+                    self.do_task(|core| {
+                        log::info!("Restoring original deploy output folders");
+                        let deploy_manager = core.deploy_manager();
+                        let mut restored = false;
+                        for target in deploy_manager.targets() {
+                            restored |= deploy_manager.restore_output(&target)?;
+                        }
+                        Ok(Message::Toast(if restored {
+                            "Menu_Tools_RestoreOutput_Done".localize()
+                        } else {
+                            "Menu_Tools_RestoreOutput_None".localize()
+                        }))
+                    })
+                }
                 Message::Remerge => {
                     self.do_task(|core| tasks::apply_changes(&core, vec![], None));
                 }
@@ -480,22 +604,29 @@ impl App {
                 }
                 Message::SaveSettings => {
                     let mut needs_reset = false;
-                    self.core.settings().platform_config().map(|old_plat| {
-                        old_plat.deploy_config.as_ref().map(|old_dep| {
-                            if let Some(new_plat) = &self.temp_settings.platform_config() {
-                                new_plat.deploy_config.as_ref().map(|new_dep| {
-                                    if old_dep.layout != new_dep.layout ||
-                                        old_dep.method != new_dep.method ||
-                                        old_dep.output != new_dep.output {
-                                        if let Ok(_) = self.core.settings()
-                                            .wipe_output(self.core.settings().current_mode.into()) {
-                                            needs_reset = true;
-                                        }
-                                    }
+                    {
+                        let settings = self.core.settings();
+                        if let (Some(old_plat), Some(new_plat)) = (
+                            settings.platform_config(),
+                            self.temp_settings.platform_config(),
+                        ) {
+                            // Undeploy from any target which has moved or been removed
+                            for (name, old_dep) in old_plat.targets() {
+                                let unchanged = new_plat.target(name).is_some_and(|new_dep| {
+                                    old_dep.layout == new_dep.layout
+                                        && old_dep.method == new_dep.method
+                                        && old_dep.output == new_dep.output
                                 });
+                                if !unchanged
+                                    && settings
+                                        .wipe_output(settings.current_mode.into(), name)
+                                        .is_ok()
+                                {
+                                    needs_reset = true;
+                                }
                             }
-                        });
-                    });
+                        }
+                    }
                     let save_res = self.temp_settings.save().and_then(|_| {
                         self.core.reload()?;
                         Ok(())
@@ -644,6 +775,28 @@ impl App {
                 }
                 Message::SetChangelog(msg) => self.changelog = Some(msg),
                 Message::CloseChangelog => self.changelog = None,
+                Message::FindConflicts => {
+                    self.do_task(tasks::find_conflicts);
+                }
+                Message::CheckDependencies => {
+                    self.do_task(tasks::check_dependencies);
+                }
+                Message::PreviewRemerge => {
+                    self.do_task(tasks::preview_remerge);
+                }
+                Message::ShowReport(title, report) => {
+                    self.busy.set(false);
+                    self.report = Some((title, report));
+                }
+                Message::CloseReport => self.report = None,
+                Message::ShowHistory => {
+                    self.history = Some(self.core.mod_manager().history(None));
+                }
+                Message::CloseHistory => self.history = None,
+                Message::Rollback(index) => {
+                    self.history = None;
+                    self.do_task(move |core| tasks::rollback(&core, index));
+                }
                 Message::OfferUpdate(version) => {
                     let message = "Update_Available".localize();
                     self.changelog = Some(format!(